@@ -0,0 +1,95 @@
+use std::{env, hint::black_box, path::PathBuf, process};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use leafadoo::anet_archive::AnetArchive;
+use leafadoo::builder::ArchiveBuilder;
+use leafadoo::deflate::anet_deflate;
+use leafadoo::inflate::anet_inflate;
+
+const FILE_COUNT: u32 = 2000;
+const FILE_SIZE: usize = 0x4000;
+const FIRST_FILE_ID: u32 = 100;
+
+/// Text-like content that compresses about as well as real entries.
+fn sample_data(seed: u32) -> Vec<u8> {
+    let words: [&[u8]; 6] = [
+        b"texture ",
+        b"model ",
+        b"sound ",
+        b"map ",
+        b"PF",
+        b"\0\0\x01\0",
+    ];
+    let mut state = seed.wrapping_mul(2654435761).max(1);
+    let mut data = Vec::with_capacity(FILE_SIZE);
+    while data.len() < FILE_SIZE {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        data.extend_from_slice(words[state as usize % words.len()]);
+    }
+    data.truncate(FILE_SIZE);
+    data
+}
+
+/// Writes a benchmark archive to the temporary directory and returns its
+/// path.
+fn build_archive(compress: bool) -> PathBuf {
+    let file_path = env::temp_dir().join(format!(
+        "leafadoo-bench-{}-{}.dat",
+        process::id(),
+        if compress { "compressed" } else { "stored" }
+    ));
+    let mut builder = ArchiveBuilder::new();
+    builder.compress(compress);
+    for file_id in FIRST_FILE_ID..FIRST_FILE_ID + FILE_COUNT {
+        builder.add_file(file_id, sample_data(file_id));
+    }
+    builder
+        .save_to_file(&file_path)
+        .expect("failed to write benchmark archive");
+    file_path
+}
+
+fn load_mft(c: &mut Criterion) {
+    let file_path = build_archive(false);
+    c.bench_function("load_mft", |b| {
+        b.iter(|| AnetArchive::load_from_file(black_box(&file_path)).unwrap())
+    });
+}
+
+fn read_entry(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_entry");
+    group.throughput(Throughput::Bytes(FILE_SIZE as u64));
+    for compress in [false, true] {
+        let archive = AnetArchive::load_from_file(build_archive(compress)).unwrap();
+        let name = if compress { "compressed" } else { "stored" };
+        let mut file_id = FIRST_FILE_ID;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                file_id = FIRST_FILE_ID + (file_id + 1 - FIRST_FILE_ID) % FILE_COUNT;
+                archive.read_file(black_box(file_id)).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn decompress(c: &mut Criterion) {
+    let data = sample_data(FIRST_FILE_ID);
+    let compressed = anet_deflate(&data);
+    let mut group = c.benchmark_group("decompress");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("anet_inflate", |b| {
+        b.iter_batched(
+            || compressed.clone(),
+            |compressed| anet_inflate(black_box(&compressed), None).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, load_mft, read_entry, decompress);
+criterion_main!(benches);
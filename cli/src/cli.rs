@@ -0,0 +1,1890 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use leafadoo::age::BuildTimeline;
+use leafadoo::anet_archive::{AnetArchive, AnetFileType, LanguageType};
+use leafadoo::animation::{AnetAnimationSequences, AnetEmoteAnimations};
+use leafadoo::bookmark::BookmarkSet;
+use leafadoo::builder::ArchiveBuilder;
+use leafadoo::cinematic::AnetCinematic;
+use leafadoo::collision::AnetCollision;
+use leafadoo::composite::AnetComposite;
+use leafadoo::content::{AnetGameContent, AnetPortalManifest};
+use leafadoo::deps::AnetDependencyTable;
+use leafadoo::eula::AnetEula;
+use leafadoo::extract::{read_id_list, ExportFormat, ExportNaming};
+use leafadoo::filter::EntryFilter;
+use leafadoo::font::AnetBitmapFont;
+use leafadoo::formats::FormatTable;
+use leafadoo::guid::{format_guid, parse_guid};
+use leafadoo::hash::HashAlgo;
+use leafadoo::layout;
+use leafadoo::local::ArchiveRole;
+use leafadoo::map::{
+    AnetMapEnvironment, AnetMapMetadata, AnetMapShadow, AnetMapTerrain, AnetPagedImageTable,
+};
+use leafadoo::material::AnetMaterial;
+use leafadoo::mesh::{save_meshes, MeshFormat};
+use leafadoo::model::{AnetModel, ModelExportOptions};
+use leafadoo::names::EntryNames;
+use leafadoo::pf::AnetPfFile;
+use leafadoo::pipeline::PipelineConfig;
+use leafadoo::progress::{no_progress, Progress, ProgressStage};
+use leafadoo::scan::FullScanOptions;
+use leafadoo::search::{GrepOptions, GrepPattern};
+use leafadoo::snapshot::MftSnapshot;
+use leafadoo::sound::{AnetBankFile, AnetBankIndex, AnetSoundData};
+use leafadoo::strictness::Strictness;
+use leafadoo::texture::BlockFormat;
+use leafadoo::token::parse_token;
+use leafadoo::trace;
+use leafadoo::watch::ArchiveWatcher;
+use serde::{Serialize, Serializer};
+
+const USAGE: &str = "Usage:
+    leafadoo info <file.dat>
+    leafadoo layout <file.dat> [--svg <out.svg>]
+    leafadoo extract <file.dat> --out <dir> [<selector>]... [--ids <ids.txt|ids.json>]
+        [--naming <leafadoo|gw2browser>] [--retries <n>] [--retry-delay <ms>] [--jobs <n>]
+        [--memory-budget <MiB>]
+        [--names <names.csv|names.json>] [--patch-safe] [--metrics] [--lenient]
+    leafadoo export <file.dat> --out <file.zip|file.tar> [<selector>]... [--names <names.csv|names.json>]
+        [--patch-safe] [--metrics] [--lenient]
+    leafadoo list <file.dat> [<selector>]... [--names <names.csv|names.json>] [--format <table|json|csv>]
+    leafadoo tui <file.dat> [--out <dir>]
+    leafadoo peek <file.dat> --id <file_id> [--bytes <n>]
+    leafadoo find <file.dat> <file_type> [--cache <scan.json>]
+    leafadoo formats [--export <formats.json>]
+    leafadoo bookmark add <set> <file_id> [comment]
+    leafadoo bookmark remove <set> <file_id>
+    leafadoo bookmark list <set>
+    leafadoo bundle <file.dat> <file_id> --out <dir> [--lenient]
+    leafadoo deps <file.dat> <file_id> [--reverse | --all]
+    leafadoo composite <file.dat> <file_id>
+    leafadoo content <file.dat> <file_id> [--records]
+    leafadoo cinematic <file.dat> <file_id>
+    leafadoo audio-script <file.dat> <file_id>
+    leafadoo bank <file.dat> <file_id> [--out <dir>]
+    leafadoo animation <file.dat> <file_id>
+    leafadoo collision <file.dat> <file_id> [--out <dir>]
+    leafadoo shaders <file.dat> <file_id> [--out <dir>]
+    leafadoo eula <file.dat> [--out <dir>]
+    leafadoo binaries <file.dat> [--out <dir>]
+    leafadoo video <file.dat> <file_id> [--out <file.bk2>]
+    leafadoo thumbnail <file.dat> <file_id> --out <file.png> [--size <n>]
+    leafadoo model <file.dat> <file_id> [--out <model.glb|model.obj|model.ply>] [--lod <n>] [--merge] [--permutation <n|all>]
+    leafadoo terrain <file.dat> <file_id> [--out <terrain.glb|terrain.obj|terrain.ply>]
+    leafadoo floor <file.dat> <file_id> --out <file.png> [--layer <n>] [--zoom <n>] [--lenient]
+    leafadoo text <file.dat> <file_id>
+    leafadoo dialogue <file.dat> [--language <english|korean|french|german|spanish|chinese>]
+    leafadoo search <file.dat> <text> [--language <english|korean|french|german|spanish|chinese>] [--lenient]
+    leafadoo guid <file.dat> <guid> [--cache <guids.json>] [--lenient]
+    leafadoo tokens <file.dat> [--token <value>] [--names <tokens.csv|tokens.json>] [--unnamed] [--lenient]
+    leafadoo grep <file.dat> <pattern> [--hex | --utf16 | --regex] [<selector>]... [--max-size <MiB>]
+        [--jobs <n>] [--memory-budget <MiB>] [--lenient]
+    leafadoo verify <file.dat> [--blocks]
+    leafadoo hash <file.dat> [--algo <sha256|crc32>] [--out <hashes.json>] [--memory-budget <MiB>]
+    leafadoo scan <file.dat> [--hash <sha256|crc32>] [--checkpoint <path>] [--out <scan.json>]
+    leafadoo build <out.dat> <file_id>=<data_file>... [--compress]
+    leafadoo compact <file.dat> <out.dat>
+    leafadoo patch <file.dat> <file_id> <data_file> [--compress]
+    leafadoo patch <file.dat> <file_id> <image.png> --texture [--format <DXT1|DXT3|DXT5|3DCX|DXTA>]
+    leafadoo local <Local.dat> [--show-secrets]
+    leafadoo stats <file.dat>
+    leafadoo pipeline <file.dat> <config.json>
+    leafadoo oldest <file.dat> [--count <n>] [--build <label>=<snapshot.lfsk>]...
+    leafadoo recent <file.dat> [--count <n>]
+    leafadoo watch <file.dat> [--interval <ms>]
+    leafadoo skeleton <file.dat> --out <out.lfsk>
+    leafadoo snapshot <file.dat> --out <out.lfms>
+    leafadoo diff <old.lfms> <new.lfms|new.dat>
+    leafadoo trace <file.dat> <index> --out <trace.json>
+
+Selectors:
+    --bookmark <set>    --id <first>-<last> | <id> | <pattern*>
+    --type <texture|sound|binary|file_type>    --size <[<|<=|=|>=|>]n[KiB|MiB|GiB]>
+    --name <pattern*>, with names loaded by --names <names.csv|names.json>";
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    let Some(command) = args.first() else {
+        return Err(usage_error());
+    };
+    FormatTable::install_from_env()?;
+    match command.as_str() {
+        "info" => info(&args[1..]),
+        "layout" => layout(&args[1..]),
+        "skeleton" => skeleton(&args[1..]),
+        "snapshot" => snapshot(&args[1..]),
+        "diff" => diff(&args[1..]),
+        "stats" => stats(&args[1..]),
+        "verify" => verify(&args[1..]),
+        "hash" => hash(&args[1..]),
+        "scan" => scan(&args[1..]),
+        "patch" => patch(&args[1..]),
+        "compact" => compact(&args[1..]),
+        "build" => build(&args[1..]),
+        "local" => local(&args[1..]),
+        "deps" => deps(&args[1..]),
+        "composite" => composite(&args[1..]),
+        "content" => content(&args[1..]),
+        "cinematic" => cinematic(&args[1..]),
+        "audio-script" => audio_script(&args[1..]),
+        "bank" => bank(&args[1..]),
+        "animation" => animation(&args[1..]),
+        "collision" => collision(&args[1..]),
+        "shaders" => shaders(&args[1..]),
+        "text" => text(&args[1..]),
+        "dialogue" => dialogue(&args[1..]),
+        "search" => search(&args[1..]),
+        "grep" => grep(&args[1..]),
+        "guid" => guid(&args[1..]),
+        "tokens" => tokens(&args[1..]),
+        "eula" => eula(&args[1..]),
+        "video" => video(&args[1..]),
+        "thumbnail" => thumbnail(&args[1..]),
+        "floor" => floor(&args[1..]),
+        "terrain" => terrain(&args[1..]),
+        "model" => model(&args[1..]),
+        "binaries" => binaries(&args[1..]),
+        "bundle" => bundle(&args[1..]),
+        "extract" => extract(&args[1..]),
+        "export" => export(&args[1..]),
+        "bookmark" => bookmark(&args[1..]),
+        "peek" => peek(&args[1..]),
+        "find" => find(&args[1..]),
+        "list" => list(&args[1..]),
+        "tui" => tui(&args[1..]),
+        "formats" => formats(&args[1..]),
+        "oldest" => oldest(&args[1..]),
+        "recent" => recent(&args[1..]),
+        "watch" => watch(&args[1..]),
+        "trace" => trace_reads(&args[1..]),
+        "pipeline" => pipeline(&args[1..]),
+        _ => Err(usage_error()),
+    }
+}
+
+fn usage_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, USAGE)
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+fn option_values<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|pair| pair[0] == name)
+        .map(|pair| pair[1].as_str())
+        .collect()
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> io::Result<T> {
+    value.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid number '{}'.", value),
+        )
+    })
+}
+
+/// Builds an entry filter from the `--id`, `--type`, `--size` and `--name`
+/// selectors.
+fn entry_filter(args: &[String]) -> io::Result<EntryFilter> {
+    let mut filter = EntryFilter::default();
+    for value in option_values(args, "--id") {
+        filter.add_ids(value)?;
+    }
+    for value in option_values(args, "--type") {
+        filter.add_file_types(value)?;
+    }
+    for value in option_values(args, "--size") {
+        filter.add_size(value)?;
+    }
+    for value in option_values(args, "--name") {
+        filter.add_name(value);
+    }
+    Ok(filter)
+}
+
+/// MFT indices of the entries named by `--bookmark` sets, in order and
+/// without duplicates, narrowed down by the entry filter selectors. All the
+/// entries the filter selects when no `--bookmark` is given, and `None` when
+/// no selection was given at all.
+fn selected_indices(archive: &AnetArchive, args: &[String]) -> io::Result<Option<Vec<usize>>> {
+    let bookmark_sets = option_values(args, "--bookmark");
+    let filter = entry_filter(args)?;
+    if bookmark_sets.is_empty() && filter.is_empty() {
+        return Ok(None);
+    }
+    let selected = archive.select(&filter)?;
+    if bookmark_sets.is_empty() {
+        return Ok(Some(selected));
+    }
+    let mut indices = Vec::new();
+    for name in bookmark_sets {
+        for index in BookmarkSet::load(name)?.indices(archive) {
+            if !indices.contains(&index) && selected.contains(&index) {
+                indices.push(index);
+            }
+        }
+    }
+    Ok(Some(indices))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// `--format table|json|csv`, a table by default.
+fn output_format(args: &[String]) -> io::Result<OutputFormat> {
+    match option_value(args, "--format") {
+        None | Some("table") => Ok(OutputFormat::Table),
+        Some("json") => Ok(OutputFormat::Json),
+        Some("csv") => Ok(OutputFormat::Csv),
+        Some(_) => Err(usage_error()),
+    }
+}
+
+/// Rows of text under named columns, printed as an aligned table, a JSON
+/// array of objects keyed by column, or CSV with a header row.
+struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(columns: &[&'static str]) -> Self {
+        Table {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    fn print(&self, format: OutputFormat) -> io::Result<()> {
+        match format {
+            OutputFormat::Table => {
+                let widths: Vec<usize> = (0..self.columns.len())
+                    .map(|column| {
+                        self.rows
+                            .iter()
+                            .map(|row| row[column].len())
+                            .chain([self.columns[column].len()])
+                            .max()
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                let print_row = |cells: Vec<&str>| {
+                    let line: Vec<String> = cells
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                        .collect();
+                    println!("{}", line.join("  ").trim_end());
+                };
+                print_row(self.columns.clone());
+                for row in &self.rows {
+                    print_row(row.iter().map(String::as_str).collect());
+                }
+            }
+            OutputFormat::Json => {
+                let rows: Vec<JsonRow> = self
+                    .rows
+                    .iter()
+                    .map(|row| JsonRow {
+                        columns: &self.columns,
+                        cells: row,
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            }
+            OutputFormat::Csv => {
+                println!("{}", self.columns.join(","));
+                for row in &self.rows {
+                    let cells: Vec<String> = row.iter().map(|cell| csv_cell(cell)).collect();
+                    println!("{}", cells.join(","));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A table row as a JSON object, keeping the column order.
+struct JsonRow<'a> {
+    columns: &'a [&'static str],
+    cells: &'a [String],
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.columns
+                .iter()
+                .zip(self.cells)
+                .map(|(column, cell)| (column, json_cell(cell))),
+        )
+    }
+}
+
+/// Numbers stay numbers in JSON output, everything else is a string.
+fn json_cell(cell: &str) -> serde_json::Value {
+    match cell.parse::<u64>() {
+        Ok(number) => number.into(),
+        Err(_) => cell.into(),
+    }
+}
+
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Draws a one-line progress indicator on stderr.
+fn show_progress(progress: Progress) {
+    let label = match progress.stage {
+        ProgressStage::LoadMft => "Loading MFT",
+        ProgressStage::Extract => "Extracting",
+        ProgressStage::Verify => "Verifying",
+        ProgressStage::Hash => "Hashing",
+        ProgressStage::Scan => "Scanning",
+        ProgressStage::Search => "Searching",
+    };
+    eprint!("\r{} {}/{}", label, progress.done, progress.total);
+    if progress.done == progress.total {
+        eprintln!();
+    }
+}
+
+/// `--lenient` skips malformed entries instead of stopping at the first.
+fn strictness(args: &[String]) -> Strictness {
+    if args.iter().any(|arg| arg == "--lenient") {
+        Strictness::Lenient
+    } else {
+        Strictness::Strict
+    }
+}
+
+fn print_warnings(archive: &AnetArchive) {
+    for warning in archive.take_warnings() {
+        match warning.index {
+            Some(index) => eprintln!("Skipped index {:>8}  {}", index, warning.message),
+            None => eprintln!("Skipped  {}", warning.message),
+        }
+    }
+}
+
+/// Attaches the name database given by `--names` to `archive`.
+fn load_entry_names(archive: &mut AnetArchive, args: &[String]) -> io::Result<()> {
+    if let Some(names_path) = option_value(args, "--names") {
+        archive.set_entry_names(EntryNames::load_from_file(names_path)?);
+    }
+    Ok(())
+}
+
+/// Applies `--memory-budget`, given in MiB, to `archive`.
+fn set_memory_budget(archive: &mut AnetArchive, args: &[String]) -> io::Result<()> {
+    if let Some(megabytes) = option_value(args, "--memory-budget") {
+        let megabytes: u64 = parse_number(megabytes)?;
+        archive.set_memory_budget(Some(megabytes.saturating_mul(1 << 20)));
+    }
+    Ok(())
+}
+
+/// Prints the read counters of `archive` to stderr when `--metrics` is given.
+fn print_metrics(archive: &AnetArchive, args: &[String]) {
+    if !args.iter().any(|arg| arg == "--metrics") {
+        return;
+    }
+    let metrics = archive.metrics();
+    eprintln!(
+        "Read {} entries, {} bytes; decompressed {} entries, {} bytes",
+        metrics.entries_read,
+        metrics.bytes_read,
+        metrics.entries_decompressed,
+        metrics.bytes_decompressed
+    );
+    if let Some(hit_rate) = metrics.cache_hit_rate() {
+        eprintln!("Type cache hit rate {:.1}%", hit_rate * 100.0);
+    }
+}
+
+fn dat_path(args: &[String]) -> io::Result<&str> {
+    args.first()
+        .filter(|arg| !arg.starts_with("--"))
+        .map(String::as_str)
+        .ok_or_else(usage_error)
+}
+
+fn info(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    println!("Archive version : {:?}", archive.version());
+    println!("{:?}", archive.dat_header);
+    println!("{:?}", archive.mft_header);
+    println!("MFT Data count : {}", archive.mft_data.len());
+    println!("In use entries : {}", archive.in_use_entries().count());
+    println!("Unused entries : {}", archive.unused_entries().count());
+    println!("MFT Data Index count : {}", archive.mft_index_data.len());
+    println!("ID collisions : {}", archive.id_collisions().len());
+    for collision in archive.id_collisions() {
+        println!(
+            "    file ID {} in MFT entries {:?}",
+            collision.file_id, collision.indices
+        );
+    }
+    Ok(())
+}
+
+fn local(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let role = archive.role();
+    println!("Archive role : {:?}", role);
+    if role == ArchiveRole::Game {
+        return Ok(());
+    }
+    let mut local_settings = archive.local_settings()?;
+    if !args.iter().any(|arg| arg == "--show-secrets") {
+        local_settings = local_settings.redacted();
+    }
+    for setting in &local_settings.settings {
+        println!("[{}] {} = {}", setting.section, setting.key, setting.value);
+    }
+    Ok(())
+}
+
+fn layout(args: &[String]) -> io::Result<()> {
+    let file_path = dat_path(args)?;
+    let archive = AnetArchive::load_from_file(file_path)?;
+    let file_length = fs::metadata(file_path)?.len();
+
+    let report = archive.layout_report(file_length);
+    for overlap in &report.overlaps {
+        println!(
+            "overlap  index {:>8} and {:>8}  {} bytes at {}",
+            overlap.first, overlap.second, overlap.size, overlap.offset
+        );
+    }
+    for index in &report.out_of_bounds {
+        println!("past end index {:>8}", index);
+    }
+    println!(
+        "{} bytes, {} live, {} free in {} gaps, {} overlaps",
+        report.file_length,
+        report.live_bytes,
+        report.free_bytes,
+        report.gaps.len(),
+        report.overlaps.len()
+    );
+
+    if let Some(svg_path) = option_value(args, "--svg") {
+        let mut writer = BufWriter::new(File::create(svg_path)?);
+        layout::render_layout_svg(&archive, file_length, &mut writer)?;
+        println!(
+            "Wrote layout of {} entries to {}",
+            archive.mft_data.len(),
+            svg_path
+        );
+    }
+    Ok(())
+}
+
+fn skeleton(args: &[String]) -> io::Result<()> {
+    let file_path = dat_path(args)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+
+    let archive = AnetArchive::load_from_file(file_path)?;
+    archive.export_skeleton_to_file(out_path)?;
+    println!("Wrote skeleton of {} to {}", file_path, out_path);
+    Ok(())
+}
+
+fn snapshot(args: &[String]) -> io::Result<()> {
+    let file_path = dat_path(args)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+
+    let archive = AnetArchive::load_from_file(file_path)?;
+    let snapshot = archive.snapshot();
+    snapshot.save_to_file(out_path)?;
+    println!(
+        "Wrote snapshot of {} entries to {}",
+        snapshot.entries.len(),
+        out_path
+    );
+    Ok(())
+}
+
+/// Loads a saved snapshot, or takes one of an archive given directly.
+fn load_snapshot(path: &str) -> io::Result<MftSnapshot> {
+    if path.ends_with(".dat") {
+        Ok(AnetArchive::load_from_file(path)?.snapshot())
+    } else {
+        MftSnapshot::load_from_file(path)
+    }
+}
+
+fn diff(args: &[String]) -> io::Result<()> {
+    let old_path = dat_path(args)?;
+    let new_path = args.get(1).ok_or_else(usage_error)?;
+
+    let diff = load_snapshot(old_path)?.diff(&load_snapshot(new_path)?);
+    for (label, entries) in [
+        ("added", &diff.added),
+        ("updated", &diff.updated),
+        ("removed", &diff.removed),
+    ] {
+        for entry in entries {
+            println!(
+                "{:<8} index {:>8}  file {:>10}  counter {:>10}  size {:>10}",
+                label, entry.index, entry.file_id, entry.counter, entry.size
+            );
+        }
+    }
+    Ok(())
+}
+
+fn stats(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let stats = archive.compression_stats();
+    println!("Uncompressed entries : {}", stats.uncompressed);
+    println!("Compressed entries : {}", stats.compressed);
+    println!("Unreadable entries : {}", stats.unreadable);
+    for (write_size_const_add, count) in &stats.by_write_size_const_add {
+        println!(
+            "  Copy length addition {} : {} entries",
+            write_size_const_add, count
+        );
+    }
+    Ok(())
+}
+
+fn deps(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let file_ids = if args.iter().any(|arg| arg == "--reverse") {
+        archive.reverse_dependencies(file_id).to_vec()
+    } else if args.iter().any(|arg| arg == "--all") {
+        archive.dependency_closure(file_id)
+    } else {
+        archive.dependencies_of(file_id).to_vec()
+    };
+    for file_id in file_ids {
+        println!("{}", file_id);
+    }
+    Ok(())
+}
+
+fn composite(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let composite = archive.read_composite(file_id)?;
+    for part in &composite.parts {
+        println!(
+            "token {:#018x}  category {:>4}  model {:>8}  textures {:?}",
+            part.token,
+            part.category,
+            part.model_file_id().unwrap_or(0),
+            part.texture_file_ids()
+        );
+    }
+    Ok(())
+}
+
+fn content(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let data = archive.read_file(file_id)?;
+    if let Ok(manifest) = AnetPortalManifest::load_from_bytes(&data) {
+        for entry in &manifest.entries {
+            println!(
+                "file {:>10}  flags {:#010x}  {}",
+                entry
+                    .file_reference
+                    .as_ref()
+                    .and_then(|file| file.file_id())
+                    .unwrap_or(0),
+                entry.flags,
+                entry.namespace
+            );
+        }
+        return Ok(());
+    }
+    let content = AnetGameContent::load_from_bytes(&data)?;
+    println!(
+        "{} types, {} namespaces, {} records, {} file references, {} bytes of content",
+        content.type_infos.len(),
+        content.namespaces.len(),
+        content.index_entries.len(),
+        content.file_references.len(),
+        content.content.len()
+    );
+    if args.iter().any(|arg| arg == "--records") {
+        for record in content.records() {
+            println!(
+                "record {:>6}  type {:>4}  {:>6} bytes  {}  {}",
+                record.index,
+                record.type_index,
+                record.data.len(),
+                record.namespace,
+                record.name.as_deref().unwrap_or("")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn cinematic(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let cinematic = archive.read_cinematic(file_id)?;
+    println!(
+        "{}: {:.2} s at {} fps",
+        cinematic.name,
+        cinematic.duration(),
+        cinematic.frame_rate
+    );
+    for (index, asset) in cinematic.assets.iter().enumerate() {
+        println!(
+            "asset {:>4}  type {:>4}  file {}",
+            index,
+            asset.asset_type,
+            asset
+                .file_reference
+                .as_ref()
+                .and_then(|file| file.file_id())
+                .unwrap_or(0)
+        );
+    }
+    for track in &cinematic.tracks {
+        println!(
+            "track {:<24}  type {:>4}  asset {:>4}  {} keys  {:.2}-{:.2} s",
+            track.name,
+            track.track_type,
+            track.asset_index.map_or(-1, |index| index as i64),
+            track.keys.len(),
+            track.start_time().unwrap_or(0.0),
+            track.end_time().unwrap_or(0.0)
+        );
+    }
+    Ok(())
+}
+
+fn bank(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let bank_file = AnetBankFile::load_from_bytes(&archive.read_file(file_id)?)?;
+    for audio_file in &bank_file.audio_files {
+        let format = match audio_file.pcm_format() {
+            Some(format) => format!(
+                "pcm {} Hz, {} channels, {} bits",
+                format.sample_rate, format.channels, format.bits_per_sample
+            ),
+            None => audio_file.extension().to_string(),
+        };
+        println!(
+            "voice {:>10}  {:>8.2} s  {:>10} bytes  {}",
+            audio_file.voice_id,
+            audio_file.length,
+            audio_file.audio_data.len(),
+            format
+        );
+    }
+    if let Some(out_dir) = option_value(args, "--out") {
+        for path in bank_file.extract_voices(out_dir)? {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn audio_script(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let script = archive.read_audio_script(file_id)?;
+    for (index, bank) in script.sound_banks.iter().enumerate() {
+        println!(
+            "bank {:>4}  {:<24}  file {}",
+            index,
+            bank.name,
+            bank.file_reference
+                .as_ref()
+                .and_then(|file| file.file_id())
+                .unwrap_or(0)
+        );
+    }
+    for event in &script.events {
+        println!(
+            "event {:<32}  {} triggers  banks {:?}",
+            event.name,
+            event.triggers.len(),
+            script.event_sound_bank_ids(event)
+        );
+        for trigger in &event.triggers {
+            println!(
+                "    trigger {:<24}  type {:>4}  bank {:>4}  sound {:>4}  delay {:.2} s",
+                trigger.name,
+                trigger.trigger_type,
+                trigger.bank_index.map_or(-1, |index| index as i64),
+                trigger.sound_index,
+                trigger.delay
+            );
+        }
+    }
+    Ok(())
+}
+
+fn animation(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let data = archive.read_file(file_id)?;
+    if let Ok(emote_animations) = AnetEmoteAnimations::load_from_bytes(&data) {
+        for emote in &emote_animations.emotes {
+            println!(
+                "emote {:<16} {} variants",
+                emote.command,
+                emote.variants.len()
+            );
+            for variant in &emote.variants {
+                println!(
+                    "  model {:#018x}  sequence {:#018x}  file {}",
+                    variant.model_token,
+                    variant.sequence_token,
+                    variant.animation_file_id().unwrap_or(0)
+                );
+            }
+        }
+        return Ok(());
+    }
+    let animation = AnetAnimationSequences::load_from_bytes(&data)?;
+    println!("model {:#018x}", animation.model_token);
+    for sequence in &animation.sequences {
+        println!(
+            "sequence {:#018x}  {:.2} s  {} tracks",
+            sequence.token,
+            sequence.duration,
+            sequence.tracks.len()
+        );
+    }
+    Ok(())
+}
+
+fn collision(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let collision = archive.read_collision(file_id)?;
+    for block in &collision.havok_blocks {
+        println!(
+            "havok {:?}  chunk {}  offset {:>8}  {} bytes",
+            block.kind,
+            block.chunk,
+            block.offset,
+            block.data.len()
+        );
+    }
+    println!(
+        "{} meshes, {} triangles",
+        collision.meshes.len(),
+        collision.triangle_count()
+    );
+    if let Some(out_dir) = option_value(args, "--out") {
+        for path in collision.save_havok_blocks(file_id, out_dir)? {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn shaders(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let cache = archive.read_shader_cache(file_id)?;
+    for (number, shader) in cache.shaders.iter().enumerate() {
+        println!(
+            "shader {:>4}  {}_{}_{}  chunk {}  offset {:>8}  {} bytes",
+            number,
+            shader.stage.short_name(),
+            shader.shader_model.0,
+            shader.shader_model.1,
+            shader.chunk,
+            shader.offset,
+            shader.size()
+        );
+    }
+    if let Some(out_dir) = option_value(args, "--out") {
+        for path in cache.save_shaders(file_id, out_dir)? {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn parse_language(name: &str) -> io::Result<LanguageType> {
+    match name.to_lowercase().as_str() {
+        "english" => Ok(LanguageType::English),
+        "korean" => Ok(LanguageType::Korean),
+        "french" => Ok(LanguageType::French),
+        "german" => Ok(LanguageType::German),
+        "spanish" => Ok(LanguageType::Spanish),
+        "chinese" => Ok(LanguageType::Chinese),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown language '{}'.", name),
+        )),
+    }
+}
+
+fn text(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    print!("{}", archive.read_text(file_id)?);
+    Ok(())
+}
+
+fn dialogue(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let language =
+        option_value(args, "--language").map_or(Ok(LanguageType::English), parse_language)?;
+    for (text, voice_file_id) in archive.dialogue(language)? {
+        println!("{:>8}  {}", voice_file_id, text);
+    }
+    Ok(())
+}
+
+fn search(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut show_progress,
+    )?;
+    let query = args.get(1).ok_or_else(usage_error)?;
+    let language = option_value(args, "--language")
+        .map(parse_language)
+        .transpose()?;
+    let matches = archive.search_strings_with_progress(query, language, &mut show_progress)?;
+    for string_match in &matches {
+        let text_id = string_match
+            .text_id
+            .map_or_else(String::new, |text_id| text_id.to_string());
+        println!(
+            "{:>10}  file {:>10}  index {:>5}  {}",
+            text_id, string_match.file_id, string_match.index, string_match.text
+        );
+    }
+    print_warnings(&archive);
+    println!("{} matches", matches.len());
+    Ok(())
+}
+
+fn grep(args: &[String]) -> io::Result<()> {
+    let mut archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut show_progress,
+    )?;
+    set_memory_budget(&mut archive, args)?;
+    let text = args.get(1).ok_or_else(usage_error)?;
+    let has_flag = |flag: &str| args.iter().any(|arg| arg == flag);
+    let pattern = if has_flag("--hex") {
+        GrepPattern::from_hex(text)?
+    } else if has_flag("--utf16") {
+        GrepPattern::utf16(text)
+    } else if has_flag("--regex") {
+        GrepPattern::regex(text)?
+    } else {
+        GrepPattern::Bytes(text.as_bytes().to_vec())
+    };
+    let mut options = GrepOptions::default();
+    if let Some(megabytes) = option_value(args, "--max-size") {
+        options.max_entry_size = parse_number::<u64>(megabytes)?.saturating_mul(1 << 20);
+    }
+    if let Some(jobs) = option_value(args, "--jobs") {
+        options.threads = parse_number(jobs)?;
+    }
+    let indices = selected_indices(&archive, args)?.unwrap_or_else(|| {
+        archive
+            .in_use_entries()
+            .filter(|entry| entry.size() > 0)
+            .map(|entry| entry.index())
+            .collect()
+    });
+    let hits = archive.grep_indices(&indices, &pattern, &options, &mut show_progress)?;
+    for hit in &hits {
+        println!(
+            "index {:>8}  file {:>10}  offset {:#010x}  length {}",
+            hit.index, hit.file_id, hit.offset, hit.length
+        );
+    }
+    print_warnings(&archive);
+    println!("{} hits", hits.len());
+    Ok(())
+}
+
+fn guid(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut show_progress,
+    )?;
+    let guid = parse_guid(args.get(1).ok_or_else(usage_error)?)?;
+    let index = match option_value(args, "--cache") {
+        Some(cache_path) => archive.guid_index_cached(cache_path, &mut show_progress)?,
+        None => archive.build_guid_index(&mut show_progress)?,
+    };
+    let locations = archive.find_guid_in(&index, &guid)?;
+    for location in &locations {
+        let record = location
+            .record
+            .map_or_else(String::new, |record| format!("  record {}", record));
+        println!(
+            "index {:>8}  file {:>10}  offset {:#010x}{}",
+            location.index, location.file_id, location.offset, record
+        );
+    }
+    print_warnings(&archive);
+    println!("{} locations of {}", locations.len(), format_guid(&guid));
+    Ok(())
+}
+
+fn tokens(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut show_progress,
+    )?;
+    let mut registry = archive.collect_tokens(&mut show_progress)?;
+    if let Some(names_path) = option_value(args, "--names") {
+        registry.load_names(names_path)?;
+    }
+    if let Some(token) = option_value(args, "--token") {
+        let token = parse_token(token)?;
+        println!(
+            "{:#018x}  {}",
+            token,
+            registry.name_of(token).unwrap_or_default()
+        );
+        for owner in registry.owners_of(token) {
+            println!(
+                "index {:>8}  file {:>10}  {:?} {}",
+                owner.index, owner.file_id, owner.source, owner.position
+            );
+        }
+        return Ok(());
+    }
+    let tokens = if args.iter().any(|arg| arg == "--unnamed") {
+        registry.unnamed_tokens()
+    } else {
+        registry.tokens()
+    };
+    for token in &tokens {
+        println!(
+            "{:#018x}  {:>6} owners  {}",
+            token,
+            registry.owners_of(*token).len(),
+            registry.name_of(*token).unwrap_or_default()
+        );
+    }
+    print_warnings(&archive);
+    println!("{} tokens", tokens.len());
+    Ok(())
+}
+
+fn eula(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let eula = archive.eula()?;
+    for text in &eula.texts {
+        println!(
+            "language {}  {} characters",
+            text.language,
+            text.text.chars().count()
+        );
+    }
+    if let Some(out_dir) = option_value(args, "--out") {
+        for path in eula.save_texts(out_dir)? {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn video(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let video = archive.read_bink2(file_id)?;
+    let header = &video.header;
+    println!(
+        "Bink 2 rev {}  {}x{}  {} frames  {:.2} fps  {:.2} s  {} audio tracks",
+        header.revision as char,
+        header.width,
+        header.height,
+        header.frame_count,
+        header.frame_rate().unwrap_or(0.0),
+        header.duration().unwrap_or(0.0),
+        header.audio_track_count
+    );
+    if video.offset != 0 {
+        println!("Skipped {} wrapper bytes", video.offset);
+    }
+    if let Some(out_path) = option_value(args, "--out") {
+        video.save_to_file(out_path)?;
+        println!("Wrote {} bytes to {}", video.data.len(), out_path);
+    }
+    Ok(())
+}
+
+fn thumbnail(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+    let size = match option_value(args, "--size") {
+        Some(value) => parse_number(value)?,
+        None => 256,
+    };
+    let thumbnail = archive.thumbnail(file_id, size)?;
+    thumbnail.save_png(out_path)?;
+    println!(
+        "Wrote {}x{} thumbnail to {}",
+        thumbnail.width, thumbnail.height, out_path
+    );
+    Ok(())
+}
+
+fn floor(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut no_progress,
+    )?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+    let layer = match option_value(args, "--layer") {
+        Some(value) => parse_number(value)?,
+        None => 0,
+    };
+    let zoom = match option_value(args, "--zoom") {
+        Some(value) => parse_number(value)?,
+        None => 0,
+    };
+    let image = archive.render_floor(file_id, layer, zoom)?;
+    image.save_png(out_path)?;
+    print_warnings(&archive);
+    println!(
+        "Wrote {}x{} floor to {}",
+        image.width, image.height, out_path
+    );
+    Ok(())
+}
+
+fn model(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let model = archive.read_model(file_id)?;
+    for (number, mesh) in model.meshes.iter().enumerate() {
+        println!(
+            "mesh {:>3}  material {:>3}  {:>6} vertices  {} lods  {:?}  {}",
+            number,
+            mesh.material_index,
+            mesh.vertex_count,
+            mesh.lod_count(),
+            mesh.vertex_format,
+            mesh.name
+        );
+    }
+    for (number, permutation) in model.permutations.iter().enumerate() {
+        println!(
+            "permutation {:>3}  token {:#018x}  {} materials",
+            number,
+            permutation.token,
+            permutation.materials.len()
+        );
+    }
+    if !model.bones.is_empty() {
+        println!("{} bones", model.bones.len());
+    }
+    let Some(out_path) = option_value(args, "--out") else {
+        return Ok(());
+    };
+    let skeleton = model.skeleton();
+    let mut options = ModelExportOptions {
+        merge_by_material: args.iter().any(|arg| arg == "--merge"),
+        ..Default::default()
+    };
+    if let Some(value) = option_value(args, "--lod") {
+        options.lod = parse_number(value)?;
+    }
+    // Every permutation goes to its own file, named after the permutation.
+    let permutations: Vec<(Option<usize>, String)> = match option_value(args, "--permutation") {
+        Some("all") => {
+            let out_path = Path::new(out_path);
+            let stem = out_path.file_stem().unwrap_or_default().to_string_lossy();
+            (0..model.permutations.len())
+                .map(|permutation| {
+                    let extension =
+                        MeshFormat::from_path(out_path).map_or("glb", MeshFormat::extension);
+                    let file_name = format!("{}_{}.{}", stem, permutation, extension);
+                    let path = out_path.with_file_name(file_name);
+                    (Some(permutation), path.to_string_lossy().into_owned())
+                })
+                .collect()
+        }
+        Some(value) => vec![(Some(parse_number(value)?), out_path.to_string())],
+        None => vec![(None, out_path.to_string())],
+    };
+    for (permutation, path) in permutations {
+        options.permutation = permutation;
+        let meshes = model.to_meshes(&options)?;
+        save_meshes(&meshes, skeleton.as_ref(), &path)?;
+        let triangles: usize = meshes.iter().map(|mesh| mesh.triangle_count()).sum();
+        println!(
+            "Wrote {} meshes, {} triangles to {}",
+            meshes.len(),
+            triangles,
+            path
+        );
+    }
+    Ok(())
+}
+
+fn terrain(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let data = archive.read_file(file_id)?;
+    let terrain = AnetMapTerrain::load_from_bytes(&data)?;
+    println!(
+        "{}x{} chunks, {} samples per side, splat image {}",
+        terrain.chunk_dims[0],
+        terrain.chunk_dims[1],
+        terrain.samples_per_side()?,
+        terrain.splat_image_file_id().unwrap_or(0)
+    );
+    for (index, material) in terrain.materials.iter().enumerate() {
+        let texture_ids: Vec<u32> = material
+            .texture_files
+            .iter()
+            .filter_map(|texture| texture.as_ref()?.file_id())
+            .collect();
+        println!(
+            "material {:>3}  file {:>10}  textures {:?}",
+            index,
+            material
+                .material_file
+                .as_ref()
+                .and_then(|file| file.file_id())
+                .unwrap_or(0),
+            texture_ids
+        );
+    }
+    if let Some(out_path) = option_value(args, "--out") {
+        // Maps carrying their metadata place the terrain at its bounds;
+        // otherwise each chunk spans one unit.
+        let (bounds_min, bounds_max) = match AnetMapMetadata::load_from_bytes(&data) {
+            Ok(metadata) => (
+                [metadata.bounds_min[0], metadata.bounds_min[1]],
+                [metadata.bounds_max[0], metadata.bounds_max[1]],
+            ),
+            Err(_) => (
+                [0.0, 0.0],
+                [terrain.chunk_dims[0] as f32, terrain.chunk_dims[1] as f32],
+            ),
+        };
+        let mesh = terrain.to_mesh(bounds_min, bounds_max)?;
+        save_meshes(std::slice::from_ref(&mesh), None, out_path)?;
+        println!(
+            "Wrote {} vertices, {} triangles to {}",
+            mesh.positions.len(),
+            mesh.triangle_count(),
+            out_path
+        );
+    }
+    Ok(())
+}
+
+fn binaries(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let out_dir = option_value(args, "--out");
+    for (index, pe_info) in archive.binaries()? {
+        println!(
+            "index {:>8}  {:?}  {:?}  linked {}  {} sections",
+            index, pe_info.kind, pe_info.machine, pe_info.timestamp, pe_info.section_count
+        );
+        if let Some(out_dir) = out_dir {
+            println!("  {}", archive.extract_binary(index, out_dir)?.display());
+        }
+    }
+    Ok(())
+}
+
+fn bundle(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut no_progress,
+    )?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+    let manifest = archive.extract_bundle(file_id, out_path)?;
+    for file in &manifest.files {
+        println!("{}", Path::new(out_path).join(&file.file_name).display());
+    }
+    for file_id in &manifest.missing {
+        println!("Missing dependency {}", file_id);
+    }
+    print_warnings(&archive);
+    Ok(())
+}
+
+fn oldest(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let count = match option_value(args, "--count") {
+        Some(value) => parse_number(value)?,
+        None => 20,
+    };
+
+    let mut timeline = BuildTimeline::default();
+    for build in option_values(args, "--build") {
+        let (label, snapshot_path) = build.split_once('=').ok_or_else(usage_error)?;
+        let snapshot = AnetArchive::import_skeleton_from_file(snapshot_path)?;
+        timeline.add_snapshot(label, &snapshot);
+    }
+    timeline.add_snapshot("current", &archive);
+
+    for entry in archive.oldest_entries(count) {
+        let build = entry
+            .estimated_age(&timeline)
+            .map_or("unknown", |build| build.label.as_str());
+        println!(
+            "index {:>8}  counter {:>10}  size {:>10}  last written by {}",
+            entry.index(),
+            entry.mft_entry().counter,
+            entry.size(),
+            build
+        );
+    }
+    Ok(())
+}
+
+fn recent(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let count = match option_value(args, "--count") {
+        Some(value) => parse_number(value)?,
+        None => 20,
+    };
+
+    for entry in archive.recently_modified(count) {
+        let file_id = archive
+            .mft_index_data
+            .get(entry.index())
+            .map_or(0, |id_entry| id_entry.base_id);
+        println!(
+            "index {:>8}  file {:>10}  counter {:>10}  size {:>10}",
+            entry.index(),
+            file_id,
+            entry.mft_entry().counter,
+            entry.size()
+        );
+    }
+    Ok(())
+}
+
+/// Prints one JSON line per changed entry until interrupted.
+fn watch(args: &[String]) -> io::Result<()> {
+    let interval: u64 = match option_value(args, "--interval") {
+        Some(value) => parse_number(value)?,
+        None => 1000,
+    };
+    let mut watcher = ArchiveWatcher::new(dat_path(args)?)?;
+    loop {
+        thread::sleep(Duration::from_millis(interval));
+        for event in watcher.poll()? {
+            println!("{}", serde_json::to_string(&event)?);
+        }
+    }
+}
+
+fn extract(args: &[String]) -> io::Result<()> {
+    let mut archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut show_progress,
+    )?;
+    if let Some(retries) = option_value(args, "--retries") {
+        archive.set_read_retries(parse_number(retries)?);
+    }
+    if let Some(milliseconds) = option_value(args, "--retry-delay") {
+        archive.set_retry_delay(Duration::from_millis(parse_number(milliseconds)?));
+    }
+    archive.set_patch_safe(args.iter().any(|arg| arg == "--patch-safe"));
+    set_memory_budget(&mut archive, args)?;
+    load_entry_names(&mut archive, args)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+    if let Some(ids_path) = option_value(args, "--ids") {
+        let file_ids = read_id_list(ids_path)?;
+        let results = archive.extract_ids(&file_ids, out_path, &mut show_progress)?;
+        for result in &results {
+            match (&result.file_path, &result.error) {
+                (Some(file_path), _) => println!("{:>10}  {}", result.file_id, file_path.display()),
+                (None, error) => println!(
+                    "{:>10}  failed: {}",
+                    result.file_id,
+                    error.as_deref().unwrap_or_default()
+                ),
+            }
+        }
+        let failed = results.iter().filter(|result| !result.is_ok()).count();
+        println!(
+            "Extracted {} of {} files",
+            results.len() - failed,
+            results.len()
+        );
+        return Ok(());
+    }
+    let indices =
+        selected_indices(&archive, args)?.unwrap_or_else(|| (0..archive.mft_data.len()).collect());
+
+    let naming = match option_value(args, "--naming") {
+        Some(name) => ExportNaming::from_name(name).ok_or_else(usage_error)?,
+        None => ExportNaming::Leafadoo,
+    };
+
+    let report = match option_value(args, "--jobs") {
+        Some(jobs) => archive.par_extract_entries(
+            &indices,
+            out_path,
+            naming,
+            parse_number(jobs)?,
+            &mut show_progress,
+        )?,
+        None => archive.extract_entries_named(&indices, out_path, naming, &mut show_progress)?,
+    };
+    for file_path in &report.written {
+        println!("{}", file_path.display());
+    }
+    print_warnings(&archive);
+    print_metrics(&archive, args);
+    Ok(())
+}
+
+fn export(args: &[String]) -> io::Result<()> {
+    let mut archive = AnetArchive::load_from_file_with_strictness(
+        dat_path(args)?,
+        strictness(args),
+        &mut show_progress,
+    )?;
+    archive.set_patch_safe(args.iter().any(|arg| arg == "--patch-safe"));
+    load_entry_names(&mut archive, args)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+    let format = ExportFormat::from_path(Path::new(out_path)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Export file must end in .zip or .tar.",
+        )
+    })?;
+    let indices =
+        selected_indices(&archive, args)?.unwrap_or_else(|| (0..archive.mft_data.len()).collect());
+
+    let writer = BufWriter::new(File::create(out_path)?);
+    let names = archive.export_archive(&indices, format, writer, &mut show_progress)?;
+    println!("Wrote {} files to {}", names.len(), out_path);
+    print_warnings(&archive);
+    print_metrics(&archive, args);
+    Ok(())
+}
+
+fn build(args: &[String]) -> io::Result<()> {
+    let out_path = dat_path(args)?;
+    let mut builder = ArchiveBuilder::new();
+    builder.compress(args.iter().any(|arg| arg == "--compress"));
+    for file in args[1..].iter().filter(|arg| !arg.starts_with("--")) {
+        let (file_id, data_path) = file.split_once('=').ok_or_else(usage_error)?;
+        builder.add_file(parse_number(file_id)?, fs::read(data_path)?);
+    }
+    builder.save_to_file(out_path)?;
+    println!("Wrote {}", out_path);
+    Ok(())
+}
+
+fn compact(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_progress(dat_path(args)?, &mut show_progress)?;
+    let report = archive.compact(args.get(1).ok_or_else(usage_error)?)?;
+    println!(
+        "Copied {} entries, dropped {} unused entries",
+        report.entries_copied, report.entries_dropped
+    );
+    println!(
+        "{} -> {} bytes, {} bytes saved",
+        report.original_size,
+        report.compacted_size,
+        report.space_saved()
+    );
+    Ok(())
+}
+
+fn patch(args: &[String]) -> io::Result<()> {
+    let mut archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let data_path = args.get(2).ok_or_else(usage_error)?;
+    let index = archive.index_of(file_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("File ID {} not found.", file_id),
+        )
+    })?;
+    if args.iter().any(|arg| arg == "--texture") {
+        let format = option_value(args, "--format")
+            .map(|format| {
+                BlockFormat::from_fourcc(format.to_ascii_uppercase().as_bytes()).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Unknown texture format '{}'.", format),
+                    )
+                })
+            })
+            .transpose()?;
+        archive.patch_texture_from_png(index, data_path, format)?;
+    } else {
+        let compress = args.iter().any(|arg| arg == "--compress");
+        archive.patch_entry(index, &fs::read(data_path)?, compress)?;
+    }
+    let entry = &archive.mft_data[index];
+    println!(
+        "Patched file {} (index {}): {} bytes stored at offset {}",
+        file_id, index, entry.size, entry.offset
+    );
+    Ok(())
+}
+
+fn verify(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_progress(dat_path(args)?, &mut show_progress)?;
+    let report = archive.verify_entries(&mut show_progress);
+    for failure in &report.failures {
+        println!("index {:>8}  {}", failure.index, failure.error);
+    }
+    println!(
+        "Checked {} entries, {} failed",
+        report.checked,
+        report.failures.len()
+    );
+    if args.iter().any(|arg| arg == "--blocks") {
+        let mut blocks = 0;
+        let mut mismatches = 0;
+        for entry in archive.entries().filter(|entry| entry.is_compressed()) {
+            let check = archive.verify_entry_blocks(entry.index())?;
+            for mismatch in &check.mismatches {
+                println!(
+                    "index {:>8}  block {:>4}  stored crc {:#010x}, computed {:#010x}",
+                    entry.index(),
+                    mismatch.block,
+                    mismatch.stored,
+                    mismatch.computed
+                );
+            }
+            blocks += check.blocks;
+            mismatches += check.mismatches.len();
+        }
+        println!("Checked {} blocks, {} CRC mismatches", blocks, mismatches);
+    }
+    Ok(())
+}
+
+fn parse_hash_algo(name: &str) -> io::Result<HashAlgo> {
+    match name.to_lowercase().as_str() {
+        "sha256" => Ok(HashAlgo::Sha256),
+        "crc32" => Ok(HashAlgo::Crc32),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown hash algorithm '{}'.", name),
+        )),
+    }
+}
+
+fn hash(args: &[String]) -> io::Result<()> {
+    let mut archive =
+        AnetArchive::load_from_file_with_progress(dat_path(args)?, &mut show_progress)?;
+    set_memory_budget(&mut archive, args)?;
+    let algo = option_value(args, "--algo").map_or(Ok(HashAlgo::Sha256), parse_hash_algo)?;
+    let report = archive.hash_entries_with_progress(algo, &mut show_progress)?;
+    if let Some(out_path) = option_value(args, "--out") {
+        serde_json::to_writer(BufWriter::new(File::create(out_path)?), &report)?;
+        println!("Wrote {} digests to {}", report.digests.len(), out_path);
+    }
+    for failure in &report.failures {
+        println!("index {:>8}  {}", failure.index, failure.error);
+    }
+    let duplicates = report.duplicates(&archive);
+    for group in &duplicates {
+        println!(
+            "{}  {:>10} bytes  indices {:?}  files {:?}",
+            group.digest, group.size, group.indices, group.file_ids
+        );
+    }
+    println!(
+        "Hashed {} entries, {} duplicate groups, {} redundant bytes",
+        report.digests.len(),
+        duplicates.len(),
+        report.duplicate_bytes(&archive)
+    );
+    Ok(())
+}
+
+/// Classifies, and optionally hashes, every entry. An interrupted scan is
+/// resumed from its checkpoint when run again.
+fn scan(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file_with_progress(dat_path(args)?, &mut show_progress)?;
+    let options = FullScanOptions {
+        hash: option_value(args, "--hash")
+            .map(parse_hash_algo)
+            .transpose()?,
+        checkpoint_path: option_value(args, "--checkpoint").map(Into::into),
+        ..Default::default()
+    };
+    let scan = archive.scan_resumable(&options, &mut show_progress)?;
+    if let Some(out_path) = option_value(args, "--out") {
+        serde_json::to_writer(BufWriter::new(File::create(out_path)?), &scan)?;
+        println!("Wrote scan to {}", out_path);
+    }
+    let identified = scan
+        .types
+        .file_types
+        .iter()
+        .filter(|file_type| **file_type != AnetFileType::AnftUnknown)
+        .count();
+    println!(
+        "Identified {} of {} entries",
+        identified,
+        scan.types.file_types.len()
+    );
+    if let Some(hashes) = &scan.hashes {
+        println!(
+            "Hashed {} entries, {} failed",
+            hashes.digests.len(),
+            hashes.failures.len()
+        );
+    }
+    Ok(())
+}
+
+fn pipeline(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let config = PipelineConfig::load_from_file(args.get(1).ok_or_else(usage_error)?)?;
+    let report = config.run(&archive)?;
+    for file_path in &report.written {
+        println!("{}", file_path.display());
+    }
+    if report.skipped > 0 {
+        println!(
+            "Skipped {} entries that could not be decoded",
+            report.skipped
+        );
+    }
+    if report.unconverted > 0 {
+        println!(
+            "Wrote {} entries as stored after their conversion failed",
+            report.unconverted
+        );
+    }
+    Ok(())
+}
+
+const HEXDUMP_ROW_SIZE: usize = 16;
+
+/// Prints `data` as rows of offset, hex bytes and printable ASCII.
+/// Formats `data` as offset, hex and ASCII columns, one line per row.
+pub(crate) fn hexdump_lines(data: &[u8]) -> Vec<String> {
+    data.chunks(HEXDUMP_ROW_SIZE)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = bytes
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  |{}|",
+                row * HEXDUMP_ROW_SIZE,
+                hex.join(" "),
+                ascii,
+                width = HEXDUMP_ROW_SIZE * 3 - 1
+            )
+        })
+        .collect()
+}
+
+fn print_hexdump(data: &[u8]) {
+    for line in hexdump_lines(data) {
+        println!("{}", line);
+    }
+}
+
+fn peek(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let file_id = parse_number(option_value(args, "--id").ok_or_else(usage_error)?)?;
+    let length = match option_value(args, "--bytes") {
+        Some(value) => parse_number(value)?,
+        None => 256,
+    };
+    let index = archive.index_of(file_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("File ID {} not found.", file_id),
+        )
+    })?;
+
+    let data = archive.read_entry(index)?;
+    let format = FormatTable::active()
+        .identify(&data)
+        .map_or("unknown", |format| format.name.as_str());
+    println!(
+        "File ID {}, MFT index {}, {} bytes, format {}",
+        file_id,
+        index,
+        data.len(),
+        format
+    );
+    if let Ok(pf_file) = AnetPfFile::load_from_bytes(&data) {
+        for chunk in &pf_file.chunks {
+            println!(
+                "  chunk {} version {} {} bytes",
+                String::from_utf8_lossy(&chunk.header.chunk_type),
+                chunk.header.chunk_version,
+                chunk.data.len()
+            );
+        }
+    }
+    print_hexdump(&data[..length.min(data.len())]);
+    Ok(())
+}
+
+fn find(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let type_name = args.get(1).ok_or_else(usage_error)?;
+    let file_type: AnetFileType =
+        serde_json::from_value(serde_json::Value::String(type_name.clone())).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown file type '{}'.", type_name),
+            )
+        })?;
+    let entries = match option_value(args, "--cache") {
+        Some(cache_path) => archive.find_by_type_cached(file_type, cache_path)?,
+        None => archive.find_by_type(file_type)?,
+    };
+    for entry in entries {
+        let file_id = archive
+            .mft_index_data
+            .get(entry.index())
+            .map_or(0, |id_entry| id_entry.base_id);
+        println!(
+            "index {:>8}  file {:>10}  size {:>10}",
+            entry.index(),
+            file_id,
+            entry.size()
+        );
+    }
+    Ok(())
+}
+
+fn list(args: &[String]) -> io::Result<()> {
+    let mut archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    load_entry_names(&mut archive, args)?;
+    let format = output_format(args)?;
+    let indices = match selected_indices(&archive, args)? {
+        Some(indices) => indices,
+        None => archive
+            .in_use_entries()
+            .map(|entry| entry.index())
+            .collect(),
+    };
+    let kinds = archive.entry_kinds(&indices)?;
+
+    let mut table = Table::new(&[
+        "index",
+        "file_id",
+        "base_id",
+        "size",
+        "uncompressed_size",
+        "type",
+        "flags",
+        "name",
+    ]);
+    for (entry, kind) in indices
+        .into_iter()
+        .filter_map(|index| archive.entry(index))
+        .zip(kinds)
+    {
+        let id_entry = archive.mft_index_data.get(entry.index());
+        let mut flags = Vec::new();
+        if entry.is_in_use() {
+            flags.push("in_use");
+        }
+        if entry.is_compressed() {
+            flags.push("compressed");
+        }
+        table.push(vec![
+            entry.index().to_string(),
+            id_entry.map_or(0, |id_entry| id_entry.file_id).to_string(),
+            id_entry.map_or(0, |id_entry| id_entry.base_id).to_string(),
+            entry.size().to_string(),
+            entry
+                .uncompressed_size()
+                .map_or_else(|_| String::new(), |size| size.to_string()),
+            format!("{:?}", kind),
+            flags.join(" "),
+            archive
+                .entry_name(entry.index())
+                .unwrap_or_default()
+                .to_string(),
+        ]);
+    }
+    table.print(format)
+}
+
+#[cfg(feature = "tui")]
+fn tui(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let out_dir = option_value(args, "--out").unwrap_or("extracted");
+    crate::tui::run(archive, out_dir.into())
+}
+
+#[cfg(not(feature = "tui"))]
+fn tui(_args: &[String]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "leafadoo was built without the tui feature.",
+    ))
+}
+
+fn formats(args: &[String]) -> io::Result<()> {
+    let table = FormatTable::active();
+    if let Some(out_path) = option_value(args, "--export") {
+        table.save_to_file(out_path)?;
+        println!("Wrote {} formats to {}", table.formats.len(), out_path);
+        return Ok(());
+    }
+    for format in &table.formats {
+        println!(
+            "{:<8} {:<10} .{:<6} {}",
+            format.name, format.category, format.extension, format.description
+        );
+    }
+    Ok(())
+}
+
+fn bookmark(args: &[String]) -> io::Result<()> {
+    let (Some(action), Some(name)) = (args.first(), args.get(1)) else {
+        return Err(usage_error());
+    };
+    let file_path = BookmarkSet::resolve_path(name);
+    let mut bookmark_set = if file_path.exists() {
+        BookmarkSet::load_from_file(&file_path)?
+    } else {
+        BookmarkSet::new(name)
+    };
+
+    match action.as_str() {
+        "add" => {
+            let file_id = parse_number(args.get(2).ok_or_else(usage_error)?)?;
+            let comment = args.get(3).map_or("", String::as_str);
+            bookmark_set.add(file_id, comment);
+            bookmark_set.save_to_file(&file_path)?;
+        }
+        "remove" => {
+            let file_id = parse_number(args.get(2).ok_or_else(usage_error)?)?;
+            bookmark_set.remove(file_id);
+            bookmark_set.save_to_file(&file_path)?;
+        }
+        "list" => {
+            for bookmark in &bookmark_set.bookmarks {
+                println!("{:>10}  {}", bookmark.file_id, bookmark.comment);
+            }
+        }
+        _ => return Err(usage_error()),
+    }
+    Ok(())
+}
+
+/// Parses entry `index` with the parser matching its chunks while recording
+/// every read, and writes the trace even when parsing fails.
+fn trace_reads(args: &[String]) -> io::Result<()> {
+    let archive = AnetArchive::load_from_file(dat_path(args)?)?;
+    let index = parse_number(args.get(1).ok_or_else(usage_error)?)?;
+    let out_path = option_value(args, "--out").ok_or_else(usage_error)?;
+
+    let data = archive.read_entry(index)?;
+    let chunk_types: Vec<[u8; 4]> = AnetPfFile::load_from_bytes(&data)
+        .map(|pf_file| {
+            pf_file
+                .chunks
+                .iter()
+                .map(|chunk| chunk.header.chunk_type)
+                .collect()
+        })
+        .unwrap_or_default();
+    let has_chunk = |chunk_type: &[u8; 4]| chunk_types.contains(chunk_type);
+
+    let (result, read_trace) = trace::trace(|| {
+        if has_chunk(b"ASND") {
+            AnetSoundData::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"BKCK") {
+            AnetBankFile::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"BIDX") {
+            AnetBankIndex::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"PGTB") {
+            AnetPagedImageTable::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"GEOM") {
+            AnetModel::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"trn ") {
+            AnetMapTerrain::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"env ") {
+            AnetMapEnvironment::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"mpsd") {
+            AnetMapShadow::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"mMet") {
+            AnetMapMetadata::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"DEPS") {
+            AnetDependencyTable::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"DX9S") {
+            AnetMaterial::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"BGFS") {
+            AnetAnimationSequences::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"EMOT") {
+            AnetEmoteAnimations::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"havk") {
+            AnetCollision::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"SCN ") {
+            AnetCinematic::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"CMPC") {
+            AnetComposite::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"eula") {
+            AnetEula::load_from_bytes(&data).map(drop)
+        } else if has_chunk(b"AFNT") {
+            AnetBitmapFont::load_from_bytes(&data).map(drop)
+        } else {
+            AnetPfFile::load_from_bytes(&data).map(drop)
+        }
+    });
+    read_trace.save_to_file(out_path)?;
+    println!("Wrote {} reads to {}", read_trace.records.len(), out_path);
+    result
+}
@@ -0,0 +1,332 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use leafadoo::anet_archive::{AnetArchive, AnetFileType};
+use leafadoo::texture::RgbaImageData;
+use leafadoo::thumbnail::decode_image;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::cli::hexdump_lines;
+
+// Bytes of an entry shown in the hex and strings previews.
+const PREVIEW_SIZE: usize = 0x10000;
+// Shortest run of printable bytes listed in the strings preview.
+const MIN_STRING_LENGTH: usize = 4;
+const PAGE_SIZE: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PreviewMode {
+    Hex,
+    Strings,
+    Image,
+}
+
+/// The entry shown in the preview pane, read once when it is selected.
+struct Preview {
+    index: usize,
+    data: io::Result<Vec<u8>>,
+    image: Option<RgbaImageData>,
+}
+
+struct Browser {
+    archive: AnetArchive,
+    file_types: Vec<AnetFileType>,   //< Indexed by MFT index.
+    type_filters: Vec<AnetFileType>, //< Types present in the archive, cycled through by `t`.
+    type_filter: Option<usize>,
+    entries: Vec<usize>,
+    selected: usize,
+    list_offset: usize, //< First entry drawn in the list.
+    mode: PreviewMode,
+    preview: Option<Preview>,
+    scroll: u16,
+    out_dir: PathBuf,
+    status: String,
+}
+
+/// Browses the entries of `archive` until the user quits. Extracted files
+/// are written to `out_dir`.
+pub fn run(archive: AnetArchive, out_dir: PathBuf) -> io::Result<()> {
+    let file_types = archive.type_scan()?.file_types.clone();
+    let mut type_filters: Vec<AnetFileType> = Vec::new();
+    for &file_type in &file_types {
+        if !type_filters.contains(&file_type) {
+            type_filters.push(file_type);
+        }
+    }
+    type_filters.sort_by_key(|file_type| format!("{:?}", file_type));
+
+    let mut browser = Browser {
+        archive,
+        file_types,
+        type_filters,
+        type_filter: None,
+        entries: Vec::new(),
+        selected: 0,
+        list_offset: 0,
+        mode: PreviewMode::Hex,
+        preview: None,
+        scroll: 0,
+        out_dir,
+        status: String::from("q quit  t type filter  tab preview  x extract"),
+    };
+    browser.apply_filter();
+
+    let mut terminal = ratatui::init();
+    let result = browser.event_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Browser {
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            self.load_preview();
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::PageDown => self.move_selection(PAGE_SIZE as isize),
+                KeyCode::PageUp => self.move_selection(-(PAGE_SIZE as isize)),
+                KeyCode::Home => self.select(0),
+                KeyCode::End => self.select(self.entries.len().saturating_sub(1)),
+                KeyCode::Char('J') => self.scroll = self.scroll.saturating_add(1),
+                KeyCode::Char('K') => self.scroll = self.scroll.saturating_sub(1),
+                KeyCode::Char('t') => {
+                    self.type_filter = match self.type_filter {
+                        None if !self.type_filters.is_empty() => Some(0),
+                        Some(position) if position + 1 < self.type_filters.len() => {
+                            Some(position + 1)
+                        }
+                        _ => None,
+                    };
+                    self.apply_filter();
+                }
+                KeyCode::Tab => {
+                    self.mode = match self.mode {
+                        PreviewMode::Hex => PreviewMode::Strings,
+                        PreviewMode::Strings => PreviewMode::Image,
+                        PreviewMode::Image => PreviewMode::Hex,
+                    };
+                    self.scroll = 0;
+                }
+                KeyCode::Char('x') => {
+                    self.status = match self.extract_selected() {
+                        Ok(file_path) => format!("Wrote {}", file_path.display()),
+                        Err(error) => format!("Extraction failed: {}", error),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn apply_filter(&mut self) {
+        let file_type = self.type_filter.map(|position| self.type_filters[position]);
+        self.entries = self
+            .archive
+            .in_use_entries()
+            .map(|entry| entry.index())
+            .filter(|&index| file_type.is_none_or(|file_type| self.file_types[index] == file_type))
+            .collect();
+        self.select(0);
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        self.entries.get(self.selected).copied()
+    }
+
+    fn select(&mut self, position: usize) {
+        self.selected = position.min(self.entries.len().saturating_sub(1));
+        self.scroll = 0;
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        self.select(self.selected.saturating_add_signed(delta));
+    }
+
+    fn load_preview(&mut self) {
+        let Some(index) = self.selected_index() else {
+            self.preview = None;
+            return;
+        };
+        if self
+            .preview
+            .as_ref()
+            .is_some_and(|preview| preview.index == index)
+        {
+            return;
+        }
+        let data = self.archive.read_entry(index);
+        let image = data.as_ref().ok().and_then(|data| decode_image(data).ok());
+        self.preview = Some(Preview { index, data, image });
+    }
+
+    fn extract_selected(&self) -> io::Result<PathBuf> {
+        let index = self
+            .selected_index()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No entry selected."))?;
+        let data = self.archive.read_entry(index)?;
+        fs::create_dir_all(&self.out_dir)?;
+        let file_path = self
+            .out_dir
+            .join(self.archive.entry_file_name_for_data(index, &data));
+        File::create(&file_path)?.write_all(&data)?;
+        Ok(file_path)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+
+        // Only the rows on screen are formatted, the full list of a game
+        // archive runs to a million entries.
+        let rows = usize::from(list_area.height.saturating_sub(2)).max(1);
+        if self.selected < self.list_offset {
+            self.list_offset = self.selected;
+        } else if self.selected >= self.list_offset + rows {
+            self.list_offset = self.selected + 1 - rows;
+        }
+        let end = self.entries.len().min(self.list_offset + rows);
+        let items: Vec<ListItem> = self.entries[self.list_offset.min(end)..end]
+            .iter()
+            .map(|&index| {
+                let base_id = self
+                    .archive
+                    .mft_index_data
+                    .get(index)
+                    .map_or(0, |id_entry| id_entry.base_id);
+                ListItem::new(format!(
+                    "{:>8} {:>10} {:>10}  {:?}",
+                    index, base_id, self.archive.mft_data[index].size, self.file_types[index]
+                ))
+            })
+            .collect();
+        let title = match self.type_filter {
+            Some(position) => format!(
+                " {} entries, {:?} ",
+                self.entries.len(),
+                self.type_filters[position]
+            ),
+            None => format!(" {} entries ", self.entries.len()),
+        };
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut list_state = ListState::default().with_selected(
+            self.selected_index()
+                .map(|_| self.selected - self.list_offset),
+        );
+        frame.render_stateful_widget(list, list_area, &mut list_state);
+
+        self.draw_preview(frame, preview_area);
+        frame.render_widget(Paragraph::new(self.status.as_str()), status);
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let title = match self.mode {
+            PreviewMode::Hex => " Hex ",
+            PreviewMode::Strings => " Strings ",
+            PreviewMode::Image => " Texture ",
+        };
+        let block = Block::bordered().title(title);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let Some(preview) = &self.preview else {
+            return;
+        };
+        let data = match &preview.data {
+            Ok(data) => data,
+            Err(error) => {
+                frame.render_widget(Paragraph::new(format!("Unreadable: {}", error)), inner);
+                return;
+            }
+        };
+        let head = &data[..data.len().min(PREVIEW_SIZE)];
+        let lines: Vec<Line> = match self.mode {
+            PreviewMode::Hex => hexdump_lines(head).into_iter().map(Line::from).collect(),
+            PreviewMode::Strings => printable_strings(head)
+                .into_iter()
+                .map(Line::from)
+                .collect(),
+            PreviewMode::Image => match &preview.image {
+                Some(image) => thumbnail_lines(image, inner.width, inner.height),
+                None => vec![Line::from("Not a texture.")],
+            },
+        };
+        frame.render_widget(Paragraph::new(lines).scroll((self.scroll, 0)), inner);
+    }
+}
+
+/// Runs of printable ASCII at least `MIN_STRING_LENGTH` long, with their
+/// offsets, like the `strings` tool.
+fn printable_strings(data: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut start = 0;
+    for (position, &byte) in data.iter().chain([&0]).enumerate() {
+        if byte.is_ascii_graphic() || byte == b' ' {
+            continue;
+        }
+        if position - start >= MIN_STRING_LENGTH {
+            let text = String::from_utf8_lossy(&data[start..position]);
+            strings.push(format!("{:08x}  {}", start, text));
+        }
+        start = position + 1;
+    }
+    strings
+}
+
+/// Scales `image` to fit `width` by `height` cells, drawing two pixel rows
+/// per cell with upper half blocks.
+fn thumbnail_lines(image: &RgbaImageData, width: u16, height: u16) -> Vec<Line<'static>> {
+    let max_dim = u32::from(width).min(u32::from(height) * 2);
+    if max_dim == 0 || image.pixels.is_empty() {
+        return Vec::new();
+    }
+    let thumbnail = image.resized_to_fit(max_dim);
+    let pixel = |x: u32, y: u32| {
+        let offset = ((y * thumbnail.width + x) * 4) as usize;
+        match thumbnail.pixels.get(offset..offset + 3) {
+            Some(rgb) => Color::Rgb(rgb[0], rgb[1], rgb[2]),
+            None => Color::Reset,
+        }
+    };
+    (0..thumbnail.height.div_ceil(2))
+        .map(|row| {
+            let spans: Vec<Span> = (0..thumbnail.width)
+                .map(|column| {
+                    let bottom = if row * 2 + 1 < thumbnail.height {
+                        pixel(column, row * 2 + 1)
+                    } else {
+                        Color::Reset
+                    };
+                    Span::styled(
+                        "\u{2580}",
+                        Style::new().fg(pixel(column, row * 2)).bg(bottom),
+                    )
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
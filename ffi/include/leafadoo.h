@@ -0,0 +1,67 @@
+#ifndef LEAFADOO_H
+#define LEAFADOO_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Opaque handle to an opened archive. Must not be used by several
+// threads at once.
+typedef struct LeafadooArchive LeafadooArchive;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the message of the last error raised on this thread, or null.
+// The string stays valid until the next failing call on the same thread.
+const char *leafadoo_last_error(void);
+
+// Opens the `.dat` archive at `path`, a NUL-terminated UTF-8 string.
+// Returns null on failure. Close the handle with `leafadoo_archive_close`.
+//
+// # Safety
+// `path` must be null or point to a NUL-terminated string.
+struct LeafadooArchive *leafadoo_archive_open(const char *path);
+
+// # Safety
+// `archive` must be null or a handle returned by `leafadoo_archive_open`
+// that has not been closed yet.
+void leafadoo_archive_close(struct LeafadooArchive *archive);
+
+// Number of MFT entries in the archive, or 0 on failure.
+//
+// # Safety
+// `archive` must be a valid handle.
+size_t leafadoo_archive_entry_count(const struct LeafadooArchive *archive);
+
+// MFT index of `file_id`, or -1 if the archive does not contain it.
+//
+// # Safety
+// `archive` must be a valid handle.
+int64_t leafadoo_archive_index_of(const struct LeafadooArchive *archive, uint32_t file_id);
+
+// Reads and decompresses the entry at `index`. On success stores a buffer
+// in `data` and its length in `length` and returns 0; returns -1 on failure.
+//
+// # Safety
+// `archive` must be a valid handle, `data` and `length` valid pointers.
+int32_t leafadoo_archive_read_entry(const struct LeafadooArchive *archive,
+                                    size_t index,
+                                    uint8_t **data,
+                                    size_t *length);
+
+// Releases a buffer returned by `leafadoo_archive_read_entry`.
+//
+// # Safety
+// `data` and `length` must come from the same successful read, and the
+// buffer must not be freed twice.
+void leafadoo_free_buffer(uint8_t *data, size_t length);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LEAFADOO_H */
@@ -0,0 +1,204 @@
+//! C interface to the leafadoo archive parser.
+//!
+//! Every function that can fail returns a null pointer or a negative value
+//! and stores a message retrievable with `leafadoo_last_error`. A panic
+//! inside the library is caught at the boundary and reported the same way.
+//! Buffers returned by the library must be released with
+//! `leafadoo_free_buffer`.
+//!
+//! Archive handles are not thread-safe: the archive keeps caches in cells
+//! without locking. Use a handle from one thread at a time, or open one
+//! handle per thread.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    io,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use leafadoo::anet_archive::AnetArchive;
+
+/// Opaque handle to an opened archive. Must not be used by several
+/// threads at once.
+pub struct LeafadooArchive {
+    archive: AnetArchive,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: &io::Error) {
+    let message = CString::new(error.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Runs `body`, returning `on_panic` with the last error set if it panics:
+/// unwinding into the caller's C frames is undefined behavior.
+fn catch_panic<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown cause");
+        set_last_error(&io::Error::other(format!("Panic: {}.", message)));
+        on_panic
+    })
+}
+
+/// Returns the message of the last error raised on this thread, or null.
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn leafadoo_last_error() -> *const c_char {
+    catch_panic(ptr::null(), || {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+}
+
+/// Opens the `.dat` archive at `path`, a NUL-terminated UTF-8 string.
+/// Returns null on failure. Close the handle with `leafadoo_archive_close`.
+///
+/// # Safety
+/// `path` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn leafadoo_archive_open(path: *const c_char) -> *mut LeafadooArchive {
+    catch_panic(ptr::null_mut(), || {
+        if path.is_null() {
+            set_last_error(&io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Path is null.",
+            ));
+            return ptr::null_mut();
+        }
+        let path = match CStr::from_ptr(path).to_str() {
+            Ok(path) => path,
+            Err(_) => {
+                set_last_error(&io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Path is not valid UTF-8.",
+                ));
+                return ptr::null_mut();
+            }
+        };
+        match AnetArchive::load_from_file(path) {
+            Ok(archive) => Box::into_raw(Box::new(LeafadooArchive { archive })),
+            Err(error) => {
+                set_last_error(&error);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// # Safety
+/// `archive` must be null or a handle returned by `leafadoo_archive_open`
+/// that has not been closed yet.
+#[no_mangle]
+pub unsafe extern "C" fn leafadoo_archive_close(archive: *mut LeafadooArchive) {
+    catch_panic((), || {
+        if !archive.is_null() {
+            drop(Box::from_raw(archive));
+        }
+    })
+}
+
+/// Number of MFT entries in the archive, or 0 on failure.
+///
+/// # Safety
+/// `archive` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn leafadoo_archive_entry_count(archive: *const LeafadooArchive) -> usize {
+    catch_panic(0, || {
+        archive
+            .as_ref()
+            .map_or(0, |archive| archive.archive.mft_data.len())
+    })
+}
+
+/// MFT index of `file_id`, or -1 if the archive does not contain it.
+///
+/// # Safety
+/// `archive` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn leafadoo_archive_index_of(
+    archive: *const LeafadooArchive,
+    file_id: u32,
+) -> i64 {
+    catch_panic(-1, || {
+        archive
+            .as_ref()
+            .and_then(|archive| archive.archive.index_of(file_id))
+            .map_or(-1, |index| index as i64)
+    })
+}
+
+/// Reads and decompresses the entry at `index`. On success stores a buffer
+/// in `data` and its length in `length` and returns 0; returns -1 on failure.
+///
+/// # Safety
+/// `archive` must be a valid handle, `data` and `length` valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn leafadoo_archive_read_entry(
+    archive: *const LeafadooArchive,
+    index: usize,
+    data: *mut *mut u8,
+    length: *mut usize,
+) -> i32 {
+    catch_panic(-1, || {
+        let (Some(archive), false, false) = (archive.as_ref(), data.is_null(), length.is_null())
+        else {
+            set_last_error(&io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Null argument.",
+            ));
+            return -1;
+        };
+        match archive.archive.read_entry(index) {
+            Ok(entry) => {
+                let entry = entry.into_boxed_slice();
+                *length = entry.len();
+                *data = Box::into_raw(entry) as *mut u8;
+                0
+            }
+            Err(error) => {
+                set_last_error(&error);
+                -1
+            }
+        }
+    })
+}
+
+/// Releases a buffer returned by `leafadoo_archive_read_entry`.
+///
+/// # Safety
+/// `data` and `length` must come from the same successful read, and the
+/// buffer must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn leafadoo_free_buffer(data: *mut u8, length: usize) {
+    catch_panic((), || {
+        if !data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, length)));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_are_reported_as_errors() {
+        let result = catch_panic(-1, || -> i32 { panic!("boom") });
+        assert_eq!(result, -1);
+        let message = unsafe { CStr::from_ptr(leafadoo_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "Panic: boom.");
+    }
+}
@@ -0,0 +1,77 @@
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+use crate::entry::EntryInfo;
+
+/// A build whose archive structure has been seen, identified by the highest
+/// MFT counter written up to that build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownBuild {
+    pub label: String,
+    pub max_counter: u32,
+}
+
+/// Known builds ordered by their counter, used to map the counter of an entry
+/// to the build that last wrote it.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct BuildTimeline {
+    pub builds: Vec<KnownBuild>,
+}
+
+impl AnetArchive {
+    pub fn max_counter(&self) -> u32 {
+        self.mft_data
+            .iter()
+            .map(|entry| entry.counter)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the `count` non-empty entries with the lowest counters, which
+    /// are the ones that have gone the longest without being rewritten.
+    pub fn oldest_entries(&self, count: usize) -> Vec<EntryInfo<'_>> {
+        let mut entries: Vec<EntryInfo> = self.entries().filter(|entry| entry.size() > 0).collect();
+        entries.sort_by_key(|entry| entry.mft_entry().counter);
+        entries.truncate(count);
+        entries
+    }
+
+    /// Returns the `count` non-empty entries with the highest counters, most
+    /// recent first. Every write takes the next counter value, so after a
+    /// patch these are the entries it touched, without needing the archive
+    /// as it was before.
+    pub fn recently_modified(&self, count: usize) -> Vec<EntryInfo<'_>> {
+        let mut entries: Vec<EntryInfo> = self.entries().filter(|entry| entry.size() > 0).collect();
+        entries.sort_by_key(|entry| Reverse(entry.mft_entry().counter));
+        entries.truncate(count);
+        entries
+    }
+}
+
+impl BuildTimeline {
+    pub fn add_snapshot(&mut self, label: &str, archive: &AnetArchive) {
+        self.builds.push(KnownBuild {
+            label: label.to_string(),
+            max_counter: archive.max_counter(),
+        });
+        self.builds.sort_by_key(|build| build.max_counter);
+    }
+
+    /// Returns the earliest known build whose counter range covers `counter`.
+    pub fn build_for_counter(&self, counter: u32) -> Option<&KnownBuild> {
+        let position = self
+            .builds
+            .partition_point(|build| build.max_counter < counter);
+        self.builds.get(position)
+    }
+}
+
+impl EntryInfo<'_> {
+    /// The earliest known build that could have written this entry last.
+    /// `None` means the entry was written after every build in the timeline.
+    pub fn estimated_age<'t>(&self, timeline: &'t BuildTimeline) -> Option<&'t KnownBuild> {
+        timeline.build_for_counter(self.mft_entry().counter)
+    }
+}
@@ -1,14 +1,27 @@
 use std::{
-    fs::File,
-    io::{self, BufReader, Read, Seek},
-    mem::{size_of, swap},
-    path::Path,
+    cell::{OnceCell, RefCell},
+    collections::{HashMap, HashSet},
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write},
+    mem::size_of,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use bitflags::bitflags;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use serde::{Deserialize, Serialize};
 
+use crate::deps::DependencyGraph;
+use crate::file_type::TypeScan;
+use crate::inflate::anet_inflate;
+use crate::metrics::Counters;
+use crate::names::EntryNames;
+use crate::progress::{no_progress, Progress, ProgressStage};
+use crate::reader::open_archive_file;
+use crate::strictness::{ArchiveWarning, Strictness};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum LanguageType {
     English,
     Korean,
@@ -17,6 +30,7 @@ pub enum LanguageType {
     Spanish,
     Chinese,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FourCC {
     // Offset 0
     FccAtex = 0x58455441,
@@ -91,6 +105,7 @@ pub enum FourCC {
     FccUtf8 = 0xbfbbef,  // UTF-8 encoding
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AnetFileType {
     AnftUnknown, //< Unknown format.
 
@@ -166,38 +181,66 @@ pub enum AnetFileType {
     AnftText, //< Text file.
 }
 
-pub enum AnetCompressionFlags {
-    AncfUncompressed = 0, //< File is uncompressed.
-    AncfCompressed = 8,   //< File is compressed.
+bitflags! {
+    /// Bits of `AnetMftEntry::compression_flag`. Only two values have been
+    /// seen in Gw2.dat and Local.dat: 0 for a file stored as-is and 8 for a
+    /// compressed stream, whose 64 KiB blocks each end in a CRC-32 word (see
+    /// `blocks`). Entries with any other bit set are refused rather than
+    /// decoded by guesswork.
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct AnetCompressionFlags: u16 {
+        const COMPRESSED = 8; //< File is an ANet Huffman/LZ77 stream with block CRCs.
+    }
 }
-pub enum AnetMftEntryFlags {
-    AnmefNone = 0,  //< No flags set.
-    AnmefInUse = 1, //< Entry is in use.
+
+bitflags! {
+    /// Bits of `AnetMftEntry::entry_flag`.
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct AnetMftEntryFlags: u16 {
+        const IN_USE = 1; //< Entry is in use.
+    }
 }
 
-pub enum AnetFlexibleVertexFormat {
-    AnfvfPosition = 0x00000001, //< 12 bytes. Position as three 32-bit floats in the order x, y, z.
-    AnfvfWeights = 0x00000002,  //< 4 bytes. Contains bone weights.
-    AnfvfGroup = 0x00000004,    //< 4 bytes. Related to bone weights.
-    AnfvfNormal = 0x00000008,   //< 12 bytes. Normal as three 32-bit floats in the order x, y, z.
-    AnfvfColor = 0x00000010,    //< 4 bytes. Vertex color.
-    AnfvfTangent = 0x00000020,  //< 12 bytes. Tangent as three 32-bit floats in the order x, y, z.
-    AnfvfBitangent = 0x00000040, //< 12 bytes. Bitangent as three 32-bit floats in the order x, y, z.
-    AnfvfTangentFrame = 0x00000080, //< 12 bytes.
-    AnfvfUv32mask = 0x0000ff00, //< 8 bytes for each set bit. Contains UV-coords as two 32-bit floats in the order u, v.
-    AnfvfUv16mask = 0x00ff0000, //< 4 bytes for each set bit. Contains UV-coords as two 16-bit floats in the order u, v.
-    AnfvfUnknown1 = 0x01000000, //< 48 bytes. Unknown data.
-    AnfvfUnknown2 = 0x02000000, //< 4 bytes. Unknown data.
-    AnfvfUnknown3 = 0x04000000, //< 4 bytes. Unknown data.
-    AnfvfUnknown4 = 0x08000000, //< 16 bytes. Unknown data.
-    AnfvfPositionCompressed = 0x10000000, //< 6 bytes. Position as three 16-bit floats in the order x, y, z.
-    AnfvfUnknown5 = 0x20000000,           //< 12 bytes. Unknown data.
+bitflags! {
+    /// Vertex attributes present in a model mesh, in the order they are
+    /// stored in each vertex.
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct AnetFlexibleVertexFormat: u32 {
+        const POSITION = 0x00000001; //< 12 bytes. Position as three 32-bit floats in the order x, y, z.
+        const WEIGHTS = 0x00000002; //< 4 bytes. Contains bone weights.
+        const GROUP = 0x00000004; //< 4 bytes. Related to bone weights.
+        const NORMAL = 0x00000008; //< 12 bytes. Normal as three 32-bit floats in the order x, y, z.
+        const COLOR = 0x00000010; //< 4 bytes. Vertex color.
+        const TANGENT = 0x00000020; //< 12 bytes. Tangent as three 32-bit floats in the order x, y, z.
+        const BITANGENT = 0x00000040; //< 12 bytes. Bitangent as three 32-bit floats in the order x, y, z.
+        const TANGENT_FRAME = 0x00000080; //< 12 bytes.
+        const UV32_MASK = 0x0000ff00; //< 8 bytes for each set bit. Contains UV-coords as two 32-bit floats in the order u, v.
+        const UV16_MASK = 0x00ff0000; //< 4 bytes for each set bit. Contains UV-coords as two 16-bit floats in the order u, v.
+        const UNKNOWN1 = 0x01000000; //< 48 bytes. Unknown data.
+        const UNKNOWN2 = 0x02000000; //< 4 bytes. Unknown data.
+        const UNKNOWN3 = 0x04000000; //< 4 bytes. Unknown data.
+        const UNKNOWN4 = 0x08000000; //< 16 bytes. Unknown data.
+        const POSITION_COMPRESSED = 0x10000000; //< 6 bytes. Position as three 16-bit floats in the order x, y, z.
+        const UNKNOWN5 = 0x20000000; //< 12 bytes. Unknown data.
+    }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+/// Layout generation of an archive, told apart by the first header byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveVersion {
+    Gw1, //< Guild Wars 1 `Gw.dat`: the byte is the `3` of `3AN\x1a` and the header is 32 bytes.
+    Gw2, //< Guild Wars 2 release archives, version 0x97 with a 40-byte header.
+    Gw2Beta(u8), //< Earlier GW2 builds, read with the release layout.
+    Unknown(u8), //< Anything else, read with the release layout.
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnetDatHeader {
     pub version: u8,
-    pub identifier: Vec<u8>,
+    pub identifier: [u8; DAT_MAGIC_NUMBER],
     pub header_size: u32,
     pub unknown_field: u32,
     pub chunk_size: u32,
@@ -210,35 +253,164 @@ pub struct AnetDatHeader {
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct AnetMftHeader {
-    pub identifier: Vec<u8>,
+    pub identifier: [u8; MFT_MAGIC_NUMBER],
     pub unknown_field: u64,
     pub num_entries: u32,
     pub unknown_field_2: u64,
 }
-#[derive(Default, Debug, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnetMftEntry {
     pub offset: u64,
     pub size: u32,
     pub compression_flag: u16,
     pub entry_flag: u16,
-    pub counter: u32,
+    pub counter: u32, //< Write sequence number, taken from an archive-wide counter on every write.
     pub crc: u32,
 }
 
+impl AnetCompressionFlags {
+    pub fn is_compressed(self) -> bool {
+        self.contains(AnetCompressionFlags::COMPRESSED)
+    }
+
+    /// Fails for flags carrying bits whose meaning is not known, which the
+    /// reader cannot decode.
+    pub fn check_supported(self) -> io::Result<()> {
+        if self.bits() & !AnetCompressionFlags::all().bits() != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unsupported compression flags {:#06x}.", self.bits()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl ArchiveVersion {
+    pub fn from_version_byte(version: u8) -> Self {
+        match version {
+            GW1_VERSION_BYTE => ArchiveVersion::Gw1,
+            GW2_VERSION_BYTE => ArchiveVersion::Gw2,
+            version if version < GW2_VERSION_BYTE => ArchiveVersion::Gw2Beta(version),
+            version => ArchiveVersion::Unknown(version),
+        }
+    }
+
+    /// Size of the DAT header the version writes.
+    pub fn header_size(self) -> usize {
+        match self {
+            ArchiveVersion::Gw1 => GW1_DAT_HEADER_SIZE,
+            _ => DAT_HEADER_SIZE,
+        }
+    }
+
+    /// Whether the header holds the two words of unknown use found around
+    /// the chunk size and CRC since GW2.
+    pub fn has_unknown_fields(self) -> bool {
+        self != ArchiveVersion::Gw1
+    }
+}
+
+impl AnetDatHeader {
+    pub fn archive_version(&self) -> ArchiveVersion {
+        ArchiveVersion::from_version_byte(self.version)
+    }
+
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u8(self.version)?;
+        writer.write_all(&self.identifier)?;
+        writer.write_u32::<LittleEndian>(self.header_size)?;
+        writer.write_u32::<LittleEndian>(self.unknown_field)?;
+        writer.write_u32::<LittleEndian>(self.chunk_size)?;
+        writer.write_u32::<LittleEndian>(self.crc)?;
+        writer.write_u32::<LittleEndian>(self.unknown_field_2)?;
+        writer.write_u64::<LittleEndian>(self.mft_offset)?;
+        writer.write_u32::<LittleEndian>(self.mft_size)?;
+        writer.write_u32::<LittleEndian>(self.flags)?;
+        Ok(())
+    }
+}
+
+impl AnetMftHeader {
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.identifier)?;
+        writer.write_u64::<LittleEndian>(self.unknown_field)?;
+        writer.write_u32::<LittleEndian>(self.num_entries)?;
+        writer.write_u64::<LittleEndian>(self.unknown_field_2)?;
+        Ok(())
+    }
+}
+
+impl AnetMftEntry {
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u64::<LittleEndian>(self.offset)?;
+        writer.write_u32::<LittleEndian>(self.size)?;
+        writer.write_u16::<LittleEndian>(self.compression_flag)?;
+        writer.write_u16::<LittleEndian>(self.entry_flag)?;
+        writer.write_u32::<LittleEndian>(self.counter)?;
+        writer.write_u32::<LittleEndian>(self.crc)?;
+        Ok(())
+    }
+
+    /// Whether the slot holds a live file. Unused slots are left behind by
+    /// patching and keep stale offsets and sizes.
+    pub fn is_in_use(&self) -> bool {
+        self.entry_flags().contains(AnetMftEntryFlags::IN_USE)
+    }
+
+    /// `entry_flag` as flags. Unknown bits are kept.
+    pub fn entry_flags(&self) -> AnetMftEntryFlags {
+        AnetMftEntryFlags::from_bits_retain(self.entry_flag)
+    }
+
+    /// `compression_flag` as flags. Unknown bits are kept.
+    pub fn compression_flags(&self) -> AnetCompressionFlags {
+        AnetCompressionFlags::from_bits_retain(self.compression_flag)
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compression_flags().is_compressed()
+    }
+}
+
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct AnetIdEntry {
     pub file_id: u32,
     pub base_id: u32,
 }
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+/// A file ID the file ID table assigns to more than one MFT entry. Lookups
+/// by the ID resolve to the last of `indices`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdCollision {
+    pub file_id: u32,
+    pub indices: Vec<usize>, //< MFT indices, in file ID table order.
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AnetFileReference {
-    pub parts: Vec<u8>,
+    pub parts: Vec<u8>, //< Three little-endian u16, the last one is always 0.
+}
+
+impl AnetFileReference {
+    fn part(&self, index: usize) -> Option<u32> {
+        let bytes = self.parts.get(index * 2..index * 2 + 2)?;
+        Some(u32::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+    }
+
+    /// Decodes the referenced file ID. Both parts are offset by 0x100 and
+    /// count from 1, so the low part only spans 0xff00 values rather than a
+    /// full 0x10000. Returns `None` for null or malformed references.
+    pub fn file_id(&self) -> Option<u32> {
+        let low = self.part(0)?.checked_sub(0x100)?;
+        let high = self.part(1)?.checked_sub(0x100)?;
+        Some(0xff00 * high + low + 1)
+    }
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct AnetAtexHeader {
-    pub identifier: Vec<u8>,
+    pub identifier: [u8; 4],
     pub identifier_integer: u32,
     pub format: u8,
     pub format_integer: u32,
@@ -248,16 +420,17 @@ pub struct AnetAtexHeader {
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct AnetPfHeader {
-    pub identifier: Vec<u8>,
+    pub identifier: [u8; 2],
     pub unknown_field: u16,
     pub unknown_field_2: u16,
-    pub file_type: Vec<u8>,
+    pub header_size: u16,
+    pub file_type: [u8; 4],
     pub file_type_integer: u32,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct AnetPfChunkHeader {
-    pub chunk_type: Vec<u8>,
+    pub chunk_type: [u8; 4],
     pub chunk_type_integer: u32,
     pub chunk_data_size: u32,
     pub chunk_version: u16,
@@ -308,121 +481,488 @@ pub struct AnetArchive {
     pub mft_header: AnetMftHeader,
     pub mft_data: Vec<AnetMftEntry>,
     pub mft_index_data: Vec<AnetIdEntry>,
+    pub file_id_table: Vec<AnetIdEntry>,
+    #[serde(skip)]
+    pub file_path: PathBuf,
+    #[serde(skip)]
+    pub id_lookup: HashMap<u32, usize>, //< File ID and base ID to MFT index.
+    #[serde(skip)]
+    pub id_aliases: Vec<Vec<u32>>, //< Every file ID of each MFT entry, ascending; the first is the base ID.
+    #[serde(skip)]
+    pub id_collisions: Vec<IdCollision>,
+    #[serde(skip)]
+    pub(crate) dependency_graph: OnceCell<DependencyGraph>,
+    #[serde(skip)]
+    pub(crate) type_scan: OnceCell<TypeScan>,
+    #[serde(skip)]
+    pub(crate) entry_kinds: RefCell<HashMap<usize, AnetFileType>>, //< Types sniffed one entry at a time.
+    #[serde(skip)]
+    pub(crate) strictness: Strictness,
+    #[serde(skip)]
+    pub(crate) read_retries: u32, //< Extra attempts at an entry that fails to read, see `read_entry_with_retry`.
+    #[serde(skip)]
+    pub(crate) retry_delay: Option<Duration>, //< Wait before the first retry; `DEFAULT_RETRY_DELAY` if unset.
+    #[serde(skip)]
+    pub(crate) counters: Counters,
+    #[serde(skip)]
+    pub(crate) patch_safe: bool, //< Check the archive is unchanged around extraction runs.
+    #[serde(skip)]
+    pub(crate) memory_budget: Option<u64>, //< Most decompressed bytes batch operations hold at once.
+    #[serde(skip)]
+    pub(crate) entry_names: EntryNames,
+    #[serde(skip)]
+    pub(crate) shared_file_stems: OnceCell<HashSet<String>>, //< Lowercased file stems of more than one entry.
+    #[serde(skip)]
+    pub(crate) warnings: RefCell<Vec<ArchiveWarning>>,
+}
+pub(crate) const DAT_MAGIC_NUMBER: usize = 3;
+pub(crate) const MFT_MAGIC_NUMBER: usize = 4;
+pub(crate) const MFT_ENTRY_INDEX_NUM: usize = 1;
+pub(crate) const MFT_ENTRY_SIZE: usize = 24;
+pub(crate) const MFT_HEADER_SIZE: usize = 24;
+pub(crate) const DAT_HEADER_SIZE: usize = 40;
+const GW1_DAT_HEADER_SIZE: usize = 32;
+const GW1_VERSION_BYTE: u8 = b'3';
+const GW2_VERSION_BYTE: u8 = 0x97;
+// Stored bytes read to decompress the head of a compressed entry.
+const COMPRESSED_HEAD_PREFIX: usize = 0x1000;
+// Number of MFT entries parsed between two progress reports.
+const MFT_PROGRESS_INTERVAL: usize = 0x4000;
+// Largest MFT accepted, well above the few tens of MB of a full Gw2.dat.
+const MAX_MFT_SIZE: u32 = 0x1000_0000;
+
+/// Length of the stream behind `reader`, leaving its position unchanged.
+pub(crate) fn stream_len<R: Seek>(reader: &mut R) -> io::Result<u64> {
+    let position = reader.stream_position()?;
+    let length = reader.seek(SeekFrom::End(0))?;
+    if position != length {
+        reader.seek(SeekFrom::Start(position))?;
+    }
+    Ok(length)
+}
+
+/// Fails unless `size` bytes at `offset` lie within a stream of
+/// `stream_len` bytes. `what` names the block in the error.
+pub(crate) fn check_range(what: &str, offset: u64, size: u64, stream_len: u64) -> io::Result<()> {
+    match offset.checked_add(size) {
+        Some(end) if end <= stream_len => Ok(()),
+        _ => {
+            log_warn!(
+                what,
+                offset,
+                size,
+                stream_len,
+                "Block runs past the end of the archive."
+            );
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} at offset {} with {} bytes runs past the end of the archive ({} bytes).",
+                    what, offset, size, stream_len
+                ),
+            ))
+        }
+    }
+}
+
+/// Parses and sanity checks a DAT header from the first
+/// `DAT_HEADER_SIZE` bytes of `data`. Which fields are present depends on
+/// the archive version, see [`ArchiveVersion`]; fields a version lacks are
+/// left at zero.
+pub fn parse_dat_header(data: &[u8]) -> io::Result<AnetDatHeader> {
+    let mut file = Cursor::new(data);
+    let mut dat_header = AnetDatHeader {
+        version: file.read_u8()?,
+        ..Default::default()
+    };
+    file.read_exact(&mut dat_header.identifier)?;
+    let check_magic = [0x41, 0x4e, 0x1a];
+    if dat_header.identifier != check_magic {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not an ArenaNet DAT file: invalid header magic.",
+        ));
+    }
+    let version = ArchiveVersion::from_version_byte(dat_header.version);
+    dat_header.header_size = file.read_u32::<LittleEndian>()?;
+    if version.has_unknown_fields() {
+        dat_header.unknown_field = file.read_u32::<LittleEndian>()?;
+    }
+    dat_header.chunk_size = file.read_u32::<LittleEndian>()?;
+    dat_header.crc = file.read_u32::<LittleEndian>()?;
+    if version.has_unknown_fields() {
+        dat_header.unknown_field_2 = file.read_u32::<LittleEndian>()?;
+    }
+    dat_header.mft_offset = file.read_u64::<LittleEndian>()?;
+    dat_header.mft_size = file.read_u32::<LittleEndian>()?;
+    dat_header.flags = file.read_u32::<LittleEndian>()?;
+    if dat_header.header_size as usize != version.header_size() {
+        log_warn!(
+            header_size = dat_header.header_size,
+            expected = version.header_size(),
+            "Unexpected DAT header size for the archive version."
+        );
+    }
+    if (dat_header.mft_size as usize) < MFT_HEADER_SIZE || dat_header.mft_size > MAX_MFT_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Implausible MFT size of {} bytes.", dat_header.mft_size),
+        ));
+    }
+    log_debug!(
+        version = dat_header.version,
+        mft_offset = dat_header.mft_offset,
+        mft_size = dat_header.mft_size,
+        "Read DAT header."
+    );
+    Ok(dat_header)
+}
+
+/// Parses one `MFT_ENTRY_SIZE` record of the MFT.
+pub(crate) fn parse_mft_entry(raw_entry: &[u8]) -> AnetMftEntry {
+    AnetMftEntry {
+        offset: LittleEndian::read_u64(&raw_entry[0..]),
+        size: LittleEndian::read_u32(&raw_entry[8..]),
+        compression_flag: LittleEndian::read_u16(&raw_entry[12..]),
+        entry_flag: LittleEndian::read_u16(&raw_entry[14..]),
+        counter: LittleEndian::read_u32(&raw_entry[16..]),
+        crc: LittleEndian::read_u32(&raw_entry[20..]),
+    }
+}
+
+/// Parses an MFT block, header included, as pointed at by the DAT header.
+pub fn parse_mft(data: &[u8]) -> io::Result<(AnetMftHeader, Vec<AnetMftEntry>)> {
+    let mut archive = AnetArchive::default();
+    archive
+        .read_mft_header(data)?
+        .read_mft_data(data, &mut no_progress)?;
+    Ok((archive.mft_header, archive.mft_data))
+}
+
+/// Parses the records of a decompressed file ID table.
+pub fn parse_file_id_table(data: &[u8]) -> io::Result<Vec<AnetIdEntry>> {
+    if !data.len().is_multiple_of(size_of::<AnetIdEntry>()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "File ID table does not hold whole ID records.",
+        ));
+    }
+    Ok(data
+        .chunks_exact(size_of::<AnetIdEntry>())
+        .map(|raw_entry| AnetIdEntry {
+            file_id: LittleEndian::read_u32(raw_entry),
+            base_id: LittleEndian::read_u32(&raw_entry[4..]),
+        })
+        .collect())
 }
-const DAT_MAGIC_NUMBER: usize = 3;
-const MFT_MAGIC_NUMBER: usize = 4;
-const MFT_ENTRY_INDEX_NUM: usize = 1;
 
 impl AnetArchive {
-    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+    pub(crate) fn check_dat_extension(file_path: &Path) -> io::Result<()> {
         // Check if the file extension is '.dat'
-        let file_path_str = file_path.as_ref().to_str().unwrap();
+        let file_path_str = file_path.to_string_lossy();
         if !file_path_str.to_lowercase().ends_with(".dat") {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "Invalid file extension. Expected '.dat'.",
             ));
         }
+        Ok(())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        Self::load_from_file_with_progress(file_path, &mut no_progress)
+    }
+
+    /// Like `load_from_file`, reporting the MFT entries parsed so far.
+    pub fn load_from_file_with_progress<P: AsRef<Path>>(
+        file_path: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Self> {
+        Self::load_from_file_with_strictness(file_path, Strictness::Strict, progress)
+    }
+
+    /// Like `load_from_file_with_progress`. In lenient mode, file ID records
+    /// pointing past the MFT are skipped and reported by `warnings`, and the
+    /// archive keeps the mode for later entry reads.
+    pub fn load_from_file_with_strictness<P: AsRef<Path>>(
+        file_path: P,
+        strictness: Strictness,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Self> {
+        Self::check_dat_extension(file_path.as_ref())?;
 
         // Open the file and create a buffered reader.
-        let file = std::fs::File::open(file_path)?;
+        let file = open_archive_file(&file_path)?;
         let mut buf_reader = BufReader::new(file);
 
         // Delegate to load_from_reader for further processing.
-        Self::load_from_reader(&mut buf_reader)
+        let mut gw2_dat_data =
+            Self::load_from_reader_with_strictness(&mut buf_reader, strictness, progress)?;
+        gw2_dat_data.file_path = file_path.as_ref().to_path_buf();
+        gw2_dat_data.load_scan_index();
+        Ok(gw2_dat_data)
     }
 
-    fn load_from_reader<R: Read + Seek>(reader: &mut R) -> io::Result<Self> {
-        let mut gw2_dat_data = AnetArchive::default();
+    /// Parses an archive held in memory, e.g. a file picked in a browser.
+    /// Entries are then read with the `_from` variants of the read methods.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        Self::load_from_reader(&mut Cursor::new(data))
+    }
+
+    /// Parses the header, MFT and file ID table from any seekable reader.
+    /// This is the file-system independent core used by every other loader.
+    pub fn load_from_reader<R: Read + Seek>(reader: &mut R) -> io::Result<Self> {
+        Self::load_from_reader_with_progress(reader, &mut no_progress)
+    }
+
+    pub fn load_from_reader_with_progress<R: Read + Seek>(
+        reader: &mut R,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Self> {
+        Self::load_from_reader_with_strictness(reader, Strictness::Strict, progress)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn load_from_reader_with_strictness<R: Read + Seek>(
+        reader: &mut R,
+        strictness: Strictness,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Self> {
+        let mut gw2_dat_data = AnetArchive {
+            strictness,
+            ..Default::default()
+        };
         gw2_dat_data.read_header(reader)?;
-        gw2_dat_data.read_mft_header(reader)?;
-        gw2_dat_data.read_mft_data(reader)?;
-        gw2_dat_data.read_mft_index(reader)?;
+        let stream_len = stream_len(reader)?;
+        gw2_dat_data.check_mft_range(stream_len)?;
+        let mft_block = gw2_dat_data.read_mft_block(reader)?;
+        gw2_dat_data.read_mft_header(&mft_block)?;
+        gw2_dat_data.read_mft_data(&mft_block, progress)?;
+        gw2_dat_data.read_mft_index(reader, stream_len)?;
+        log_debug!(
+            entries = gw2_dat_data.mft_data.len(),
+            file_ids = gw2_dat_data.file_id_table.len(),
+            "Loaded archive."
+        );
         Ok(gw2_dat_data)
     }
 
-    fn read_header<R: Read + Seek>(&mut self, file: &mut R) -> io::Result<&mut Self> {
-        self.dat_header.version = file.read_u8()?;
-        let mut magic = [0; DAT_MAGIC_NUMBER];
-        file.read_exact(&mut magic)?;
-        self.dat_header.identifier = Vec::from(magic);
-        self.dat_header.header_size = file.read_u32::<LittleEndian>()?;
-        self.dat_header.unknown_field = file.read_u32::<LittleEndian>()?;
-        self.dat_header.chunk_size = file.read_u32::<LittleEndian>()?;
-        self.dat_header.crc = file.read_u32::<LittleEndian>()?;
-        self.dat_header.unknown_field_2 = file.read_u32::<LittleEndian>()?;
-        self.dat_header.mft_offset = file.read_u64::<LittleEndian>()?;
-        self.dat_header.mft_size = file.read_u32::<LittleEndian>()?;
-        self.dat_header.flags = file.read_u32::<LittleEndian>()?;
-        let check_magic = [0x41, 0x4e, 0x1a];
-        if self.dat_header.identifier != check_magic {
-            panic!("Not an GW2 DAT file: invalid header magic");
+    pub(crate) fn read_header<R: Read>(&mut self, file: &mut R) -> io::Result<&mut Self> {
+        let mut header = [0; DAT_HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        self.dat_header = parse_dat_header(&header)?;
+        Ok(self)
+    }
+
+    /// Checks that the MFT the header points at lies within the archive.
+    pub(crate) fn check_mft_range(&self, stream_len: u64) -> io::Result<()> {
+        check_range(
+            "MFT",
+            self.dat_header.mft_offset,
+            u64::from(self.dat_header.mft_size),
+            stream_len,
+        )
+    }
+
+    /// Reads the whole MFT in a single I/O operation.
+    fn read_mft_block<R: Read + Seek>(&self, file: &mut R) -> io::Result<Vec<u8>> {
+        log_trace!(
+            offset = self.dat_header.mft_offset,
+            length = self.dat_header.mft_size,
+            "Seeking to MFT."
+        );
+        file.seek(std::io::SeekFrom::Start(self.dat_header.mft_offset))?;
+        let mut mft_block = vec![0; self.dat_header.mft_size as usize];
+        file.read_exact(&mut mft_block)?;
+        Ok(mft_block)
+    }
+
+    pub(crate) fn read_mft_header(&mut self, mft_block: &[u8]) -> io::Result<&mut Self> {
+        if mft_block.len() < MFT_HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "MFT is too small to hold its header.",
+            ));
         }
+        self.mft_header
+            .identifier
+            .copy_from_slice(&mft_block[..MFT_MAGIC_NUMBER]);
+        self.mft_header.unknown_field = LittleEndian::read_u64(&mft_block[4..]);
+        self.mft_header.num_entries = LittleEndian::read_u32(&mft_block[12..]);
+        self.mft_header.unknown_field_2 = LittleEndian::read_u64(&mft_block[16..]);
         Ok(self)
     }
-    fn read_mft_header<R: Read + Seek>(&mut self, file: &mut R) -> io::Result<&mut Self> {
-        file.seek(std::io::SeekFrom::Start(self.dat_header.mft_offset as u64))?;
 
-        let mut magic = [0; MFT_MAGIC_NUMBER];
-        file.read_exact(&mut magic)?;
-        self.mft_header.identifier = Vec::from(magic);
-        self.mft_header.unknown_field = file.read_u64::<LittleEndian>()?;
-        self.mft_header.num_entries = file.read_u32::<LittleEndian>()?;
-        self.mft_header.unknown_field_2 = file.read_u64::<LittleEndian>()?;
+    pub(crate) fn read_mft_data(
+        &mut self,
+        mft_block: &[u8],
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<&mut Self> {
+        // Only complete entries inside the MFT block are parsed, even if the
+        // header claims more.
+        let raw_entries = mft_block[MFT_HEADER_SIZE..]
+            .chunks_exact(MFT_ENTRY_SIZE)
+            .take(self.mft_header.num_entries as usize);
+        if raw_entries.len() < self.mft_header.num_entries as usize {
+            log_warn!(
+                claimed = self.mft_header.num_entries,
+                present = raw_entries.len(),
+                "MFT header claims more entries than the MFT holds."
+            );
+        }
+        let total = raw_entries.len();
+        self.mft_data.reserve(total);
+        for (done, raw_entry) in raw_entries.enumerate() {
+            self.mft_data.push(parse_mft_entry(raw_entry));
+            if done.is_multiple_of(MFT_PROGRESS_INTERVAL) {
+                progress(Progress {
+                    stage: ProgressStage::LoadMft,
+                    done,
+                    total,
+                });
+            }
+        }
+        progress(Progress {
+            stage: ProgressStage::LoadMft,
+            done: total,
+            total,
+        });
         Ok(self)
     }
 
-    fn read_mft_data<R: Read + Seek>(&mut self, file: &mut R) -> io::Result<&mut Self> {
-        for _ in 0..self.mft_header.num_entries {
-            let mut mft_data = AnetMftEntry::default();
-            mft_data.offset = file.read_u64::<LittleEndian>()?;
-            mft_data.size = file.read_u32::<LittleEndian>()?;
-            mft_data.compression_flag = file.read_u16::<LittleEndian>()?;
-            mft_data.entry_flag = file.read_u16::<LittleEndian>()?;
-            mft_data.counter = file.read_u32::<LittleEndian>()?;
-            mft_data.crc = file.read_u32::<LittleEndian>()?;
-            self.mft_data.push(mft_data);
+    /// The MFT entry holding the file ID table, entry 1 by convention.
+    pub(crate) fn id_table_entry(&self) -> io::Result<&AnetMftEntry> {
+        let id_table_entry = self.mft_data.get(MFT_ENTRY_INDEX_NUM).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "MFT has no file ID table entry.",
+            )
+        })?;
+        if !id_table_entry.is_compressed()
+            && !(id_table_entry.size as usize).is_multiple_of(size_of::<AnetIdEntry>())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File ID table entry does not hold whole ID records.",
+            ));
         }
+        Ok(id_table_entry)
+    }
+
+    fn read_mft_index<R: Read + Seek>(
+        &mut self,
+        file: &mut R,
+        stream_len: u64,
+    ) -> io::Result<&mut Self> {
+        let id_table_entry = self.id_table_entry()?.clone();
+        check_range(
+            "File ID table",
+            id_table_entry.offset,
+            u64::from(id_table_entry.size),
+            stream_len,
+        )?;
+        log_trace!(
+            offset = id_table_entry.offset,
+            length = id_table_entry.size,
+            "Seeking to file ID table."
+        );
+        file.seek(std::io::SeekFrom::Start(id_table_entry.offset))?;
+        let mut id_table = vec![0; id_table_entry.size as usize];
+        file.read_exact(&mut id_table)?;
+        let id_table = Self::decompress_entry(&id_table_entry, id_table)?;
+        self.read_file_id_table(&id_table)?;
         Ok(self)
     }
-    fn read_mft_index<R: Read + Seek>(&mut self, file: &mut R) -> io::Result<&mut Self> {
-        let num_file_id_entries = self.mft_data.get(MFT_ENTRY_INDEX_NUM).unwrap().size as usize
-            / size_of::<AnetIdEntry>() as usize;
-        file.seek(std::io::SeekFrom::Start(
-            self.mft_data.get(MFT_ENTRY_INDEX_NUM).unwrap().offset as u64,
-        ))?;
-        let mut file_id_table: Vec<AnetIdEntry> = Vec::default();
-        for _ in 0..num_file_id_entries {
-            file_id_table.push(AnetIdEntry {
-                file_id: file.read_u32::<LittleEndian>()?,
-                base_id: file.read_u32::<LittleEndian>()?,
-            });
+
+    /// Parses the file ID table stored in MFT entry 1 and builds the index.
+    pub(crate) fn read_file_id_table(&mut self, id_table: &[u8]) -> io::Result<&mut Self> {
+        self.file_id_table = parse_file_id_table(id_table)?;
+        self.build_mft_index()?;
+        Ok(self)
+    }
+
+    /// Fills `mft_index_data`, `id_aliases`, `id_lookup` and
+    /// `id_collisions` from `file_id_table`. The base ID of an entry is its
+    /// lowest file ID and `file_id` the next one; entries can have more,
+    /// which are kept in `id_aliases`. Fails if the table refers to an MFT
+    /// entry that does not exist, unless the archive is lenient.
+    pub(crate) fn build_mft_index(&mut self) -> io::Result<()> {
+        self.mft_index_data.clear();
+        self.id_lookup.clear();
+        self.id_collisions.clear();
+        self.id_aliases = vec![Vec::new(); self.mft_data.len()];
+        self.shared_file_stems.take();
+        let mut owners: HashMap<u32, Vec<usize>> = HashMap::new();
+
+        for position in 0..self.file_id_table.len() {
+            let file_id = self.file_id_table[position].file_id;
+            let entry_index = self.file_id_table[position].base_id as usize;
+            if entry_index >= self.id_aliases.len() {
+                log_warn!(file_id, entry_index, "File ID table entry out of range.");
+                self.tolerate::<()>(
+                    None,
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "File ID {} refers to MFT entry {} past the end of the MFT.",
+                            file_id, entry_index
+                        ),
+                    )),
+                )?;
+                continue;
+            }
+            let indices = owners.entry(file_id).or_default();
+            if !indices.contains(&entry_index) {
+                indices.push(entry_index);
+                self.id_aliases[entry_index].push(file_id);
+            }
+            self.id_lookup.insert(file_id, entry_index);
         }
 
-        for _ in 0..self.mft_data.len() {
+        for aliases in &mut self.id_aliases {
+            aliases.sort_unstable();
             self.mft_index_data.push(AnetIdEntry {
-                file_id: 0,
-                base_id: 0,
+                base_id: aliases.first().copied().unwrap_or(0),
+                file_id: aliases.get(1).copied().unwrap_or(0),
             });
         }
-
-        for i in 0..num_file_id_entries {
-            let entry_index = file_id_table.get(i).unwrap().base_id as usize;
-            let entry = &mut self.mft_index_data[entry_index];
-            if entry.base_id == 0 {
-                entry.base_id = file_id_table.get(i).unwrap().file_id;
-            } else if entry.file_id == 0 {
-                entry.file_id = file_id_table.get(i).unwrap().file_id;
-            }
-
-            if entry.base_id > 0 && entry.file_id > 0 {
-                if entry.base_id > entry.file_id {
-                    swap(&mut entry.base_id, &mut entry.file_id);
-                }
+        for (file_id, indices) in owners {
+            if indices.len() > 1 {
+                log_warn!(file_id, indices = ?indices, "File ID assigned to several MFT entries.");
+                self.id_collisions.push(IdCollision { file_id, indices });
             }
         }
+        self.id_collisions
+            .sort_unstable_by_key(|collision| collision.file_id);
+        Ok(())
+    }
 
-        Ok(self)
+    pub fn version(&self) -> ArchiveVersion {
+        self.dat_header.archive_version()
+    }
+
+    /// Returns the MFT index holding the file with the given file ID or base ID.
+    pub fn index_of(&self, file_id: u32) -> Option<usize> {
+        self.id_lookup.get(&file_id).copied()
+    }
+
+    /// Every file ID of the entry holding `file_id`, itself included, in
+    /// ascending order. Empty if no entry has the ID.
+    pub fn aliases_of(&self, file_id: u32) -> &[u32] {
+        self.index_of(file_id)
+            .and_then(|index| self.id_aliases.get(index))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// File IDs the file ID table assigns to more than one MFT entry, by
+    /// ascending file ID.
+    pub fn id_collisions(&self) -> &[IdCollision] {
+        &self.id_collisions
+    }
+
+    pub fn mft_entry_by_id(&self, file_id: u32) -> Option<&AnetMftEntry> {
+        self.index_of(file_id)
+            .and_then(|index| self.mft_data.get(index))
     }
 
     pub fn get_mft_data<P: AsRef<Path>>(
@@ -430,29 +970,139 @@ impl AnetArchive {
         file_path: P,
         index: usize,
     ) -> io::Result<Vec<u8>> {
-        // Check if the file extension is '.dat'
-        let file_path_str = file_path.as_ref().to_str().unwrap();
-        if !file_path_str.to_lowercase().ends_with(".dat") {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Invalid file extension. Expected '.dat'.",
-            ));
-        }
+        Self::check_dat_extension(file_path.as_ref())?;
 
         // Open the file and create a buffered reader.
-        let file = std::fs::File::open(file_path)?;
+        let file = open_archive_file(file_path)?;
         let mut buf_reader = BufReader::new(file);
 
-        let mft_table = &self.mft_data[index];
+        // Read the stored, possibly compressed, data
+        self.read_entry_data_from(&mut buf_reader, index)
+    }
+    /// Reads up to `length` raw bytes from the start of the entry at `index`
+    /// in the archive this was loaded from.
+    pub fn read_entry_prefix(&self, index: usize, length: usize) -> io::Result<Vec<u8>> {
+        self.shared_reader()?.read_entry_prefix(index, length)
+    }
 
-        // Call mft_read_data to read the compressed data
-        let data = Self::mft_read_data(&mut buf_reader, mft_table.offset, mft_table.size);
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, reader))
+    )]
+    pub fn read_entry_prefix_from<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        index: usize,
+        length: usize,
+    ) -> io::Result<Vec<u8>> {
+        let mft_table = self.mft_data.get(index).ok_or_else(|| {
+            log_warn!(
+                index,
+                entries = self.mft_data.len(),
+                "MFT entry index out of range."
+            );
+            io::Error::new(io::ErrorKind::NotFound, "MFT entry index out of range.")
+        })?;
+        let length = length.min(mft_table.size as usize);
+        check_range(
+            "Entry",
+            mft_table.offset,
+            length as u64,
+            stream_len(reader)?,
+        )?;
+        log_trace!(offset = mft_table.offset, length, "Seeking to entry.");
+        reader.seek(std::io::SeekFrom::Start(mft_table.offset))?;
+        let mut data = vec![0; length];
+        reader.read_exact(&mut data)?;
+        self.counters.record_read(1, length as u64);
         Ok(data)
     }
-    fn mft_read_data(file: &mut BufReader<File>, offset: u64, length: u32) -> Vec<u8> {
-        file.seek(std::io::SeekFrom::Start(offset as u64)).unwrap();
-        let mut data = vec![0; length as usize];
-        file.read_exact(&mut data).unwrap();
-        data
+
+    /// Reads the raw stored bytes of the entry at `index`.
+    pub fn read_entry_data(&self, index: usize) -> io::Result<Vec<u8>> {
+        self.read_entry_prefix(index, usize::MAX)
+    }
+
+    pub fn read_entry_data_from<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        index: usize,
+    ) -> io::Result<Vec<u8>> {
+        self.read_entry_prefix_from(reader, index, usize::MAX)
+    }
+
+    /// Reads the entry at `index`, decompressing it if needed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub fn read_entry(&self, index: usize) -> io::Result<Vec<u8>> {
+        let data = self.read_entry_data(index)?;
+        self.counters.decompress_entry(&self.mft_data[index], data)
+    }
+
+    /// Reads the entry at `index` from `reader`, which must hold the archive
+    /// this MFT was loaded from.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, reader))
+    )]
+    pub fn read_entry_from<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        index: usize,
+    ) -> io::Result<Vec<u8>> {
+        let data = self.read_entry_data_from(reader, index)?;
+        self.counters.decompress_entry(&self.mft_data[index], data)
+    }
+
+    /// Reads the first `length` bytes of the content of the entry at `index`,
+    /// decompressing only as much as needed to identify it.
+    pub fn read_entry_head_from<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        index: usize,
+        length: usize,
+    ) -> io::Result<Vec<u8>> {
+        let mft_entry = self.mft_data.get(index).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "MFT entry index out of range.")
+        })?;
+        mft_entry.compression_flags().check_supported()?;
+        if !mft_entry.is_compressed() {
+            return self.read_entry_prefix_from(reader, index, length);
+        }
+        // The Huffman tables at the start of the stream usually fit in the
+        // prefix; entries whose tables do not are read in full.
+        let length = u32::try_from(length).unwrap_or(u32::MAX);
+        let prefix = self.read_entry_prefix_from(reader, index, COMPRESSED_HEAD_PREFIX)?;
+        if let Ok(head) = anet_inflate(&prefix, Some(length)) {
+            return Ok(head);
+        }
+        let data = self.read_entry_data_from(reader, index)?;
+        Ok(anet_inflate(&data, Some(length))?)
+    }
+
+    /// Turns the stored bytes of `entry` into its content.
+    pub(crate) fn decompress_entry(entry: &AnetMftEntry, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        entry.compression_flags().check_supported()?;
+        if !entry.is_compressed() {
+            return Ok(data);
+        }
+        let content = anet_inflate(&data, None).inspect_err(|_error| {
+            log_warn!(offset = entry.offset, error = %_error, "Decompression failed.");
+        })?;
+        log_trace!(
+            compressed = data.len(),
+            decompressed = content.len(),
+            "Decompressed entry."
+        );
+        Ok(content)
+    }
+
+    /// Reads only the DAT header of the archive at `file_path`, which is cheap
+    /// enough to poll for changes.
+    pub fn read_dat_header_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<AnetDatHeader> {
+        let file = open_archive_file(file_path)?;
+        let mut buf_reader = BufReader::new(file);
+        let mut gw2_dat_data = AnetArchive::default();
+        gw2_dat_data.read_header(&mut buf_reader)?;
+        Ok(gw2_dat_data.dat_header)
     }
 }
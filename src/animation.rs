@@ -0,0 +1,287 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const ANIC_FILE_TYPE: &[u8; 4] = b"anic";
+const EMOC_FILE_TYPE: &[u8; 4] = b"emoc";
+const BGFS_CHUNK: &[u8; 4] = b"BGFS";
+const EMOT_CHUNK: &[u8; 4] = b"EMOT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnetAnimationTrackType {
+    Translation, //< Key values are x, y, z.
+    Rotation,    //< Key values are a quaternion x, y, z, w.
+    Scale,       //< Key values are x, y, z.
+    Unknown(u32),
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnetAnimationKey {
+    pub time: f32, //< Seconds from the start of the sequence.
+    pub value: [f32; 4],
+}
+
+/// Keys animating one property of one bone.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetAnimationTrack {
+    pub bone_token: u64, //< Token of the skeleton bone the track moves.
+    pub track_type: u32,
+    pub keys: Vec<AnetAnimationKey>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetAnimationSequence {
+    pub token: u64, //< Name token of the sequence, e.g. an idle or an attack.
+    pub duration: f32,
+    pub flags: u32,
+    pub tracks: Vec<AnetAnimationTrack>,
+}
+
+/// A parsed `anic` file: the animation sequences of one skeleton.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetAnimationSequences {
+    pub model_token: u64, //< Token of the skeleton the sequences are made for.
+    pub sequences: Vec<AnetAnimationSequence>,
+}
+
+/// The animation an emote plays on one model, e.g. one race and gender.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEmoteVariant {
+    pub model_token: u64,
+    pub sequence_token: u64, //< Sequence in `animation_file` to play.
+    pub animation_file: Option<AnetFileReference>, //< The `anic` file holding the sequence.
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEmote {
+    pub token: u64,
+    pub command: String, //< Chat command, e.g. `wave`.
+    pub variants: Vec<AnetEmoteVariant>,
+}
+
+/// A parsed `emoc` file: emotes and the animations they play per model.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEmoteAnimations {
+    pub emotes: Vec<AnetEmote>,
+}
+
+impl AnetAnimationTrack {
+    pub fn kind(&self) -> AnetAnimationTrackType {
+        match self.track_type {
+            0 => AnetAnimationTrackType::Translation,
+            1 => AnetAnimationTrackType::Rotation,
+            2 => AnetAnimationTrackType::Scale,
+            other => AnetAnimationTrackType::Unknown(other),
+        }
+    }
+
+    /// Value at `time`, interpolated linearly between the surrounding keys
+    /// and held at the first and last key outside of them. Rotations are
+    /// renormalized after interpolating.
+    pub fn sample(&self, time: f32) -> Option<[f32; 4]> {
+        let next = self.keys.iter().position(|key| key.time > time);
+        let value = match next {
+            None => self.keys.last()?.value,
+            Some(0) => self.keys[0].value,
+            Some(next) => {
+                let (from, to) = (&self.keys[next - 1], &self.keys[next]);
+                let span = to.time - from.time;
+                let t = if span > 0.0 {
+                    (time - from.time) / span
+                } else {
+                    0.0
+                };
+                let mut value = [0.0; 4];
+                for (component, value) in value.iter_mut().enumerate() {
+                    *value =
+                        from.value[component] + (to.value[component] - from.value[component]) * t;
+                }
+                value
+            }
+        };
+        if self.kind() != AnetAnimationTrackType::Rotation {
+            return Some(value);
+        }
+        let length = value
+            .iter()
+            .map(|component| component * component)
+            .sum::<f32>()
+            .sqrt();
+        if length > 0.0 {
+            Some(value.map(|component| component / length))
+        } else {
+            Some(value)
+        }
+    }
+}
+
+impl AnetAnimationSequence {
+    /// Tracks moving the given bone.
+    pub fn bone_tracks(&self, bone_token: u64) -> impl Iterator<Item = &AnetAnimationTrack> {
+        self.tracks
+            .iter()
+            .filter(move |track| track.bone_token == bone_token)
+    }
+}
+
+impl AnetAnimationSequences {
+    /// Parses the `BGFS` chunk of a decompressed PF `anic` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != ANIC_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not an anic file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(BGFS_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing BGFS chunk."))?;
+
+        let _scope = trace::scope("BGFS");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut animation = AnetAnimationSequences {
+            model_token: reader.field("model_token").read_u64()?,
+            ..Default::default()
+        };
+        let (count, position) = reader.field("sequences").read_array()?;
+        let mut sequence_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            let mut sequence = AnetAnimationSequence {
+                token: sequence_reader.field("token").read_u64()?,
+                duration: sequence_reader.field("duration").read_f32()?,
+                flags: sequence_reader.field("flags").read_u32()?,
+                ..Default::default()
+            };
+            let (track_count, track_position) = sequence_reader.field("tracks").read_array()?;
+            let mut track_reader = PfReader::at(&chunk.data, track_position);
+            for _ in 0..track_count {
+                let mut track = AnetAnimationTrack {
+                    bone_token: track_reader.field("bone_token").read_u64()?,
+                    track_type: track_reader.field("track_type").read_u32()?,
+                    ..Default::default()
+                };
+                let (key_count, key_position) = track_reader.field("keys").read_array()?;
+                let mut key_reader = PfReader::at(&chunk.data, key_position);
+                for _ in 0..key_count {
+                    track.keys.push(AnetAnimationKey {
+                        time: key_reader.field("time").read_f32()?,
+                        value: [
+                            key_reader.read_f32()?,
+                            key_reader.read_f32()?,
+                            key_reader.read_f32()?,
+                            key_reader.read_f32()?,
+                        ],
+                    });
+                }
+                sequence.tracks.push(track);
+            }
+            animation.sequences.push(sequence);
+        }
+        Ok(animation)
+    }
+
+    pub fn sequence(&self, token: u64) -> Option<&AnetAnimationSequence> {
+        self.sequences
+            .iter()
+            .find(|sequence| sequence.token == token)
+    }
+}
+
+impl AnetEmoteAnimations {
+    /// Parses the `EMOT` chunk of a decompressed PF `emoc` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != EMOC_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not an emoc file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(EMOT_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing EMOT chunk."))?;
+
+        let _scope = trace::scope("EMOT");
+        let mut emote_animations = AnetEmoteAnimations::default();
+        let mut reader = PfReader::new(&chunk.data);
+        let (count, position) = reader.field("emotes").read_array()?;
+        let mut emote_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            let mut emote = AnetEmote {
+                token: emote_reader.field("token").read_u64()?,
+                command: emote_reader.field("command").read_string()?,
+                ..Default::default()
+            };
+            let (variant_count, variant_position) = emote_reader.field("variants").read_array()?;
+            let mut variant_reader = PfReader::at(&chunk.data, variant_position);
+            for _ in 0..variant_count {
+                emote.variants.push(AnetEmoteVariant {
+                    model_token: variant_reader.field("model_token").read_u64()?,
+                    sequence_token: variant_reader.field("sequence_token").read_u64()?,
+                    animation_file: variant_reader
+                        .field("animation_file")
+                        .read_file_reference()?,
+                });
+            }
+            emote_animations.emotes.push(emote);
+        }
+        Ok(emote_animations)
+    }
+
+    pub fn emote(&self, command: &str) -> Option<&AnetEmote> {
+        self.emotes
+            .iter()
+            .find(|emote| emote.command.eq_ignore_ascii_case(command))
+    }
+}
+
+impl AnetEmote {
+    /// The variant played on the given model.
+    pub fn variant_for(&self, model_token: u64) -> Option<&AnetEmoteVariant> {
+        self.variants
+            .iter()
+            .find(|variant| variant.model_token == model_token)
+    }
+}
+
+impl AnetEmoteVariant {
+    pub fn animation_file_id(&self) -> Option<u32> {
+        self.animation_file.as_ref()?.file_id()
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the `anic` file with the given file ID.
+    pub fn read_animation_sequences(&self, file_id: u32) -> io::Result<AnetAnimationSequences> {
+        AnetAnimationSequences::load_from_bytes(&self.read_file(file_id)?)
+    }
+
+    /// Reads and parses the `emoc` file with the given file ID.
+    pub fn read_emote_animations(&self, file_id: u32) -> io::Result<AnetEmoteAnimations> {
+        AnetEmoteAnimations::load_from_bytes(&self.read_file(file_id)?)
+    }
+
+    /// Resolves the sequence an emote variant plays from its `anic` file.
+    pub fn read_emote_sequence(
+        &self,
+        variant: &AnetEmoteVariant,
+    ) -> io::Result<Option<AnetAnimationSequence>> {
+        let file_id = variant.animation_file_id().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Emote variant has no animation file.",
+            )
+        })?;
+        let mut animation = self.read_animation_sequences(file_id)?;
+        Ok(animation
+            .sequences
+            .iter()
+            .position(|sequence| sequence.token == variant.sequence_token)
+            .map(|index| animation.sequences.swap_remove(index)))
+    }
+}
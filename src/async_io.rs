@@ -0,0 +1,87 @@
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+};
+
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+    task,
+};
+
+use crate::anet_archive::{check_range, parse_dat_header, AnetArchive, DAT_HEADER_SIZE};
+use crate::progress::no_progress;
+
+async fn read_at(file: &mut File, offset: u64, length: usize) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut data = vec![0; length];
+    file.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+// The async API only moves the file I/O onto `tokio::fs`; parsing reuses the
+// synchronous code on in-memory buffers.
+impl AnetArchive {
+    pub async fn load_from_file_async<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        Self::check_dat_extension(file_path.as_ref())?;
+        let mut file = File::open(&file_path).await?;
+
+        let mut archive = AnetArchive::default();
+        let header = read_at(&mut file, 0, DAT_HEADER_SIZE).await?;
+        archive.dat_header = parse_dat_header(&header)?;
+        let stream_len = file.metadata().await?.len();
+        archive.check_mft_range(stream_len)?;
+
+        let mft_block = read_at(
+            &mut file,
+            archive.dat_header.mft_offset,
+            archive.dat_header.mft_size as usize,
+        )
+        .await?;
+        archive.read_mft_header(&mft_block)?;
+        archive.read_mft_data(&mft_block, &mut no_progress)?;
+
+        let id_table_entry = archive.id_table_entry()?.clone();
+        check_range(
+            "File ID table",
+            id_table_entry.offset,
+            u64::from(id_table_entry.size),
+            stream_len,
+        )?;
+        let id_table = read_at(
+            &mut file,
+            id_table_entry.offset,
+            id_table_entry.size as usize,
+        )
+        .await?;
+        let id_table = AnetArchive::decompress_entry(&id_table_entry, id_table)?;
+        archive.read_file_id_table(&id_table)?;
+        archive.file_path = file_path.as_ref().to_path_buf();
+        Ok(archive)
+    }
+
+    /// Reads the raw stored bytes of the entry at `index`.
+    pub async fn read_entry_data_async(&self, index: usize) -> io::Result<Vec<u8>> {
+        let mft_entry = self.mft_data.get(index).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "MFT entry index out of range.")
+        })?;
+        let mut file = File::open(&self.file_path).await?;
+        check_range(
+            "Entry",
+            mft_entry.offset,
+            u64::from(mft_entry.size),
+            file.metadata().await?.len(),
+        )?;
+        read_at(&mut file, mft_entry.offset, mft_entry.size as usize).await
+    }
+
+    /// Reads the entry at `index`, decompressing it on the blocking thread
+    /// pool so large entries do not stall the runtime.
+    pub async fn read_entry_async(&self, index: usize) -> io::Result<Vec<u8>> {
+        let data = self.read_entry_data_async(index).await?;
+        let mft_entry = self.mft_data[index].clone();
+        task::spawn_blocking(move || AnetArchive::decompress_entry(&mft_entry, data))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
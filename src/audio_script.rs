@@ -0,0 +1,142 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const AMSP_FILE_TYPE: &[u8; 4] = b"AMSP";
+const AMSP_CHUNK: &[u8; 4] = b"AMSP";
+
+/// A sound bank the script plays sounds from.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetAudioSoundBank {
+    pub name: String,
+    pub file_reference: Option<AnetFileReference>,
+}
+
+/// A condition under which an event plays, e.g. a game state change or a
+/// named game event, and what it plays.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetAudioTrigger {
+    pub trigger_type: u32,
+    pub name: String,
+    pub bank_index: Option<usize>, //< Index into the sound banks of the script.
+    pub sound_index: u32,          //< Sound within the bank.
+    pub delay: f32,                //< Seconds between the trigger firing and the sound playing.
+    pub flags: u32,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetAudioEvent {
+    pub name: String,
+    pub category: u32,
+    pub volume: f32,
+    pub triggers: Vec<AnetAudioTrigger>,
+}
+
+/// A parsed `AMSP` file: the events of an audio script and the sound banks
+/// their triggers play from.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetAudioScript {
+    pub sound_banks: Vec<AnetAudioSoundBank>,
+    pub events: Vec<AnetAudioEvent>,
+}
+
+impl AnetAudioScript {
+    /// Parses the `AMSP` chunk of a decompressed PF `AMSP` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != AMSP_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not an AMSP file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(AMSP_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing AMSP chunk."))?;
+
+        let _scope = trace::scope("AMSP");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut script = AnetAudioScript::default();
+
+        let (count, position) = reader.field("sound_banks").read_array()?;
+        let mut bank_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            script.sound_banks.push(AnetAudioSoundBank {
+                name: bank_reader.field("name").read_string()?,
+                file_reference: bank_reader.field("file_reference").read_file_reference()?,
+            });
+        }
+
+        let (count, position) = reader.field("events").read_array()?;
+        let mut event_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            let mut event = AnetAudioEvent {
+                name: event_reader.field("name").read_string()?,
+                category: event_reader.field("category").read_u32()?,
+                volume: event_reader.field("volume").read_f32()?,
+                ..Default::default()
+            };
+            let (trigger_count, trigger_position) = event_reader.field("triggers").read_array()?;
+            let mut trigger_reader = PfReader::at(&chunk.data, trigger_position);
+            for _ in 0..trigger_count {
+                event.triggers.push(AnetAudioTrigger {
+                    trigger_type: trigger_reader.field("trigger_type").read_u32()?,
+                    name: trigger_reader.field("name").read_string()?,
+                    bank_index: usize::try_from(trigger_reader.field("bank_index").read_i32()?)
+                        .ok(),
+                    sound_index: trigger_reader.field("sound_index").read_u32()?,
+                    delay: trigger_reader.field("delay").read_f32()?,
+                    flags: trigger_reader.field("flags").read_u32()?,
+                });
+            }
+            script.events.push(event);
+        }
+        Ok(script)
+    }
+
+    pub fn event(&self, name: &str) -> Option<&AnetAudioEvent> {
+        self.events.iter().find(|event| event.name == name)
+    }
+
+    /// The sound bank a trigger plays from, if any.
+    pub fn trigger_bank(&self, trigger: &AnetAudioTrigger) -> Option<&AnetAudioSoundBank> {
+        self.sound_banks.get(trigger.bank_index?)
+    }
+
+    /// File IDs of the sound banks the triggers of `event` play from, in
+    /// trigger order without repeats.
+    pub fn event_sound_bank_ids(&self, event: &AnetAudioEvent) -> Vec<u32> {
+        let mut file_ids = Vec::new();
+        for trigger in &event.triggers {
+            let Some(file_id) = self
+                .trigger_bank(trigger)
+                .and_then(|bank| bank.file_reference.as_ref()?.file_id())
+            else {
+                continue;
+            };
+            if !file_ids.contains(&file_id) {
+                file_ids.push(file_id);
+            }
+        }
+        file_ids
+    }
+
+    /// File IDs of every sound bank, in bank order.
+    pub fn sound_bank_ids(&self) -> Vec<u32> {
+        self.sound_banks
+            .iter()
+            .filter_map(|bank| bank.file_reference.as_ref()?.file_id())
+            .collect()
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the `AMSP` file with the given file ID.
+    pub fn read_audio_script(&self, file_id: u32) -> io::Result<AnetAudioScript> {
+        AnetAudioScript::load_from_bytes(&self.read_file(file_id)?)
+    }
+}
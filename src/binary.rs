@@ -0,0 +1,160 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+
+const MZ_MAGIC: &[u8; 2] = b"MZ";
+const PE_MAGIC: &[u8; 4] = b"PE\0\0";
+// Offset of the DOS header field pointing at the PE signature.
+const PE_OFFSET_FIELD: usize = 0x3c;
+const COFF_HEADER_SIZE: usize = 20;
+const OPTIONAL_HEADER_MAGIC_PE32: u16 = 0x10b;
+const OPTIONAL_HEADER_MAGIC_PE32_PLUS: u16 = 0x20b;
+const IMAGE_FILE_DLL: u16 = 0x2000;
+// Appended to extracted binaries so they cannot be launched by accident.
+const SAFE_EXTENSION: &str = "bin";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeKind {
+    Exe,
+    Dll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeMachine {
+    X86,
+    X64,
+    Arm64,
+    Other(u16), //< Raw `IMAGE_FILE_MACHINE_*` value.
+}
+
+/// What the PE header of an `MZ` entry says about the binary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnetPeInfo {
+    pub kind: PeKind,
+    pub machine: PeMachine,
+    pub timestamp: u32, //< Link time in seconds since the Unix epoch.
+    pub section_count: u16,
+    pub characteristics: u16,
+    pub is_pe32_plus: bool, //< 64-bit optional header.
+}
+
+impl PeMachine {
+    fn from_raw(machine: u16) -> Self {
+        match machine {
+            0x014c => PeMachine::X86,
+            0x8664 => PeMachine::X64,
+            0xaa64 => PeMachine::Arm64,
+            _ => PeMachine::Other(machine),
+        }
+    }
+}
+
+impl AnetPeInfo {
+    /// Parses the DOS stub pointer, PE signature and COFF header of `data`.
+    /// Plain DOS programs without a PE header are rejected.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if !data.starts_with(MZ_MAGIC) || data.len() < PE_OFFSET_FIELD + 4 {
+            return Err(invalid("Not an MZ executable."));
+        }
+        let pe_offset = LittleEndian::read_u32(&data[PE_OFFSET_FIELD..]) as usize;
+        let coff_offset = pe_offset
+            .checked_add(PE_MAGIC.len())
+            .filter(|&offset| data.get(pe_offset..offset) == Some(PE_MAGIC))
+            .ok_or_else(|| invalid("MZ executable has no PE header."))?;
+        let coff_header = data
+            .get(coff_offset..coff_offset + COFF_HEADER_SIZE)
+            .ok_or_else(|| invalid("PE header is truncated."))?;
+
+        let characteristics = LittleEndian::read_u16(&coff_header[18..]);
+        let optional_header_size = LittleEndian::read_u16(&coff_header[16..]) as usize;
+        let optional_header = coff_offset + COFF_HEADER_SIZE;
+        let optional_magic = data
+            .get(optional_header..optional_header + 2)
+            .filter(|_| optional_header_size >= 2)
+            .map(LittleEndian::read_u16);
+        if let Some(_magic) = optional_magic.filter(|&magic| {
+            magic != OPTIONAL_HEADER_MAGIC_PE32 && magic != OPTIONAL_HEADER_MAGIC_PE32_PLUS
+        }) {
+            log_warn!(magic = _magic, "Unknown PE optional header magic.");
+        }
+        Ok(AnetPeInfo {
+            kind: if characteristics & IMAGE_FILE_DLL != 0 {
+                PeKind::Dll
+            } else {
+                PeKind::Exe
+            },
+            machine: PeMachine::from_raw(LittleEndian::read_u16(coff_header)),
+            timestamp: LittleEndian::read_u32(&coff_header[4..]),
+            section_count: LittleEndian::read_u16(&coff_header[2..]),
+            characteristics,
+            is_pe32_plus: optional_magic == Some(OPTIONAL_HEADER_MAGIC_PE32_PLUS),
+        })
+    }
+
+    pub fn file_type(&self) -> AnetFileType {
+        match self.kind {
+            PeKind::Exe => AnetFileType::AnftExe,
+            PeKind::Dll => AnetFileType::AnftDll,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self.kind {
+            PeKind::Exe => "exe",
+            PeKind::Dll => "dll",
+        }
+    }
+}
+
+impl AnetArchive {
+    /// Reads the PE header of the binary with the given file ID.
+    pub fn read_pe_info(&self, file_id: u32) -> io::Result<AnetPeInfo> {
+        AnetPeInfo::load_from_bytes(&self.read_file(file_id)?)
+    }
+
+    /// Every `MZ` entry with its PE header. Entries whose header cannot be
+    /// parsed are left out.
+    pub fn binaries(&self) -> io::Result<Vec<(usize, AnetPeInfo)>> {
+        let mut binaries = Vec::new();
+        for entry in self.find_by_type(AnetFileType::AnftExe)? {
+            let data = self.read_entry(entry.index())?;
+            match AnetPeInfo::load_from_bytes(&data) {
+                Ok(pe_info) => binaries.push((entry.index(), pe_info)),
+                Err(_error) => {
+                    log_warn!(index = entry.index(), error = %_error, "Unreadable PE header.");
+                }
+            }
+        }
+        Ok(binaries)
+    }
+
+    /// Writes the binary at `index` into `directory` as
+    /// `<base_id>.<exe|dll>.bin`, so it cannot be run by opening it, and
+    /// returns the written path. Fails unless the entry has a PE header.
+    pub fn extract_binary<P: AsRef<Path>>(
+        &self,
+        index: usize,
+        directory: P,
+    ) -> io::Result<PathBuf> {
+        let data = self.read_entry(index)?;
+        let pe_info = AnetPeInfo::load_from_bytes(&data)?;
+        fs::create_dir_all(&directory)?;
+        let file_name = Path::new(&self.entry_file_name(index)).with_extension(format!(
+            "{}.{}",
+            pe_info.extension(),
+            SAFE_EXTENSION
+        ));
+        let file_path = directory.as_ref().join(file_name);
+        fs::write(&file_path, &data)?;
+        Ok(file_path)
+    }
+}
@@ -0,0 +1,160 @@
+use std::io;
+
+use flate2::Crc;
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{check_range, AnetArchive};
+use crate::inflate::CRC_INTERVAL_WORDS;
+use crate::reader::{open_archive_file, read_exact_at};
+
+// Compressed streams are split into 64 KiB blocks whose last word is the
+// CRC-32 of the words before it.
+pub const CRC_BLOCK_SIZE: usize = CRC_INTERVAL_WORDS * 4;
+const CRC_BLOCK_PAYLOAD_SIZE: usize = CRC_BLOCK_SIZE - 4;
+// Archives written before the header carried a chunk size are read in
+// blocks of this size.
+const DEFAULT_CHUNK_SIZE: u64 = 0x200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCrcMismatch {
+    pub block: usize,
+    pub stored: u32,
+    pub computed: u32,
+}
+
+/// Outcome of checking the block CRCs of a compressed entry.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCheck {
+    pub blocks: usize, //< Complete blocks checked. A trailing partial block carries no CRC.
+    pub mismatches: Vec<BlockCrcMismatch>,
+}
+
+impl BlockCheck {
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Checks block CRCs of a compressed stream fed to it in pieces of any
+/// size, so large entries can be verified without holding them in memory.
+#[derive(Default)]
+pub struct BlockCrcChecker {
+    crc: Crc,
+    position: usize, //< Position in the current block.
+    stored: [u8; 4],
+    check: BlockCheck,
+}
+
+impl BlockCrcChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.position < CRC_BLOCK_PAYLOAD_SIZE {
+                let length = data.len().min(CRC_BLOCK_PAYLOAD_SIZE - self.position);
+                self.crc.update(&data[..length]);
+                self.position += length;
+                data = &data[length..];
+                continue;
+            }
+            let stored_position = self.position - CRC_BLOCK_PAYLOAD_SIZE;
+            let length = data.len().min(4 - stored_position);
+            self.stored[stored_position..stored_position + length].copy_from_slice(&data[..length]);
+            self.position += length;
+            data = &data[length..];
+            if self.position == CRC_BLOCK_SIZE {
+                self.finish_block();
+            }
+        }
+    }
+
+    fn finish_block(&mut self) {
+        let stored = u32::from_le_bytes(self.stored);
+        let computed = self.crc.sum();
+        if stored != computed {
+            self.check.mismatches.push(BlockCrcMismatch {
+                block: self.check.blocks,
+                stored,
+                computed,
+            });
+        }
+        self.check.blocks += 1;
+        self.crc.reset();
+        self.position = 0;
+    }
+
+    pub fn finish(self) -> BlockCheck {
+        self.check
+    }
+}
+
+/// Checks every complete block of the compressed stream `data`.
+pub fn check_block_crcs(data: &[u8]) -> BlockCheck {
+    let mut checker = BlockCrcChecker::new();
+    checker.update(data);
+    checker.finish()
+}
+
+impl AnetArchive {
+    /// Block size data is laid out in, from the DAT header.
+    pub fn chunk_size(&self) -> u64 {
+        match self.dat_header.chunk_size {
+            0 => DEFAULT_CHUNK_SIZE,
+            chunk_size => u64::from(chunk_size),
+        }
+    }
+
+    /// Reads the stored bytes of the entry at `index` one archive chunk at a
+    /// time, cut at the chunk boundaries of the archive, and hands each
+    /// piece to `visit` in order.
+    pub fn read_entry_chunks(
+        &self,
+        index: usize,
+        visit: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mft_entry = self.mft_data.get(index).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "MFT entry index out of range.")
+        })?;
+        let file = open_archive_file(&self.file_path)?;
+        check_range(
+            "Entry",
+            mft_entry.offset,
+            u64::from(mft_entry.size),
+            file.metadata()?.len(),
+        )?;
+        // In range of the file, so the sum no longer overflows.
+        let end = mft_entry.offset + u64::from(mft_entry.size);
+
+        let chunk_size = self.chunk_size();
+        let mut buffer = vec![0; chunk_size as usize];
+        let mut position = mft_entry.offset;
+        while position < end {
+            let chunk_end = (position / chunk_size + 1) * chunk_size;
+            let length = (chunk_end.min(end) - position) as usize;
+            read_exact_at(&file, &mut buffer[..length], position)?;
+            visit(&buffer[..length])?;
+            position += length as u64;
+        }
+        Ok(())
+    }
+
+    /// Checks the block CRCs of the entry at `index` chunk by chunk. An
+    /// uncompressed entry has no blocks.
+    pub fn verify_entry_blocks(&self, index: usize) -> io::Result<BlockCheck> {
+        let mut checker = BlockCrcChecker::new();
+        if self
+            .mft_data
+            .get(index)
+            .is_some_and(|mft_entry| !mft_entry.is_compressed())
+        {
+            return Ok(checker.finish());
+        }
+        self.read_entry_chunks(index, &mut |chunk| {
+            checker.update(chunk);
+            Ok(())
+        })?;
+        Ok(checker.finish())
+    }
+}
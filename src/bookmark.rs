@@ -0,0 +1,103 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+
+const BOOKMARK_DIR_VARIABLE: &str = "LEAFADOO_BOOKMARKS";
+const DEFAULT_BOOKMARK_DIR: &str = "bookmarks";
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub file_id: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub comment: String,
+}
+
+/// A named, shareable list of file IDs, stored as JSON.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkSet {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl BookmarkSet {
+    pub fn new(name: &str) -> Self {
+        BookmarkSet {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(file_path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        if let Some(parent) = file_path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Resolves a bookmark set given either a path to a JSON file or the
+    /// name of a set in the bookmark directory.
+    pub fn resolve_path(name_or_path: &str) -> PathBuf {
+        let path = Path::new(name_or_path);
+        if path.extension().is_some() || path.components().count() > 1 {
+            return path.to_path_buf();
+        }
+        let directory = env::var_os(BOOKMARK_DIR_VARIABLE)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BOOKMARK_DIR));
+        directory.join(format!("{}.json", name_or_path))
+    }
+
+    pub fn load(name_or_path: &str) -> io::Result<Self> {
+        Self::load_from_file(Self::resolve_path(name_or_path))
+    }
+
+    pub fn add(&mut self, file_id: u32, comment: &str) {
+        match self.bookmarks.iter_mut().find(|b| b.file_id == file_id) {
+            Some(bookmark) => bookmark.comment = comment.to_string(),
+            None => self.bookmarks.push(Bookmark {
+                file_id,
+                comment: comment.to_string(),
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, file_id: u32) -> bool {
+        let length = self.bookmarks.len();
+        self.bookmarks
+            .retain(|bookmark| bookmark.file_id != file_id);
+        self.bookmarks.len() != length
+    }
+
+    pub fn contains(&self, file_id: u32) -> bool {
+        self.bookmarks
+            .iter()
+            .any(|bookmark| bookmark.file_id == file_id)
+    }
+
+    pub fn file_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.bookmarks.iter().map(|bookmark| bookmark.file_id)
+    }
+
+    /// MFT indices of the bookmarked files present in `archive`.
+    pub fn indices(&self, archive: &AnetArchive) -> Vec<usize> {
+        self.file_ids()
+            .filter_map(|file_id| archive.index_of(file_id))
+            .collect()
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    io,
+    sync::{Condvar, Mutex, PoisonError},
+};
+
+use crate::anet_archive::AnetArchive;
+use crate::reader::ArchiveReader;
+
+/// A byte budget shared by the threads of a batch operation. Producers
+/// acquire the size of the data they are about to load and block while the
+/// budget is spent, until consumers drop the permits of the data they are
+/// done with.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: u64,
+    in_use: Mutex<u64>,
+    released: Condvar,
+}
+
+/// Bytes held against a `MemoryBudget`, given back when dropped.
+#[derive(Debug)]
+pub struct BudgetPermit<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: u64) -> Self {
+        MemoryBudget {
+            limit,
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Bytes currently held by permits.
+    pub fn in_use(&self) -> u64 {
+        *self.in_use.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits until `bytes` fit in the budget and holds them until the
+    /// permit is dropped. A request larger than the whole budget is capped
+    /// to it, so it waits for every other permit and then runs alone.
+    pub fn acquire(&self, bytes: u64) -> BudgetPermit<'_> {
+        let bytes = bytes.min(self.limit);
+        let mut in_use = self.in_use.lock().unwrap_or_else(PoisonError::into_inner);
+        while *in_use + bytes > self.limit {
+            in_use = self
+                .released
+                .wait(in_use)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *in_use += bytes;
+        BudgetPermit {
+            budget: self,
+            bytes,
+        }
+    }
+
+    /// Acquires the decompressed size of the entry at `index`. An unlimited
+    /// budget hands out empty permits without reading the size.
+    pub(crate) fn acquire_entry(
+        &self,
+        reader: &ArchiveReader,
+        index: usize,
+    ) -> io::Result<BudgetPermit<'_>> {
+        if self.limit == u64::MAX {
+            return Ok(self.acquire(0));
+        }
+        Ok(self.acquire(u64::from(reader.uncompressed_size(index)?)))
+    }
+}
+
+impl BudgetPermit<'_> {
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for BudgetPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_use = self
+            .budget
+            .in_use
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *in_use -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+impl AnetArchive {
+    pub fn memory_budget(&self) -> Option<u64> {
+        self.memory_budget
+    }
+
+    /// Caps the decompressed bytes that parallel extraction and hashing
+    /// hold at once; reading threads wait for memory to be freed rather than
+    /// load more. `None`, the default, leaves them unbounded.
+    pub fn set_memory_budget(&mut self, memory_budget: Option<u64>) {
+        self.memory_budget = memory_budget;
+    }
+
+    /// A budget for one batch operation, unlimited without a memory budget.
+    pub(crate) fn batch_budget(&self) -> MemoryBudget {
+        MemoryBudget::new(self.memory_budget.unwrap_or(u64::MAX))
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use crate::anet_archive::{
+    AnetCompressionFlags, AnetDatHeader, AnetMftEntry, AnetMftEntryFlags, AnetMftHeader,
+    DAT_HEADER_SIZE, MFT_ENTRY_SIZE, MFT_HEADER_SIZE,
+};
+use crate::deflate::{anet_deflate, crc32};
+
+const DAT_VERSION: u8 = 0x97;
+const DAT_IDENTIFIER: [u8; 3] = [0x41, 0x4e, 0x1a];
+const MFT_IDENTIFIER: [u8; 4] = *b"Mft\x1a";
+// Every block starts on a multiple of the chunk size recorded in the header.
+const DAT_CHUNK_SIZE: u64 = 0x200;
+const ID_ENTRY_SIZE: usize = 8;
+// Entries 0 to 2 are the DAT header, the file ID table and the MFT.
+const FIRST_FILE_ENTRY: usize = 3;
+
+/// Writes a new archive holding the added files: DAT header, file data,
+/// file ID table and MFT, in that order.
+#[derive(Default, Debug)]
+pub struct ArchiveBuilder {
+    files: Vec<(u32, Vec<u8>)>,
+    compress: bool,
+}
+
+/// Bytes needed after `position` to reach the next block boundary.
+pub(crate) fn padding(position: u64, chunk_size: u64) -> u64 {
+    position.next_multiple_of(chunk_size) - position
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compresses the added files when set. Off by default.
+    pub fn compress(&mut self, compress: bool) -> &mut Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Adds a file, stored in its own MFT entry in the order added.
+    pub fn add_file(&mut self, file_id: u32, data: impl Into<Vec<u8>>) -> &mut Self {
+        self.files.push((file_id, data.into()));
+        self
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut file_ids = HashSet::new();
+        if let Some((file_id, _)) = self
+            .files
+            .iter()
+            .find(|(file_id, _)| !file_ids.insert(*file_id))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("File ID {} was added twice.", file_id),
+            ));
+        }
+
+        let stored: Vec<Vec<u8>> = self
+            .files
+            .iter()
+            .map(|(_, data)| {
+                if self.compress {
+                    anet_deflate(data)
+                } else {
+                    data.clone()
+                }
+            })
+            .collect();
+        let compression_flag = if self.compress {
+            AnetCompressionFlags::COMPRESSED.bits()
+        } else {
+            AnetCompressionFlags::empty().bits()
+        };
+        let entry = |offset: u64, data: &[u8], compression_flag: u16| -> io::Result<AnetMftEntry> {
+            Ok(AnetMftEntry {
+                offset,
+                size: u32::try_from(data.len()).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "File is too large for the MFT.",
+                    )
+                })?,
+                compression_flag,
+                entry_flag: AnetMftEntryFlags::IN_USE.bits(),
+                counter: 0,
+                crc: crc32(data),
+            })
+        };
+
+        let mut id_table = Vec::with_capacity(self.files.len() * ID_ENTRY_SIZE);
+        for (number, (file_id, _)) in self.files.iter().enumerate() {
+            id_table.write_u32::<LittleEndian>(*file_id)?;
+            id_table.write_u32::<LittleEndian>((FIRST_FILE_ENTRY + number) as u32)?;
+        }
+
+        // Lay the blocks out first so the header can point at the MFT.
+        let mut position = DAT_HEADER_SIZE as u64;
+        position += padding(position, DAT_CHUNK_SIZE);
+        let mut file_entries = Vec::with_capacity(stored.len());
+        for data in &stored {
+            file_entries.push(entry(position, data, compression_flag)?);
+            position += data.len() as u64;
+            position += padding(position, DAT_CHUNK_SIZE);
+        }
+        let id_table_entry = entry(position, &id_table, 0)?;
+        position += id_table.len() as u64;
+        position += padding(position, DAT_CHUNK_SIZE);
+
+        let entry_count = FIRST_FILE_ENTRY + file_entries.len();
+        let mft_size = MFT_HEADER_SIZE + entry_count * MFT_ENTRY_SIZE;
+        let header = AnetDatHeader {
+            version: DAT_VERSION,
+            identifier: DAT_IDENTIFIER,
+            header_size: DAT_HEADER_SIZE as u32,
+            chunk_size: DAT_CHUNK_SIZE as u32,
+            mft_offset: position,
+            mft_size: mft_size as u32,
+            ..Default::default()
+        };
+        let mut header_bytes = Vec::with_capacity(DAT_HEADER_SIZE);
+        header.write_to(&mut header_bytes)?;
+        let header_entry = entry(0, &header_bytes, 0)?;
+        let mft_entry = AnetMftEntry {
+            size: mft_size as u32,
+            ..entry(position, &[], 0)?
+        };
+        let mft_header = AnetMftHeader {
+            identifier: MFT_IDENTIFIER,
+            num_entries: entry_count as u32,
+            ..Default::default()
+        };
+
+        let mut position = 0;
+        let mut write_block = |writer: &mut W, data: &[u8]| -> io::Result<()> {
+            writer.write_all(data)?;
+            position += data.len() as u64;
+            writer.write_all(&vec![0; padding(position, DAT_CHUNK_SIZE) as usize])?;
+            position += padding(position, DAT_CHUNK_SIZE);
+            Ok(())
+        };
+        write_block(writer, &header_bytes)?;
+        for data in &stored {
+            write_block(writer, data)?;
+        }
+        write_block(writer, &id_table)?;
+        mft_header.write_to(writer)?;
+        for entry in [&header_entry, &id_table_entry, &mft_entry]
+            .into_iter()
+            .chain(&file_entries)
+        {
+            entry.write_to(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.write_to(&mut data)?;
+        Ok(data)
+    }
+}
@@ -0,0 +1,148 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const CINP_FILE_TYPE: &[u8; 4] = b"CINP";
+const SCN_CHUNK: &[u8; 4] = b"SCN ";
+
+/// A model, sound or effect file the cinematic plays.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetCinematicAsset {
+    pub file_reference: Option<AnetFileReference>,
+    pub asset_type: u32,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnetCinematicKey {
+    pub time: f32, //< Seconds from the start of the cinematic.
+    pub value: [f32; 4],
+}
+
+/// An animated property over time, e.g. the camera position or the
+/// transform of an actor.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetCinematicTrack {
+    pub name: String,
+    pub track_type: u32,
+    pub asset_index: Option<usize>, //< Index into the assets of the cinematic.
+    pub flags: u32,
+    pub keys: Vec<AnetCinematicKey>,
+}
+
+/// A parsed `CINP` file: a scripted scene made of tracks driving assets.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetCinematic {
+    pub name: String,
+    pub length: f32, //< Length in seconds.
+    pub frame_rate: f32,
+    pub assets: Vec<AnetCinematicAsset>,
+    pub tracks: Vec<AnetCinematicTrack>,
+}
+
+impl AnetCinematicTrack {
+    pub fn start_time(&self) -> Option<f32> {
+        self.keys.first().map(|key| key.time)
+    }
+
+    pub fn end_time(&self) -> Option<f32> {
+        self.keys.last().map(|key| key.time)
+    }
+}
+
+impl AnetCinematic {
+    /// Parses the `SCN ` chunk of a decompressed PF `CINP` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != CINP_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a CINP file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(SCN_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing SCN chunk."))?;
+
+        let _scope = trace::scope("SCN ");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut cinematic = AnetCinematic {
+            name: reader.field("name").read_wstring()?,
+            length: reader.field("length").read_f32()?,
+            frame_rate: reader.field("frame_rate").read_f32()?,
+            ..Default::default()
+        };
+
+        let (count, position) = reader.field("assets").read_array()?;
+        let mut asset_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            cinematic.assets.push(AnetCinematicAsset {
+                file_reference: asset_reader.field("file_reference").read_file_reference()?,
+                asset_type: asset_reader.field("asset_type").read_u32()?,
+            });
+        }
+
+        let (count, position) = reader.field("tracks").read_array()?;
+        let mut track_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            let mut track = AnetCinematicTrack {
+                name: track_reader.field("name").read_string()?,
+                track_type: track_reader.field("track_type").read_u32()?,
+                asset_index: usize::try_from(track_reader.field("asset_index").read_i32()?).ok(),
+                flags: track_reader.field("flags").read_u32()?,
+                ..Default::default()
+            };
+            let (key_count, key_position) = track_reader.field("keys").read_array()?;
+            let mut key_reader = PfReader::at(&chunk.data, key_position);
+            for _ in 0..key_count {
+                track.keys.push(AnetCinematicKey {
+                    time: key_reader.field("time").read_f32()?,
+                    value: [
+                        key_reader.read_f32()?,
+                        key_reader.read_f32()?,
+                        key_reader.read_f32()?,
+                        key_reader.read_f32()?,
+                    ],
+                });
+            }
+            cinematic.tracks.push(track);
+        }
+        Ok(cinematic)
+    }
+
+    /// Length of the cinematic, extended to its last key if a track runs
+    /// past the stored length.
+    pub fn duration(&self) -> f32 {
+        self.tracks
+            .iter()
+            .filter_map(AnetCinematicTrack::end_time)
+            .fold(self.length, f32::max)
+    }
+
+    pub fn track(&self, name: &str) -> Option<&AnetCinematicTrack> {
+        self.tracks.iter().find(|track| track.name == name)
+    }
+
+    /// The asset a track drives, if any.
+    pub fn track_asset(&self, track: &AnetCinematicTrack) -> Option<&AnetCinematicAsset> {
+        self.assets.get(track.asset_index?)
+    }
+
+    /// File IDs of the assets, in asset order.
+    pub fn asset_file_ids(&self) -> Vec<u32> {
+        self.assets
+            .iter()
+            .filter_map(|asset| asset.file_reference.as_ref()?.file_id())
+            .collect()
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the `CINP` file with the given file ID.
+    pub fn read_cinematic(&self, file_id: u32) -> io::Result<AnetCinematic> {
+        AnetCinematic::load_from_bytes(&self.read_file(file_id)?)
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const HVKC_FILE_TYPE: &[u8; 4] = b"hvkC";
+const CMAC_FILE_TYPE: &[u8; 4] = b"cmaC";
+const HAVK_CHUNK: &[u8; 4] = b"havk";
+
+// First eight bytes of Havok binary packfiles and of Havok tagfiles.
+const HAVOK_PACKFILE_MAGIC: [u8; 8] = [0x57, 0xe0, 0xe0, 0x57, 0x10, 0xc0, 0xc0, 0x10];
+const HAVOK_TAGFILE_MAGIC: [u8; 8] = [0x1e, 0x0d, 0xb0, 0xca, 0xce, 0xfa, 0x11, 0xd0];
+const HAVOK_BLOCK_EXTENSION: &str = "hkx";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HavokBlockKind {
+    Packfile,
+    Tagfile,
+}
+
+/// A Havok binary embedded in a collision file. Blocks are found by their
+/// magic and run to the next block or the end of the chunk holding them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HavokBlock {
+    pub kind: HavokBlockKind,
+    pub chunk: String, //< FourCC of the PF chunk the block was found in.
+    pub offset: usize, //< Offset of the block in the chunk data.
+    pub data: Vec<u8>,
+}
+
+/// Triangle mesh of the collision geometry, in map units.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetCollisionMesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub indices: Vec<u16>,  //< Three per triangle.
+    pub surfaces: Vec<u16>, //< Surface type of each triangle, e.g. walkable or water.
+}
+
+/// A parsed `hvkC` or `cmaC` collision file.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetCollision {
+    pub meshes: Vec<AnetCollisionMesh>, //< Empty when the file holds no `havk` geometry.
+    pub havok_blocks: Vec<HavokBlock>,
+}
+
+/// Whether `data` is a PF collision file this module can read.
+pub fn is_collision_file(data: &[u8]) -> bool {
+    let file_type = data.get(8..12);
+    data.starts_with(b"PF")
+        && (file_type == Some(HVKC_FILE_TYPE) || file_type == Some(CMAC_FILE_TYPE))
+}
+
+/// Finds the Havok binaries embedded in `data`.
+pub fn find_havok_blocks(data: &[u8]) -> Vec<(HavokBlockKind, usize)> {
+    data.windows(HAVOK_PACKFILE_MAGIC.len())
+        .enumerate()
+        .filter_map(|(offset, window)| {
+            if window == HAVOK_PACKFILE_MAGIC {
+                Some((HavokBlockKind::Packfile, offset))
+            } else if window == HAVOK_TAGFILE_MAGIC {
+                Some((HavokBlockKind::Tagfile, offset))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+impl AnetCollisionMesh {
+    fn read(reader: &mut PfReader) -> io::Result<Self> {
+        let data = reader.data();
+        let mut mesh = AnetCollisionMesh::default();
+        let (count, position) = reader.field("vertices").read_array()?;
+        let mut vertex_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            mesh.vertices.push([
+                vertex_reader.read_f32()?,
+                vertex_reader.read_f32()?,
+                vertex_reader.read_f32()?,
+            ]);
+        }
+        mesh.indices = reader.field("indices").read_u16_array()?;
+        mesh.surfaces = reader.field("surfaces").read_u16_array()?;
+        if !mesh.indices.len().is_multiple_of(3)
+            || mesh
+                .indices
+                .iter()
+                .any(|index| usize::from(*index) >= mesh.vertices.len())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Collision mesh indices do not form triangles over its vertices.",
+            ));
+        }
+        Ok(mesh)
+    }
+
+    /// Vertex positions of every triangle.
+    pub fn triangles(&self) -> impl Iterator<Item = [[f32; 3]; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| self.vertices[usize::from(triangle[corner])]))
+    }
+}
+
+impl AnetCollision {
+    /// Parses a decompressed PF `hvkC` or `cmaC` entry. Havok blocks are
+    /// looked for in every chunk, geometry is read from the `havk` chunk.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        if !is_collision_file(data) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a collision file.",
+            ));
+        }
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let mut collision = AnetCollision::default();
+
+        for chunk in &pf_file.chunks {
+            let chunk_name = String::from_utf8_lossy(&chunk.header.chunk_type).into_owned();
+            let blocks = find_havok_blocks(&chunk.data);
+            for (number, (kind, offset)) in blocks.iter().enumerate() {
+                let end = blocks
+                    .get(number + 1)
+                    .map_or(chunk.data.len(), |(_, next)| *next);
+                collision.havok_blocks.push(HavokBlock {
+                    kind: *kind,
+                    chunk: chunk_name.clone(),
+                    offset: *offset,
+                    data: chunk.data[*offset..end].to_vec(),
+                });
+            }
+        }
+
+        if let Some(chunk) = pf_file.chunk(HAVK_CHUNK) {
+            let _scope = trace::scope("havk");
+            let mut reader = PfReader::new(&chunk.data);
+            let (count, position) = reader.field("meshes").read_array()?;
+            let mut mesh_reader = PfReader::at(&chunk.data, position);
+            for _ in 0..count {
+                collision
+                    .meshes
+                    .push(AnetCollisionMesh::read(&mut mesh_reader)?);
+            }
+        }
+        Ok(collision)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.meshes.iter().map(|mesh| mesh.indices.len() / 3).sum()
+    }
+
+    /// Writes every Havok block to `out_dir` as `<file_id>_<n>.hkx` and
+    /// returns the paths written.
+    pub fn save_havok_blocks<P: AsRef<Path>>(
+        &self,
+        file_id: u32,
+        out_dir: P,
+    ) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(&out_dir)?;
+        let mut paths = Vec::new();
+        for (number, block) in self.havok_blocks.iter().enumerate() {
+            let path = out_dir
+                .as_ref()
+                .join(format!("{}_{}.{}", file_id, number, HAVOK_BLOCK_EXTENSION));
+            fs::write(&path, &block.data)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the collision file with the given file ID.
+    pub fn read_collision(&self, file_id: u32) -> io::Result<AnetCollision> {
+        AnetCollision::load_from_bytes(&self.read_file(file_id)?)
+    }
+}
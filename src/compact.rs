@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::anet_archive::{
+    AnetArchive, AnetDatHeader, AnetMftEntry, AnetMftHeader, DAT_HEADER_SIZE, MFT_ENTRY_INDEX_NUM,
+    MFT_ENTRY_SIZE, MFT_HEADER_SIZE,
+};
+use crate::builder::padding;
+use crate::reader::open_archive_file;
+
+// Entry 0 is the DAT header and entry 2 the MFT itself.
+const HEADER_ENTRY_INDEX: usize = 0;
+const MFT_ENTRY_INDEX: usize = 2;
+
+/// Outcome of `AnetArchive::compact`.
+#[derive(Default, Debug)]
+pub struct CompactReport {
+    pub entries_copied: usize,
+    pub entries_dropped: usize, //< Unused entries whose data was left behind.
+    pub original_size: u64,
+    pub compacted_size: u64,
+}
+
+impl CompactReport {
+    pub fn space_saved(&self) -> u64 {
+        self.original_size.saturating_sub(self.compacted_size)
+    }
+}
+
+impl AnetArchive {
+    /// Writes a copy of the archive to `output_path` holding only the data
+    /// of in-use entries, packed one after the other with the MFT last.
+    ///
+    /// MFT indices and the file ID table are kept, so file IDs still resolve
+    /// to the same entries. Unused entries keep their slot with no data.
+    /// Entries sharing data keep sharing it.
+    pub fn compact<P: AsRef<Path>>(&self, output_path: P) -> io::Result<CompactReport> {
+        let output_path = output_path.as_ref();
+        if fs::canonicalize(output_path).ok() == Some(fs::canonicalize(&self.file_path)?) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot compact an archive onto itself.",
+            ));
+        }
+        let chunk_size = u64::from(self.dat_header.chunk_size.max(1));
+        let mut report = CompactReport {
+            original_size: fs::metadata(&self.file_path)?.len(),
+            ..Default::default()
+        };
+
+        // Lay the blocks out first so the header can point at the MFT.
+        let mut position = u64::from(self.dat_header.header_size).max(DAT_HEADER_SIZE as u64);
+        position += padding(position, chunk_size);
+        let mut blocks = Vec::new(); //< Old offset, size and new offset of each copied block.
+        let mut moved_offsets = HashMap::new();
+        let mut mft_data = self.mft_data.clone();
+        for (index, entry) in mft_data.iter_mut().enumerate() {
+            if index == HEADER_ENTRY_INDEX || index == MFT_ENTRY_INDEX {
+                continue;
+            }
+            if !entry.is_in_use() && index != MFT_ENTRY_INDEX_NUM {
+                *entry = AnetMftEntry {
+                    entry_flag: entry.entry_flag,
+                    counter: entry.counter,
+                    ..Default::default()
+                };
+                report.entries_dropped += 1;
+                continue;
+            }
+            entry.offset = *moved_offsets
+                .entry((entry.offset, entry.size))
+                .or_insert_with(|| {
+                    blocks.push((entry.offset, entry.size, position));
+                    let offset = position;
+                    position += u64::from(entry.size);
+                    position += padding(position, chunk_size);
+                    offset
+                });
+            report.entries_copied += 1;
+        }
+
+        let mft_size = MFT_HEADER_SIZE + mft_data.len() * MFT_ENTRY_SIZE;
+        if let Some(mft_entry) = mft_data.get_mut(MFT_ENTRY_INDEX) {
+            mft_entry.offset = position;
+            mft_entry.size = mft_size as u32;
+        }
+        let header = AnetDatHeader {
+            mft_offset: position,
+            mft_size: mft_size as u32,
+            ..self.dat_header.clone()
+        };
+
+        let mut reader = BufReader::new(open_archive_file(&self.file_path)?);
+        let mut writer = BufWriter::new(File::create(output_path)?);
+        header.write_to(&mut writer)?;
+        let mut written = DAT_HEADER_SIZE as u64;
+        for (old_offset, size, new_offset) in blocks {
+            writer.write_all(&vec![0; (new_offset - written) as usize])?;
+            reader.seek(SeekFrom::Start(old_offset))?;
+            let copied = io::copy(&mut (&mut reader).take(u64::from(size)), &mut writer)?;
+            if copied != u64::from(size) {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Entry data runs past the end of the archive.",
+                ));
+            }
+            written = new_offset + copied;
+        }
+        writer.write_all(&vec![0; (position - written) as usize])?;
+        let mft_header = AnetMftHeader {
+            identifier: self.mft_header.identifier,
+            unknown_field: self.mft_header.unknown_field,
+            num_entries: mft_data.len() as u32,
+            unknown_field_2: self.mft_header.unknown_field_2,
+        };
+        mft_header.write_to(&mut writer)?;
+        for entry in &mft_data {
+            entry.write_to(&mut writer)?;
+        }
+        writer.flush()?;
+
+        report.compacted_size = position + mft_size as u64;
+        Ok(report)
+    }
+}
@@ -0,0 +1,111 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const CMPC_FILE_TYPE: &[u8; 4] = b"cmpc";
+const CMPC_CHUNK: &[u8; 4] = b"CMPC";
+
+/// One piece of a character appearance: an armor piece, face or hairstyle,
+/// with the model it is drawn with and the textures bound to it.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetCompositePart {
+    pub token: u64,    //< Name token of the part.
+    pub category: u32, //< Armor slot, face or hair group the part fills.
+    pub flags: u32,
+    pub model_file: Option<AnetFileReference>,
+    pub textures: Vec<Option<AnetFileReference>>,
+}
+
+/// A parsed `cmpc` file: the parts character customization combines.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetComposite {
+    pub parts: Vec<AnetCompositePart>,
+}
+
+impl AnetCompositePart {
+    pub fn model_file_id(&self) -> Option<u32> {
+        self.model_file.as_ref()?.file_id()
+    }
+
+    /// File IDs of the textures of the part, in slot order.
+    pub fn texture_file_ids(&self) -> Vec<u32> {
+        self.textures
+            .iter()
+            .filter_map(|texture| texture.as_ref()?.file_id())
+            .collect()
+    }
+}
+
+impl AnetComposite {
+    /// Parses the `CMPC` chunk of a decompressed PF `cmpc` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != CMPC_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a cmpc file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(CMPC_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing CMPC chunk."))?;
+
+        let _scope = trace::scope("CMPC");
+        let mut composite = AnetComposite::default();
+        let mut reader = PfReader::new(&chunk.data);
+        let (count, position) = reader.field("parts").read_array()?;
+        let mut part_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            let mut part = AnetCompositePart {
+                token: part_reader.field("token").read_u64()?,
+                category: part_reader.field("category").read_u32()?,
+                flags: part_reader.field("flags").read_u32()?,
+                model_file: part_reader.field("model_file").read_file_reference()?,
+                ..Default::default()
+            };
+            let (texture_count, texture_position) = part_reader.field("textures").read_array()?;
+            let mut texture_reader = PfReader::at(&chunk.data, texture_position);
+            for _ in 0..texture_count {
+                part.textures
+                    .push(texture_reader.field("texture_file").read_file_reference()?);
+            }
+            composite.parts.push(part);
+        }
+        Ok(composite)
+    }
+
+    /// Parts filling the given category.
+    pub fn parts_in(&self, category: u32) -> impl Iterator<Item = &AnetCompositePart> {
+        self.parts
+            .iter()
+            .filter(move |part| part.category == category)
+    }
+
+    /// Every model and texture file ID the composite refers to, sorted and
+    /// without duplicates.
+    pub fn referenced_file_ids(&self) -> Vec<u32> {
+        let mut file_ids: Vec<u32> = self
+            .parts
+            .iter()
+            .flat_map(|part| {
+                part.model_file_id()
+                    .into_iter()
+                    .chain(part.texture_file_ids())
+            })
+            .collect();
+        file_ids.sort_unstable();
+        file_ids.dedup();
+        file_ids
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the `cmpc` file with the given file ID.
+    pub fn read_composite(&self, file_id: u32) -> io::Result<AnetComposite> {
+        AnetComposite::load_from_bytes(&self.read_file(file_id)?)
+    }
+}
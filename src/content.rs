@@ -0,0 +1,368 @@
+use std::{collections::HashSet, io};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const CNTC_FILE_TYPE: &[u8; 4] = b"cntc";
+const MAIN_CHUNK: &[u8; 4] = b"Main";
+const PRLT_FILE_TYPE: &[u8; 4] = b"prlt";
+const RLTN_CHUNK: &[u8; 4] = b"rltn";
+
+// Field offsets of a content type that it does not have.
+const NO_FIELD: u32 = u32::MAX;
+const GUID_SIZE: usize = 16;
+// Pointers in the content blob are 64 bits wide.
+const CONTENT_POINTER_SIZE: usize = 8;
+
+/// Where the known fields sit in the records of one content type.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnetContentTypeInfo {
+    pub guid_offset: u32, //< `NO_FIELD` if the type has no GUID.
+    pub uid_offset: u32,
+    pub data_id_offset: u32,
+    pub name_offset: u32, //< Offset of a pointer to the UTF-16 name.
+    pub track_references: u8,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetContentNamespace {
+    pub name: String,
+    pub domain: u32,
+    pub parent_index: Option<usize>, //< Index into the namespaces of the file.
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnetContentIndexEntry {
+    pub type_index: u32,
+    pub offset: u32, //< Offset of the record in the content blob.
+    pub namespace_index: u32,
+    pub root_index: u32,
+}
+
+/// A pointer in the content blob to another content file, by its index in
+/// the file references.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnetContentExternalOffset {
+    pub offset: u32,
+    pub file_index: u32,
+}
+
+/// A parsed `cntc` file. Records are laid out back to back in `content`;
+/// `index_entries` says where each starts and of which type it is.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetGameContent {
+    pub flags: u32,
+    pub type_infos: Vec<AnetContentTypeInfo>,
+    pub namespaces: Vec<AnetContentNamespace>,
+    pub file_references: Vec<Option<AnetFileReference>>,
+    pub index_entries: Vec<AnetContentIndexEntry>,
+    pub local_offsets: Vec<u32>, //< Content offsets holding pointers into the content blob.
+    pub external_offsets: Vec<AnetContentExternalOffset>,
+    pub file_indices: Vec<u32>, //< Content offsets holding an index into `file_references`.
+    #[serde(skip)]
+    pub content: Vec<u8>,
+}
+
+/// One record of a content file, with the fields its type info locates
+/// decoded and the rest left as raw bytes.
+#[derive(Debug, Serialize)]
+pub struct AnetContentRecord<'a> {
+    pub index: usize,
+    pub type_index: u32,
+    pub namespace: String, //< Full namespace path, parts joined with `.`.
+    pub offset: usize,
+    pub guid: Option<[u8; GUID_SIZE]>,
+    pub uid: Option<u32>,
+    pub data_id: Option<u32>,
+    pub name: Option<String>,
+    #[serde(skip)]
+    pub data: &'a [u8],
+    pub tail_offset: usize, //< Offset in `data` past the last known field.
+}
+
+/// One content file listed by the portal manifest.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetPortalManifestEntry {
+    pub file_reference: Option<AnetFileReference>,
+    pub namespace: String,
+    pub flags: u32,
+}
+
+/// A parsed `prlt` file: the content files that together make up the game
+/// content, in load order.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetPortalManifest {
+    pub entries: Vec<AnetPortalManifestEntry>,
+}
+
+fn optional_field(offset: u32) -> Option<usize> {
+    (offset != NO_FIELD).then_some(offset as usize)
+}
+
+impl<'a> AnetContentRecord<'a> {
+    /// Bytes of the record not covered by a known field, where most of the
+    /// per-type data sits.
+    pub fn tail(&self) -> &'a [u8] {
+        &self.data[self.tail_offset.min(self.data.len())..]
+    }
+}
+
+impl AnetGameContent {
+    /// Parses the `Main` chunk of a decompressed PF `cntc` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != CNTC_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a cntc file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(MAIN_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Main chunk."))?;
+
+        let _scope = trace::scope("Main");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut content = AnetGameContent {
+            flags: reader.field("flags").read_u32()?,
+            ..Default::default()
+        };
+
+        let (count, position) = reader.field("type_infos").read_array()?;
+        let mut type_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            content.type_infos.push(AnetContentTypeInfo {
+                guid_offset: type_reader.field("guid_offset").read_u32()?,
+                uid_offset: type_reader.field("uid_offset").read_u32()?,
+                data_id_offset: type_reader.field("data_id_offset").read_u32()?,
+                name_offset: type_reader.field("name_offset").read_u32()?,
+                track_references: type_reader.field("track_references").read_u8()?,
+            });
+        }
+
+        let (count, position) = reader.field("namespaces").read_array()?;
+        let mut namespace_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            content.namespaces.push(AnetContentNamespace {
+                name: namespace_reader.field("name").read_wstring()?,
+                domain: namespace_reader.field("domain").read_u32()?,
+                parent_index: usize::try_from(namespace_reader.field("parent_index").read_i32()?)
+                    .ok(),
+            });
+        }
+
+        let (count, position) = reader.field("file_references").read_array()?;
+        let mut file_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            content
+                .file_references
+                .push(file_reader.field("file_reference").read_file_reference()?);
+        }
+
+        let (count, position) = reader.field("index_entries").read_array()?;
+        let mut entry_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            content.index_entries.push(AnetContentIndexEntry {
+                type_index: entry_reader.field("type_index").read_u32()?,
+                offset: entry_reader.field("offset").read_u32()?,
+                namespace_index: entry_reader.field("namespace_index").read_u32()?,
+                root_index: entry_reader.field("root_index").read_u32()?,
+            });
+        }
+
+        content.local_offsets = reader.field("local_offsets").read_u32_array()?;
+        let (count, position) = reader.field("external_offsets").read_array()?;
+        let mut external_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            content.external_offsets.push(AnetContentExternalOffset {
+                offset: external_reader.field("offset").read_u32()?,
+                file_index: external_reader.field("file_index").read_u32()?,
+            });
+        }
+        content.file_indices = reader.field("file_indices").read_u32_array()?;
+        content.content = reader.field("content").read_byte_array()?;
+        Ok(content)
+    }
+
+    /// Full path of a namespace, from the root down, parts joined with `.`.
+    pub fn namespace_path(&self, namespace_index: usize) -> String {
+        let mut parts = Vec::new();
+        let mut next = Some(namespace_index);
+        // Bounded by the namespace count so that a parent cycle terminates.
+        while let Some(index) = next.filter(|_| parts.len() <= self.namespaces.len()) {
+            let Some(namespace) = self.namespaces.get(index) else {
+                break;
+            };
+            parts.push(namespace.name.as_str());
+            next = namespace.parent_index;
+        }
+        parts.reverse();
+        parts.join(".")
+    }
+
+    /// Byte range of each record in the content blob. A record runs to the
+    /// start of the next one, or to the end of the blob for the last.
+    fn record_ranges(&self) -> Vec<(usize, usize)> {
+        let mut starts: Vec<usize> = self
+            .index_entries
+            .iter()
+            .map(|entry| entry.offset as usize)
+            .filter(|offset| *offset <= self.content.len())
+            .collect();
+        starts.sort_unstable();
+        starts.dedup();
+        self.index_entries
+            .iter()
+            .map(|entry| {
+                let start = (entry.offset as usize).min(self.content.len());
+                let end = starts
+                    .iter()
+                    .find(|offset| **offset > start)
+                    .copied()
+                    .unwrap_or(self.content.len());
+                (start, end)
+            })
+            .collect()
+    }
+
+    /// Reads the UTF-16 string a local pointer at `offset` in the content
+    /// blob points to. Values at offsets not listed as local pointers are
+    /// not pointers and give `None`.
+    fn pointed_wstring(&self, local_offsets: &HashSet<u32>, offset: usize) -> Option<String> {
+        if !local_offsets.contains(&u32::try_from(offset).ok()?) {
+            return None;
+        }
+        let pointer = self.content.get(offset..offset + CONTENT_POINTER_SIZE)?;
+        let target = usize::try_from(LittleEndian::read_u64(pointer)).ok()?;
+        let units: Vec<u16> = self
+            .content
+            .get(target..)?
+            .chunks_exact(2)
+            .map(LittleEndian::read_u16)
+            .take_while(|unit| *unit != 0)
+            .collect();
+        Some(String::from_utf16_lossy(&units))
+    }
+
+    /// The records of the file, in index order.
+    pub fn records(&self) -> Vec<AnetContentRecord<'_>> {
+        let local_offsets: HashSet<u32> = self.local_offsets.iter().copied().collect();
+        self.index_entries
+            .iter()
+            .zip(self.record_ranges())
+            .enumerate()
+            .map(|(index, (entry, (start, end)))| {
+                let data = &self.content[start..end];
+                let type_info = self
+                    .type_infos
+                    .get(entry.type_index as usize)
+                    .copied()
+                    .unwrap_or(AnetContentTypeInfo {
+                        guid_offset: NO_FIELD,
+                        uid_offset: NO_FIELD,
+                        data_id_offset: NO_FIELD,
+                        name_offset: NO_FIELD,
+                        track_references: 0,
+                    });
+                let mut tail_offset = 0;
+                let mut field = |offset: u32, size: usize| {
+                    let offset = optional_field(offset)?;
+                    let bytes = data.get(offset..offset.checked_add(size)?)?;
+                    tail_offset = tail_offset.max(offset + size);
+                    Some((offset, bytes))
+                };
+                let guid = field(type_info.guid_offset, GUID_SIZE)
+                    .map(|(_, bytes)| bytes.try_into().unwrap_or_default());
+                let uid =
+                    field(type_info.uid_offset, 4).map(|(_, bytes)| LittleEndian::read_u32(bytes));
+                let data_id = field(type_info.data_id_offset, 4)
+                    .map(|(_, bytes)| LittleEndian::read_u32(bytes));
+                let name = field(type_info.name_offset, CONTENT_POINTER_SIZE)
+                    .and_then(|(offset, _)| self.pointed_wstring(&local_offsets, start + offset));
+                AnetContentRecord {
+                    index,
+                    type_index: entry.type_index,
+                    namespace: self.namespace_path(entry.namespace_index as usize),
+                    offset: start,
+                    guid,
+                    uid,
+                    data_id,
+                    name,
+                    data,
+                    tail_offset,
+                }
+            })
+            .collect()
+    }
+
+    /// Records of the given type.
+    pub fn records_of_type(&self, type_index: u32) -> Vec<AnetContentRecord<'_>> {
+        self.records()
+            .into_iter()
+            .filter(|record| record.type_index == type_index)
+            .collect()
+    }
+
+    /// File IDs of the other content files the records point into.
+    pub fn referenced_file_ids(&self) -> Vec<u32> {
+        self.file_references
+            .iter()
+            .filter_map(|file| file.as_ref()?.file_id())
+            .collect()
+    }
+}
+
+impl AnetPortalManifest {
+    /// Parses the `rltn` chunk of a decompressed PF `prlt` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != PRLT_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a prlt file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(RLTN_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing rltn chunk."))?;
+
+        let _scope = trace::scope("rltn");
+        let mut reader = PfReader::new(&chunk.data);
+        let (count, position) = reader.field("entries").read_array()?;
+        let mut entry_reader = PfReader::at(&chunk.data, position);
+        let mut manifest = AnetPortalManifest::default();
+        for _ in 0..count {
+            manifest.entries.push(AnetPortalManifestEntry {
+                file_reference: entry_reader.field("file_reference").read_file_reference()?,
+                namespace: entry_reader.field("namespace").read_wstring()?,
+                flags: entry_reader.field("flags").read_u32()?,
+            });
+        }
+        Ok(manifest)
+    }
+
+    /// File IDs of the content files, in load order.
+    pub fn content_file_ids(&self) -> Vec<u32> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.file_reference.as_ref()?.file_id())
+            .collect()
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the `cntc` file with the given file ID.
+    pub fn read_game_content(&self, file_id: u32) -> io::Result<AnetGameContent> {
+        AnetGameContent::load_from_bytes(&self.read_file(file_id)?)
+    }
+
+    /// Reads and parses the `prlt` file with the given file ID.
+    pub fn read_portal_manifest(&self, file_id: u32) -> io::Result<AnetPortalManifest> {
+        AnetPortalManifest::load_from_bytes(&self.read_file(file_id)?)
+    }
+}
@@ -0,0 +1,364 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use flate2::Crc;
+
+use crate::inflate::{
+    dictionary_builder, HuffmanTreeBuilder, TextureLayout, CRC_INTERVAL_WORDS, MAX_SYMBOL_VALUE,
+};
+
+// Compressor producing the stream `anet_inflate` reads: greedy LZ77 over a
+// hash chain, with Huffman trees built per block from the block's own
+// symbol frequencies. Codes are derived with the decoder's tree builder so
+// both sides always agree on them.
+
+const WRITE_SIZE_CONST_ADD: usize = 4; //< Shortest copy; sizes are stored minus this.
+const MAX_WRITE_SIZE: usize = WRITE_SIZE_CONST_ADD + 0xff;
+const MAX_WRITE_OFFSET: usize = 0x20000;
+// A block holds exactly `(n + 1) << 12` codes for a 4-bit n, except the last.
+const CODES_PER_BLOCK: usize = 16 << 12;
+const BLOCK_COUNT_SHIFT: usize = 12;
+const COPY_SYMBOL_BASE: usize = 0x100;
+const COPY_OFFSET_SYMBOLS: usize = 34;
+const MAX_CODE_LENGTH: u8 = 15;
+const MAX_RUN_LENGTH: usize = 8; //< Code lengths sent per dictionary symbol.
+const MATCH_HASH_BITS: u32 = 15;
+const MAX_CHAIN_LENGTH: usize = 32;
+
+enum Token {
+    Literal(u8),
+    Copy { size: usize, offset: usize },
+}
+
+struct BitWriter {
+    words: Vec<u32>,
+    crc: Crc,
+    pending: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            words: Vec::new(),
+            crc: Crc::new(),
+            pending: 0,
+            bits: 0,
+        }
+    }
+
+    /// Appends a stream word, preceded by the CRC-32 of the block when the
+    /// word falls on the slot the decoder skips.
+    fn push_word(&mut self, word: u32) {
+        if (self.words.len() + 1).is_multiple_of(CRC_INTERVAL_WORDS) {
+            self.words.push(self.crc.sum());
+            self.crc.reset();
+        }
+        self.crc.update(&word.to_le_bytes());
+        self.words.push(word);
+    }
+
+    /// Writes the low `bits` bits of `value`, most significant first.
+    fn write_bits(&mut self, value: u32, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+        self.pending = (self.pending << bits) | (u64::from(value) & ((1 << bits) - 1));
+        self.bits += bits;
+        if self.bits >= 32 {
+            self.bits -= 32;
+            self.push_word((self.pending >> self.bits) as u32);
+            self.pending &= (1 << self.bits) - 1;
+        }
+    }
+
+    fn write_code(&mut self, (code, bits): (u32, u8)) {
+        self.write_bits(code, u32::from(bits));
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.push_word((self.pending << (32 - self.bits)) as u32);
+        }
+        self.words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+}
+
+/// Huffman code lengths for `frequencies`, 0 for unused symbols.
+fn huffman_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0; frequencies.len()];
+    let mut parents = vec![usize::MAX; frequencies.len()];
+    let mut heap: BinaryHeap<_> = frequencies
+        .iter()
+        .enumerate()
+        .filter(|(_, frequency)| **frequency > 0)
+        .map(|(symbol, frequency)| Reverse((u64::from(*frequency), symbol)))
+        .collect();
+    if heap.len() == 1 {
+        let Reverse((_, symbol)) = heap.pop().unwrap();
+        lengths[symbol] = 1;
+        return lengths;
+    }
+    while let (Some(Reverse((first, first_node))), Some(Reverse((second, second_node)))) =
+        (heap.pop(), heap.pop())
+    {
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[first_node] = node;
+        parents[second_node] = node;
+        heap.push(Reverse((first + second, node)));
+    }
+    for symbol in (0..frequencies.len()).filter(|symbol| frequencies[*symbol] > 0) {
+        let mut node = symbol;
+        while parents[node] != usize::MAX {
+            lengths[symbol] += 1;
+            node = parents[node];
+        }
+    }
+    lengths
+}
+
+/// Huffman code lengths no longer than `MAX_CODE_LENGTH`, flattening the
+/// frequencies until the tree is shallow enough.
+fn code_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut frequencies = frequencies.to_vec();
+    loop {
+        let lengths = huffman_lengths(&frequencies);
+        if lengths.iter().all(|length| *length <= MAX_CODE_LENGTH) {
+            return lengths;
+        }
+        for frequency in frequencies.iter_mut().filter(|frequency| **frequency > 0) {
+            *frequency = (*frequency / 2).max(1);
+        }
+    }
+}
+
+/// Sends the code lengths of a block tree, highest symbol first in runs of
+/// equal lengths, and returns the codes the decoder will build from them.
+fn write_tree(writer: &mut BitWriter, dictionary: &[(u32, u8)], lengths: &[u8]) -> Vec<(u32, u32)> {
+    let number_of_symbols = lengths
+        .iter()
+        .rposition(|length| *length > 0)
+        .map_or(0, |symbol| symbol + 1);
+    writer.write_bits(number_of_symbols as u32, 16);
+
+    let mut builder = HuffmanTreeBuilder::new();
+    let mut symbol = number_of_symbols;
+    while symbol > 0 {
+        let bits = lengths[symbol - 1];
+        let mut run = 1;
+        while run < MAX_RUN_LENGTH && run < symbol && lengths[symbol - 1 - run] == bits {
+            run += 1;
+        }
+        writer.write_code(dictionary[((run - 1) << 5) | usize::from(bits)]);
+        for _ in 0..run {
+            symbol -= 1;
+            if bits > 0 {
+                builder
+                    .add_symbol(symbol as u16, bits)
+                    .expect("code lengths are within the tree limits");
+            }
+        }
+    }
+    builder
+        .codes()
+        .into_iter()
+        .map(|(code, bits)| (code, u32::from(bits)))
+        .collect()
+}
+
+/// Symbol, extra bit count and extra bits of a copy size, stored minus
+/// `WRITE_SIZE_CONST_ADD`.
+fn size_code(value: usize) -> (usize, u32, u32) {
+    if value < 4 {
+        return (value, 0, 0);
+    }
+    (4..28usize)
+        .map(|code| {
+            let (quotient, remainder) = (code / 4, code % 4);
+            let base = (1 << (quotient - 1)) * (4 + remainder);
+            (code, base, (quotient - 1) as u32)
+        })
+        .find(|(_, base, extra_bits)| value >= *base && value < base + (1 << extra_bits))
+        .map(|(code, base, extra_bits)| (code, extra_bits, (value - base) as u32))
+        .expect("copy sizes are at most 0xff over the minimum")
+}
+
+/// Symbol, extra bit count and extra bits of a copy offset minus one.
+fn offset_code(value: usize) -> (usize, u32, u32) {
+    if value < 2 {
+        return (value, 0, 0);
+    }
+    (2..COPY_OFFSET_SYMBOLS)
+        .map(|code| {
+            let (quotient, remainder) = (code / 2, code % 2);
+            let base = (1 << (quotient - 1)) * (2 + remainder);
+            (code, base, (quotient - 1) as u32)
+        })
+        .find(|(_, base, extra_bits)| value >= *base && value < base + (1 << extra_bits))
+        .map(|(code, base, extra_bits)| (code, extra_bits, (value - base) as u32))
+        .expect("copy offsets are within the window")
+}
+
+fn tokenize(input: &[u8]) -> Vec<Token> {
+    let hash = |position: usize| {
+        let word = u32::from_le_bytes(input[position..position + 4].try_into().unwrap());
+        (word.wrapping_mul(0x9e3779b1) >> (32 - MATCH_HASH_BITS)) as usize
+    };
+    let mut head = vec![usize::MAX; 1 << MATCH_HASH_BITS];
+    let mut previous = vec![usize::MAX; MAX_WRITE_OFFSET];
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    while position < input.len() {
+        let (mut best_size, mut best_offset) = (0, 0);
+        if position + WRITE_SIZE_CONST_ADD <= input.len() {
+            let max_size = MAX_WRITE_SIZE.min(input.len() - position);
+            let mut candidate = head[hash(position)];
+            for _ in 0..MAX_CHAIN_LENGTH {
+                if candidate >= position || position - candidate > MAX_WRITE_OFFSET {
+                    break;
+                }
+                let size = input[candidate..]
+                    .iter()
+                    .zip(&input[position..position + max_size])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if size > best_size {
+                    (best_size, best_offset) = (size, position - candidate);
+                    if size == max_size {
+                        break;
+                    }
+                }
+                // Ring slots are reused, so a newer position ends the chain.
+                let next = previous[candidate % MAX_WRITE_OFFSET];
+                if next >= candidate {
+                    break;
+                }
+                candidate = next;
+            }
+        }
+
+        let step = if best_size >= WRITE_SIZE_CONST_ADD {
+            tokens.push(Token::Copy {
+                size: best_size,
+                offset: best_offset,
+            });
+            best_size
+        } else {
+            tokens.push(Token::Literal(input[position]));
+            1
+        };
+        for inserted in position..(position + step).min(input.len().saturating_sub(3)) {
+            let slot = hash(inserted);
+            previous[inserted % MAX_WRITE_OFFSET] = head[slot];
+            head[slot] = inserted;
+        }
+        position += step;
+    }
+    tokens
+}
+
+/// CRC-32 of stored entry data, as kept in MFT records.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Compresses `input` into an ANet compressed entry.
+pub fn anet_deflate(input: &[u8]) -> Vec<u8> {
+    let tokens = tokenize(input);
+    let dictionary = dictionary_builder().codes();
+    let mut writer = BitWriter::new();
+    writer.write_bits(0, 32); // Header word, unused by the decoder.
+    writer.write_bits(input.len() as u32, 32);
+    writer.write_bits(0, 4);
+    writer.write_bits((WRITE_SIZE_CONST_ADD - 1) as u32, 4);
+
+    for block in tokens.chunks(CODES_PER_BLOCK) {
+        let mut symbol_frequencies = vec![0; MAX_SYMBOL_VALUE];
+        let mut offset_frequencies = vec![0; COPY_OFFSET_SYMBOLS];
+        for token in block {
+            match token {
+                Token::Literal(byte) => symbol_frequencies[usize::from(*byte)] += 1,
+                Token::Copy { size, offset } => {
+                    symbol_frequencies
+                        [COPY_SYMBOL_BASE + size_code(size - WRITE_SIZE_CONST_ADD).0] += 1;
+                    offset_frequencies[offset_code(offset - 1).0] += 1;
+                }
+            }
+        }
+        // The decoder stops at an empty tree, so one is always sent.
+        if offset_frequencies.iter().all(|frequency| *frequency == 0) {
+            offset_frequencies[0] = 1;
+        }
+
+        let symbol_codes = write_tree(&mut writer, &dictionary, &code_lengths(&symbol_frequencies));
+        let offset_codes = write_tree(&mut writer, &dictionary, &code_lengths(&offset_frequencies));
+        writer.write_bits(((block.len() - 1) >> BLOCK_COUNT_SHIFT) as u32, 4);
+        for token in block {
+            match token {
+                Token::Literal(byte) => {
+                    let (code, bits) = symbol_codes[usize::from(*byte)];
+                    writer.write_bits(code, bits);
+                }
+                Token::Copy { size, offset } => {
+                    let (symbol, extra_bits, extra) = size_code(size - WRITE_SIZE_CONST_ADD);
+                    let (code, bits) = symbol_codes[COPY_SYMBOL_BASE + symbol];
+                    writer.write_bits(code, bits);
+                    writer.write_bits(extra, extra_bits);
+                    let (symbol, extra_bits, extra) = offset_code(offset - 1);
+                    let (code, bits) = offset_codes[symbol];
+                    writer.write_bits(code, bits);
+                    writer.write_bits(extra, extra_bits);
+                }
+            }
+        }
+    }
+    writer.finish()
+}
+
+/// Wraps the standard blocks of every mip level of a texture, `levels`
+/// starting with the top one of `width` by `height` pixels, in the texture
+/// compression container `inflate_texture_level` reads. No constant-block
+/// pass is used: every block component is stored. `format` must be one
+/// `inflate_texture` knows.
+pub fn deflate_texture(
+    identifier: &[u8; 4],
+    format: &[u8; 4],
+    width: u16,
+    height: u16,
+    levels: &[Vec<u8>],
+) -> Vec<u8> {
+    let layout = TextureLayout::of(format).expect("texture format has a known layout");
+    let total_size: usize = levels.iter().map(|blocks| 8 + blocks.len()).sum();
+    let mut output = Vec::with_capacity(12 + total_size);
+    output.extend_from_slice(identifier);
+    output.extend_from_slice(format);
+    output.extend_from_slice(&width.to_le_bytes());
+    output.extend_from_slice(&height.to_le_bytes());
+
+    for blocks in levels {
+        let components = blocks.chunks_exact(layout.block_bytes);
+        // The flags and the stored components follow the data size.
+        let data_size = 4 + components.len() * layout.block_bytes;
+        output.extend_from_slice(&(data_size as u32).to_le_bytes());
+        output.extend_from_slice(&0u32.to_le_bytes()); //< Compression flags: no passes.
+        if layout.stores_alpha() {
+            for block in components.clone() {
+                output.extend_from_slice(&block[..layout.component_bytes()]);
+            }
+        }
+        if layout.has_color {
+            for word in (0..layout.component_bytes()).step_by(4) {
+                let start = layout.color_offset() + word;
+                for block in components.clone() {
+                    output.extend_from_slice(&block[start..start + 4]);
+                }
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const DEPS_FILE_TYPE: &[u8; 4] = b"DEPS";
+const DEPS_CHUNK: &[u8; 4] = b"DEPS";
+
+/// One row of a `DEPS` table: a file and the files it references.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetDependencyRecord {
+    pub file: Option<AnetFileReference>,
+    pub dependencies: Vec<AnetFileReference>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetDependencyTable {
+    pub records: Vec<AnetDependencyRecord>,
+}
+
+/// File references between archive entries, by file ID, in both directions.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyGraph {
+    pub dependencies: HashMap<u32, Vec<u32>>,
+    pub dependents: HashMap<u32, Vec<u32>>,
+}
+
+impl AnetDependencyTable {
+    /// Parses the `DEPS` chunk of a decompressed PF `DEPS` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(DEPS_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing DEPS chunk."))?;
+
+        let _scope = trace::scope("DEPS");
+        let mut reader = PfReader::new(&chunk.data);
+        let (count, position) = reader.field("records").read_array()?;
+        let mut record_reader = PfReader::at(&chunk.data, position);
+        let mut table = AnetDependencyTable::default();
+        for _ in 0..count {
+            let file = record_reader.field("file").read_file_reference()?;
+            let (dependency_count, dependency_position) =
+                record_reader.field("dependencies").read_array()?;
+            let mut dependency_reader = PfReader::at(&chunk.data, dependency_position);
+            let mut dependencies = Vec::new();
+            for _ in 0..dependency_count {
+                if let Some(dependency) = dependency_reader.read_file_reference()? {
+                    dependencies.push(dependency);
+                }
+            }
+            table
+                .records
+                .push(AnetDependencyRecord { file, dependencies });
+        }
+        Ok(table)
+    }
+}
+
+impl DependencyGraph {
+    pub fn add_table(&mut self, table: &AnetDependencyTable) {
+        for record in &table.records {
+            let Some(file_id) = record.file.as_ref().and_then(AnetFileReference::file_id) else {
+                continue;
+            };
+            for dependency_id in record
+                .dependencies
+                .iter()
+                .filter_map(AnetFileReference::file_id)
+            {
+                self.add_edge(file_id, dependency_id);
+            }
+        }
+    }
+
+    pub fn add_edge(&mut self, file_id: u32, dependency_id: u32) {
+        let dependencies = self.dependencies.entry(file_id).or_default();
+        if !dependencies.contains(&dependency_id) {
+            dependencies.push(dependency_id);
+            self.dependents
+                .entry(dependency_id)
+                .or_default()
+                .push(file_id);
+        }
+    }
+
+    /// Every file reachable from `file_id`, nearest first, without `file_id`
+    /// itself.
+    pub fn transitive_dependencies(&self, file_id: u32) -> Vec<u32> {
+        let mut seen = HashSet::from([file_id]);
+        let mut queue = VecDeque::from([file_id]);
+        let mut found = Vec::new();
+        while let Some(current) = queue.pop_front() {
+            for &dependency_id in self.dependencies.get(&current).into_iter().flatten() {
+                if seen.insert(dependency_id) {
+                    found.push(dependency_id);
+                    queue.push_back(dependency_id);
+                }
+            }
+        }
+        found
+    }
+}
+
+impl AnetArchive {
+    /// Builds the graph from every `DEPS` entry of the archive. Entries that
+    /// cannot be read or parsed are left out.
+    pub fn build_dependency_graph(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for index in 0..self.mft_data.len() {
+            let Ok(data) = self.read_entry(index) else {
+                continue;
+            };
+            if !data.starts_with(b"PF") || data.get(8..12) != Some(DEPS_FILE_TYPE) {
+                continue;
+            }
+            if let Ok(table) = AnetDependencyTable::load_from_bytes(&data) {
+                graph.add_table(&table);
+            }
+        }
+        graph
+    }
+
+    /// The dependency graph, built on first use.
+    pub fn dependency_graph(&self) -> &DependencyGraph {
+        self.dependency_graph
+            .get_or_init(|| self.build_dependency_graph())
+    }
+
+    /// File IDs directly referenced by `file_id`.
+    pub fn dependencies_of(&self, file_id: u32) -> &[u32] {
+        self.dependency_graph()
+            .dependencies
+            .get(&file_id)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// File IDs that directly reference `file_id`.
+    pub fn reverse_dependencies(&self, file_id: u32) -> &[u32] {
+        self.dependency_graph()
+            .dependents
+            .get(&file_id)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// `file_id` followed by everything it needs, directly or not.
+    pub fn dependency_closure(&self, file_id: u32) -> Vec<u32> {
+        let mut file_ids = vec![file_id];
+        file_ids.extend(self.dependency_graph().transitive_dependencies(file_id));
+        file_ids
+    }
+}
@@ -0,0 +1,237 @@
+use std::{cell::OnceCell, collections::BTreeMap, io};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetMftEntry};
+use crate::progress::{Progress, ProgressStage};
+
+// Compressed entries start with a header word followed by the uncompressed
+// size of the payload.
+pub(crate) const COMPRESSED_HEADER_SIZE: usize = 8;
+pub(crate) const UNCOMPRESSED_SIZE_OFFSET: usize = 4;
+// The first word of the bitstream carries the compression parameters.
+const COMPRESSION_PARAMETERS_SIZE: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompressionMethod {
+    Uncompressed,
+    AnetHuffman, //< ANet LZ77 variant with per-block Huffman trees.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionInfo {
+    pub method: CompressionMethod,
+    pub header: u32,
+    pub uncompressed_size: u32,
+    pub write_size_const_add: u8, //< Added to the length of every back-reference.
+}
+
+#[derive(Default, Debug)]
+pub struct CompressionStats {
+    pub uncompressed: usize,
+    pub compressed: usize,
+    pub unreadable: usize,
+    pub by_write_size_const_add: BTreeMap<u8, usize>,
+}
+
+/// A view of a single MFT entry. Values that need reading entry data are
+/// fetched on first use and cached.
+pub struct EntryInfo<'a> {
+    archive: &'a AnetArchive,
+    index: usize,
+    uncompressed_size: OnceCell<u32>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct VerifyFailure {
+    pub index: usize,
+    pub error: String,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub failures: Vec<VerifyFailure>,
+}
+
+impl AnetArchive {
+    pub fn entry(&self, index: usize) -> Option<EntryInfo<'_>> {
+        if index >= self.mft_data.len() {
+            return None;
+        }
+        Some(EntryInfo {
+            archive: self,
+            index,
+            uncompressed_size: OnceCell::new(),
+        })
+    }
+
+    pub fn entry_by_id(&self, file_id: u32) -> Option<EntryInfo<'_>> {
+        self.index_of(file_id).and_then(|index| self.entry(index))
+    }
+
+    /// Reads the content of the file with the given file ID.
+    pub fn read_file(&self, file_id: u32) -> io::Result<Vec<u8>> {
+        let entry = self.entry_by_id(file_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("File ID {} not found.", file_id),
+            )
+        })?;
+        self.read_entry(entry.index())
+    }
+
+    pub fn compression_stats(&self) -> CompressionStats {
+        let mut stats = CompressionStats::default();
+        for entry in self.entries().filter(|entry| entry.size() > 0) {
+            match entry.compression_info() {
+                Ok(info) if info.method == CompressionMethod::Uncompressed => {
+                    stats.uncompressed += 1;
+                }
+                Ok(info) => {
+                    stats.compressed += 1;
+                    *stats
+                        .by_write_size_const_add
+                        .entry(info.write_size_const_add)
+                        .or_default() += 1;
+                }
+                Err(_) => stats.unreadable += 1,
+            }
+        }
+        stats
+    }
+
+    /// Reads and decompresses every non-empty entry, collecting the ones
+    /// that fail.
+    pub fn verify_entries(&self, progress: &mut dyn FnMut(Progress)) -> VerifyReport {
+        let total = self.mft_data.len();
+        let mut report = VerifyReport::default();
+        for index in 0..total {
+            progress(Progress {
+                stage: ProgressStage::Verify,
+                done: index,
+                total,
+            });
+            if self.mft_data[index].size == 0 {
+                continue;
+            }
+            report.checked += 1;
+            if let Err(error) = self.read_entry(index) {
+                report.failures.push(VerifyFailure {
+                    index,
+                    error: error.to_string(),
+                });
+            }
+        }
+        progress(Progress {
+            stage: ProgressStage::Verify,
+            done: total,
+            total,
+        });
+        report
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = EntryInfo<'_>> {
+        (0..self.mft_data.len()).filter_map(|index| self.entry(index))
+    }
+
+    /// Entries whose MFT slot is flagged in use, i.e. the real files.
+    pub fn in_use_entries(&self) -> impl Iterator<Item = EntryInfo<'_>> {
+        self.entries().filter(|entry| entry.is_in_use())
+    }
+
+    /// Free or deleted MFT slots.
+    pub fn unused_entries(&self) -> impl Iterator<Item = EntryInfo<'_>> {
+        self.entries().filter(|entry| !entry.is_in_use())
+    }
+}
+
+impl<'a> EntryInfo<'a> {
+    pub fn archive(&self) -> &'a AnetArchive {
+        self.archive
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn mft_entry(&self) -> &'a AnetMftEntry {
+        &self.archive.mft_data[self.index]
+    }
+
+    pub fn is_in_use(&self) -> bool {
+        self.mft_entry().is_in_use()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.mft_entry().is_compressed()
+    }
+
+    /// Size of the entry as stored in the archive.
+    pub fn size(&self) -> u32 {
+        self.mft_entry().size
+    }
+
+    /// Size of the entry once decompressed, read from the compressed data
+    /// header on first use.
+    pub fn uncompressed_size(&self) -> io::Result<u32> {
+        if let Some(size) = self.uncompressed_size.get() {
+            return Ok(*size);
+        }
+        let size = if self.is_compressed() {
+            let header = self
+                .archive
+                .read_entry_prefix(self.index, COMPRESSED_HEADER_SIZE)?;
+            if header.len() < COMPRESSED_HEADER_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Compressed entry is too small to hold its header.",
+                ));
+            }
+            LittleEndian::read_u32(&header[UNCOMPRESSED_SIZE_OFFSET..])
+        } else {
+            self.size()
+        };
+        Ok(*self.uncompressed_size.get_or_init(|| size))
+    }
+
+    /// Ratio of stored size to uncompressed size; 1.0 for uncompressed entries.
+    pub fn compression_ratio(&self) -> io::Result<f64> {
+        let uncompressed_size = self.uncompressed_size()?;
+        if uncompressed_size == 0 {
+            return Ok(1.0);
+        }
+        Ok(f64::from(self.size()) / f64::from(uncompressed_size))
+    }
+
+    /// Identifies how the entry is compressed and with which parameters, by
+    /// reading the start of the compressed stream.
+    pub fn compression_info(&self) -> io::Result<CompressionInfo> {
+        if !self.is_compressed() {
+            return Ok(CompressionInfo {
+                method: CompressionMethod::Uncompressed,
+                header: 0,
+                uncompressed_size: self.size(),
+                write_size_const_add: 0,
+            });
+        }
+        let header = self
+            .archive
+            .read_entry_prefix(self.index, COMPRESSION_PARAMETERS_SIZE)?;
+        if header.len() < COMPRESSION_PARAMETERS_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Compressed entry is too small to hold its header.",
+            ));
+        }
+        let parameters = LittleEndian::read_u32(&header[COMPRESSED_HEADER_SIZE..]);
+        Ok(CompressionInfo {
+            method: CompressionMethod::AnetHuffman,
+            header: LittleEndian::read_u32(&header),
+            uncompressed_size: LittleEndian::read_u32(&header[UNCOMPRESSED_SIZE_OFFSET..]),
+            write_size_const_add: ((parameters >> 24) & 0xf) as u8 + 1,
+        })
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType, LanguageType};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const EULA_FILE_TYPE: &[u8; 4] = b"eula";
+const EULA_CHUNK: &[u8; 4] = b"eula";
+const EULA_TEXT_EXTENSION: &str = "txt";
+
+/// The license text in one language.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEulaText {
+    pub language: u32, //< Index of a `LanguageType`.
+    pub text: String,
+}
+
+/// A parsed `eula` file: the license agreement in every language it ships in.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEula {
+    pub texts: Vec<AnetEulaText>,
+}
+
+impl AnetEula {
+    /// Parses the `eula` chunk of a decompressed PF `eula` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != EULA_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a eula file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(EULA_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing eula chunk."))?;
+
+        let _scope = trace::scope("eula");
+        let mut eula = AnetEula::default();
+        let mut reader = PfReader::new(&chunk.data);
+        let (count, position) = reader.field("texts").read_array()?;
+        let mut text_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            eula.texts.push(AnetEulaText {
+                language: text_reader.field("language").read_u32()?,
+                text: text_reader.field("text").read_wstring()?,
+            });
+        }
+        Ok(eula)
+    }
+
+    pub fn text(&self, language: LanguageType) -> Option<&str> {
+        self.texts
+            .iter()
+            .find(|text| text.language == language as u32)
+            .map(|text| text.text.as_str())
+    }
+
+    /// Writes every text to `out_dir` as UTF-8 `eula_<language>.txt` and
+    /// returns the paths written.
+    pub fn save_texts<P: AsRef<Path>>(&self, out_dir: P) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(&out_dir)?;
+        let mut paths = Vec::new();
+        for text in &self.texts {
+            let path = out_dir
+                .as_ref()
+                .join(format!("eula_{}.{}", text.language, EULA_TEXT_EXTENSION));
+            fs::write(&path, &text.text)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the `eula` file with the given file ID.
+    pub fn read_eula(&self, file_id: u32) -> io::Result<AnetEula> {
+        AnetEula::load_from_bytes(&self.read_file(file_id)?)
+    }
+
+    /// Parses the first `eula` entry of the archive.
+    pub fn eula(&self) -> io::Result<AnetEula> {
+        let entry = self
+            .find_by_type(AnetFileType::AnftEula)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Archive has no eula file."))?;
+        AnetEula::load_from_bytes(&self.read_entry(entry.index())?)
+    }
+}
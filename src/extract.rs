@@ -0,0 +1,748 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+use crate::formats::FormatTable;
+use crate::prefetch::PrefetchOptions;
+use crate::progress::{Progress, ProgressStage};
+
+const BUNDLE_MANIFEST_NAME: &str = "manifest.json";
+const EXPORTED_FILE_MODE: u32 = 0o644;
+const PF_MAGIC: &[u8; 2] = b"PF";
+const PF_FILE_TYPE_OFFSET: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Zip,
+    Tar,
+}
+
+impl ExportFormat {
+    /// Picks the format from the extension of `file_path`.
+    pub fn from_path(file_path: &Path) -> Option<Self> {
+        match file_path.extension()?.to_str()?.to_lowercase().as_str() {
+            "zip" => Some(ExportFormat::Zip),
+            "tar" => Some(ExportFormat::Tar),
+            _ => None,
+        }
+    }
+}
+
+/// How extracted files are named.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportNaming {
+    #[default]
+    Leafadoo, //< `<base_id>.<ext>` in one directory, see `entry_file_name_for_data`.
+    Gw2Browser, //< `<Category>/<base_id>.<ext>`, the layout of Gw2Browser and gw2DatTools.
+}
+
+impl ExportNaming {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "leafadoo" => Some(ExportNaming::Leafadoo),
+            "gw2browser" | "gw2dattools" => Some(ExportNaming::Gw2Browser),
+            _ => None,
+        }
+    }
+}
+
+/// Folder and extension Gw2Browser gives a file of `file_type`. PF files
+/// are named after their PF type tag, the way the C++ tools do.
+fn gw2browser_location(file_type: AnetFileType, data: &[u8]) -> (&'static str, String) {
+    use AnetFileType::*;
+    let pf_extension = || {
+        data.get(PF_FILE_TYPE_OFFSET..PF_FILE_TYPE_OFFSET + 4)
+            .map(|tag| {
+                String::from_utf8_lossy(tag)
+                    .trim_end_matches('\0')
+                    .to_lowercase()
+            })
+            .filter(|tag| !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or_else(|| String::from("pf"))
+    };
+    let (folder, extension) = match file_type {
+        AnftAtex => ("Textures", "atex"),
+        AnftAttx => ("Textures", "attx"),
+        AnftAtec => ("Textures", "atec"),
+        AnftAtep => ("Textures", "atep"),
+        AnftAteu => ("Textures", "ateu"),
+        AnftAtet => ("Textures", "atet"),
+        AnftCtex => ("Textures", "ctex"),
+        AnftDds => ("Textures", "dds"),
+        AnftJpeg => ("Textures", "jpg"),
+        AnftWebp => ("Textures", "webp"),
+        AnftPng => ("Textures", "png"),
+        AnftSound | AnftAsndMp3 | AnftAsndOgg => ("Sounds", "asnd"),
+        AnftPackedMp3 | AnftPackedOgg => return ("Sounds", pf_extension()),
+        AnftMp3 => ("Sounds", "mp3"),
+        AnftOgg => ("Sounds", "ogg"),
+        AnftModel | AnftModelCollisionManifest | AnftAnimSequences | AnftEmoteAnimation => {
+            return ("Models", pf_extension())
+        }
+        AnftMapCollision | AnftMapParam | AnftMapShadow | AnftMapMetadata | AnftPagedImageTable => {
+            return ("Maps", pf_extension())
+        }
+        AnftTextPackManifest | AnftTextPackVariant | AnftTextPackVoices => {
+            return ("Strings", pf_extension())
+        }
+        AnftStringFile => ("Strings", "strs"),
+        AnftBinary => ("Binaries", "bin"),
+        AnftDll => ("Binaries", "dll"),
+        AnftExe => ("Binaries", "exe"),
+        AnftFontFile => ("Fonts", "ttf"),
+        AnftBitmapFontFile => return ("Fonts", pf_extension()),
+        AnftBink2video => ("Videos", "bk2"),
+        AnftUtf8 | AnftText => ("Text", "txt"),
+        AnftRiff => ("Misc", "riff"),
+        AnftArap => ("Misc", "arap"),
+        AnftUnknown => ("Unknown", "raw"),
+        _ if data.starts_with(PF_MAGIC) => return ("Misc", pf_extension()),
+        _ => ("Misc", "bin"),
+    };
+    (folder, extension.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct WatchedExtractOptions {
+    pub poll_interval: Duration, //< Wait between reloads while the archive is changing.
+    pub max_polls: usize, //< Give up when the archive is still changing after this many reloads.
+    pub max_refreshes: usize, //< Give up after the archive changed this many times.
+}
+
+impl Default for WatchedExtractOptions {
+    fn default() -> Self {
+        WatchedExtractOptions {
+            poll_interval: Duration::from_millis(500),
+            max_polls: 120,
+            max_refreshes: 16,
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct ExtractReport {
+    pub written: Vec<PathBuf>,
+    pub refreshes: usize, //< Number of times the archive changed during extraction.
+    pub reextracted: Vec<usize>, //< Already written entries rewritten after a change.
+}
+
+/// Outcome of extracting one file ID of a list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdExtractResult {
+    pub file_id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl IdExtractResult {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Reads a list of file IDs. A `.json` file holds an array of IDs; any
+/// other file holds IDs separated by whitespace or commas, with `#`
+/// starting a comment that runs to the end of the line.
+pub fn read_id_list<P: AsRef<Path>>(file_path: P) -> io::Result<Vec<u32>> {
+    let text = fs::read_to_string(&file_path)?;
+    let is_json = file_path
+        .as_ref()
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if is_json {
+        return Ok(serde_json::from_str(&text)?);
+    }
+    let mut file_ids = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        for word in line.split(|c: char| c.is_whitespace() || c == ',') {
+            if word.is_empty() {
+                continue;
+            }
+            let file_id = word.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid file ID '{}' on line {}.", word, number + 1),
+                )
+            })?;
+            file_ids.push(file_id);
+        }
+    }
+    Ok(file_ids)
+}
+
+/// Loads the archive at `dat_path` and writes the decompressed file with
+/// the given ID to `out_path`. When `out_path` is a directory the file is
+/// named as by `entry_file_name_for_data`; a file path without extension
+/// gets the extension of the detected format. Returns the path written.
+pub fn extract_file_to_path<P: AsRef<Path>, Q: AsRef<Path>>(
+    dat_path: P,
+    file_id: u32,
+    out_path: Q,
+) -> io::Result<PathBuf> {
+    let archive = AnetArchive::load_from_file(dat_path)?;
+    let index = archive.index_of(file_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("File ID {} not found.", file_id),
+        )
+    })?;
+    let data = archive.read_entry(index)?;
+    let out_path = out_path.as_ref();
+    let file_path = if out_path.is_dir() {
+        out_path.join(archive.entry_file_name_for_data(index, &data))
+    } else if out_path.extension().is_none() {
+        match FormatTable::active().identify(&data) {
+            Some(format) => out_path.with_extension(&format.extension),
+            None => out_path.to_path_buf(),
+        }
+    } else {
+        out_path.to_path_buf()
+    };
+    File::create(&file_path)?.write_all(&data)?;
+    Ok(file_path)
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct BundleFile {
+    pub file_id: u32,
+    pub file_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    pub dependencies: Vec<u32>, //< File IDs this file references directly.
+}
+
+/// Written as `manifest.json` next to the files of an extracted bundle.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub root_file_id: u32,
+    pub files: Vec<BundleFile>,
+    pub missing: Vec<u32>, //< Referenced file IDs that are not in the archive.
+}
+
+impl AnetArchive {
+    /// `<name>.bin` for entries the name database knows, else
+    /// `<base_id>.bin`. A name that more entries end up with, ignoring
+    /// case, gets the base ID appended, `<name>_<base_id>.bin`, so the
+    /// files do not overwrite each other.
+    pub fn entry_file_name(&self, index: usize) -> String {
+        let id_stem = self.entry_id_stem(index);
+        match self.entry_file_stem(index) {
+            Some(stem) if self.shared_file_stems().contains(&stem.to_lowercase()) => {
+                format!("{}_{}.bin", stem, id_stem)
+            }
+            Some(stem) => format!("{}.bin", stem),
+            None => format!("{}.bin", id_stem),
+        }
+    }
+
+    fn entry_id_stem(&self, index: usize) -> String {
+        match self.mft_index_data.get(index) {
+            Some(id_entry) if id_entry.base_id != 0 => id_entry.base_id.to_string(),
+            _ => format!("index_{}", index),
+        }
+    }
+
+    /// Lowercased stems `entry_file_name` would give more than one entry
+    /// without the base ID suffix.
+    fn shared_file_stems(&self) -> &HashSet<String> {
+        self.shared_file_stems.get_or_init(|| {
+            let mut seen = HashSet::new();
+            let mut shared = HashSet::new();
+            for index in 0..self.mft_data.len() {
+                let stem = self
+                    .entry_file_stem(index)
+                    .unwrap_or_else(|| self.entry_id_stem(index))
+                    .to_lowercase();
+                if !seen.insert(stem.clone()) {
+                    shared.insert(stem);
+                }
+            }
+            shared
+        })
+    }
+
+    /// File name for the decompressed `data` of entry `index`, using the
+    /// extension of its format when the active format table knows it.
+    pub fn entry_file_name_for_data(&self, index: usize, data: &[u8]) -> String {
+        let file_name = self.entry_file_name(index);
+        match FormatTable::active().identify(data) {
+            Some(format) => Path::new(&file_name)
+                .with_extension(&format.extension)
+                .to_string_lossy()
+                .into_owned(),
+            None => file_name,
+        }
+    }
+
+    /// Path, relative to the extraction directory, of the decompressed
+    /// `data` of entry `index` under `naming`.
+    pub fn entry_path_for_data(&self, index: usize, data: &[u8], naming: ExportNaming) -> PathBuf {
+        match naming {
+            ExportNaming::Leafadoo => PathBuf::from(self.entry_file_name_for_data(index, data)),
+            ExportNaming::Gw2Browser => {
+                let (folder, extension) = gw2browser_location(AnetFileType::identify(data), data);
+                Path::new(folder)
+                    .join(self.entry_file_name(index))
+                    .with_extension(extension)
+            }
+        }
+    }
+
+    /// Writes the decompressed entries at `indices` into `directory`, named
+    /// by `entry_file_name_for_data`. Entries are read in archive order, see
+    /// `prefetch_entries`, so `written` lists them in that order rather than
+    /// the order of `indices`. Entries that fail to read are retried
+    /// as set by `set_read_retries`. A lenient archive skips entries that
+    /// cannot be read. A memory budget also caps the size of the coalesced
+    /// reads.
+    pub fn extract_entries<P: AsRef<Path>>(
+        &self,
+        indices: &[usize],
+        directory: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<ExtractReport> {
+        self.extract_entries_named(indices, directory, ExportNaming::Leafadoo, progress)
+    }
+
+    /// Like `extract_entries`, naming the files by `naming`.
+    pub fn extract_entries_named<P: AsRef<Path>>(
+        &self,
+        indices: &[usize],
+        directory: P,
+        naming: ExportNaming,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<ExtractReport> {
+        self.check_patch_safe()?;
+        fs::create_dir_all(&directory)?;
+        let mut report = ExtractReport::default();
+        for &index in indices
+            .iter()
+            .filter(|index| **index >= self.mft_data.len())
+        {
+            self.tolerate::<()>(
+                Some(index),
+                Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "MFT entry index out of range.",
+                )),
+            )?;
+        }
+        // Entries are read in archive order with coalesced reads; the ones
+        // that fail are read again on their own, with retries.
+        let mut options = PrefetchOptions::default();
+        if let Some(memory_budget) = self.memory_budget {
+            options.max_span = options.max_span.min(memory_budget);
+        }
+        let mut done = 0;
+        self.prefetch_entries(indices, &options, &mut |index, data| {
+            progress(Progress {
+                stage: ProgressStage::Extract,
+                done,
+                total: indices.len(),
+            });
+            done += 1;
+            let data = match data {
+                Ok(data) => data,
+                Err(_) => {
+                    let data = self.read_entry_with_retry(index, self.read_retries);
+                    let Some(data) = self.tolerate(Some(index), data)? else {
+                        return Ok(());
+                    };
+                    data
+                }
+            };
+            let file_path = directory
+                .as_ref()
+                .join(self.entry_path_for_data(index, &data, naming));
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            File::create(&file_path)?.write_all(&data)?;
+            report.written.push(file_path);
+            Ok(())
+        })?;
+        progress(Progress {
+            stage: ProgressStage::Extract,
+            done: indices.len(),
+            total: indices.len(),
+        });
+        self.check_patch_safe()?;
+        Ok(report)
+    }
+
+    /// Like `extract_entries_named`, reading and decompressing the entries
+    /// on `threads` threads while this one writes them out. The readers
+    /// wait while the entries not yet written use up the memory budget, see
+    /// `set_memory_budget`. `written` lists the files in the order they
+    /// were written.
+    pub fn par_extract_entries<P: AsRef<Path>>(
+        &self,
+        indices: &[usize],
+        directory: P,
+        naming: ExportNaming,
+        threads: usize,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<ExtractReport> {
+        self.check_patch_safe()?;
+        fs::create_dir_all(&directory)?;
+        let total = indices.len();
+        let thread_count = threads.clamp(1, total.max(1));
+        let mut report = ExtractReport::default();
+        let reader = self.shared_reader()?;
+        let budget = self.batch_budget();
+        thread::scope(|scope| -> io::Result<()> {
+            let (sender, receiver) = mpsc::channel();
+            for worker in 0..thread_count {
+                let sender = sender.clone();
+                let (reader, budget) = (&reader, &budget);
+                scope.spawn(move || {
+                    for &index in indices.iter().skip(worker).step_by(thread_count) {
+                        let result = budget
+                            .acquire_entry(reader, index)
+                            .and_then(|permit| Ok((reader.read_entry(index)?, permit)));
+                        if sender.send((index, result)).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // Dropping the receiver on error drops the queued permits, which
+            // lets blocked readers through to find the channel closed.
+            for (done, (index, result)) in receiver.into_iter().enumerate() {
+                progress(Progress {
+                    stage: ProgressStage::Extract,
+                    done,
+                    total,
+                });
+                let (data, _permit) = match result {
+                    Ok((data, permit)) => (data, Some(permit)),
+                    Err(_) => {
+                        let data = self.read_entry_with_retry(index, self.read_retries);
+                        let Some(data) = self.tolerate(Some(index), data)? else {
+                            continue;
+                        };
+                        (data, None)
+                    }
+                };
+                let file_path = directory
+                    .as_ref()
+                    .join(self.entry_path_for_data(index, &data, naming));
+                if let Some(parent) = file_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(&file_path)?.write_all(&data)?;
+                report.written.push(file_path);
+            }
+            Ok(())
+        })?;
+        progress(Progress {
+            stage: ProgressStage::Extract,
+            done: total,
+            total,
+        });
+        self.check_patch_safe()?;
+        Ok(report)
+    }
+
+    /// Writes the decompressed files with the given IDs into `directory`,
+    /// like `extract_entries`. An ID that is missing or cannot be read is
+    /// reported in its result and the rest carry on; only failing to write
+    /// to `directory` stops the extraction.
+    pub fn extract_ids<P: AsRef<Path>>(
+        &self,
+        file_ids: &[u32],
+        directory: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Vec<IdExtractResult>> {
+        self.check_patch_safe()?;
+        fs::create_dir_all(&directory)?;
+        let mut results = Vec::new();
+        for (done, &file_id) in file_ids.iter().enumerate() {
+            progress(Progress {
+                stage: ProgressStage::Extract,
+                done,
+                total: file_ids.len(),
+            });
+            let data = self
+                .index_of(file_id)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "File ID not in the archive.")
+                })
+                .and_then(|index| {
+                    Ok((index, self.read_entry_with_retry(index, self.read_retries)?))
+                });
+            let result = match data {
+                Ok((index, data)) => {
+                    let file_path = directory
+                        .as_ref()
+                        .join(self.entry_file_name_for_data(index, &data));
+                    File::create(&file_path)?.write_all(&data)?;
+                    IdExtractResult {
+                        file_id,
+                        file_path: Some(file_path),
+                        error: None,
+                    }
+                }
+                Err(error) => IdExtractResult {
+                    file_id,
+                    file_path: None,
+                    error: Some(error.to_string()),
+                },
+            };
+            results.push(result);
+        }
+        progress(Progress {
+            stage: ProgressStage::Extract,
+            done: file_ids.len(),
+            total: file_ids.len(),
+        });
+        self.check_patch_safe()?;
+        Ok(results)
+    }
+
+    /// Streams the decompressed entries at `indices` into a ZIP or tar
+    /// archive written to `writer`, named by `entry_file_name_for_data`.
+    /// Returns the names of the stored files.
+    pub fn export_archive<W: Write>(
+        &self,
+        indices: &[usize],
+        format: ExportFormat,
+        writer: W,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Vec<String>> {
+        self.check_patch_safe()?;
+        let mut names = Vec::new();
+        let mut report = |done| {
+            progress(Progress {
+                stage: ProgressStage::Extract,
+                done,
+                total: indices.len(),
+            })
+        };
+        match format {
+            ExportFormat::Zip => {
+                let mut zip_writer = ZipWriter::new_stream(writer);
+                let options =
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+                for (done, &index) in indices.iter().enumerate() {
+                    report(done);
+                    let Some(data) = self.tolerate(Some(index), self.read_entry(index))? else {
+                        continue;
+                    };
+                    let name = self.entry_file_name_for_data(index, &data);
+                    zip_writer.start_file(name.as_str(), options)?;
+                    zip_writer.write_all(&data)?;
+                    names.push(name);
+                }
+                zip_writer.finish()?;
+            }
+            ExportFormat::Tar => {
+                let modified = fs::metadata(&self.file_path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |duration| duration.as_secs());
+                let mut tar_builder = tar::Builder::new(writer);
+                for (done, &index) in indices.iter().enumerate() {
+                    report(done);
+                    let Some(data) = self.tolerate(Some(index), self.read_entry(index))? else {
+                        continue;
+                    };
+                    let name = self.entry_file_name_for_data(index, &data);
+                    let mut header = tar::Header::new_gnu();
+                    header.set_size(data.len() as u64);
+                    header.set_mode(EXPORTED_FILE_MODE);
+                    header.set_mtime(modified);
+                    tar_builder.append_data(&mut header, &name, data.as_slice())?;
+                    names.push(name);
+                }
+                tar_builder.finish()?;
+            }
+        }
+        report(indices.len());
+        self.check_patch_safe()?;
+        Ok(names)
+    }
+
+    pub fn is_patch_safe(&self) -> bool {
+        self.patch_safe
+    }
+
+    /// In patch-safe mode, extraction and export check that the archive on
+    /// disk is unchanged before they start and once they are done, and fail
+    /// rather than return data mixed from before and after a patch.
+    pub fn set_patch_safe(&mut self, patch_safe: bool) {
+        self.patch_safe = patch_safe;
+    }
+
+    /// Fails if the DAT header on disk, CRC included, no longer matches the
+    /// one the archive was loaded with.
+    pub fn check_unchanged(&self) -> io::Result<()> {
+        let on_disk_header = AnetArchive::read_dat_header_from_file(&self.file_path)?;
+        if on_disk_header != self.dat_header {
+            log_warn!(
+                crc = self.dat_header.crc,
+                on_disk_crc = on_disk_header.crc,
+                "Archive changed since it was loaded."
+            );
+            return Err(io::Error::other(format!(
+                "Archive changed since it was loaded: header CRC {:#010x}, now {:#010x}.",
+                self.dat_header.crc, on_disk_header.crc
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_patch_safe(&self) -> io::Result<()> {
+        if self.patch_safe {
+            self.check_unchanged()?;
+        }
+        Ok(())
+    }
+
+    /// Reloads the header and MFT from disk once they have stopped changing,
+    /// i.e. two consecutive loads agree. Fails when they still differ after
+    /// `max_polls` reloads.
+    pub fn reload_when_stable(
+        &self,
+        poll_interval: Duration,
+        max_polls: usize,
+    ) -> io::Result<AnetArchive> {
+        let mut current = AnetArchive::load_from_file(&self.file_path)?;
+        for _ in 0..max_polls {
+            thread::sleep(poll_interval);
+            let next = AnetArchive::load_from_file(&self.file_path)?;
+            if next.dat_header == current.dat_header && next.mft_data == current.mft_data {
+                return Ok(next);
+            }
+            current = next;
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "Archive did not stop changing.",
+        ))
+    }
+
+    /// Extracts the entries at `indices` into `directory` while watching the
+    /// archive for changes. When the DAT header changes mid-extraction the
+    /// extraction pauses until the archive is stable again, reloads the MFT,
+    /// rewrites already extracted entries whose MFT record changed and then
+    /// resumes, so a concurrent patch never leaves mixed data behind.
+    pub fn extract_entries_watched<P: AsRef<Path>>(
+        &mut self,
+        indices: &[usize],
+        directory: P,
+        options: &WatchedExtractOptions,
+    ) -> io::Result<ExtractReport> {
+        fs::create_dir_all(&directory)?;
+        let mut report = ExtractReport::default();
+        let mut done: Vec<usize> = Vec::new();
+
+        let mut position = 0;
+        while position < indices.len() {
+            let index = indices[position];
+            let data = self.read_entry(index);
+
+            let on_disk_header = AnetArchive::read_dat_header_from_file(&self.file_path)?;
+            if on_disk_header != self.dat_header {
+                if report.refreshes >= options.max_refreshes {
+                    return Err(io::Error::other("Archive kept changing during extraction."));
+                }
+                report.refreshes += 1;
+                let current = self.reload_when_stable(options.poll_interval, options.max_polls)?;
+                for &done_index in &done {
+                    if current.mft_data.get(done_index) != self.mft_data.get(done_index) {
+                        let file_path =
+                            directory.as_ref().join(current.entry_file_name(done_index));
+                        File::create(file_path)?.write_all(&current.read_entry(done_index)?)?;
+                        report.reextracted.push(done_index);
+                    }
+                }
+                *self = current;
+                // Read the current entry again against the refreshed MFT.
+                continue;
+            }
+
+            let file_path = directory.as_ref().join(self.entry_file_name(index));
+            File::create(&file_path)?.write_all(&data?)?;
+            report.written.push(file_path);
+            done.push(index);
+            position += 1;
+        }
+        Ok(report)
+    }
+
+    /// Extracts `file_id` and everything it references, directly or not,
+    /// into `directory`, together with a manifest describing the bundle.
+    pub fn extract_bundle<P: AsRef<Path>>(
+        &self,
+        file_id: u32,
+        directory: P,
+    ) -> io::Result<BundleManifest> {
+        let index = self.index_of(file_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("File ID {} is not in the archive.", file_id),
+            )
+        })?;
+        // Read the root before creating anything so a broken entry leaves no
+        // empty bundle behind.
+        let root_data = self.read_entry(index)?;
+        fs::create_dir_all(&directory)?;
+
+        let mut manifest = BundleManifest {
+            root_file_id: file_id,
+            ..Default::default()
+        };
+        let mut written_indices = Vec::new();
+        for bundle_file_id in self.dependency_closure(file_id) {
+            let Some(index) = self.index_of(bundle_file_id) else {
+                manifest.missing.push(bundle_file_id);
+                continue;
+            };
+            // Several file IDs can share an entry.
+            if written_indices.contains(&index) {
+                continue;
+            }
+            let data = if bundle_file_id == file_id {
+                root_data.clone()
+            } else {
+                let Some(data) = self.tolerate(Some(index), self.read_entry(index))? else {
+                    continue;
+                };
+                data
+            };
+            let file_name = self.entry_file_name_for_data(index, &data);
+            File::create(directory.as_ref().join(&file_name))?.write_all(&data)?;
+            written_indices.push(index);
+            manifest.files.push(BundleFile {
+                file_id: bundle_file_id,
+                file_name,
+                format: FormatTable::active()
+                    .identify(&data)
+                    .map(|format| format.name.clone()),
+                dependencies: self.dependencies_of(bundle_file_id).to_vec(),
+            });
+        }
+
+        let manifest_file = File::create(directory.as_ref().join(BUNDLE_MANIFEST_NAME))?;
+        serde_json::to_writer_pretty(manifest_file, &manifest)?;
+        Ok(manifest)
+    }
+}
@@ -0,0 +1,427 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType, FourCC};
+use crate::entry::EntryInfo;
+use crate::reader::open_archive_file;
+
+/// Bytes of content needed to identify an entry.
+pub const FILE_TYPE_HEAD_SIZE: usize = 16;
+const PF_FILE_TYPE_OFFSET: usize = 8;
+const RIFF_FORM_TYPE_OFFSET: usize = 8;
+const MASK_24_BITS: u32 = 0x00ffffff;
+const MASK_16_BITS: u32 = 0x0000ffff;
+const SCAN_INDEX_EXTENSION: &str = ".leafidx";
+
+const FOURCC_FILE_TYPES: [(FourCC, AnetFileType); 13] = [
+    (FourCC::FccAtex, AnetFileType::AnftAtex),
+    (FourCC::FccAttx, AnetFileType::AnftAttx),
+    (FourCC::FccAtec, AnetFileType::AnftAtec),
+    (FourCC::FccAtep, AnetFileType::AnftAtep),
+    (FourCC::FccAteu, AnetFileType::AnftAteu),
+    (FourCC::FccAtet, AnetFileType::AnftAtet),
+    (FourCC::FccCtex, AnetFileType::AnftCtex),
+    (FourCC::FccDds, AnetFileType::AnftDds),
+    (FourCC::FccPng, AnetFileType::AnftPng),
+    (FourCC::FccStrs, AnetFileType::AnftStringFile),
+    (FourCC::FccAsnd, AnetFileType::AnftSound),
+    (FourCC::FccOggS, AnetFileType::AnftOgg),
+    (FourCC::FccArap, AnetFileType::AnftArap),
+];
+
+const PF_FILE_TYPES: [(FourCC, AnetFileType); 28] = [
+    (FourCC::FccArmf, AnetFileType::AnftManifest),
+    (FourCC::FccAsndPf, AnetFileType::AnftSound),
+    (FourCC::FccAbnk, AnetFileType::AnftBank),
+    (FourCC::FccAbix, AnetFileType::AnftBankIndex),
+    (FourCC::FccAmsp, AnetFileType::AnftAudioScript),
+    (FourCC::FccCdhs, AnetFileType::AnftShaderCache),
+    (FourCC::FccCinp, AnetFileType::AnftCinematic),
+    (FourCC::FccCntc, AnetFileType::AnftGameContent),
+    (FourCC::FccModl, AnetFileType::AnftModel),
+    (FourCC::FccGeom, AnetFileType::AnftModel),
+    (FourCC::FccDeps, AnetFileType::AnftDependencyTable),
+    (FourCC::FccEula, AnetFileType::AnftEula),
+    (FourCC::FccHvkC, AnetFileType::AnftModelCollisionManifest),
+    (FourCC::FccLocl, AnetFileType::AnftConfig),
+    (FourCC::FccMapc, AnetFileType::AnftMapParam),
+    (FourCC::FccMpsd, AnetFileType::AnftMapShadow),
+    (FourCC::FccPimg, AnetFileType::AnftPagedImageTable),
+    (FourCC::FccAmat, AnetFileType::AnftMaterial),
+    (FourCC::FccAnic, AnetFileType::AnftAnimSequences),
+    (FourCC::FccEmoc, AnetFileType::AnftEmoteAnimation),
+    (FourCC::FccPrlt, AnetFileType::AnftGameContentPortalManifest),
+    (FourCC::FccCmpc, AnetFileType::AnftComposite),
+    (FourCC::FccTxtm, AnetFileType::AnftTextPackManifest),
+    (FourCC::FccTxtV, AnetFileType::AnftTextPackVariant),
+    (FourCC::FccTxtv, AnetFileType::AnftTextPackVoices),
+    (FourCC::FccCmaC, AnetFileType::AnftMapCollision),
+    (FourCC::FccMMet, AnetFileType::AnftMapMetadata),
+    (FourCC::FccAfnt, AnetFileType::AnftBitmapFontFile),
+];
+
+const SHORT_MAGIC_FILE_TYPES: [(FourCC, u32, AnetFileType); 6] = [
+    (FourCC::FccJpeg, MASK_24_BITS, AnetFileType::AnftJpeg),
+    (FourCC::FccId3, MASK_24_BITS, AnetFileType::AnftMp3),
+    (FourCC::FccBink2, MASK_24_BITS, AnetFileType::AnftBink2video),
+    (FourCC::FccUtf8, MASK_24_BITS, AnetFileType::AnftUtf8),
+    (FourCC::FccMz, MASK_16_BITS, AnetFileType::AnftExe),
+    (FourCC::FccMp3, MASK_16_BITS, AnetFileType::AnftMp3),
+];
+
+impl FourCC {
+    pub const ALL: [FourCC; 62] = [
+        FourCC::FccAtex,
+        FourCC::FccAttx,
+        FourCC::FccAtec,
+        FourCC::FccAtep,
+        FourCC::FccAteu,
+        FourCC::FccAtet,
+        FourCC::Fcc3dcx,
+        FourCC::FccDxt,
+        FourCC::FccDds,
+        FourCC::FccStrs,
+        FourCC::FccAsnd,
+        FourCC::FccRiff,
+        FourCC::FccTtf,
+        FourCC::FccOggS,
+        FourCC::FccArap,
+        FourCC::FccCtex,
+        FourCC::FccDxt1,
+        FourCC::FccDxt2,
+        FourCC::FccDxt3,
+        FourCC::FccDxt4,
+        FourCC::FccDxt5,
+        FourCC::FccDxtn,
+        FourCC::FccDxtl,
+        FourCC::FccDxta,
+        FourCC::FccR32f,
+        FourCC::FccWebp,
+        FourCC::FccArmf,
+        FourCC::FccAsndPf,
+        FourCC::FccAbnk,
+        FourCC::FccAbix,
+        FourCC::FccAmsp,
+        FourCC::FccCdhs,
+        FourCC::FccCinp,
+        FourCC::FccCntc,
+        FourCC::FccModl,
+        FourCC::FccGeom,
+        FourCC::FccDeps,
+        FourCC::FccEula,
+        FourCC::FccHvkC,
+        FourCC::FccLocl,
+        FourCC::FccMapc,
+        FourCC::FccMpsd,
+        FourCC::FccPimg,
+        FourCC::FccAmat,
+        FourCC::FccAnic,
+        FourCC::FccEmoc,
+        FourCC::FccPrlt,
+        FourCC::FccCmpc,
+        FourCC::FccTxtm,
+        FourCC::FccTxtV,
+        FourCC::FccTxtv,
+        FourCC::FccPng,
+        FourCC::FccCmaC,
+        FourCC::FccMMet,
+        FourCC::FccAfnt,
+        FourCC::FccMz,
+        FourCC::FccPf,
+        FourCC::FccMp3,
+        FourCC::FccJpeg,
+        FourCC::FccId3,
+        FourCC::FccBink2,
+        FourCC::FccUtf8,
+    ];
+
+    /// The tag whose value is `value`, read little-endian from data. Short
+    /// magics only match values with the unused high bytes cleared.
+    pub fn from_u32(value: u32) -> Option<FourCC> {
+        FourCC::ALL
+            .iter()
+            .copied()
+            .find(|fourcc| *fourcc as u32 == value)
+    }
+
+    /// The tag as it appears in data.
+    pub fn as_bytes(self) -> [u8; 4] {
+        (self as u32).to_le_bytes()
+    }
+
+    /// The type of content starting with this tag. Container tags give the
+    /// generic type; use `AnetFileType::identify` to look inside them.
+    pub fn file_type(self) -> Option<AnetFileType> {
+        match self {
+            FourCC::FccPf => Some(AnetFileType::AnftPf),
+            FourCC::FccRiff => Some(AnetFileType::AnftRiff),
+            FourCC::FccTtf => Some(AnetFileType::AnftFontFile),
+            _ => lookup(&FOURCC_FILE_TYPES, self).or_else(|| {
+                SHORT_MAGIC_FILE_TYPES
+                    .iter()
+                    .find(|(fourcc, _, _)| *fourcc == self)
+                    .map(|(_, _, file_type)| *file_type)
+            }),
+        }
+    }
+
+    /// The type of a PF file with this tag as its file type.
+    pub fn pf_file_type(self) -> Option<AnetFileType> {
+        lookup(&PF_FILE_TYPES, self)
+    }
+}
+
+impl TryFrom<u32> for FourCC {
+    type Error = u32;
+
+    /// Fails with the value itself when no tag has it.
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        FourCC::from_u32(value).ok_or(value)
+    }
+}
+
+fn lookup(table: &[(FourCC, AnetFileType)], fourcc: FourCC) -> Option<AnetFileType> {
+    table
+        .iter()
+        .find(|(table_fourcc, _)| *table_fourcc == fourcc)
+        .map(|(_, file_type)| *file_type)
+}
+
+impl AnetFileType {
+    /// Identifies content from its first bytes, `FILE_TYPE_HEAD_SIZE` of
+    /// which are enough for every known format.
+    pub fn identify(head: &[u8]) -> AnetFileType {
+        if head.len() < 4 {
+            return AnetFileType::AnftUnknown;
+        }
+        let magic = LittleEndian::read_u32(head);
+        if magic & MASK_16_BITS == FourCC::FccPf as u32 {
+            return head
+                .get(PF_FILE_TYPE_OFFSET..PF_FILE_TYPE_OFFSET + 4)
+                .and_then(|file_type| FourCC::from_u32(LittleEndian::read_u32(file_type)))
+                .and_then(FourCC::pf_file_type)
+                .unwrap_or(AnetFileType::AnftPf);
+        }
+        if magic == FourCC::FccRiff as u32 {
+            let form_type = head.get(RIFF_FORM_TYPE_OFFSET..RIFF_FORM_TYPE_OFFSET + 4);
+            return match form_type.map(LittleEndian::read_u32) {
+                Some(form_type) if form_type == FourCC::FccWebp as u32 => AnetFileType::AnftWebp,
+                _ => AnetFileType::AnftRiff,
+            };
+        }
+        if let Some(file_type) = FourCC::from_u32(magic).and_then(FourCC::file_type) {
+            return file_type;
+        }
+        // Embedded OpenType fonts start with a version word rather than a tag.
+        if magic == FourCC::FccTtf as u32 {
+            return AnetFileType::AnftFontFile;
+        }
+        SHORT_MAGIC_FILE_TYPES
+            .iter()
+            .find(|(fourcc, mask, _)| magic & mask == *fourcc as u32)
+            .map_or(AnetFileType::AnftUnknown, |(_, _, file_type)| *file_type)
+    }
+}
+
+/// Identifies the state of an archive a scan was made from. The game
+/// rewrites the header CRC and the file when it patches the archive.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanKey {
+    pub archive_crc: u32,
+    pub modified: u64, //< Modification time of the archive in nanoseconds since the Unix epoch.
+    pub mft_offset: u64,
+    pub entry_count: usize,
+}
+
+/// File type of every MFT entry, as found by `AnetArchive::scan_file_types`.
+/// Entries that could not be read are `AnftUnknown`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct TypeScan {
+    pub key: ScanKey,
+    pub file_types: Vec<AnetFileType>, //< Indexed by MFT index.
+}
+
+impl EntryInfo<'_> {
+    /// The type of the entry, identified on first use and cached on the
+    /// archive. See `AnetArchive::entry_kinds`.
+    pub fn kind(&self) -> io::Result<AnetFileType> {
+        let kinds = self.archive().entry_kinds(&[self.index()])?;
+        Ok(kinds[0])
+    }
+}
+
+impl TypeScan {
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(file_path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Whether the scan was made from the archive as it is now.
+    pub fn matches(&self, archive: &AnetArchive) -> bool {
+        self.file_types.len() == archive.mft_data.len()
+            && archive.scan_key().is_ok_and(|key| key == self.key)
+    }
+
+    pub fn find<'a>(
+        &self,
+        archive: &'a AnetArchive,
+        file_type: AnetFileType,
+    ) -> Vec<EntryInfo<'a>> {
+        self.file_types
+            .iter()
+            .enumerate()
+            .filter(|(_, entry_type)| **entry_type == file_type)
+            .filter_map(|(index, _)| archive.entry(index))
+            .collect()
+    }
+}
+
+impl AnetArchive {
+    pub fn scan_key(&self) -> io::Result<ScanKey> {
+        let modified = fs::metadata(&self.file_path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Ok(ScanKey {
+            archive_crc: self.dat_header.crc,
+            modified,
+            mft_offset: self.dat_header.mft_offset,
+            entry_count: self.mft_data.len(),
+        })
+    }
+
+    /// Sidecar file the scan index is kept in, e.g. `Gw2.dat.leafidx`.
+    pub fn scan_index_path(&self) -> PathBuf {
+        let mut file_name = OsString::from(self.file_path.as_os_str());
+        file_name.push(SCAN_INDEX_EXTENSION);
+        PathBuf::from(file_name)
+    }
+
+    /// Picks up the sidecar scan index if it was made from this archive.
+    pub(crate) fn load_scan_index(&mut self) {
+        if let Ok(scan) = TypeScan::load_from_file(self.scan_index_path()) {
+            if scan.matches(self) {
+                self.type_scan = scan.into();
+            }
+        }
+    }
+
+    /// Reads the head of every in-use entry and identifies its type.
+    pub fn scan_file_types(&self) -> io::Result<TypeScan> {
+        let mut reader = BufReader::new(open_archive_file(&self.file_path)?);
+        let mut scan = TypeScan {
+            key: self.scan_key()?,
+            file_types: vec![AnetFileType::AnftUnknown; self.mft_data.len()],
+        };
+        for entry in self.in_use_entries().filter(|entry| entry.size() > 0) {
+            if let Ok(head) =
+                self.read_entry_head_from(&mut reader, entry.index(), FILE_TYPE_HEAD_SIZE)
+            {
+                scan.file_types[entry.index()] = AnetFileType::identify(&head);
+            }
+        }
+        Ok(scan)
+    }
+
+    /// The type of every entry, from the sidecar scan index when it is up
+    /// to date. Otherwise the archive is scanned and the index rewritten;
+    /// failing to write it only costs a rescan next time.
+    pub fn type_scan(&self) -> io::Result<&TypeScan> {
+        if let Some(scan) = self.type_scan.get() {
+            return Ok(scan);
+        }
+        let scan = self.scan_file_types()?;
+        if let Err(_error) = scan.save_to_file(self.scan_index_path()) {
+            log_warn!(error = %_error, "Could not save the scan index.");
+        }
+        Ok(self.type_scan.get_or_init(|| scan))
+    }
+
+    /// Types of the entries at `indices`, in the same order. Types come
+    /// from the type scan when one is loaded; otherwise each entry is
+    /// identified from its head the first time it is asked for and the
+    /// result cached. Unused, empty and unreadable entries are
+    /// `AnftUnknown`.
+    pub fn entry_kinds(&self, indices: &[usize]) -> io::Result<Vec<AnetFileType>> {
+        if let Some(scan) = self.type_scan.get() {
+            self.counters.record_cache_hits(indices.len() as u64);
+            return Ok(indices
+                .iter()
+                .map(|&index| {
+                    scan.file_types
+                        .get(index)
+                        .copied()
+                        .unwrap_or(AnetFileType::AnftUnknown)
+                })
+                .collect());
+        }
+        let mut reader = None;
+        let mut kinds = Vec::with_capacity(indices.len());
+        for &index in indices {
+            if let Some(&kind) = self.entry_kinds.borrow().get(&index) {
+                self.counters.record_cache_hits(1);
+                kinds.push(kind);
+                continue;
+            }
+            self.counters.record_cache_miss();
+            let kind = match self.entry(index) {
+                Some(entry) if entry.is_in_use() && entry.size() > 0 => {
+                    let reader = match &mut reader {
+                        Some(reader) => reader,
+                        None => reader.insert(BufReader::new(open_archive_file(&self.file_path)?)),
+                    };
+                    self.read_entry_head_from(reader, index, FILE_TYPE_HEAD_SIZE)
+                        .map_or(AnetFileType::AnftUnknown, |head| {
+                            AnetFileType::identify(&head)
+                        })
+                }
+                _ => AnetFileType::AnftUnknown,
+            };
+            self.entry_kinds.borrow_mut().insert(index, kind);
+            kinds.push(kind);
+        }
+        Ok(kinds)
+    }
+
+    /// Entries of the given type.
+    pub fn find_by_type(&self, file_type: AnetFileType) -> io::Result<Vec<EntryInfo<'_>>> {
+        Ok(self.type_scan()?.find(self, file_type))
+    }
+
+    /// Like `find_by_type`, reusing the scan saved at `cache_path` when it
+    /// was made from this MFT and saving a fresh scan otherwise.
+    pub fn find_by_type_cached<P: AsRef<Path>>(
+        &self,
+        file_type: AnetFileType,
+        cache_path: P,
+    ) -> io::Result<Vec<EntryInfo<'_>>> {
+        let cache_path = cache_path.as_ref();
+        let scan = match TypeScan::load_from_file(cache_path) {
+            Ok(scan) if scan.matches(self) => scan,
+            _ => {
+                let scan = self.scan_file_types()?;
+                if let Some(parent) = cache_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                scan.save_to_file(cache_path)?;
+                scan
+            }
+        };
+        Ok(scan.find(self, file_type))
+    }
+}
@@ -0,0 +1,328 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+use crate::entry::EntryInfo;
+
+const TEXTURE_TYPES: [AnetFileType; 11] = [
+    AnetFileType::AnftAtex,
+    AnetFileType::AnftAttx,
+    AnetFileType::AnftAtec,
+    AnetFileType::AnftAtep,
+    AnetFileType::AnftAteu,
+    AnetFileType::AnftAtet,
+    AnetFileType::AnftCtex,
+    AnetFileType::AnftDds,
+    AnetFileType::AnftJpeg,
+    AnetFileType::AnftWebp,
+    AnetFileType::AnftPng,
+];
+const SOUND_TYPES: [AnetFileType; 7] = [
+    AnetFileType::AnftSound,
+    AnetFileType::AnftAsndMp3,
+    AnetFileType::AnftAsndOgg,
+    AnetFileType::AnftPackedMp3,
+    AnetFileType::AnftPackedOgg,
+    AnetFileType::AnftOgg,
+    AnetFileType::AnftMp3,
+];
+const BINARY_TYPES: [AnetFileType; 3] = [
+    AnetFileType::AnftBinary,
+    AnetFileType::AnftDll,
+    AnetFileType::AnftExe,
+];
+
+/// Selects entries by file ID, either an inclusive range or a pattern
+/// matched against the decimal ID, where `*` stands for any digits and `?`
+/// for exactly one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdSelector {
+    Range(u32, u32),
+    Pattern(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SizeComparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// A bound on the stored size of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeCondition {
+    pub comparison: SizeComparison,
+    pub bytes: u64,
+}
+
+/// Which entries to work on. An entry is selected when it is in use and
+/// passes every kind of selector given: at least one of the ID selectors,
+/// at least one of the file types, every size condition and at least one
+/// of the name patterns. An empty filter selects every in-use entry.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct EntryFilter {
+    pub ids: Vec<IdSelector>,
+    pub file_types: Vec<AnetFileType>,
+    pub sizes: Vec<SizeCondition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>, //< Patterns matched against `AnetArchive::entry_name`, ignoring case.
+}
+
+fn invalid_selector(what: &str, value: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Invalid {} selector '{}'.", what, value),
+    )
+}
+
+/// Matches `text` against a pattern of literal characters, `*` and `?`.
+pub(crate) fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| glob_matches(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && glob_matches(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_matches(rest, &text[1..]),
+    }
+}
+
+impl IdSelector {
+    /// Parses `100000-100500`, a single ID, or a pattern such as `1234*`.
+    pub fn parse(value: &str) -> io::Result<Self> {
+        let invalid = || invalid_selector("ID", value);
+        if value.contains(['*', '?']) {
+            if !value
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '*' || c == '?')
+            {
+                return Err(invalid());
+            }
+            return Ok(IdSelector::Pattern(value.to_string()));
+        }
+        let (first, last) = value.split_once('-').unwrap_or((value, value));
+        let first = first.trim().parse().map_err(|_| invalid())?;
+        let last = last.trim().parse().map_err(|_| invalid())?;
+        if first > last {
+            return Err(invalid());
+        }
+        Ok(IdSelector::Range(first, last))
+    }
+
+    pub fn matches(&self, file_id: u32) -> bool {
+        match self {
+            IdSelector::Range(first, last) => (*first..=*last).contains(&file_id),
+            IdSelector::Pattern(pattern) => {
+                glob_matches(pattern.as_bytes(), file_id.to_string().as_bytes())
+            }
+        }
+    }
+}
+
+impl SizeCondition {
+    /// Parses a size with an optional leading comparison and unit suffix,
+    /// e.g. `>1MiB`, `<=4096` or `=512B`. `K`, `M` and `G` (with or without
+    /// `iB`) are binary multiples, `KB`, `MB` and `GB` decimal ones.
+    pub fn parse(value: &str) -> io::Result<Self> {
+        let invalid = || invalid_selector("size", value);
+        let (comparison, rest) = [
+            (">=", SizeComparison::GreaterOrEqual),
+            ("<=", SizeComparison::LessOrEqual),
+            (">", SizeComparison::Greater),
+            ("<", SizeComparison::Less),
+            ("=", SizeComparison::Equal),
+        ]
+        .iter()
+        .find_map(|(prefix, comparison)| Some((*comparison, value.strip_prefix(prefix)?)))
+        .unwrap_or((SizeComparison::Equal, value));
+
+        let rest = rest.trim();
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (number, unit) = rest.split_at(split);
+        let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kib" => 1 << 10,
+            "m" | "mib" => 1 << 20,
+            "g" | "gib" => 1 << 30,
+            "kb" => 1_000,
+            "mb" => 1_000_000,
+            "gb" => 1_000_000_000,
+            _ => return Err(invalid()),
+        };
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+        Ok(SizeCondition {
+            comparison,
+            bytes: (number * multiplier as f64) as u64,
+        })
+    }
+
+    pub fn matches(&self, size: u64) -> bool {
+        match self.comparison {
+            SizeComparison::Less => size < self.bytes,
+            SizeComparison::LessOrEqual => size <= self.bytes,
+            SizeComparison::Equal => size == self.bytes,
+            SizeComparison::GreaterOrEqual => size >= self.bytes,
+            SizeComparison::Greater => size > self.bytes,
+        }
+    }
+}
+
+/// Parses a file type given either as a category (`texture`, `sound`,
+/// `binary`), by its name without the prefix (`atex`, `model`) or by its
+/// full name (`AnftAtex`).
+pub fn parse_file_types(value: &str) -> io::Result<Vec<AnetFileType>> {
+    match value.to_ascii_lowercase().as_str() {
+        "texture" => return Ok(TEXTURE_TYPES.to_vec()),
+        "sound" => return Ok(SOUND_TYPES.to_vec()),
+        "binary" => return Ok(BINARY_TYPES.to_vec()),
+        _ => {}
+    }
+    let from_name = |name: String| serde_json::from_value(serde_json::Value::String(name)).ok();
+    let mut short_name = String::from("Anft");
+    let mut chars = value.chars();
+    short_name.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+    short_name.extend(chars.map(|c| c.to_ascii_lowercase()));
+    from_name(value.to_string())
+        .or_else(|| from_name(short_name))
+        .map(|file_type| vec![file_type])
+        .ok_or_else(|| invalid_selector("file type", value))
+}
+
+impl EntryFilter {
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+            && self.file_types.is_empty()
+            && self.sizes.is_empty()
+            && self.names.is_empty()
+    }
+
+    /// Adds the comma-separated ID selectors of `value`.
+    pub fn add_ids(&mut self, value: &str) -> io::Result<()> {
+        for selector in value.split(',') {
+            self.ids.push(IdSelector::parse(selector.trim())?);
+        }
+        Ok(())
+    }
+
+    pub fn add_file_types(&mut self, value: &str) -> io::Result<()> {
+        for name in value.split(',') {
+            self.file_types.extend(parse_file_types(name.trim())?);
+        }
+        Ok(())
+    }
+
+    pub fn add_size(&mut self, value: &str) -> io::Result<()> {
+        self.sizes.push(SizeCondition::parse(value)?);
+        Ok(())
+    }
+
+    /// Adds a name pattern, where `*` stands for any text and `?` for one
+    /// character.
+    pub fn add_name(&mut self, value: &str) {
+        self.names.push(value.to_string());
+    }
+
+    fn matches_name(&self, archive: &AnetArchive, index: usize) -> bool {
+        if self.names.is_empty() {
+            return true;
+        }
+        let Some(name) = archive.entry_name(index) else {
+            return false;
+        };
+        let name = name.to_lowercase();
+        self.names
+            .iter()
+            .any(|pattern| glob_matches(pattern.to_lowercase().as_bytes(), name.as_bytes()))
+    }
+
+    /// Whether `entry` passes the ID, size and name selectors; the file type
+    /// needs the archive scanned and is checked by `AnetArchive::select`.
+    fn matches_mft(&self, archive: &AnetArchive, entry: &EntryInfo) -> bool {
+        let file_id = archive
+            .mft_index_data
+            .get(entry.index())
+            .map_or(0, |id_entry| id_entry.base_id);
+        entry.is_in_use()
+            && (self.ids.is_empty() || self.ids.iter().any(|ids| ids.matches(file_id)))
+            && self
+                .sizes
+                .iter()
+                .all(|size| size.matches(u64::from(entry.size())))
+            && self.matches_name(archive, entry.index())
+    }
+}
+
+impl AnetArchive {
+    /// MFT indices of the entries `filter` selects, in MFT order. Only the
+    /// entries that pass the ID, size and name selectors are classified for the
+    /// file type selectors, see `entry_kinds`.
+    pub fn select(&self, filter: &EntryFilter) -> io::Result<Vec<usize>> {
+        let indices: Vec<usize> = self
+            .entries()
+            .filter(|entry| filter.matches_mft(self, entry))
+            .map(|entry| entry.index())
+            .collect();
+        if filter.file_types.is_empty() {
+            return Ok(indices);
+        }
+        let kinds = self.entry_kinds(&indices)?;
+        Ok(indices
+            .into_iter()
+            .zip(kinds)
+            .filter(|(_, kind)| filter.file_types.contains(kind))
+            .map(|(index, _)| index)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_selectors_parse() {
+        assert_eq!(
+            IdSelector::parse("100").unwrap(),
+            IdSelector::Range(100, 100)
+        );
+        assert_eq!(
+            IdSelector::parse("100 - 200").unwrap(),
+            IdSelector::Range(100, 200)
+        );
+        assert!(IdSelector::parse("12?4*").unwrap().matches(1234567));
+        for value in [
+            "",
+            "-",
+            "1-",
+            "-1",
+            "5-3",
+            "1-2-3",
+            "abc",
+            "12a*",
+            "1-2*",
+            "4294967296",
+        ] {
+            assert!(IdSelector::parse(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn size_conditions_parse() {
+        let condition = SizeCondition::parse(">=1.5KiB").unwrap();
+        assert_eq!(condition.comparison, SizeComparison::GreaterOrEqual);
+        assert_eq!(condition.bytes, 1536);
+        assert_eq!(SizeCondition::parse("2 MB").unwrap().bytes, 2_000_000);
+        assert_eq!(
+            SizeCondition::parse("512").unwrap().comparison,
+            SizeComparison::Equal
+        );
+        for value in [
+            "", ">", "<=KiB", "-5", "1.2.3", ".", "1e3", "5TB", "5 K B", "=>5", "nan",
+        ] {
+            assert!(SizeCondition::parse(value).is_err(), "{}", value);
+        }
+    }
+}
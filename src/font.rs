@@ -0,0 +1,207 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetFileReference;
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const SFNT_VERSION_TRUETYPE: u32 = 0x00010000;
+const SFNT_VERSION_OPENTYPE: u32 = 0x4f54544f; // 'OTTO'
+const SFNT_HEADER_SIZE: usize = 12;
+const SFNT_TABLE_RECORD_SIZE: usize = 16;
+const HEAD_CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
+const HEAD_CHECKSUM_MAGIC: u32 = 0xb1b0afba;
+
+const EOT_MAGIC_NUMBER: u16 = 0x504c;
+const EOT_MAGIC_OFFSET: usize = 34;
+const EOT_FLAGS_OFFSET: usize = 12;
+const EOT_FLAG_COMPRESSED: u32 = 0x00000004;
+const EOT_FLAG_XOR_ENCRYPTED: u32 = 0x10000000;
+const EOT_XOR_KEY: u8 = 0x50;
+
+const AFNT_CHUNK: &[u8; 4] = b"AFNT";
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetGlyph {
+    pub code_point: u16,
+    pub x: u16,
+    pub y: u16,
+    pub width: u8,
+    pub height: u8,
+    pub offset_x: i8,
+    pub offset_y: i8,
+    pub advance: u8,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetBitmapFont {
+    pub line_height: u16,
+    pub baseline: u16,
+    pub texture: Option<AnetFileReference>, //< Atlas texture holding the glyph bitmaps.
+    pub glyphs: Vec<AnetGlyph>,
+}
+
+impl AnetBitmapFont {
+    /// Parses the `AFNT` chunk of a decompressed bitmap font entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(AFNT_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing AFNT chunk."))?;
+
+        let _scope = trace::scope("AFNT");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut font = AnetBitmapFont {
+            line_height: reader.field("line_height").read_u16()?,
+            baseline: reader.field("baseline").read_u16()?,
+            texture: reader.field("texture").read_file_reference()?,
+            ..Default::default()
+        };
+        let (count, position) = reader.field("glyphs").read_array()?;
+        let mut glyph_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            font.glyphs.push(AnetGlyph {
+                code_point: glyph_reader.read_u16()?,
+                x: glyph_reader.read_u16()?,
+                y: glyph_reader.read_u16()?,
+                width: glyph_reader.read_u8()?,
+                height: glyph_reader.read_u8()?,
+                offset_x: glyph_reader.read_u8()? as i8,
+                offset_y: glyph_reader.read_u8()? as i8,
+                advance: glyph_reader.read_u8()?,
+            });
+            glyph_reader.skip(1)?;
+        }
+        Ok(font)
+    }
+
+    /// Writes the glyph metrics as JSON, to be used together with the atlas
+    /// texture extracted from `texture`.
+    pub fn write_metrics<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let file = File::create(file_path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+pub fn is_sfnt(data: &[u8]) -> bool {
+    data.len() >= SFNT_HEADER_SIZE
+        && matches!(
+            BigEndian::read_u32(data),
+            SFNT_VERSION_TRUETYPE | SFNT_VERSION_OPENTYPE
+        )
+}
+
+pub fn is_eot(data: &[u8]) -> bool {
+    data.len() > EOT_MAGIC_OFFSET + 2
+        && LittleEndian::read_u16(&data[EOT_MAGIC_OFFSET..]) == EOT_MAGIC_NUMBER
+}
+
+/// Returns a standalone `.ttf` from a font entry, stripping an Embedded
+/// OpenType wrapper if present and repairing the table checksums.
+pub fn extract_font(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut font_data = if is_eot(data) {
+        strip_eot_header(data)?
+    } else {
+        data.to_vec()
+    };
+    if !is_sfnt(&font_data) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Font entry does not contain a TrueType font.",
+        ));
+    }
+    repair_checksums(&mut font_data)?;
+    Ok(font_data)
+}
+
+pub fn extract_font_to_file<P: AsRef<Path>>(data: &[u8], file_path: P) -> io::Result<()> {
+    let font_data = extract_font(data)?;
+    File::create(file_path)?.write_all(&font_data)
+}
+
+fn strip_eot_header(data: &[u8]) -> io::Result<Vec<u8>> {
+    let eot_size = LittleEndian::read_u32(data) as usize;
+    let font_data_size = LittleEndian::read_u32(&data[4..]) as usize;
+    let flags = LittleEndian::read_u32(&data[EOT_FLAGS_OFFSET..]);
+    if flags & EOT_FLAG_COMPRESSED != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "MicroType Express compressed EOT fonts are not supported.",
+        ));
+    }
+    let eot_size = eot_size.min(data.len());
+    let font_start = eot_size
+        .checked_sub(font_data_size)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid EOT font data size."))?;
+
+    let mut font_data = data[font_start..eot_size].to_vec();
+    if flags & EOT_FLAG_XOR_ENCRYPTED != 0 {
+        font_data.iter_mut().for_each(|byte| *byte ^= EOT_XOR_KEY);
+    }
+    Ok(font_data)
+}
+
+fn table_checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, word| {
+        let mut padded = [0; 4];
+        padded[..word.len()].copy_from_slice(word);
+        sum.wrapping_add(BigEndian::read_u32(&padded))
+    })
+}
+
+fn repair_checksums(data: &mut [u8]) -> io::Result<()> {
+    let num_tables = BigEndian::read_u16(&data[4..]) as usize;
+    let directory_end = SFNT_HEADER_SIZE + num_tables * SFNT_TABLE_RECORD_SIZE;
+    if directory_end > data.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Font table directory is truncated.",
+        ));
+    }
+
+    let mut head_offset = None;
+    for index in 0..num_tables {
+        let record = SFNT_HEADER_SIZE + index * SFNT_TABLE_RECORD_SIZE;
+        let tag = &data[record..record + 4];
+        let offset = BigEndian::read_u32(&data[record + 8..]) as usize;
+        let length = BigEndian::read_u32(&data[record + 12..]) as usize;
+        if offset
+            .checked_add(length)
+            .is_none_or(|end| end > data.len())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Font table extends past the end of the font.",
+            ));
+        }
+        if tag == b"head" {
+            if length < HEAD_CHECKSUM_ADJUSTMENT_OFFSET + 4 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Font head table is truncated.",
+                ));
+            }
+            head_offset = Some(offset);
+            BigEndian::write_u32(&mut data[offset + HEAD_CHECKSUM_ADJUSTMENT_OFFSET..], 0);
+        }
+        let checksum = table_checksum(&data[offset..offset + length]);
+        BigEndian::write_u32(&mut data[record + 4..], checksum);
+    }
+
+    if let Some(head_offset) = head_offset {
+        let adjustment = HEAD_CHECKSUM_MAGIC.wrapping_sub(table_checksum(data));
+        BigEndian::write_u32(
+            &mut data[head_offset + HEAD_CHECKSUM_ADJUSTMENT_OFFSET..],
+            adjustment,
+        );
+    }
+    Ok(())
+}
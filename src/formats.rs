@@ -0,0 +1,162 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+const EMBEDDED_FORMATS: &str = include_str!("../data/formats.json");
+const FORMATS_FILE_VARIABLE: &str = "LEAFADOO_FORMATS";
+const PF_MAGIC: &[u8; 2] = b"PF";
+const PF_FILE_TYPE_OFFSET: usize = 8;
+
+static ACTIVE_FORMATS: OnceLock<FormatTable> = OnceLock::new();
+
+/// Bytes expected at `offset`, written as a hex string in the format file.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FormatSignature {
+    pub offset: usize,
+    pub bytes: String,
+    #[serde(skip)]
+    decoded: Vec<u8>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FormatInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<FormatSignature>, //< All must match, for non-PF formats.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pf_type: Option<String>, //< File type of PF formats.
+    pub extension: String,
+    pub category: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>, //< Chunks leafadoo knows how to parse.
+}
+
+/// Everything leafadoo knows about the formats stored in archives. A copy is
+/// compiled into the binary so release builds are self-contained; it can be
+/// replaced by a file of the same layout through `LEAFADOO_FORMATS`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FormatTable {
+    pub formats: Vec<FormatInfo>,
+}
+
+fn decode_hex(hex: &str) -> io::Result<Vec<u8>> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid signature bytes '{}'.", hex),
+        )
+    };
+    if !hex.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(invalid)
+}
+
+impl FormatSignature {
+    fn matches(&self, data: &[u8]) -> bool {
+        data.get(self.offset..)
+            .is_some_and(|data| data.starts_with(&self.decoded))
+    }
+}
+
+impl FormatInfo {
+    pub fn matches(&self, data: &[u8]) -> bool {
+        match &self.pf_type {
+            Some(pf_type) => {
+                data.starts_with(PF_MAGIC)
+                    && data
+                        .get(PF_FILE_TYPE_OFFSET..PF_FILE_TYPE_OFFSET + 4)
+                        .is_some_and(|file_type| file_type == pf_type.as_bytes())
+            }
+            None => {
+                !self.signatures.is_empty()
+                    && self
+                        .signatures
+                        .iter()
+                        .all(|signature| signature.matches(data))
+            }
+        }
+    }
+}
+
+impl FormatTable {
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let mut table: FormatTable = serde_json::from_slice(data)?;
+        table.prepare()?;
+        Ok(table)
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(file_path)?);
+        let mut table: FormatTable = serde_json::from_reader(reader)?;
+        table.prepare()?;
+        Ok(table)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    fn prepare(&mut self) -> io::Result<()> {
+        for format in &mut self.formats {
+            if format.pf_type.is_none() && format.signatures.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Format {} has neither a PF type nor signatures.",
+                        format.name
+                    ),
+                ));
+            }
+            for signature in &mut format.signatures {
+                signature.decoded = decode_hex(&signature.bytes)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn embedded() -> Self {
+        Self::load_from_bytes(EMBEDDED_FORMATS.as_bytes())
+            .expect("Embedded format table is invalid.")
+    }
+
+    /// Loads the table named by `LEAFADOO_FORMATS` and makes it the active
+    /// one. Does nothing when the variable is unset.
+    pub fn install_from_env() -> io::Result<()> {
+        let Some(file_path) = env::var_os(FORMATS_FILE_VARIABLE) else {
+            return Ok(());
+        };
+        let table = Self::load_from_file(file_path)?;
+        ACTIVE_FORMATS
+            .set(table)
+            .map_err(|_| io::Error::other("Format table is already in use."))
+    }
+
+    /// The table in use: the installed one, or the embedded one.
+    pub fn active() -> &'static FormatTable {
+        ACTIVE_FORMATS.get_or_init(Self::embedded)
+    }
+
+    /// First format whose PF type or signatures match decompressed `data`.
+    pub fn identify(&self, data: &[u8]) -> Option<&FormatInfo> {
+        self.formats.iter().find(|format| format.matches(data))
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&FormatInfo> {
+        self.formats.iter().find(|format| format.name == name)
+    }
+}
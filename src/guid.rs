@@ -0,0 +1,223 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+use crate::content::AnetGameContent;
+use crate::file_type::ScanKey;
+use crate::progress::{no_progress, Progress, ProgressStage};
+use crate::search::{GrepOptions, GrepPattern};
+
+pub type Guid = [u8; 16];
+
+// File types whose GUIDs sit at offsets no parser knows yet, so they are
+// searched for byte by byte.
+const SCANNED_FILE_TYPES: [AnetFileType; 2] = [AnetFileType::AnftMapParam, AnetFileType::AnftModel];
+
+/// An entry holding a GUID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuidLocation {
+    pub index: usize,
+    pub file_id: u32,          //< Base ID of the entry.
+    pub offset: usize, //< Offset of the GUID in the decompressed entry, or in the content blob of a `cntc` file.
+    pub record: Option<usize>, //< Index of the `cntc` record the GUID identifies.
+}
+
+/// GUIDs of the `cntc` records of an archive, keyed by `format_guid`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct GuidIndex {
+    pub key: ScanKey,
+    pub guids: HashMap<String, Vec<GuidLocation>>,
+}
+
+/// Parses a GUID written as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`, with or
+/// without braces, into the byte order it is stored in: the first three
+/// groups little-endian, the rest as written. 32 hex digits without dashes
+/// are taken as the stored bytes.
+pub fn parse_guid(text: &str) -> io::Result<Guid> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid GUID '{}'.", text),
+        )
+    };
+    let trimmed = text.trim().trim_start_matches('{').trim_end_matches('}');
+    let digits: String = trimmed.chars().filter(|c| *c != '-').collect();
+    if digits.len() != 32 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut guid = [0; 16];
+    for (index, byte) in guid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    if trimmed.contains('-') {
+        guid[0..4].reverse();
+        guid[4..6].reverse();
+        guid[6..8].reverse();
+    }
+    Ok(guid)
+}
+
+/// Writes a stored GUID in the usual `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`
+/// form, see `parse_guid`.
+pub fn format_guid(guid: &Guid) -> String {
+    let tail: String = guid[8..]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!(
+        "{:08x}-{:04x}-{:04x}-{}-{}",
+        LittleEndian::read_u32(&guid[0..4]),
+        LittleEndian::read_u16(&guid[4..6]),
+        LittleEndian::read_u16(&guid[6..8]),
+        &tail[..4],
+        &tail[4..]
+    )
+}
+
+impl GuidIndex {
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(file_path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        if let Some(parent) = file_path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Whether the index was made from the archive as it is now.
+    pub fn matches(&self, archive: &AnetArchive) -> bool {
+        archive.scan_key().is_ok_and(|key| key == self.key)
+    }
+
+    pub fn get(&self, guid: &Guid) -> &[GuidLocation] {
+        self.guids
+            .get(&format_guid(guid))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.guids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guids.is_empty()
+    }
+}
+
+impl AnetArchive {
+    /// Collects the GUID of every record of every `cntc` file.
+    pub fn build_guid_index(&self, progress: &mut dyn FnMut(Progress)) -> io::Result<GuidIndex> {
+        let mut index = GuidIndex {
+            key: self.scan_key()?,
+            ..Default::default()
+        };
+        let entries = self.find_by_type(AnetFileType::AnftGameContent)?;
+        let total = entries.len();
+        for (done, entry) in entries.iter().enumerate() {
+            progress(Progress {
+                stage: ProgressStage::Scan,
+                done,
+                total,
+            });
+            let content = self
+                .read_entry(entry.index())
+                .and_then(|data| AnetGameContent::load_from_bytes(&data));
+            let Some(content) = self.tolerate(Some(entry.index()), content)? else {
+                continue;
+            };
+            let file_id = self
+                .mft_index_data
+                .get(entry.index())
+                .map_or(0, |id_entry| id_entry.base_id);
+            for record in content.records() {
+                let Some(guid) = record.guid else {
+                    continue;
+                };
+                let guid_offset = content
+                    .type_infos
+                    .get(record.type_index as usize)
+                    .map_or(0, |type_info| type_info.guid_offset as usize);
+                index
+                    .guids
+                    .entry(format_guid(&guid))
+                    .or_default()
+                    .push(GuidLocation {
+                        index: entry.index(),
+                        file_id,
+                        offset: record.offset + guid_offset,
+                        record: Some(record.index),
+                    });
+            }
+        }
+        progress(Progress {
+            stage: ProgressStage::Scan,
+            done: total,
+            total,
+        });
+        Ok(index)
+    }
+
+    /// Like `build_guid_index`, reusing the index saved at `cache_path`
+    /// when it was made from this archive and saving a fresh one otherwise.
+    pub fn guid_index_cached<P: AsRef<Path>>(
+        &self,
+        cache_path: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<GuidIndex> {
+        match GuidIndex::load_from_file(&cache_path) {
+            Ok(index) if index.matches(self) => Ok(index),
+            _ => {
+                let index = self.build_guid_index(progress)?;
+                index.save_to_file(cache_path)?;
+                Ok(index)
+            }
+        }
+    }
+
+    /// Entries holding `guid`: the `cntc` records it identifies, from
+    /// `index`, followed by the map and model files containing its bytes.
+    pub fn find_guid_in(&self, index: &GuidIndex, guid: &Guid) -> io::Result<Vec<GuidLocation>> {
+        let mut locations = index.get(guid).to_vec();
+        let mut indices = Vec::new();
+        for file_type in SCANNED_FILE_TYPES {
+            indices.extend(
+                self.find_by_type(file_type)?
+                    .iter()
+                    .map(|entry| entry.index()),
+            );
+        }
+        let pattern = GrepPattern::Bytes(guid.to_vec());
+        let hits = self.grep_indices(
+            &indices,
+            &pattern,
+            &GrepOptions::default(),
+            &mut no_progress,
+        )?;
+        locations.extend(hits.into_iter().map(|hit| GuidLocation {
+            index: hit.index,
+            file_id: hit.file_id,
+            offset: hit.offset,
+            record: None,
+        }));
+        Ok(locations)
+    }
+
+    /// `find_guid_in` with a freshly built index. Keep the index, or use
+    /// `guid_index_cached`, to look up more than one GUID.
+    pub fn find_guid(&self, guid: &Guid) -> io::Result<Vec<GuidLocation>> {
+        let index = self.build_guid_index(&mut no_progress)?;
+        self.find_guid_in(&index, guid)
+    }
+}
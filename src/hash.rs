@@ -0,0 +1,189 @@
+use std::{collections::HashMap, io, sync::mpsc, thread};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::anet_archive::AnetArchive;
+use crate::entry::VerifyFailure;
+use crate::progress::{no_progress, Progress, ProgressStage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgo {
+    Sha256,
+    Crc32, //< Fast, for spotting duplicates rather than verifying content.
+}
+
+/// Digest of the decompressed content of one entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryDigest {
+    pub index: usize,
+    pub size: usize,    //< Decompressed size.
+    pub digest: String, //< Lowercase hex.
+}
+
+/// Entries holding identical content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub digest: String,
+    pub size: usize,
+    pub indices: Vec<usize>,
+    pub file_ids: Vec<u32>, //< Every file ID and base ID of the entries.
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HashReport {
+    pub algo: HashAlgo,
+    pub digests: Vec<EntryDigest>, //< Sorted by MFT index.
+    pub failures: Vec<VerifyFailure>,
+}
+
+impl HashAlgo {
+    pub fn digest(self, data: &[u8]) -> String {
+        match self {
+            HashAlgo::Sha256 => to_hex(&Sha256::digest(data)),
+            HashAlgo::Crc32 => format!("{:08x}", crc32fast::hash(data)),
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl HashReport {
+    /// Groups of two or more entries with the same size and digest, largest
+    /// payloads first.
+    pub fn duplicates(&self, archive: &AnetArchive) -> Vec<DuplicateGroup> {
+        let mut by_content: HashMap<(&str, usize), Vec<usize>> = HashMap::new();
+        for digest in &self.digests {
+            by_content
+                .entry((digest.digest.as_str(), digest.size))
+                .or_default()
+                .push(digest.index);
+        }
+        let mut groups: Vec<DuplicateGroup> = by_content
+            .into_iter()
+            .filter(|(_, indices)| indices.len() > 1)
+            .map(|((digest, size), mut indices)| {
+                indices.sort_unstable();
+                let file_ids = indices
+                    .iter()
+                    .filter_map(|&index| archive.mft_index_data.get(index))
+                    .flat_map(|id_entry| [id_entry.base_id, id_entry.file_id])
+                    .filter(|&file_id| file_id != 0)
+                    .collect();
+                DuplicateGroup {
+                    digest: digest.to_string(),
+                    size,
+                    indices,
+                    file_ids,
+                }
+            })
+            .collect();
+        groups.sort_by(|a, b| b.size.cmp(&a.size).then(a.indices.cmp(&b.indices)));
+        groups
+    }
+
+    /// Bytes that would be saved by storing every duplicate only once.
+    pub fn duplicate_bytes(&self, archive: &AnetArchive) -> usize {
+        self.duplicates(archive)
+            .iter()
+            .map(|group| group.size * (group.indices.len() - 1))
+            .sum()
+    }
+}
+
+impl AnetArchive {
+    /// Hashes the decompressed content of every in-use, non-empty entry
+    /// using one thread per available core.
+    pub fn hash_entries(&self, algo: HashAlgo) -> io::Result<HashReport> {
+        self.hash_entries_with_progress(algo, &mut no_progress)
+    }
+
+    /// Like `hash_entries`, reporting the entries hashed so far.
+    pub fn hash_entries_with_progress(
+        &self,
+        algo: HashAlgo,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<HashReport> {
+        let indices: Vec<usize> = self
+            .in_use_entries()
+            .filter(|entry| entry.size() > 0)
+            .map(|entry| entry.index())
+            .collect();
+        self.hash_indices(&indices, algo, progress)
+    }
+
+    /// Hashes the entries at `indices`. The threads share one positioned
+    /// reader and each keeps one entry in memory at a time, within the
+    /// memory budget of the archive.
+    pub fn hash_indices(
+        &self,
+        indices: &[usize],
+        algo: HashAlgo,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<HashReport> {
+        let total = indices.len();
+        let thread_count = thread::available_parallelism()
+            .map_or(1, |count| count.get())
+            .min(total.max(1));
+
+        let mut report = HashReport {
+            algo,
+            digests: Vec::with_capacity(total),
+            failures: Vec::new(),
+        };
+        let reader = self.shared_reader()?;
+        let budget = self.batch_budget();
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            for worker in 0..thread_count {
+                let sender = sender.clone();
+                let (reader, budget) = (&reader, &budget);
+                scope.spawn(move || {
+                    for &index in indices.iter().skip(worker).step_by(thread_count) {
+                        let result = budget.acquire_entry(reader, index).and_then(|_permit| {
+                            reader
+                                .read_entry(index)
+                                .map(|data| (data.len(), algo.digest(&data)))
+                        });
+                        if sender.send((index, result)).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            for (done, (index, result)) in receiver.into_iter().enumerate() {
+                progress(Progress {
+                    stage: ProgressStage::Hash,
+                    done,
+                    total,
+                });
+                match result {
+                    Ok((size, digest)) => report.digests.push(EntryDigest {
+                        index,
+                        size,
+                        digest,
+                    }),
+                    Err(error) => report.failures.push(VerifyFailure {
+                        index,
+                        error: error.to_string(),
+                    }),
+                }
+            }
+        });
+        progress(Progress {
+            stage: ProgressStage::Hash,
+            done: total,
+            total,
+        });
+
+        report.digests.sort_unstable_by_key(|digest| digest.index);
+        report
+            .failures
+            .sort_unstable_by_key(|failure| failure.index);
+        Ok(report)
+    }
+}
@@ -0,0 +1,938 @@
+use std::{error::Error, fmt, io, sync::OnceLock};
+
+// Port of the ANet entry decompressor: an LZ77 variant whose literals, copy
+// lengths and copy offsets are Huffman coded, with trees sent per block.
+// Every state transition is bounds checked so corrupt input yields
+// `InflateError::CorruptData` instead of a panic.
+//
+// Texture entries carry a second compression inside the entry data, see
+// `inflate_texture`: runs of blocks sharing a constant component are coded,
+// the remaining block components are stored.
+
+pub(crate) const MAX_CODE_BITS_LENGTH: usize = 32;
+pub(crate) const MAX_SYMBOL_VALUE: usize = 285;
+const MAX_NB_BITS_HASH: u32 = 8;
+const HASH_SIZE: usize = 1 << MAX_NB_BITS_HASH;
+
+// The last word of every 64 KiB block of input is a CRC, not stream data.
+pub(crate) const CRC_INTERVAL_WORDS: usize = 0x4000;
+// Upper bound on the bytes a single input byte can expand to: a copy code
+// takes at least 2 bits and writes at most 271 bytes.
+const MAX_EXPANSION_RATIO: usize = 4 * 271;
+
+// Bits of the texture compression flags word: which constant-block passes
+// precede the stored components.
+const TEXTURE_WHITE_COLOR: u32 = 0x01;
+const TEXTURE_CONSTANT_ALPHA_4_BITS: u32 = 0x02;
+const TEXTURE_CONSTANT_ALPHA_8_BITS: u32 = 0x04;
+const TEXTURE_PLAIN_COLOR: u32 = 0x08;
+// Block written by the white color pass.
+const TEXTURE_WHITE_BLOCK: u64 = 0xffff_ffff_ffff_fffe;
+// A 3-bit run code covers up to 18 blocks of at most 16 bytes in each of
+// the four passes.
+const MAX_TEXTURE_EXPANSION_RATIO: usize = 4 * 8 * 18 * 16 / 3;
+const TEXTURE_BLOCK_SIZE: usize = 4;
+// Identifier, format, width and height.
+const TEXTURE_HEADER_SIZE: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflateError {
+    CorruptData(&'static str),
+}
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InflateError::CorruptData(reason) => write!(f, "Corrupt compressed data: {}", reason),
+        }
+    }
+}
+
+impl Error for InflateError {}
+
+impl From<InflateError> for io::Error {
+    fn from(error: InflateError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+type InflateResult<T> = Result<T, InflateError>;
+
+struct State<'a> {
+    input: &'a [u8],
+    input_size: usize, //< Input size in 32-bit words.
+    input_position: usize,
+    head: u32,
+    buffer: u32,
+    bits: u32,
+    is_empty: bool,
+    skips_crcs: bool, //< Entry streams interleave CRC words; texture streams do not.
+}
+
+impl<'a> State<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        State {
+            input,
+            input_size: input.len() / 4,
+            input_position: 0,
+            head: 0,
+            buffer: 0,
+            bits: 0,
+            is_empty: false,
+            skips_crcs: true,
+        }
+    }
+
+    fn without_crcs(input: &'a [u8]) -> Self {
+        State {
+            skips_crcs: false,
+            ..State::new(input)
+        }
+    }
+
+    fn pull_word(&mut self) -> InflateResult<()> {
+        if self.bits >= 32 {
+            return Err(InflateError::CorruptData("bit buffer overflow"));
+        }
+        if self.skips_crcs && (self.input_position + 1).is_multiple_of(CRC_INTERVAL_WORDS) {
+            self.input_position += 1;
+        }
+
+        let mut value = 0;
+        if self.input_position >= self.input_size {
+            // One word past the end is allowed so the last codes can be read.
+            if self.is_empty {
+                return Err(InflateError::CorruptData("reached end of input"));
+            }
+            self.is_empty = true;
+        } else {
+            let start = self.input_position * 4;
+            value = u32::from_le_bytes([
+                self.input[start],
+                self.input[start + 1],
+                self.input[start + 2],
+                self.input[start + 3],
+            ]);
+        }
+
+        if self.bits == 0 {
+            self.head = value;
+            self.buffer = 0;
+        } else {
+            self.head |= value >> self.bits;
+            self.buffer = value << (32 - self.bits);
+        }
+        self.bits += 32;
+        self.input_position += 1;
+        Ok(())
+    }
+
+    fn need_bits(&mut self, bits: u32) -> InflateResult<()> {
+        if self.bits < bits {
+            self.pull_word()?;
+        }
+        Ok(())
+    }
+
+    fn read_bits(&mut self, bits: u32) -> InflateResult<u32> {
+        self.need_bits(bits)?;
+        Ok(if bits == 0 {
+            0
+        } else {
+            self.head >> (32 - bits)
+        })
+    }
+
+    fn drop_bits(&mut self, bits: u32) -> InflateResult<()> {
+        if bits > 32 || bits > self.bits {
+            return Err(InflateError::CorruptData(
+                "dropped more bits than available",
+            ));
+        }
+        if bits == 32 {
+            self.head = self.buffer;
+            self.buffer = 0;
+        } else if bits > 0 {
+            self.head = (self.head << bits) | (self.buffer >> (32 - bits));
+            self.buffer <<= bits;
+        }
+        self.bits -= bits;
+        Ok(())
+    }
+
+    fn take_bits(&mut self, bits: u32) -> InflateResult<u32> {
+        let value = self.read_bits(bits)?;
+        self.drop_bits(bits)?;
+        Ok(value)
+    }
+
+    /// Steps back to the first word no bits have been taken from, for
+    /// reading the data that follows the bit stream word by word.
+    fn align_to_word(&mut self) {
+        if self.bits >= 32 {
+            self.input_position -= 1;
+        }
+        self.head = 0;
+        self.buffer = 0;
+        self.bits = 0;
+    }
+
+    fn next_word(&mut self) -> Option<&'a [u8]> {
+        if self.input_position >= self.input_size {
+            return None;
+        }
+        let start = self.input_position * 4;
+        self.input_position += 1;
+        Some(&self.input[start..start + 4])
+    }
+}
+
+struct HuffmanTree {
+    code_comparison: [u32; MAX_CODE_BITS_LENGTH],
+    symbol_value_offset: [u16; MAX_CODE_BITS_LENGTH],
+    code_bits: [u8; MAX_CODE_BITS_LENGTH],
+    comparison_count: usize,
+    symbol_values: [u16; MAX_SYMBOL_VALUE],
+    symbol_count: usize,
+    hash_exists: [bool; HASH_SIZE],
+    hash_symbol: [u16; HASH_SIZE],
+    hash_bits: [u8; HASH_SIZE],
+}
+
+impl HuffmanTree {
+    fn empty() -> Self {
+        HuffmanTree {
+            code_comparison: [0; MAX_CODE_BITS_LENGTH],
+            symbol_value_offset: [0; MAX_CODE_BITS_LENGTH],
+            code_bits: [0; MAX_CODE_BITS_LENGTH],
+            comparison_count: 0,
+            symbol_values: [0; MAX_SYMBOL_VALUE],
+            symbol_count: 0,
+            hash_exists: [false; HASH_SIZE],
+            hash_symbol: [0; HASH_SIZE],
+            hash_bits: [0; HASH_SIZE],
+        }
+    }
+
+    fn read_code(&self, state: &mut State) -> InflateResult<u16> {
+        state.need_bits(32)?;
+        let hash = (state.head >> (32 - MAX_NB_BITS_HASH)) as usize;
+        if self.hash_exists[hash] {
+            state.drop_bits(u32::from(self.hash_bits[hash]))?;
+            return Ok(self.hash_symbol[hash]);
+        }
+
+        let index = (0..self.comparison_count)
+            .find(|index| state.head >= self.code_comparison[*index])
+            .ok_or(InflateError::CorruptData("invalid Huffman code"))?;
+        let bits = u32::from(self.code_bits[index]);
+        let delta = (state.head - self.code_comparison[index]) >> (32 - bits);
+        let symbol_index = usize::from(self.symbol_value_offset[index])
+            .checked_sub(delta as usize)
+            .filter(|symbol_index| *symbol_index < self.symbol_count)
+            .ok_or(InflateError::CorruptData("Huffman code out of range"))?;
+        state.drop_bits(bits)?;
+        Ok(self.symbol_values[symbol_index])
+    }
+}
+
+pub(crate) struct HuffmanTreeBuilder {
+    bits_head: [Option<u16>; MAX_CODE_BITS_LENGTH],
+    symbol_next: [Option<u16>; MAX_SYMBOL_VALUE],
+}
+
+impl HuffmanTreeBuilder {
+    pub(crate) fn new() -> Self {
+        HuffmanTreeBuilder {
+            bits_head: [None; MAX_CODE_BITS_LENGTH],
+            symbol_next: [None; MAX_SYMBOL_VALUE],
+        }
+    }
+
+    pub(crate) fn add_symbol(&mut self, symbol: u16, bits: u8) -> InflateResult<()> {
+        let bits = usize::from(bits);
+        let symbol_index = usize::from(symbol);
+        if bits >= MAX_CODE_BITS_LENGTH || symbol_index >= MAX_SYMBOL_VALUE {
+            return Err(InflateError::CorruptData("invalid Huffman symbol"));
+        }
+        self.symbol_next[symbol_index] = self.bits_head[bits];
+        self.bits_head[bits] = Some(symbol);
+        Ok(())
+    }
+
+    fn symbols_of_length(&self, bits: usize) -> impl Iterator<Item = u16> + '_ {
+        std::iter::successors(self.bits_head[bits], |symbol| {
+            self.symbol_next[usize::from(*symbol)]
+        })
+        .take(MAX_SYMBOL_VALUE)
+    }
+
+    /// Code and length of every symbol, indexed by symbol, assigned the way
+    /// `build` assigns them. Symbols that were not added have length 0.
+    pub(crate) fn codes(&self) -> Vec<(u32, u8)> {
+        let mut codes = vec![(0, 0); MAX_SYMBOL_VALUE];
+        let mut code: u32 = 0;
+        for bits in 0..MAX_CODE_BITS_LENGTH {
+            for symbol in self.symbols_of_length(bits) {
+                codes[usize::from(symbol)] = (code, bits as u8);
+                code = code.wrapping_sub(1);
+            }
+            code = (code << 1).wrapping_add(1);
+        }
+        codes
+    }
+
+    /// Builds the decoding tables. Returns `None` when no symbol was added.
+    fn build(&self) -> InflateResult<Option<HuffmanTree>> {
+        if self.bits_head.iter().all(Option::is_none) {
+            return Ok(None);
+        }
+        let mut tree = HuffmanTree::empty();
+        let mut code: u32 = 0;
+        let mut bits: usize = 0;
+
+        // Codes of up to 8 bits are resolved with a single table lookup.
+        while bits <= MAX_NB_BITS_HASH as usize {
+            for symbol in self.symbols_of_length(bits) {
+                let shift = MAX_NB_BITS_HASH - bits as u32;
+                let start = (code << shift) as usize;
+                let end = (code.wrapping_add(1) << shift) as usize;
+                if start >= end || end > HASH_SIZE {
+                    return Err(InflateError::CorruptData("over-subscribed Huffman tree"));
+                }
+                for hash in start..end {
+                    tree.hash_exists[hash] = true;
+                    tree.hash_symbol[hash] = symbol;
+                    tree.hash_bits[hash] = bits as u8;
+                }
+                code = code.wrapping_sub(1);
+            }
+            code = (code << 1).wrapping_add(1);
+            bits += 1;
+        }
+
+        // Longer codes are found by comparing against the smallest code of
+        // each length.
+        while bits < MAX_CODE_BITS_LENGTH {
+            let mut has_symbols = false;
+            for symbol in self.symbols_of_length(bits) {
+                if tree.symbol_count >= MAX_SYMBOL_VALUE {
+                    return Err(InflateError::CorruptData("too many Huffman symbols"));
+                }
+                tree.symbol_values[tree.symbol_count] = symbol;
+                tree.symbol_count += 1;
+                code = code.wrapping_sub(1);
+                has_symbols = true;
+            }
+            if has_symbols {
+                let index = tree.comparison_count;
+                tree.code_comparison[index] = code.wrapping_add(1) << (32 - bits);
+                tree.code_bits[index] = bits as u8;
+                tree.symbol_value_offset[index] = (tree.symbol_count - 1) as u16;
+                tree.comparison_count += 1;
+            }
+            code = (code << 1).wrapping_add(1);
+            bits += 1;
+        }
+        Ok(Some(tree))
+    }
+}
+
+// Code lengths of the static tree used to decode the per-block trees.
+const DICT_CODE_LENGTHS: &[(u8, &[u16])] = &[
+    (3, &[0x0a, 0x09, 0x08]),
+    (4, &[0x0c, 0x0b, 0x07, 0x00]),
+    (5, &[0xe0, 0x2a, 0x29, 0x06]),
+    (6, &[0x4a, 0x40, 0x2c, 0x2b, 0x28, 0x20, 0x05, 0x04]),
+    (7, &[0x49, 0x48, 0x27, 0x26, 0x25, 0x0d, 0x03]),
+    (8, &[0x6a, 0x69, 0x4c, 0x4b, 0x47, 0x24]),
+    (
+        9,
+        &[0xe8, 0xa0, 0x89, 0x88, 0x68, 0x67, 0x63, 0x60, 0x46, 0x23],
+    ),
+    (
+        10,
+        &[
+            0xe9, 0xc9, 0xc0, 0xa9, 0xa8, 0x8a, 0x87, 0x80, 0x66, 0x65, 0x45, 0x44, 0x43, 0x2d,
+            0x02, 0x01,
+        ],
+    ),
+    (
+        11,
+        &[
+            0xe5, 0xc8, 0xaa, 0xa5, 0xa4, 0x8b, 0x85, 0x84, 0x6c, 0x6b, 0x64, 0x4d, 0x0e,
+        ],
+    ),
+    (12, &[0xe7, 0xca, 0xc7, 0xa7, 0xa6, 0x86, 0x83]),
+    (13, &[0xe6, 0xe4, 0xc4, 0x8c, 0x2e, 0x22]),
+    (14, &[0xec, 0xc6, 0x6d, 0x4e]),
+    (15, &[0xea, 0xcc, 0xac, 0xab, 0x8d, 0x11, 0x10, 0x0f]),
+];
+// Every byte value not listed above is coded on 16 bits.
+const DICT_DEFAULT_CODE_LENGTH: u8 = 16;
+
+/// Symbols of the static tree used to send the per-block trees.
+pub(crate) fn dictionary_builder() -> HuffmanTreeBuilder {
+    let mut builder = HuffmanTreeBuilder::new();
+    let mut listed = [false; 256];
+    for (bits, symbols) in DICT_CODE_LENGTHS {
+        for symbol in *symbols {
+            listed[usize::from(*symbol)] = true;
+            builder
+                .add_symbol(*symbol, *bits)
+                .expect("static Huffman dictionary is valid");
+        }
+    }
+    for symbol in (0..=0xffu16).rev() {
+        if !listed[usize::from(symbol)] {
+            builder
+                .add_symbol(symbol, DICT_DEFAULT_CODE_LENGTH)
+                .expect("static Huffman dictionary is valid");
+        }
+    }
+    builder
+}
+
+fn dictionary_tree() -> &'static HuffmanTree {
+    static DICTIONARY_TREE: OnceLock<HuffmanTree> = OnceLock::new();
+    DICTIONARY_TREE.get_or_init(|| {
+        dictionary_builder()
+            .build()
+            .ok()
+            .flatten()
+            .expect("static Huffman dictionary is valid")
+    })
+}
+
+fn parse_huffman_tree(
+    state: &mut State,
+    dictionary: &HuffmanTree,
+) -> InflateResult<Option<HuffmanTree>> {
+    let number_of_symbols = state.take_bits(16)? as i32;
+    if number_of_symbols as usize > MAX_SYMBOL_VALUE {
+        return Err(InflateError::CorruptData("too many symbols to decode"));
+    }
+
+    let mut builder = HuffmanTreeBuilder::new();
+    let mut remaining_symbols = number_of_symbols - 1;
+    while remaining_symbols > -1 {
+        let code = dictionary.read_code(state)?;
+        let code_bits = (code & 0x1f) as u8;
+        let code_symbols = i32::from(code >> 5) + 1;
+        if code_bits == 0 {
+            remaining_symbols -= code_symbols;
+        } else {
+            if code_symbols > remaining_symbols + 1 {
+                return Err(InflateError::CorruptData("Huffman tree symbol underflow"));
+            }
+            for _ in 0..code_symbols {
+                builder.add_symbol(remaining_symbols as u16, code_bits)?;
+                remaining_symbols -= 1;
+            }
+        }
+    }
+    builder.build()
+}
+
+fn inflate_data(state: &mut State, output: &mut [u8]) -> InflateResult<()> {
+    let output_size = output.len();
+    let mut output_position = 0;
+
+    state.take_bits(4)?;
+    let write_size_const_add = state.take_bits(4)? + 1;
+
+    let dictionary = dictionary_tree();
+    while output_position < output_size {
+        let Some(tree_dict) = parse_huffman_tree(state, dictionary)? else {
+            break;
+        };
+        let Some(tree_copy) = parse_huffman_tree(state, dictionary)? else {
+            break;
+        };
+
+        let max_count = (state.take_bits(4)? + 1) << 12;
+        let mut code_read_count = 0;
+        while code_read_count < max_count && output_position < output_size {
+            code_read_count += 1;
+
+            let code = tree_dict.read_code(state)?;
+            if code < 0x100 {
+                output[output_position] = code as u8;
+                output_position += 1;
+                continue;
+            }
+
+            // Back-reference: decode the write size...
+            let code = u32::from(code - 0x100);
+            let (quotient, remainder) = (code / 4, code % 4);
+            let mut write_size = if quotient == 0 {
+                code
+            } else if quotient < 7 {
+                (1 << (quotient - 1)) * (4 + remainder)
+            } else if code == 28 {
+                0xff
+            } else {
+                return Err(InflateError::CorruptData("invalid write size code"));
+            };
+            if quotient > 1 && code != 28 {
+                write_size |= state.take_bits(quotient - 1)?;
+            }
+            write_size += write_size_const_add;
+
+            // ...then the write offset.
+            let code = u32::from(tree_copy.read_code(state)?);
+            let (quotient, remainder) = (code / 2, code % 2);
+            let mut write_offset = if quotient == 0 {
+                code
+            } else if quotient < 17 {
+                (1 << (quotient - 1)) * (2 + remainder)
+            } else {
+                return Err(InflateError::CorruptData("invalid write offset code"));
+            };
+            if quotient > 1 {
+                write_offset |= state.take_bits(quotient - 1)?;
+            }
+            let write_offset = write_offset as usize + 1;
+            if write_offset > output_position {
+                return Err(InflateError::CorruptData(
+                    "back-reference before start of output",
+                ));
+            }
+
+            let write_end = (output_position + write_size as usize).min(output_size);
+            while output_position < write_end {
+                output[output_position] = output[output_position - write_offset];
+                output_position += 1;
+            }
+        }
+    }
+
+    if output_position < output_size {
+        return Err(InflateError::CorruptData(
+            "stream ended before the output was complete",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads the uncompressed size stored in the header of a compressed entry.
+pub fn inflated_size(input: &[u8]) -> InflateResult<u32> {
+    let mut state = State::new(input);
+    state.take_bits(32)?;
+    state.take_bits(32)
+}
+
+/// Decompresses an ANet compressed entry. `expected_size` limits the output
+/// to the first bytes of the entry, which is enough to identify its type;
+/// `None` decompresses the whole entry.
+pub fn anet_inflate(input: &[u8], expected_size: Option<u32>) -> InflateResult<Vec<u8>> {
+    let mut state = State::new(input);
+
+    // Skipping the header, then reading the uncompressed size.
+    state.take_bits(32)?;
+    let mut output_size = state.take_bits(32)?;
+    if let Some(expected_size) = expected_size {
+        output_size = output_size.min(expected_size);
+    }
+    if output_size as usize > input.len().saturating_mul(MAX_EXPANSION_RATIO) {
+        return Err(InflateError::CorruptData(
+            "uncompressed size is implausibly large",
+        ));
+    }
+
+    let mut output = vec![0; output_size as usize];
+    inflate_data(&mut state, &mut output)?;
+    Ok(output)
+}
+
+/// How the blocks of a texture format split into the components the
+/// texture compression stores separately.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TextureLayout {
+    pub(crate) has_color: bool,
+    pub(crate) has_alpha: bool,
+    pub(crate) deduced_alpha: bool, //< Alpha is part of the color block (DXT1).
+    pub(crate) block_bytes: usize,
+}
+
+impl TextureLayout {
+    pub(crate) fn of(format: &[u8]) -> Option<Self> {
+        let (has_color, has_alpha, deduced_alpha, block_bytes) = match format {
+            b"DXT1" => (true, true, true, 8),
+            b"DXT2" | b"DXT3" | b"DXT4" | b"DXT5" | b"DXTL" => (true, true, false, 16),
+            // Two-channel normal maps: both halves are stored like DXT5's.
+            b"DXTN" | b"3DCX" | b"ATI2" => (true, true, false, 16),
+            b"DXTA" => (false, true, false, 8),
+            _ => return None,
+        };
+        Some(TextureLayout {
+            has_color,
+            has_alpha,
+            deduced_alpha,
+            block_bytes,
+        })
+    }
+
+    /// Whether an alpha half precedes the color half of every block.
+    pub(crate) fn has_two_components(&self) -> bool {
+        self.has_color && self.has_alpha && !self.deduced_alpha
+    }
+
+    pub(crate) fn component_bytes(&self) -> usize {
+        if self.has_two_components() {
+            self.block_bytes / 2
+        } else {
+            self.block_bytes
+        }
+    }
+
+    pub(crate) fn color_offset(&self) -> usize {
+        if self.has_two_components() {
+            self.component_bytes()
+        } else {
+            0
+        }
+    }
+
+    /// Whether the alpha component is stored on its own.
+    pub(crate) fn stores_alpha(&self) -> bool {
+        self.has_alpha && !self.deduced_alpha
+    }
+}
+
+// Run lengths of the constant-block passes: 1 on one bit, 18 on two and 2
+// to 17 on six.
+fn texture_dictionary_tree() -> &'static HuffmanTree {
+    static TEXTURE_DICTIONARY_TREE: OnceLock<HuffmanTree> = OnceLock::new();
+    TEXTURE_DICTIONARY_TREE.get_or_init(|| {
+        let mut builder = HuffmanTreeBuilder::new();
+        let lengths = [(0x01, 1), (0x12, 2)]
+            .into_iter()
+            .chain((0x02..=0x11).rev().map(|symbol| (symbol, 6)));
+        for (symbol, bits) in lengths {
+            builder
+                .add_symbol(symbol, bits)
+                .expect("static texture dictionary is valid");
+        }
+        builder
+            .build()
+            .ok()
+            .flatten()
+            .expect("static texture dictionary is valid")
+    })
+}
+
+/// Where a constant-block pass writes its value into each block.
+struct TextureRunTarget {
+    block_bytes: usize,
+    offset: usize,
+    length: usize, //< At most 8, the size of the value.
+}
+
+/// Runs one constant-block pass: each code gives a number of blocks not yet
+/// set in `done`, and `read_value` the value to write into all of them, if
+/// any. Blocks written are also marked in `also_done`.
+fn decode_texture_runs(
+    state: &mut State,
+    done: &mut [bool],
+    mut also_done: Option<&mut [bool]>,
+    output: &mut [u8],
+    target: &TextureRunTarget,
+    read_value: &mut dyn FnMut(&mut State) -> InflateResult<Option<u64>>,
+) -> InflateResult<()> {
+    let dictionary = texture_dictionary_tree();
+    let mut position = 0;
+    while position < done.len() {
+        let mut count = dictionary.read_code(state)?;
+        let value = read_value(state)?;
+        while count > 0 {
+            if position >= done.len() {
+                return Err(InflateError::CorruptData("texture run past the last block"));
+            }
+            if !done[position] {
+                if let Some(value) = value {
+                    let start = position * target.block_bytes + target.offset;
+                    output[start..start + target.length]
+                        .copy_from_slice(&value.to_le_bytes()[..target.length]);
+                    done[position] = true;
+                    if let Some(also_done) = also_done.as_deref_mut() {
+                        also_done[position] = true;
+                    }
+                }
+                count -= 1;
+            }
+            position += 1;
+        }
+        while position < done.len() && done[position] {
+            position += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Rounds an 8-bit channel to `bits` bits two ways and returns both
+/// candidates with the rounding error in twelfths.
+fn split_plain_channel(value: u32, bits: u32) -> (u32, u32, u32, u32) {
+    let drop = 8 - bits;
+    let low = (value - (value >> bits)) >> drop;
+    let expanded = (low << drop) + (low >> (bits - drop));
+    let divisor = if low & 0x11 == 0x11 && bits == 5 {
+        7
+    } else {
+        8
+    };
+    let error = 12u32.wrapping_mul(value.wrapping_sub(expanded)) / divisor;
+    let (first, second) = match error {
+        0..=1 => (low, low),
+        2..=5 => (low, low + 1),
+        6..=9 => (low + 1, low),
+        _ => (low + 1, low + 1),
+    };
+    (low, error, first, second)
+}
+
+/// The color block the plain color pass writes for the 8-bit `red`,
+/// `green` and `blue` it reads.
+fn plain_color_block(red: u32, green: u32, blue: u32, deduced_alpha: bool) -> u64 {
+    let (red_low, red_error, red_1, red_2) = split_plain_channel(red, 5);
+    let (blue_low, blue_error, blue_1, blue_2) = split_plain_channel(blue, 5);
+    let (green_low, green_error, green_1, green_2) = split_plain_channel(green, 6);
+    let mut color_1 = blue_1 | ((green_1 | (red_1 << 6)) << 5);
+    let mut color_2 = blue_2 | ((green_2 | (red_2 << 6)) << 5);
+
+    let mut weight = 0;
+    let mut rounded = 0;
+    for (low, error, first, second) in [
+        (red_low, red_error, red_1, red_2),
+        (blue_low, blue_error, blue_1, blue_2),
+        (green_low, green_error, green_1, green_2),
+    ] {
+        if first != second {
+            weight += if first == low {
+                error
+            } else {
+                12u32.wrapping_sub(error)
+            };
+            rounded += 1;
+        }
+    }
+    weight = (weight + rounded / 2)
+        .checked_div(rounded)
+        .unwrap_or(weight);
+
+    let dxt1_special_case = deduced_alpha && (weight == 5 || weight == 6 || rounded != 0);
+    if rounded > 0 && !dxt1_special_case {
+        if color_2 == 0xffff {
+            weight = 12;
+            color_1 = color_1.wrapping_sub(1);
+        } else {
+            weight = 0;
+            color_2 += 1;
+        }
+    }
+    if color_2 >= color_1 {
+        std::mem::swap(&mut color_1, &mut color_2);
+        weight = 12u32.wrapping_sub(weight);
+    }
+
+    let selected: u64 = if dxt1_special_case {
+        2
+    } else {
+        match weight {
+            0..=1 => 0,
+            2..=5 => 2,
+            6..=9 => 3,
+            _ => 1,
+        }
+    };
+    let indices = (0..16).fold(0u64, |indices, pixel| indices | (selected << (pixel * 2)));
+    u64::from(color_1 & 0xffff) | (u64::from(color_2 & 0xffff) << 16) | (indices << 32)
+}
+
+/// Undoes the texture compression of a texture entry (`ATEX` family or
+/// `CTEX`, as stored after the entry compression): returns the top mip
+/// level as standard blocks of the format named in its header. Port of
+/// gw2DatTools' `inflateTextureFileBuffer`.
+pub fn inflate_texture(input: &[u8]) -> InflateResult<Vec<u8>> {
+    inflate_texture_level(input, 0)
+}
+
+/// Like `inflate_texture`, for mip level `level`, 0 being the top one.
+/// Each level is a data size, the byte count of the rest of the level, and
+/// the compressed level; lower levels follow the top one, with both sides
+/// halved, as `deflate_texture` writes them.
+pub fn inflate_texture_level(input: &[u8], level: u32) -> InflateResult<Vec<u8>> {
+    let mut state = State::without_crcs(input);
+
+    // Identifier, format, then width and height.
+    state.take_bits(32)?;
+    let format = state.take_bits(32)?.to_le_bytes();
+    let dimensions = state.take_bits(32)?;
+    let layout = TextureLayout::of(&format)
+        .ok_or(InflateError::CorruptData("unsupported texture format"))?;
+    let mut width = (dimensions & 0xffff) as usize;
+    let mut height = (dimensions >> 16) as usize;
+    let mut start = TEXTURE_HEADER_SIZE;
+    for _ in 0..level {
+        let data_size = input
+            .get(start..start + 4)
+            .ok_or(InflateError::CorruptData("texture has fewer mip levels"))?;
+        let data_size =
+            u32::from_le_bytes([data_size[0], data_size[1], data_size[2], data_size[3]]);
+        start = (start + 4)
+            .checked_add(data_size as usize)
+            .filter(|end| *end <= input.len())
+            .ok_or(InflateError::CorruptData("texture mip level past the end"))?;
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+    }
+    inflate_texture_data(&input[start..], layout, width, height)
+}
+
+/// Undoes the texture compression of one level, `input` starting at its
+/// data size.
+fn inflate_texture_data(
+    input: &[u8],
+    layout: TextureLayout,
+    width: usize,
+    height: usize,
+) -> InflateResult<Vec<u8>> {
+    let mut state = State::without_crcs(input);
+    let block_count = width.div_ceil(TEXTURE_BLOCK_SIZE) * height.div_ceil(TEXTURE_BLOCK_SIZE);
+    let output_size = block_count * layout.block_bytes;
+    if output_size > input.len().saturating_mul(MAX_TEXTURE_EXPANSION_RATIO) {
+        return Err(InflateError::CorruptData(
+            "texture size is implausibly large",
+        ));
+    }
+    let mut output = vec![0; output_size];
+
+    let _data_size = state.take_bits(32)?;
+    let flags = state.take_bits(32)?;
+    let mut color_done = vec![false; block_count];
+    let mut alpha_done = vec![false; block_count];
+
+    if flags & (TEXTURE_WHITE_COLOR | TEXTURE_PLAIN_COLOR) != 0 && !layout.has_color {
+        return Err(InflateError::CorruptData(
+            "color pass on a texture without color",
+        ));
+    }
+    if flags & (TEXTURE_CONSTANT_ALPHA_4_BITS | TEXTURE_CONSTANT_ALPHA_8_BITS) != 0
+        && !layout.stores_alpha()
+    {
+        return Err(InflateError::CorruptData(
+            "alpha pass on a texture without stored alpha",
+        ));
+    }
+
+    if flags & TEXTURE_WHITE_COLOR != 0 {
+        let mut read_value =
+            |state: &mut State| Ok((state.take_bits(1)? != 0).then_some(TEXTURE_WHITE_BLOCK));
+        let target = TextureRunTarget {
+            block_bytes: layout.block_bytes,
+            offset: layout.color_offset(),
+            length: 8,
+        };
+        decode_texture_runs(
+            &mut state,
+            &mut color_done,
+            Some(&mut alpha_done),
+            &mut output,
+            &target,
+            &mut read_value,
+        )?;
+        // White blocks are opaque: their alpha half, if any, is all ones.
+        if layout.stores_alpha() {
+            for block in (0..block_count).filter(|block| alpha_done[*block]) {
+                let start = block * layout.block_bytes;
+                output[start..start + layout.component_bytes()].fill(0xff);
+            }
+        }
+    }
+    for (flag, value_bits) in [
+        (TEXTURE_CONSTANT_ALPHA_4_BITS, 4),
+        (TEXTURE_CONSTANT_ALPHA_8_BITS, 8),
+    ] {
+        if flags & flag == 0 {
+            continue;
+        }
+        let alpha = u64::from(state.take_bits(value_bits)?);
+        let constant = if value_bits == 4 {
+            // Every 4-bit explicit alpha of the block.
+            (0..16).fold(0, |value, pixel| value | (alpha << (pixel * 4)))
+        } else {
+            // Both interpolated alpha endpoints, all indices on the first.
+            alpha | (alpha << 8)
+        };
+        let mut read_value = |state: &mut State| {
+            let is_set = state.take_bits(1)? != 0;
+            let is_not_null = state.read_bits(1)? != 0;
+            if !is_set {
+                return Ok(None);
+            }
+            state.drop_bits(1)?;
+            Ok(Some(if is_not_null { constant } else { 0 }))
+        };
+        let target = TextureRunTarget {
+            block_bytes: layout.block_bytes,
+            offset: 0,
+            length: layout.component_bytes().min(8),
+        };
+        decode_texture_runs(
+            &mut state,
+            &mut alpha_done,
+            None,
+            &mut output,
+            &target,
+            &mut read_value,
+        )?;
+    }
+    if flags & TEXTURE_PLAIN_COLOR != 0 {
+        let red = state.take_bits(8)?;
+        let green = state.take_bits(8)?;
+        let blue = state.take_bits(8)?;
+        let block = plain_color_block(red, green, blue, layout.deduced_alpha);
+        let mut read_value = |state: &mut State| Ok((state.take_bits(1)? != 0).then_some(block));
+        let target = TextureRunTarget {
+            block_bytes: layout.block_bytes,
+            offset: layout.color_offset(),
+            length: layout.component_bytes().min(8),
+        };
+        decode_texture_runs(
+            &mut state,
+            &mut color_done,
+            None,
+            &mut output,
+            &target,
+            &mut read_value,
+        )?;
+    }
+
+    // The components no pass set follow as whole words: the alpha of each
+    // block, then the first color word of each block, then the second.
+    state.align_to_word();
+    let words = layout.component_bytes() / 4;
+    if layout.stores_alpha() {
+        for block in (0..block_count).filter(|block| !alpha_done[*block]) {
+            for word in 0..words {
+                let Some(bytes) = state.next_word() else {
+                    return Ok(output);
+                };
+                let start = block * layout.block_bytes + word * 4;
+                output[start..start + 4].copy_from_slice(bytes);
+            }
+        }
+    }
+    if layout.has_color {
+        for word in 0..words {
+            for block in (0..block_count).filter(|block| !color_done[*block]) {
+                let Some(bytes) = state.next_word() else {
+                    return Ok(output);
+                };
+                let start = block * layout.block_bytes + layout.color_offset() + word * 4;
+                output[start..start + 4].copy_from_slice(bytes);
+            }
+        }
+    }
+    Ok(output)
+}
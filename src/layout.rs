@@ -0,0 +1,216 @@
+use std::io::{self, Write};
+
+use crate::anet_archive::AnetArchive;
+
+const SVG_WIDTH: u64 = 1024;
+const SVG_ROW_HEIGHT: u64 = 4;
+const SVG_ROWS: u64 = 256;
+const GAP_COLOR: &str = "#ff2020";
+const HEADER_COLOR: &str = "#404040";
+// Entries 0 to 2 hold the DAT header, the file ID table and the MFT.
+const METADATA_ENTRIES: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutBlock {
+    pub index: usize,
+    pub offset: u64,
+    pub size: u64,
+    pub counter: u32,
+    pub compressed: bool,
+    pub in_use: bool,
+}
+
+impl LayoutBlock {
+    /// End of the byte range, saturated for corrupt offsets near `u64::MAX`.
+    pub fn end(&self) -> u64 {
+        self.offset.saturating_add(self.size)
+    }
+}
+
+/// Two entries whose byte ranges partly cover each other. Entries sharing
+/// the exact same range are not reported.
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutOverlap {
+    pub first: usize, //< MFT index of the entry starting first.
+    pub second: usize,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// A byte range of the file no live entry covers.
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutGap {
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Result of `AnetArchive::layout_report`. Live entries are the in-use ones
+/// and the metadata entries; the data of unused entries counts as free.
+#[derive(Default, Debug)]
+pub struct LayoutReport {
+    pub file_length: u64,
+    pub live_bytes: u64, //< Bytes covered by at least one live entry.
+    pub free_bytes: u64, //< Total size of the gaps.
+    pub overlaps: Vec<LayoutOverlap>,
+    pub gaps: Vec<LayoutGap>,
+    pub out_of_bounds: Vec<usize>, //< Live entries reaching past the end of the file.
+}
+
+impl AnetArchive {
+    /// Returns the non-empty MFT entries sorted by their offset in the archive.
+    pub fn layout_blocks(&self) -> Vec<LayoutBlock> {
+        let mut blocks: Vec<LayoutBlock> = self
+            .mft_data
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.size > 0)
+            .map(|(index, entry)| LayoutBlock {
+                index,
+                offset: entry.offset,
+                size: u64::from(entry.size),
+                counter: entry.counter,
+                compressed: entry.is_compressed(),
+                in_use: index < METADATA_ENTRIES || entry.is_in_use(),
+            })
+            .collect();
+        blocks.sort_by_key(|block| block.offset);
+        blocks
+    }
+
+    /// Walks the live entries in file order and reports where they overlap
+    /// and which parts of the file, `file_length` bytes long, none of them
+    /// covers. The DAT header counts as covered.
+    pub fn layout_report(&self, file_length: u64) -> LayoutReport {
+        let mut report = LayoutReport {
+            file_length,
+            ..Default::default()
+        };
+        let header_end = u64::from(self.dat_header.header_size).min(file_length);
+        let mut covered_end = header_end;
+        let mut last: Option<LayoutBlock> = None; //< Live block reaching furthest so far.
+        report.live_bytes = header_end;
+        for block in self
+            .layout_blocks()
+            .into_iter()
+            .filter(|block| block.in_use)
+        {
+            if block.end() > file_length {
+                report.out_of_bounds.push(block.index);
+            }
+            let start = block.offset.min(file_length);
+            if start > covered_end {
+                report.gaps.push(LayoutGap {
+                    offset: covered_end,
+                    size: start - covered_end,
+                });
+            }
+            if let Some(previous) = last.filter(|previous| previous.end() > block.offset) {
+                if (previous.offset, previous.size) != (block.offset, block.size) {
+                    report.overlaps.push(LayoutOverlap {
+                        first: previous.index,
+                        second: block.index,
+                        offset: block.offset,
+                        size: previous.end().min(block.end()) - block.offset,
+                    });
+                }
+            }
+            let end = block.end().min(file_length);
+            if end > covered_end {
+                report.live_bytes += end - covered_end.max(start);
+            }
+            covered_end = covered_end.max(start).max(end);
+            if last.is_none_or(|previous| block.end() > previous.end()) {
+                last = Some(block);
+            }
+        }
+        if file_length > covered_end {
+            report.gaps.push(LayoutGap {
+                offset: covered_end,
+                size: file_length - covered_end,
+            });
+        }
+        report.free_bytes = report.gaps.iter().map(|gap| gap.size).sum();
+        report
+    }
+}
+
+/// Renders the physical layout of the archive as an SVG image. The file is
+/// drawn as rows of equal byte ranges; entries are shaded by their counter
+/// (newer writes are brighter) and unused space, including the data of
+/// unused entries, is highlighted, as counted by `layout_report`.
+pub fn render_layout_svg<W: Write>(
+    archive: &AnetArchive,
+    file_length: u64,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut blocks = archive.layout_blocks();
+    blocks.retain(|block| block.in_use);
+    let bytes_per_row = file_length.div_ceil(SVG_ROWS).max(1);
+    let max_counter = blocks.iter().map(|block| block.counter).max().unwrap_or(0);
+
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" shape-rendering="crispEdges">"#,
+        SVG_WIDTH,
+        SVG_ROWS * SVG_ROW_HEIGHT
+    )?;
+    writeln!(
+        writer,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        GAP_COLOR
+    )?;
+
+    let header_end = u64::from(archive.dat_header.header_size);
+    write_span(writer, 0, header_end, bytes_per_row, HEADER_COLOR)?;
+    for block in &blocks {
+        let color = block_color(block, max_counter);
+        write_span(writer, block.offset, block.end(), bytes_per_row, &color)?;
+    }
+    writeln!(writer, "</svg>")
+}
+
+fn block_color(block: &LayoutBlock, max_counter: u32) -> String {
+    let age = if max_counter == 0 {
+        0.0
+    } else {
+        f64::from(block.counter) / f64::from(max_counter)
+    };
+    let brightness = (64.0 + age * 191.0) as u8;
+    if block.compressed {
+        format!("#20{:02x}{:02x}", brightness, brightness)
+    } else {
+        format!("#{:02x}{:02x}20", brightness, brightness)
+    }
+}
+
+fn write_span<W: Write>(
+    writer: &mut W,
+    start: u64,
+    end: u64,
+    bytes_per_row: u64,
+    color: &str,
+) -> io::Result<()> {
+    let mut position = start;
+    while position < end {
+        let row = position / bytes_per_row;
+        if row >= SVG_ROWS {
+            break;
+        }
+        let row_end = ((row + 1) * bytes_per_row).min(end);
+        let x = (position % bytes_per_row) * SVG_WIDTH / bytes_per_row;
+        let width = ((row_end - position) * SVG_WIDTH)
+            .div_ceil(bytes_per_row)
+            .max(1);
+        writeln!(
+            writer,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+            x,
+            row * SVG_ROW_HEIGHT,
+            width,
+            SVG_ROW_HEIGHT,
+            color
+        )?;
+        position = row_end;
+    }
+    Ok(())
+}
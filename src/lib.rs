@@ -0,0 +1,94 @@
+//! Parser for the Guild Wars 2 `.dat` archive and the files stored in it.
+//!
+//! Load an archive with [`AnetArchive::load_from_file`], or from memory
+//! with `load_from_bytes`, then look entries up by file ID and read them
+//! decompressed:
+//!
+//! ```no_run
+//! use leafadoo::AnetArchive;
+//!
+//! let archive = AnetArchive::load_from_file("Gw2.dat")?;
+//! let data = archive.read_file(16)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! To pull out a single file, [`extract()`] does all of it in one call and
+//! names the output after the detected format:
+//!
+//! ```no_run
+//! let written = leafadoo::extract("Gw2.dat", 16, "out")?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! PF files, the container most game data comes in, are parsed with
+//! [`AnetPfFile`]; the other modules decode the formats inside, e.g.
+//! [`texture`], [`sound`] and [`map`]. The command line tool lives in the
+//! `leafadoo-cli` crate.
+#[macro_use]
+mod logging;
+pub mod age;
+pub mod anet_archive;
+pub mod animation;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod audio_script;
+pub mod binary;
+pub mod blocks;
+pub mod bookmark;
+pub mod budget;
+pub mod builder;
+pub mod cinematic;
+pub mod collision;
+pub mod compact;
+pub mod composite;
+pub mod content;
+pub mod deflate;
+pub mod deps;
+pub mod entry;
+pub mod eula;
+pub mod extract;
+pub mod file_type;
+pub mod filter;
+pub mod font;
+pub mod formats;
+pub mod guid;
+pub mod hash;
+pub mod inflate;
+pub mod layout;
+pub mod local;
+pub mod map;
+pub mod material;
+pub mod mesh;
+pub mod metrics;
+pub mod model;
+pub mod names;
+pub mod patch;
+pub mod pf;
+pub mod pipeline;
+pub mod prefetch;
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reader;
+pub mod retry;
+pub mod scan;
+pub mod search;
+pub mod shader_cache;
+pub mod skeleton;
+pub mod snapshot;
+pub mod sound;
+pub mod strictness;
+pub mod text;
+pub mod texture;
+pub mod thumbnail;
+pub mod token;
+pub mod trace;
+pub mod video;
+pub mod watch;
+
+pub use anet_archive::{AnetArchive, AnetFileType};
+pub use entry::EntryInfo;
+pub use extract::{extract_file_to_path, extract_file_to_path as extract};
+pub use pf::AnetPfFile;
+pub use progress::{Progress, ProgressStage};
+pub use strictness::Strictness;
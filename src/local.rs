@@ -0,0 +1,154 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const LOCL_FILE_TYPE: &[u8; 4] = b"locl";
+const GAME_ARCHIVE_NAME: &str = "gw2.dat";
+const LOCAL_ARCHIVE_NAME: &str = "local.dat";
+// Local.dat stores its settings among its first entries, so a renamed
+// archive is recognised without reading all of it.
+const LOCAL_PROBE_ENTRIES: usize = 64;
+const REDACTED_VALUE: &str = "<redacted>";
+// Keys holding credentials or personal data, matched case-insensitively.
+const SENSITIVE_KEY_PARTS: [&str; 6] = ["password", "email", "mail", "token", "hash", "credential"];
+
+/// Which of the two archives of an installation this is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveRole {
+    Game,  //< Gw2.dat, the shared game content.
+    Local, //< Local.dat, the per-user cache, settings and stored login.
+    Unknown,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AnetLocalSetting {
+    pub section: String, //< FourCC of the chunk holding the setting.
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AnetLocalSettings {
+    pub settings: Vec<AnetLocalSetting>,
+}
+
+impl AnetLocalSetting {
+    /// Whether the value is a credential or personal data that should not
+    /// be shown by default.
+    pub fn is_sensitive(&self) -> bool {
+        let key = self.key.to_lowercase();
+        SENSITIVE_KEY_PARTS.iter().any(|part| key.contains(part))
+    }
+}
+
+impl AnetLocalSettings {
+    /// Parses a decompressed `locl` entry. Every chunk is a settings section
+    /// holding an array of key/value string pairs.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != LOCL_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a locl file.",
+            ));
+        }
+
+        let _scope = trace::scope("locl");
+        let mut local_settings = AnetLocalSettings::default();
+        for chunk in &pf_file.chunks {
+            let section = String::from_utf8_lossy(&chunk.header.chunk_type).into_owned();
+            let mut reader = PfReader::new(&chunk.data);
+            let (count, position) = reader.field("settings").read_array()?;
+            let mut setting_reader = PfReader::at(&chunk.data, position);
+            for _ in 0..count {
+                local_settings.settings.push(AnetLocalSetting {
+                    section: section.clone(),
+                    key: setting_reader.field("key").read_string()?,
+                    value: setting_reader.field("value").read_wstring()?,
+                });
+            }
+        }
+        Ok(local_settings)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.settings
+            .iter()
+            .find(|setting| setting.key == key)
+            .map(|setting| setting.value.as_str())
+    }
+
+    /// A copy with the values of sensitive settings replaced, safe to print
+    /// or share.
+    pub fn redacted(&self) -> Self {
+        AnetLocalSettings {
+            settings: self
+                .settings
+                .iter()
+                .map(|setting| AnetLocalSetting {
+                    value: if setting.is_sensitive() {
+                        REDACTED_VALUE.to_string()
+                    } else {
+                        setting.value.clone()
+                    },
+                    ..setting.clone()
+                })
+                .collect(),
+        }
+    }
+}
+
+fn is_locl(data: &[u8]) -> bool {
+    data.starts_with(b"PF") && data.get(8..12) == Some(LOCL_FILE_TYPE)
+}
+
+impl AnetArchive {
+    /// Guesses the role of the archive from its file name, falling back to
+    /// looking for settings among its first entries.
+    pub fn role(&self) -> ArchiveRole {
+        let file_name = self
+            .file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase());
+        match file_name.as_deref() {
+            Some(GAME_ARCHIVE_NAME) => return ArchiveRole::Game,
+            Some(LOCAL_ARCHIVE_NAME) => return ArchiveRole::Local,
+            _ => {}
+        }
+        let has_settings = self
+            .in_use_entries()
+            .take(LOCAL_PROBE_ENTRIES)
+            .any(|entry| {
+                self.read_entry(entry.index())
+                    .is_ok_and(|data| is_locl(&data))
+            });
+        if has_settings {
+            ArchiveRole::Local
+        } else {
+            ArchiveRole::Unknown
+        }
+    }
+
+    /// MFT indices of the `locl` entries. Reads every entry, so it is meant
+    /// for Local.dat rather than the game archive.
+    pub fn local_settings_indices(&self) -> Vec<usize> {
+        self.in_use_entries()
+            .map(|entry| entry.index())
+            .filter(|&index| self.read_entry(index).is_ok_and(|data| is_locl(&data)))
+            .collect()
+    }
+
+    /// Settings of every `locl` entry, in MFT order.
+    pub fn local_settings(&self) -> io::Result<AnetLocalSettings> {
+        let mut local_settings = AnetLocalSettings::default();
+        for index in self.local_settings_indices() {
+            let entry_settings = AnetLocalSettings::load_from_bytes(&self.read_entry(index)?)?;
+            local_settings.settings.extend(entry_settings.settings);
+        }
+        Ok(local_settings)
+    }
+}
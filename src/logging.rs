@@ -0,0 +1,24 @@
+// Logging macros forwarding to `tracing` when the `tracing` feature is on and
+// expanding to nothing otherwise, so call sites need no `cfg` of their own.
+// Arguments are not evaluated when the feature is off.
+
+macro_rules! log_trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    };
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    };
+}
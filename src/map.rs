@@ -0,0 +1,709 @@
+use std::{fs::File, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::mesh::{Mesh, MeshGroup};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::texture::RgbaImageData;
+use crate::thumbnail::decode_image;
+use crate::trace;
+
+const PGTB_CHUNK: &[u8; 4] = b"PGTB";
+// Tiles further along an axis are taken for corrupt and left out.
+const MAX_TILE_COORD: f32 = 4096.0;
+// Largest side of a stitched floor, in pixels.
+const MAX_FLOOR_SIDE: u64 = 0x8000;
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetPagedImageLayer {
+    pub raw_format: u32,
+    pub stripped_format: u32,
+    pub compressed_format: u32,
+    pub unknown_field: u32,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetPagedImagePage {
+    pub layer: u32,
+    pub file_reference: Option<AnetFileReference>, //< Texture holding the tile.
+    pub coord: [f32; 2],                           //< Tile position in the page grid.
+    pub level: u32,                                //< Mip level, 0 is the most detailed.
+    pub solid_color: [u8; 4], //< BGRA, used instead of a texture when the tile is uniform.
+    pub flags: u32,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetPagedImageTable {
+    pub layers: Vec<AnetPagedImageLayer>,
+    pub raw_pages: Vec<AnetPagedImagePage>,
+    pub stripped_pages: Vec<AnetPagedImagePage>,
+    pub flags: u32,
+}
+
+impl AnetPagedImagePage {
+    /// Column and row of the tile, or `None` if its coordinates are not
+    /// finite, negative or implausibly far out.
+    pub fn grid_position(&self) -> Option<(u32, u32)> {
+        let [x, y] = self.coord;
+        let in_range = |coord: f32| (0.0..MAX_TILE_COORD).contains(&coord);
+        (in_range(x) && in_range(y)).then_some((x as u32, y as u32))
+    }
+
+    fn read(reader: &mut PfReader) -> io::Result<Self> {
+        let mut page = AnetPagedImagePage {
+            layer: reader.read_u32()?,
+            file_reference: reader.read_file_reference()?,
+            coord: [reader.read_f32()?, reader.read_f32()?],
+            level: reader.read_u32()?,
+            ..Default::default()
+        };
+        page.solid_color.copy_from_slice(reader.read_bytes(4)?);
+        page.flags = reader.read_u32()?;
+        Ok(page)
+    }
+}
+
+impl AnetPagedImageTable {
+    /// Parses the `PGTB` chunk of a decompressed PF `PIMG` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(PGTB_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing PGTB chunk."))?;
+
+        let _scope = trace::scope("PGTB");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut table = AnetPagedImageTable::default();
+
+        let (layer_count, layer_position) = reader.field("layers").read_array()?;
+        let mut layer_reader = PfReader::at(&chunk.data, layer_position);
+        for _ in 0..layer_count {
+            table.layers.push(AnetPagedImageLayer {
+                raw_format: layer_reader.read_u32()?,
+                stripped_format: layer_reader.read_u32()?,
+                compressed_format: layer_reader.read_u32()?,
+                unknown_field: layer_reader.read_u32()?,
+            });
+        }
+
+        table.raw_pages = Self::read_pages(reader.field("raw_pages"), &chunk.data)?;
+        table.stripped_pages = Self::read_pages(reader.field("stripped_pages"), &chunk.data)?;
+        table.flags = reader.field("flags").read_u32()?;
+        Ok(table)
+    }
+
+    fn read_pages(reader: &mut PfReader, data: &[u8]) -> io::Result<Vec<AnetPagedImagePage>> {
+        let (count, position) = reader.read_array()?;
+        let mut page_reader = PfReader::at(data, position);
+        let mut pages = Vec::new();
+        for _ in 0..count {
+            pages.push(AnetPagedImagePage::read(&mut page_reader)?);
+        }
+        Ok(pages)
+    }
+
+    /// Returns the stripped pages of `layer` at mip `level`, ordered row by
+    /// row, which is the order a tile stitcher consumes them in. Pages
+    /// without a `grid_position` are left out.
+    pub fn tiles(&self, layer: u32, level: u32) -> Vec<&AnetPagedImagePage> {
+        let mut tiles: Vec<&AnetPagedImagePage> = self
+            .stripped_pages
+            .iter()
+            .filter(|page| page.layer == layer && page.level == level)
+            .filter(|page| page.grid_position().is_some())
+            .collect();
+        tiles.sort_by(|a, b| {
+            a.coord[1]
+                .total_cmp(&b.coord[1])
+                .then(a.coord[0].total_cmp(&b.coord[0]))
+        });
+        tiles
+    }
+
+    /// Number of tiles along each axis for `layer` at mip `level`.
+    pub fn grid_size(&self, layer: u32, level: u32) -> (u32, u32) {
+        self.tiles(layer, level)
+            .iter()
+            .filter_map(|page| page.grid_position())
+            .fold((0, 0), |(width, height), (column, row)| {
+                (width.max(column + 1), height.max(row + 1))
+            })
+    }
+
+    /// Mip levels with tiles for `layer`, least detailed first.
+    pub fn levels(&self, layer: u32) -> Vec<u32> {
+        let mut levels: Vec<u32> = self
+            .stripped_pages
+            .iter()
+            .filter(|page| page.layer == layer)
+            .map(|page| page.level)
+            .collect();
+        levels.sort_unstable_by(|a, b| b.cmp(a));
+        levels.dedup();
+        levels
+    }
+}
+
+// Side of the tiles when every tile of a floor is a solid color.
+const DEFAULT_TILE_SIZE: u32 = 256;
+
+/// Copies `tile` into `canvas` with its top left corner at `x`, `y`,
+/// clipping whatever falls outside.
+fn blit(canvas: &mut RgbaImageData, tile: &RgbaImageData, x: u32, y: u32) {
+    let width = tile.width.min(canvas.width.saturating_sub(x)) as usize;
+    for row in 0..tile.height.min(canvas.height.saturating_sub(y)) {
+        let source = row as usize * tile.width as usize * 4;
+        let target = ((y + row) as usize * canvas.width as usize + x as usize) * 4;
+        canvas.pixels[target..target + width * 4]
+            .copy_from_slice(&tile.pixels[source..source + width * 4]);
+    }
+}
+
+/// A transparent canvas for `columns` by `rows` tiles of `tile_width` by
+/// `tile_height` pixels. Fails if either side exceeds `MAX_FLOOR_SIDE`.
+fn floor_canvas(
+    columns: u32,
+    rows: u32,
+    tile_width: u32,
+    tile_height: u32,
+) -> io::Result<RgbaImageData> {
+    let width = u64::from(columns) * u64::from(tile_width);
+    let height = u64::from(rows) * u64::from(tile_height);
+    if width > MAX_FLOOR_SIDE || height > MAX_FLOOR_SIDE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Floor of {}x{} pixels is too large to render.",
+                width, height
+            ),
+        ));
+    }
+    Ok(RgbaImageData {
+        width: width as u32,
+        height: height as u32,
+        pixels: vec![0; (width * height * 4) as usize],
+    })
+}
+
+impl AnetArchive {
+    /// Stitches the tiles of a map floor into one image. `file_id` is the
+    /// `PIMG` file of the floor and `zoom` counts its mip levels from 0,
+    /// the least detailed, see `AnetPagedImageTable::levels`. Tiles are
+    /// placed by their grid position at the size of the first tile
+    /// texture; solid tiles are filled with their color. Tiles that fail
+    /// to read or decode fail the render, or are left transparent by a
+    /// lenient archive. Floors over 32768 pixels on a side are rejected.
+    pub fn render_floor(&self, file_id: u32, layer: u32, zoom: u32) -> io::Result<RgbaImageData> {
+        let table = AnetPagedImageTable::load_from_bytes(&self.read_file(file_id)?)?;
+        let level = *table.levels(layer).get(zoom as usize).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Floor {} has no zoom level {} in layer {}.",
+                    file_id, zoom, layer
+                ),
+            )
+        })?;
+        let tiles = table.tiles(layer, level);
+        let (columns, rows) = table.grid_size(layer, level);
+
+        let mut images = Vec::with_capacity(tiles.len());
+        for page in &tiles {
+            let Some(texture_id) = page.file_reference.as_ref().and_then(|file| file.file_id())
+            else {
+                images.push(None);
+                continue;
+            };
+            let image = self
+                .read_file(texture_id)
+                .and_then(|data| decode_image(&data));
+            images.push(self.tolerate(self.index_of(texture_id), image)?);
+        }
+        let (tile_width, tile_height) = images
+            .iter()
+            .flatten()
+            .next()
+            .map_or((DEFAULT_TILE_SIZE, DEFAULT_TILE_SIZE), |image| {
+                (image.width, image.height)
+            });
+
+        let mut canvas = floor_canvas(columns, rows, tile_width, tile_height)?;
+        for (page, image) in tiles.iter().zip(images) {
+            let Some((column, row)) = page.grid_position() else {
+                continue;
+            };
+            // Both products are within the canvas, which is far below `u32::MAX`.
+            let x = column * tile_width;
+            let y = row * tile_height;
+            let textured = page
+                .file_reference
+                .as_ref()
+                .is_some_and(|file| file.file_id().is_some());
+            if textured && image.is_none() {
+                continue;
+            }
+            let tile = image.unwrap_or_else(|| {
+                let [blue, green, red, alpha] = page.solid_color;
+                RgbaImageData {
+                    width: tile_width,
+                    height: tile_height,
+                    pixels: [red, green, blue, alpha].repeat((tile_width * tile_height) as usize),
+                }
+            });
+            blit(&mut canvas, &tile, x, y);
+        }
+        Ok(canvas)
+    }
+}
+
+const ENV_CHUNK: &[u8; 4] = b"env ";
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEnvLighting {
+    pub ambient_color: [u8; 4], //< BGRA.
+    pub diffuse_color: [u8; 4], //< BGRA.
+    pub intensity: f32,
+    pub direction: [f32; 3],
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEnvFog {
+    pub color: [u8; 4], //< BGRA.
+    pub distance_start: f32,
+    pub distance_end: f32,
+    pub density: f32,
+    pub height_start: f32,
+    pub height_end: f32,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEnvSky {
+    pub texture: Option<AnetFileReference>,
+    pub color: [u8; 4], //< BGRA.
+    pub day_brightness: f32,
+    pub night_brightness: f32,
+}
+
+/// A key of a time-of-day curve, selecting which lighting, fog and sky
+/// settings apply at `time`.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetEnvTimeKey {
+    pub time: f32, //< Fraction of the day, 0.0 is midnight.
+    pub lighting_index: u32,
+    pub fog_index: u32,
+    pub sky_index: u32,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMapEnvironment {
+    pub lighting: Vec<AnetEnvLighting>,
+    pub fog: Vec<AnetEnvFog>,
+    pub sky: Vec<AnetEnvSky>,
+    pub time_of_day: Vec<AnetEnvTimeKey>,
+}
+
+impl AnetMapEnvironment {
+    /// Parses the `env ` chunk of a decompressed PF `mapc` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(ENV_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing env chunk."))?;
+        Self::load_from_chunk(&chunk.data)
+    }
+
+    pub fn load_from_chunk(data: &[u8]) -> io::Result<Self> {
+        let _scope = trace::scope("env ");
+        let mut reader = PfReader::new(data);
+        let mut environment = AnetMapEnvironment::default();
+
+        let (count, position) = reader.field("lighting").read_array()?;
+        let mut item_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            environment.lighting.push(AnetEnvLighting {
+                ambient_color: read_color(&mut item_reader)?,
+                diffuse_color: read_color(&mut item_reader)?,
+                intensity: item_reader.read_f32()?,
+                direction: [
+                    item_reader.read_f32()?,
+                    item_reader.read_f32()?,
+                    item_reader.read_f32()?,
+                ],
+            });
+        }
+
+        let (count, position) = reader.field("fog").read_array()?;
+        let mut item_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            environment.fog.push(AnetEnvFog {
+                color: read_color(&mut item_reader)?,
+                distance_start: item_reader.read_f32()?,
+                distance_end: item_reader.read_f32()?,
+                density: item_reader.read_f32()?,
+                height_start: item_reader.read_f32()?,
+                height_end: item_reader.read_f32()?,
+            });
+        }
+
+        let (count, position) = reader.field("sky").read_array()?;
+        let mut item_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            environment.sky.push(AnetEnvSky {
+                texture: item_reader.read_file_reference()?,
+                color: read_color(&mut item_reader)?,
+                day_brightness: item_reader.read_f32()?,
+                night_brightness: item_reader.read_f32()?,
+            });
+        }
+
+        let (count, position) = reader.field("time_of_day").read_array()?;
+        let mut item_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            environment.time_of_day.push(AnetEnvTimeKey {
+                time: item_reader.read_f32()?,
+                lighting_index: item_reader.read_u32()?,
+                fog_index: item_reader.read_u32()?,
+                sky_index: item_reader.read_u32()?,
+            });
+        }
+        Ok(environment)
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let file = File::create(file_path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+fn read_color(reader: &mut PfReader) -> io::Result<[u8; 4]> {
+    let mut color = [0; 4];
+    color.copy_from_slice(reader.read_bytes(4)?);
+    Ok(color)
+}
+
+const MPSD_CHUNK: &[u8; 4] = b"mpsd";
+const MMET_CHUNK: &[u8; 4] = b"mMet";
+
+/// Baked terrain shadows of a map: one darkness value per texel over the
+/// map bounds.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMapShadow {
+    pub width: u32,
+    pub height: u32,
+    pub bounds_min: [f32; 2], //< Map x and y covered by the first texel.
+    pub bounds_max: [f32; 2],
+    pub texels: Vec<u8>, //< Row by row, 0 is fully lit.
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMapMetadata {
+    pub map_id: u32,
+    pub map_type: u32,
+    pub flags: u32,
+    pub name: String,
+    pub bounds_min: [f32; 3],
+    pub bounds_max: [f32; 3],
+}
+
+impl AnetMapShadow {
+    /// Parses the `mpsd` chunk of a decompressed PF `mpsd` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(MPSD_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing mpsd chunk."))?;
+        Self::load_from_chunk(&chunk.data)
+    }
+
+    pub fn load_from_chunk(data: &[u8]) -> io::Result<Self> {
+        let _scope = trace::scope("mpsd");
+        let mut reader = PfReader::new(data);
+        let shadow = AnetMapShadow {
+            width: reader.field("width").read_u32()?,
+            height: reader.field("height").read_u32()?,
+            bounds_min: [reader.read_f32()?, reader.read_f32()?],
+            bounds_max: [reader.read_f32()?, reader.read_f32()?],
+            texels: reader.field("texels").read_byte_array()?,
+        };
+        if shadow.texels.len() as u64 != u64::from(shadow.width) * u64::from(shadow.height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Shadow texels do not match the shadow size.",
+            ));
+        }
+        Ok(shadow)
+    }
+
+    /// Shadow value at the map position `x`, `y`, or `None` outside of the
+    /// bounds.
+    pub fn shadow_at(&self, x: f32, y: f32) -> Option<u8> {
+        let extent = [
+            self.bounds_max[0] - self.bounds_min[0],
+            self.bounds_max[1] - self.bounds_min[1],
+        ];
+        if extent[0] <= 0.0 || extent[1] <= 0.0 {
+            return None;
+        }
+        let u = (x - self.bounds_min[0]) / extent[0];
+        let v = (y - self.bounds_min[1]) / extent[1];
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        let column = ((u * self.width as f32) as u32).min(self.width.saturating_sub(1));
+        let row = ((v * self.height as f32) as u32).min(self.height.saturating_sub(1));
+        self.texels
+            .get(row as usize * self.width as usize + column as usize)
+            .copied()
+    }
+}
+
+impl AnetMapMetadata {
+    /// Parses the `mMet` chunk of a decompressed PF `mMet` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(MMET_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing mMet chunk."))?;
+        Self::load_from_chunk(&chunk.data)
+    }
+
+    pub fn load_from_chunk(data: &[u8]) -> io::Result<Self> {
+        let _scope = trace::scope("mMet");
+        let mut reader = PfReader::new(data);
+        Ok(AnetMapMetadata {
+            map_id: reader.field("map_id").read_u32()?,
+            map_type: reader.field("map_type").read_u32()?,
+            flags: reader.field("flags").read_u32()?,
+            name: reader.field("name").read_wstring()?,
+            bounds_min: [reader.read_f32()?, reader.read_f32()?, reader.read_f32()?],
+            bounds_max: [reader.read_f32()?, reader.read_f32()?, reader.read_f32()?],
+        })
+    }
+}
+
+const TRN_CHUNK: &[u8; 4] = b"trn ";
+
+/// A square of terrain: its height samples and the material painted on it.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetTerrainChunk {
+    pub flags: u32,
+    pub material_index: u32, //< Index into `AnetMapTerrain::materials`.
+    pub surface_indices: Vec<u16>,
+    pub surface_tokens: Vec<u64>, //< Footstep and collision surface of each surface index.
+}
+
+/// A terrain material and the textures its splat channels blend.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetTerrainMaterial {
+    pub material_file: Option<AnetFileReference>,
+    pub texture_files: Vec<Option<AnetFileReference>>,
+}
+
+/// The terrain of a map: a grid of chunks, each with a square grid of
+/// height samples.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMapTerrain {
+    pub chunk_dims: [u32; 2], //< Chunks along x and y.
+    pub swap_distance: f32,
+    pub heights: Vec<f32>, //< Chunk by chunk, each row by row.
+    pub tile_flags: Vec<u32>,
+    pub chunks: Vec<AnetTerrainChunk>,          //< Row by row.
+    pub splat_image: Option<AnetFileReference>, //< `PIMG` file weighting the material textures.
+    pub materials: Vec<AnetTerrainMaterial>,
+}
+
+impl AnetMapTerrain {
+    /// Parses the `trn ` chunk of a decompressed PF `mapc` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(TRN_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing trn chunk."))?;
+        Self::load_from_chunk(&chunk.data)
+    }
+
+    pub fn load_from_chunk(data: &[u8]) -> io::Result<Self> {
+        let _scope = trace::scope("trn ");
+        let mut reader = PfReader::new(data);
+        let mut terrain = AnetMapTerrain {
+            chunk_dims: [reader.field("chunk_dims").read_u32()?, reader.read_u32()?],
+            swap_distance: reader.field("swap_distance").read_f32()?,
+            ..Default::default()
+        };
+
+        terrain.heights = reader.field("heights").read_f32_array()?;
+        terrain.tile_flags = reader.field("tile_flags").read_u32_array()?;
+
+        let (count, position) = reader.field("chunks").read_array()?;
+        let mut item_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            terrain.chunks.push(AnetTerrainChunk {
+                flags: item_reader.field("flags").read_u32()?,
+                material_index: item_reader.field("material_index").read_u32()?,
+                surface_indices: item_reader.field("surface_indices").read_u16_array()?,
+                surface_tokens: item_reader.field("surface_tokens").read_u64_array()?,
+            });
+        }
+
+        if let Some(position) = reader.field("materials").read_pointer()? {
+            let mut materials_reader = PfReader::at(data, position);
+            terrain.splat_image = materials_reader
+                .field("splat_image")
+                .read_file_reference()?;
+            let (count, position) = materials_reader.field("materials").read_array()?;
+            let mut item_reader = PfReader::at(data, position);
+            for _ in 0..count {
+                let mut material = AnetTerrainMaterial {
+                    material_file: item_reader.field("material_file").read_file_reference()?,
+                    ..Default::default()
+                };
+                let (count, position) = item_reader.field("texture_files").read_array()?;
+                let mut texture_reader = PfReader::at(data, position);
+                for _ in 0..count {
+                    material
+                        .texture_files
+                        .push(texture_reader.field("texture_file").read_file_reference()?);
+                }
+                terrain.materials.push(material);
+            }
+        }
+
+        terrain.samples_per_side()?;
+        Ok(terrain)
+    }
+
+    /// Height samples along each side of a chunk.
+    pub fn samples_per_side(&self) -> io::Result<usize> {
+        let chunk_count = self.chunk_dims[0] as usize * self.chunk_dims[1] as usize;
+        let per_chunk = self.heights.len().checked_div(chunk_count).unwrap_or(0);
+        let side = (per_chunk as f64).sqrt() as usize;
+        if chunk_count == 0 || side < 2 || side * side * chunk_count != self.heights.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Terrain heights do not form a square grid per chunk.",
+            ));
+        }
+        Ok(side)
+    }
+
+    pub fn splat_image_file_id(&self) -> Option<u32> {
+        self.splat_image.as_ref()?.file_id()
+    }
+
+    /// Name of the mesh group drawn with material `index`: its material
+    /// file ID when it has one.
+    fn material_name(&self, index: u32) -> String {
+        let file_id = self
+            .materials
+            .get(index as usize)
+            .and_then(|material| material.material_file.as_ref()?.file_id());
+        match file_id {
+            Some(file_id) => format!("terrain_{}_{}", index, file_id),
+            None => format!("terrain_{}", index),
+        }
+    }
+
+    /// Triangulates the terrain over the map rectangle from `bounds_min` to
+    /// `bounds_max`, in map x and y. Heights become the up axis. Triangles
+    /// are grouped by the material of their chunk, named
+    /// `terrain_<index>_<material file ID>`, and the UVs span the whole
+    /// terrain so they address the splat image.
+    pub fn to_mesh(&self, bounds_min: [f32; 2], bounds_max: [f32; 2]) -> io::Result<Mesh> {
+        let side = self.samples_per_side()?;
+        let [columns, rows] = self.chunk_dims;
+        let extent = [bounds_max[0] - bounds_min[0], bounds_max[1] - bounds_min[1]];
+        let mut mesh = Mesh {
+            name: "terrain".to_string(),
+            ..Default::default()
+        };
+        let mut groups: Vec<(u32, MeshGroup)> = Vec::new();
+        let step = 1.0 / (side - 1) as f32;
+        for (chunk_index, heights) in self.heights.chunks_exact(side * side).enumerate() {
+            let column = (chunk_index % columns as usize) as f32;
+            let row = (chunk_index / columns as usize) as f32;
+            let base = mesh.positions.len() as u32;
+            for (sample, height) in heights.iter().enumerate() {
+                let u = (column + (sample % side) as f32 * step) / columns as f32;
+                let v = (row + (sample / side) as f32 * step) / rows as f32;
+                mesh.positions.push([
+                    bounds_min[0] + u * extent[0],
+                    *height,
+                    bounds_min[1] + v * extent[1],
+                ]);
+                mesh.uvs.push([u, v]);
+            }
+
+            let material_index = self
+                .chunks
+                .get(chunk_index)
+                .map_or(0, |chunk| chunk.material_index);
+            let group = match groups
+                .iter()
+                .position(|(index, _)| *index == material_index)
+            {
+                Some(group) => group,
+                None => {
+                    groups.push((
+                        material_index,
+                        MeshGroup {
+                            material: self.material_name(material_index),
+                            indices: Vec::new(),
+                        },
+                    ));
+                    groups.len() - 1
+                }
+            };
+            let indices = &mut groups[group].1.indices;
+            let side = side as u32;
+            for y in 0..side - 1 {
+                for x in 0..side - 1 {
+                    let corner = base + y * side + x;
+                    indices.extend([corner, corner + side, corner + 1]);
+                    indices.extend([corner + 1, corner + side, corner + side + 1]);
+                }
+            }
+        }
+        mesh.groups = groups.into_iter().map(|(_, group)| group).collect();
+        mesh.compute_normals();
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(coord: [f32; 2]) -> AnetPagedImagePage {
+        AnetPagedImagePage {
+            coord,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tiles_with_bad_coords_are_left_out() {
+        let table = AnetPagedImageTable {
+            stripped_pages: vec![
+                page([1.0, 2.0]),
+                page([f32::NAN, 0.0]),
+                page([0.0, f32::INFINITY]),
+                page([1e30, 0.0]),
+                page([-1.0, 0.0]),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(table.tiles(0, 0).len(), 1);
+        assert_eq!(table.grid_size(0, 0), (2, 3));
+    }
+
+    #[test]
+    fn oversized_floors_are_rejected() {
+        let canvas = floor_canvas(2, 3, 256, 256).unwrap();
+        assert_eq!((canvas.width, canvas.height), (512, 768));
+        assert_eq!(canvas.pixels.len(), 512 * 768 * 4);
+        assert!(floor_canvas(4095, 1, 0xffff, 256).is_err());
+        assert!(floor_canvas(u32::MAX, u32::MAX, u32::MAX, u32::MAX).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const GRMT_CHUNK: &[u8; 4] = b"GRMT";
+const DX9S_CHUNK: &[u8; 4] = b"DX9S";
+
+// Texture slot tokens shared by the stock material shaders.
+const DIFFUSE_TEXTURE_TOKEN: u32 = 0x67531924;
+const NORMAL_TEXTURE_TOKEN: u32 = 0x1816c9ee;
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMaterialSampler {
+    pub texture_index: u32, //< Index into the texture slots of the model material.
+    pub state: Vec<u32>,    //< D3DSAMPLERSTATETYPE and value pairs.
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMaterialShader {
+    pub bytecode: Vec<u8>, //< DX9 shader blob.
+    pub constant_registers: Vec<u32>,
+    pub instruction_count: u32,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMaterialPass {
+    pub vertex_shader: u32, //< Index into the shaders of the material.
+    pub pixel_shader: u32,
+    pub samplers: Vec<u32>, //< Indices into the samplers of the material.
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMaterialTechnique {
+    pub name: String,
+    pub passes: Vec<AnetMaterialPass>,
+}
+
+/// A parsed `AMAT` file: the shaders a material is rendered with. Textures
+/// and constant values are not part of it, they come from the model that
+/// references the material, see [`AnetModelMaterial`].
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMaterial {
+    pub texture_array_range: u8,
+    pub texture_array_count: u8,
+    pub flags: u16,
+    pub texture_tokens: Vec<u64>, //< Tokens of the texture slots the shaders sample.
+    pub samplers: Vec<AnetMaterialSampler>,
+    pub shaders: Vec<AnetMaterialShader>,
+    pub techniques: Vec<AnetMaterialTechnique>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMaterialTextureSlot {
+    pub file_reference: Option<AnetFileReference>,
+    pub flags: u32,
+    pub token: u32, //< Identifies the slot, e.g. diffuse or normal map.
+    pub blit_id: u64,
+    pub uv_animation_id: u32,
+    pub uv_input_index: u8,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetMaterialConstant {
+    pub token: u32,
+    pub value: [f32; 4],
+    pub flags: u32,
+}
+
+/// Material entry of a model: the `AMAT` file to render with, plus the
+/// textures and constant values bound to it.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetModelMaterial {
+    pub material_file: Option<AnetFileReference>,
+    pub flags: u32,
+    pub material_id: u32,
+    pub textures: Vec<AnetMaterialTextureSlot>,
+    pub constants: Vec<AnetMaterialConstant>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<AnetMaterial>, //< Parsed `material_file`, once resolved.
+}
+
+impl AnetMaterial {
+    /// Parses the `GRMT` and `DX9S` chunks of a decompressed PF `AMAT` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let mut material = AnetMaterial::default();
+
+        if let Some(chunk) = pf_file.chunk(GRMT_CHUNK) {
+            let _scope = trace::scope("GRMT");
+            let mut reader = PfReader::new(&chunk.data);
+            material.texture_array_range = reader.field("texture_array_range").read_u8()?;
+            material.texture_array_count = reader.field("texture_array_count").read_u8()?;
+            material.flags = reader.field("flags").read_u16()?;
+            material.texture_tokens = reader.field("texture_tokens").read_u64_array()?;
+        }
+
+        let chunk = pf_file
+            .chunk(DX9S_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing DX9S chunk."))?;
+        let _scope = trace::scope("DX9S");
+        let mut reader = PfReader::new(&chunk.data);
+
+        let (count, position) = reader.field("samplers").read_array()?;
+        let mut sampler_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            material.samplers.push(AnetMaterialSampler {
+                texture_index: sampler_reader.field("texture_index").read_u32()?,
+                state: sampler_reader.field("state").read_u32_array()?,
+            });
+        }
+
+        let (count, position) = reader.field("shaders").read_array()?;
+        let mut shader_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            let bytecode: Vec<u8> = shader_reader
+                .field("bytecode")
+                .read_u32_array()?
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .collect();
+            material.shaders.push(AnetMaterialShader {
+                bytecode,
+                constant_registers: shader_reader.field("constant_registers").read_u32_array()?,
+                instruction_count: shader_reader.field("instruction_count").read_u32()?,
+            });
+        }
+
+        let (count, position) = reader.field("techniques").read_array()?;
+        let mut technique_reader = PfReader::at(&chunk.data, position);
+        for _ in 0..count {
+            let name = technique_reader.field("name").read_string()?;
+            let (pass_count, pass_position) = technique_reader.field("passes").read_array()?;
+            let mut pass_reader = PfReader::at(&chunk.data, pass_position);
+            let mut passes = Vec::new();
+            for _ in 0..pass_count {
+                passes.push(AnetMaterialPass {
+                    vertex_shader: pass_reader.field("vertex_shader").read_u32()?,
+                    pixel_shader: pass_reader.field("pixel_shader").read_u32()?,
+                    samplers: pass_reader.field("samplers").read_u32_array()?,
+                });
+            }
+            material
+                .techniques
+                .push(AnetMaterialTechnique { name, passes });
+        }
+        Ok(material)
+    }
+}
+
+impl AnetModelMaterial {
+    /// Reads one material entry of a model's material array.
+    pub fn read(reader: &mut PfReader) -> io::Result<Self> {
+        let data = reader.data();
+        let mut material = AnetModelMaterial {
+            material_file: reader.field("material_file").read_file_reference()?,
+            flags: reader.field("flags").read_u32()?,
+            material_id: reader.field("material_id").read_u32()?,
+            ..Default::default()
+        };
+
+        let (count, position) = reader.field("textures").read_array()?;
+        let mut texture_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            material.textures.push(AnetMaterialTextureSlot {
+                file_reference: texture_reader
+                    .field("file_reference")
+                    .read_file_reference()?,
+                flags: texture_reader.field("flags").read_u32()?,
+                token: texture_reader.field("token").read_u32()?,
+                blit_id: texture_reader.field("blit_id").read_u64()?,
+                uv_animation_id: texture_reader.field("uv_animation_id").read_u32()?,
+                uv_input_index: texture_reader.field("uv_input_index").read_u8()?,
+            });
+            texture_reader.skip(3)?;
+        }
+
+        let (count, position) = reader.field("constants").read_array()?;
+        let mut constant_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            material.constants.push(AnetMaterialConstant {
+                token: constant_reader.field("token").read_u32()?,
+                value: [
+                    constant_reader.read_f32()?,
+                    constant_reader.read_f32()?,
+                    constant_reader.read_f32()?,
+                    constant_reader.read_f32()?,
+                ],
+                flags: constant_reader.field("flags").read_u32()?,
+            });
+        }
+        Ok(material)
+    }
+
+    pub fn material_file_id(&self) -> Option<u32> {
+        self.material_file.as_ref()?.file_id()
+    }
+
+    /// File IDs of every texture bound to the material, in slot order.
+    pub fn texture_file_ids(&self) -> Vec<u32> {
+        self.textures
+            .iter()
+            .filter_map(|texture| texture.file_reference.as_ref()?.file_id())
+            .collect()
+    }
+
+    pub fn texture_by_token(&self, token: u32) -> Option<&AnetFileReference> {
+        self.textures
+            .iter()
+            .find(|texture| texture.token == token)
+            .and_then(|texture| texture.file_reference.as_ref())
+    }
+
+    /// The diffuse texture, falling back to the first texture for materials
+    /// that do not use the stock slot tokens.
+    pub fn diffuse_texture(&self) -> Option<&AnetFileReference> {
+        self.texture_by_token(DIFFUSE_TEXTURE_TOKEN).or_else(|| {
+            self.textures
+                .first()
+                .and_then(|texture| texture.file_reference.as_ref())
+        })
+    }
+
+    pub fn normal_texture(&self) -> Option<&AnetFileReference> {
+        self.texture_by_token(NORMAL_TEXTURE_TOKEN)
+    }
+
+    /// Parses `data` as the `AMAT` file referenced by `material_file` and
+    /// attaches it to this entry.
+    pub fn resolve(&mut self, data: &[u8]) -> io::Result<&AnetMaterial> {
+        Ok(self.material.insert(AnetMaterial::load_from_bytes(data)?))
+    }
+
+    /// Reads and attaches the referenced `AMAT` file from `archive`.
+    pub fn resolve_from_archive(&mut self, archive: &AnetArchive) -> io::Result<&AnetMaterial> {
+        let file_id = self.material_file_id().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Material has no file reference.",
+            )
+        })?;
+        let entry = archive.entry_by_id(file_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Material file {} is not in the archive.", file_id),
+            )
+        })?;
+        let data = archive.read_entry(entry.index())?;
+        self.resolve(&data)
+    }
+}
@@ -0,0 +1,693 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const GLB_JSON_CHUNK: &[u8; 4] = b"JSON";
+const GLB_BIN_CHUNK: &[u8; 4] = b"BIN\0";
+
+// glTF accessor component types and buffer view targets.
+const GL_FLOAT: u32 = 5126;
+const GL_UNSIGNED_SHORT: u32 = 5123;
+const GL_UNSIGNED_INT: u32 = 5125;
+const GL_ARRAY_BUFFER: u32 = 34962;
+const GL_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Triangles sharing one material.
+#[derive(Default, Debug, Clone)]
+pub struct MeshGroup {
+    pub material: String,
+    pub indices: Vec<u32>, //< Three vertex indices per triangle.
+}
+
+/// Geometry in the form every exporter writes: one vertex list and the
+/// triangles over it, grouped by material. Positions are y-up, as glTF
+/// expects.
+#[derive(Default, Debug, Clone)]
+pub struct Mesh {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>, //< One per vertex, or empty.
+    pub uvs: Vec<[f32; 2]>,     //< One per vertex, or empty.
+    pub joints: Vec<[u16; 4]>,  //< Skeleton bones moving each vertex, or empty.
+    pub weights: Vec<[f32; 4]>, //< Influence of each of `joints`, summing to 1.
+    pub groups: Vec<MeshGroup>,
+}
+
+/// A bone and its bind pose relative to its parent, y-up.
+#[derive(Default, Debug, Clone)]
+pub struct Bone {
+    pub name: String,
+    pub parent: Option<usize>, //< Always an earlier bone.
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4], //< Quaternion, x, y, z, w.
+    pub scale: [f32; 3],
+}
+
+/// The bones the `joints` of a mesh index.
+#[derive(Default, Debug, Clone)]
+pub struct Skeleton {
+    pub bones: Vec<Bone>,
+}
+
+// 4x4 matrices are column-major, as glTF stores them.
+type Matrix = [f32; 16];
+
+fn trs_matrix(translation: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> Matrix {
+    let [x, y, z, w] = rotation;
+    let columns = [
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y + z * w),
+            2.0 * (x * z - y * w),
+        ],
+        [
+            2.0 * (x * y - z * w),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z + x * w),
+        ],
+        [
+            2.0 * (x * z + y * w),
+            2.0 * (y * z - x * w),
+            1.0 - 2.0 * (x * x + y * y),
+        ],
+    ];
+    let mut matrix = [0.0; 16];
+    for (column, axis) in columns.iter().enumerate() {
+        for row in 0..3 {
+            matrix[column * 4 + row] = axis[row] * scale[column];
+        }
+    }
+    matrix[12..15].copy_from_slice(&translation);
+    matrix[15] = 1.0;
+    matrix
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            product[column * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+        }
+    }
+    product
+}
+
+/// Inverts an affine matrix, giving the identity for singular ones.
+fn affine_inverse(m: &Matrix) -> Matrix {
+    let at = |row: usize, column: usize| m[column * 4 + row];
+    let cofactor = |row: usize, column: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((column + 1) % 3, (column + 2) % 3);
+        at(r0, c0) * at(r1, c1) - at(r0, c1) * at(r1, c0)
+    };
+    let determinant: f32 = (0..3)
+        .map(|column| at(0, column) * cofactor(0, column))
+        .sum();
+    let mut inverse = [0.0; 16];
+    inverse[0] = 1.0;
+    inverse[5] = 1.0;
+    inverse[10] = 1.0;
+    inverse[15] = 1.0;
+    if determinant.abs() < f32::EPSILON {
+        return inverse;
+    }
+    for row in 0..3 {
+        for column in 0..3 {
+            inverse[column * 4 + row] = cofactor(column, row) / determinant;
+        }
+    }
+    for row in 0..3 {
+        inverse[12 + row] = -(0..3)
+            .map(|k| inverse[k * 4 + row] * m[12 + k])
+            .sum::<f32>();
+    }
+    inverse
+}
+
+impl Skeleton {
+    /// Bind pose of each bone in model space.
+    pub fn world_matrices(&self) -> Vec<Matrix> {
+        let mut matrices: Vec<Matrix> = Vec::with_capacity(self.bones.len());
+        for bone in &self.bones {
+            let local = trs_matrix(bone.translation, bone.rotation, bone.scale);
+            let world = match bone.parent.and_then(|parent| matrices.get(parent)) {
+                Some(parent) => multiply(parent, &local),
+                None => local,
+            };
+            matrices.push(world);
+        }
+        matrices
+    }
+
+    /// Matrices taking model space into the space of each bone.
+    pub fn inverse_bind_matrices(&self) -> Vec<Matrix> {
+        self.world_matrices().iter().map(affine_inverse).collect()
+    }
+}
+
+impl Mesh {
+    pub fn triangle_count(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|group| group.indices.len() / 3)
+            .sum()
+    }
+
+    /// Replaces the normals with the area-weighted average of the faces
+    /// around each vertex.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![[0.0f32; 3]; self.positions.len()];
+        for group in &self.groups {
+            for triangle in group.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|corner| self.positions[triangle[corner] as usize]);
+                let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+                let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+                let face = [
+                    u[1] * v[2] - u[2] * v[1],
+                    u[2] * v[0] - u[0] * v[2],
+                    u[0] * v[1] - u[1] * v[0],
+                ];
+                for corner in triangle {
+                    let normal = &mut normals[*corner as usize];
+                    for axis in 0..3 {
+                        normal[axis] += face[axis];
+                    }
+                }
+            }
+        }
+        for normal in &mut normals {
+            let length =
+                (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            *normal = if length > 0.0 {
+                normal.map(|value| value / length)
+            } else {
+                [0.0, 1.0, 0.0]
+            };
+        }
+        self.normals = normals;
+    }
+
+    /// Checks that every index names a vertex and that the other vertex
+    /// attributes, when present, cover every vertex.
+    pub fn validate(&self) -> io::Result<()> {
+        let vertex_count = self.positions.len();
+        let attributes_match = [
+            self.normals.len(),
+            self.uvs.len(),
+            self.joints.len(),
+            self.weights.len(),
+        ]
+        .iter()
+        .all(|count| *count == 0 || *count == vertex_count);
+        let indices_match = self.groups.iter().all(|group| {
+            group.indices.len().is_multiple_of(3)
+                && group
+                    .indices
+                    .iter()
+                    .all(|index| (*index as usize) < vertex_count)
+        });
+        if !attributes_match || !indices_match {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Mesh indices or attributes do not match its vertices.",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the mesh as a binary glTF 2.0 file, see `write_glb_meshes`.
+    pub fn write_glb<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_glb_meshes(std::slice::from_ref(self), None, writer)
+    }
+
+    pub fn save_glb<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        save_glb_meshes(std::slice::from_ref(self), None, file_path)
+    }
+}
+
+/// The JSON document and binary buffer of a glTF file being assembled.
+#[derive(Default)]
+struct GlbBuilder<'a> {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    materials: Vec<&'a str>,
+    meshes: Vec<Value>,
+    nodes: Vec<Value>,
+    roots: Vec<usize>, //< Nodes of the scene, the others are their children.
+    skins: Vec<Value>,
+}
+
+impl<'a> GlbBuilder<'a> {
+    /// Appends `data` as a buffer view and returns the index of the
+    /// accessor describing it.
+    fn push_view(&mut self, data: Vec<u8>, target: Option<u32>, mut accessor: Value) -> usize {
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": data.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.buffer_views.push(view);
+        self.buffer.extend(data);
+        accessor["bufferView"] = json!(self.buffer_views.len() - 1);
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn material(&mut self, name: &'a str) -> usize {
+        match self.materials.iter().position(|material| *material == name) {
+            Some(material) => material,
+            None => {
+                self.materials.push(name);
+                self.materials.len() - 1
+            }
+        }
+    }
+
+    /// Adds a node per bone of `skeleton` and a skin binding them.
+    fn add_skeleton(&mut self, skeleton: &'a Skeleton) -> io::Result<()> {
+        let first = self.nodes.len();
+        for (index, bone) in skeleton.bones.iter().enumerate() {
+            if bone.parent.is_some_and(|parent| parent >= index) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Bone parent does not precede the bone.",
+                ));
+            }
+            self.nodes.push(json!({
+                "name": bone.name,
+                "translation": bone.translation,
+                "rotation": bone.rotation,
+                "scale": bone.scale,
+            }));
+            match bone.parent {
+                Some(parent) => {
+                    let children = self.nodes[first + parent]
+                        .as_object_mut()
+                        .map(|node| node.entry("children").or_insert_with(|| json!([])));
+                    if let Some(Value::Array(children)) = children {
+                        children.push(json!(first + index));
+                    }
+                }
+                None => self.roots.push(first + index),
+            }
+        }
+        let matrices = skeleton.inverse_bind_matrices();
+        // Inverse bind matrices are not vertex data, so their view has no target.
+        let inverse_bind_matrices = self.push_view(
+            float_bytes(matrices.iter().flatten()),
+            None,
+            json!({"componentType": GL_FLOAT, "count": matrices.len(), "type": "MAT4"}),
+        );
+        self.skins.push(json!({
+            "joints": (first..self.nodes.len()).collect::<Vec<_>>(),
+            "inverseBindMatrices": inverse_bind_matrices,
+        }));
+        Ok(())
+    }
+
+    /// Adds `mesh` with a node instancing it, one primitive per group.
+    /// Meshes with joints are bound to the skin, when there is one.
+    fn add_mesh(&mut self, mesh: &'a Mesh) -> io::Result<()> {
+        mesh.validate()?;
+        let (min, max) = bounds(&mesh.positions);
+        let mut attributes = serde_json::Map::new();
+        let position = self.push_view(
+            float_bytes(mesh.positions.iter().flatten()),
+            Some(GL_ARRAY_BUFFER),
+            json!({
+                "componentType": GL_FLOAT,
+                "count": mesh.positions.len(),
+                "type": "VEC3",
+                "min": min,
+                "max": max,
+            }),
+        );
+        attributes.insert("POSITION".to_string(), json!(position));
+        if !mesh.normals.is_empty() {
+            let normal = self.push_view(
+                float_bytes(mesh.normals.iter().flatten()),
+                Some(GL_ARRAY_BUFFER),
+                json!({"componentType": GL_FLOAT, "count": mesh.normals.len(), "type": "VEC3"}),
+            );
+            attributes.insert("NORMAL".to_string(), json!(normal));
+        }
+        if !mesh.uvs.is_empty() {
+            let uv = self.push_view(
+                float_bytes(mesh.uvs.iter().flatten()),
+                Some(GL_ARRAY_BUFFER),
+                json!({"componentType": GL_FLOAT, "count": mesh.uvs.len(), "type": "VEC2"}),
+            );
+            attributes.insert("TEXCOORD_0".to_string(), json!(uv));
+        }
+        let skinned = !mesh.joints.is_empty() && !self.skins.is_empty();
+        if skinned {
+            let joints = self.push_view(
+                mesh.joints
+                    .iter()
+                    .flatten()
+                    .flat_map(|joint| joint.to_le_bytes())
+                    .collect(),
+                Some(GL_ARRAY_BUFFER),
+                json!({"componentType": GL_UNSIGNED_SHORT, "count": mesh.joints.len(), "type": "VEC4"}),
+            );
+            attributes.insert("JOINTS_0".to_string(), json!(joints));
+            let weights = self.push_view(
+                float_bytes(mesh.weights.iter().flatten()),
+                Some(GL_ARRAY_BUFFER),
+                json!({"componentType": GL_FLOAT, "count": mesh.weights.len(), "type": "VEC4"}),
+            );
+            attributes.insert("WEIGHTS_0".to_string(), json!(weights));
+        }
+
+        let mut primitives = Vec::new();
+        for group in mesh.groups.iter().filter(|group| !group.indices.is_empty()) {
+            let indices = self.push_view(
+                group
+                    .indices
+                    .iter()
+                    .flat_map(|index| index.to_le_bytes())
+                    .collect(),
+                Some(GL_ELEMENT_ARRAY_BUFFER),
+                json!({"componentType": GL_UNSIGNED_INT, "count": group.indices.len(), "type": "SCALAR"}),
+            );
+            primitives.push(json!({
+                "attributes": attributes,
+                "indices": indices,
+                "material": self.material(&group.material),
+            }));
+        }
+        let mut node = json!({"mesh": self.meshes.len(), "name": mesh.name});
+        if skinned {
+            node["skin"] = json!(0);
+        }
+        self.roots.push(self.nodes.len());
+        self.nodes.push(node);
+        self.meshes
+            .push(json!({"name": mesh.name, "primitives": primitives}));
+        Ok(())
+    }
+
+    fn write<W: Write>(mut self, mut writer: W) -> io::Result<()> {
+        let mut document = json!({
+            "asset": {"version": "2.0", "generator": "leafadoo"},
+            "scene": 0,
+            "scenes": [{"nodes": self.roots}],
+            "nodes": self.nodes,
+            "meshes": self.meshes,
+            "materials": self.materials.iter().map(|name| json!({"name": name})).collect::<Vec<_>>(),
+            "buffers": [{"byteLength": self.buffer.len()}],
+            "bufferViews": self.buffer_views,
+            "accessors": self.accessors,
+        });
+
+        if !self.skins.is_empty() {
+            document["skins"] = json!(self.skins);
+        }
+        let mut json_chunk = serde_json::to_vec(&document)?;
+        json_chunk.resize(json_chunk.len().next_multiple_of(4), b' ');
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+        let total = 12 + 8 + json_chunk.len() + 8 + self.buffer.len();
+
+        writer.write_all(GLB_MAGIC)?;
+        writer.write_all(&GLB_VERSION.to_le_bytes())?;
+        writer.write_all(&(total as u32).to_le_bytes())?;
+        for (chunk_type, data) in [(GLB_JSON_CHUNK, &json_chunk), (GLB_BIN_CHUNK, &self.buffer)] {
+            writer.write_all(&(data.len() as u32).to_le_bytes())?;
+            writer.write_all(chunk_type)?;
+            writer.write_all(data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `meshes` as one binary glTF 2.0 file, each mesh under its own
+/// node and each group as a primitive. Group materials become named glTF
+/// materials, shared between meshes by name. With a `skeleton`, its bones
+/// become a node hierarchy and meshes with joints are skinned to it.
+pub fn write_glb_meshes<W: Write>(
+    meshes: &[Mesh],
+    skeleton: Option<&Skeleton>,
+    writer: W,
+) -> io::Result<()> {
+    let mut builder = GlbBuilder::default();
+    if let Some(skeleton) = skeleton.filter(|skeleton| !skeleton.bones.is_empty()) {
+        builder.add_skeleton(skeleton)?;
+    }
+    for mesh in meshes {
+        builder.add_mesh(mesh)?;
+    }
+    builder.write(writer)
+}
+
+pub fn save_glb_meshes<P: AsRef<Path>>(
+    meshes: &[Mesh],
+    skeleton: Option<&Skeleton>,
+    file_path: P,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    write_glb_meshes(meshes, skeleton, &mut writer)?;
+    writer.flush()
+}
+
+/// File formats meshes are saved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeshFormat {
+    Glb,
+    Obj, //< With a `.mtl` file of the same name beside it.
+    Ply,
+}
+
+impl MeshFormat {
+    /// Picks the format from the extension of `file_path`.
+    pub fn from_path(file_path: &Path) -> Option<Self> {
+        match file_path.extension()?.to_str()?.to_lowercase().as_str() {
+            "glb" => Some(MeshFormat::Glb),
+            "obj" => Some(MeshFormat::Obj),
+            "ply" => Some(MeshFormat::Ply),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            MeshFormat::Glb => "glb",
+            MeshFormat::Obj => "obj",
+            MeshFormat::Ply => "ply",
+        }
+    }
+}
+
+/// Unique group materials of `meshes`, in first use order.
+fn material_names(meshes: &[Mesh]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for group in meshes.iter().flat_map(|mesh| &mesh.groups) {
+        if !names.contains(&group.material.as_str()) {
+            names.push(&group.material);
+        }
+    }
+    names
+}
+
+/// Writes `meshes` as Wavefront OBJ text, one object per mesh and a
+/// `usemtl` per group. `mtl_file_name` is referenced with `mtllib`. UVs
+/// are flipped to the bottom-left origin OBJ uses. Skinning is dropped.
+pub fn write_obj_meshes<W: Write>(
+    meshes: &[Mesh],
+    mtl_file_name: Option<&str>,
+    mut writer: W,
+) -> io::Result<()> {
+    writeln!(writer, "# leafadoo")?;
+    if let Some(mtl_file_name) = mtl_file_name {
+        writeln!(writer, "mtllib {}", mtl_file_name)?;
+    }
+    // OBJ indices count from 1 across the whole file.
+    let mut first_vertex = 1;
+    for (number, mesh) in meshes.iter().enumerate() {
+        mesh.validate()?;
+        if mesh.name.is_empty() {
+            writeln!(writer, "o mesh_{}", number)?;
+        } else {
+            writeln!(writer, "o {}", mesh.name)?;
+        }
+        for [x, y, z] in &mesh.positions {
+            writeln!(writer, "v {} {} {}", x, y, z)?;
+        }
+        for [u, v] in &mesh.uvs {
+            writeln!(writer, "vt {} {}", u, 1.0 - v)?;
+        }
+        for [x, y, z] in &mesh.normals {
+            writeln!(writer, "vn {} {} {}", x, y, z)?;
+        }
+        let corner = |index: u32| {
+            let index = index as usize + first_vertex;
+            match (mesh.uvs.is_empty(), mesh.normals.is_empty()) {
+                (true, true) => format!("{}", index),
+                (false, true) => format!("{0}/{0}", index),
+                (true, false) => format!("{0}//{0}", index),
+                (false, false) => format!("{0}/{0}/{0}", index),
+            }
+        };
+        for group in &mesh.groups {
+            writeln!(writer, "usemtl {}", group.material)?;
+            for triangle in group.indices.chunks_exact(3) {
+                writeln!(
+                    writer,
+                    "f {} {} {}",
+                    corner(triangle[0]),
+                    corner(triangle[1]),
+                    corner(triangle[2])
+                )?;
+            }
+        }
+        first_vertex += mesh.positions.len();
+    }
+    Ok(())
+}
+
+/// Writes a material library naming every group material of `meshes`,
+/// each a plain grey surface for the textures to be bound to.
+pub fn write_mtl<W: Write>(meshes: &[Mesh], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "# leafadoo")?;
+    for name in material_names(meshes) {
+        writeln!(writer, "newmtl {}", name)?;
+        writeln!(writer, "Kd 0.8 0.8 0.8")?;
+        writeln!(writer, "illum 1")?;
+    }
+    Ok(())
+}
+
+/// Writes `meshes` to `file_path` as OBJ, with the material library next
+/// to it under the same name with a `.mtl` extension.
+pub fn save_obj_meshes<P: AsRef<Path>>(meshes: &[Mesh], file_path: P) -> io::Result<()> {
+    let mtl_path = file_path.as_ref().with_extension("mtl");
+    let mtl_file_name = mtl_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    let mut writer = BufWriter::new(File::create(&file_path)?);
+    write_obj_meshes(meshes, mtl_file_name.as_deref(), &mut writer)?;
+    writer.flush()?;
+    let mut writer = BufWriter::new(File::create(mtl_path)?);
+    write_mtl(meshes, &mut writer)?;
+    writer.flush()
+}
+
+/// Writes `meshes` as one binary little-endian PLY file. Vertices carry
+/// normals and UVs when every mesh has them; materials and skinning are
+/// dropped.
+pub fn write_ply_meshes<W: Write>(meshes: &[Mesh], mut writer: W) -> io::Result<()> {
+    for mesh in meshes {
+        mesh.validate()?;
+    }
+    let has_normals = meshes.iter().all(|mesh| !mesh.normals.is_empty());
+    let has_uvs = meshes.iter().all(|mesh| !mesh.uvs.is_empty());
+    let vertex_count: usize = meshes.iter().map(|mesh| mesh.positions.len()).sum();
+    let face_count: usize = meshes.iter().map(Mesh::triangle_count).sum();
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format binary_little_endian 1.0")?;
+    writeln!(writer, "comment leafadoo")?;
+    writeln!(writer, "element vertex {}", vertex_count)?;
+    for axis in ["x", "y", "z"] {
+        writeln!(writer, "property float {}", axis)?;
+    }
+    if has_normals {
+        for axis in ["nx", "ny", "nz"] {
+            writeln!(writer, "property float {}", axis)?;
+        }
+    }
+    if has_uvs {
+        for axis in ["s", "t"] {
+            writeln!(writer, "property float {}", axis)?;
+        }
+    }
+    writeln!(writer, "element face {}", face_count)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for mesh in meshes {
+        for (index, position) in mesh.positions.iter().enumerate() {
+            let mut values = position.to_vec();
+            if has_normals {
+                values.extend(mesh.normals[index]);
+            }
+            if has_uvs {
+                values.extend(mesh.uvs[index]);
+            }
+            writer.write_all(&float_bytes(values.iter()))?;
+        }
+    }
+    let mut first_vertex = 0;
+    for mesh in meshes {
+        for triangle in mesh
+            .groups
+            .iter()
+            .flat_map(|group| group.indices.chunks_exact(3))
+        {
+            writer.write_all(&[3])?;
+            for index in triangle {
+                writer.write_all(&(index + first_vertex).to_le_bytes())?;
+            }
+        }
+        first_vertex += mesh.positions.len() as u32;
+    }
+    Ok(())
+}
+
+pub fn save_ply_meshes<P: AsRef<Path>>(meshes: &[Mesh], file_path: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    write_ply_meshes(meshes, &mut writer)?;
+    writer.flush()
+}
+
+/// Saves `meshes` in the format the extension of `file_path` names, see
+/// `MeshFormat`. Only glTF keeps the `skeleton`.
+pub fn save_meshes<P: AsRef<Path>>(
+    meshes: &[Mesh],
+    skeleton: Option<&Skeleton>,
+    file_path: P,
+) -> io::Result<()> {
+    let format = MeshFormat::from_path(file_path.as_ref()).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Cannot tell the mesh format of {}; use .glb, .obj or .ply.",
+                file_path.as_ref().display()
+            ),
+        )
+    })?;
+    match format {
+        MeshFormat::Glb => save_glb_meshes(meshes, skeleton, file_path),
+        MeshFormat::Obj => save_obj_meshes(meshes, file_path),
+        MeshFormat::Ply => save_ply_meshes(meshes, file_path),
+    }
+}
+
+fn float_bytes<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|value| value.to_le_bytes()).collect()
+}
+
+/// Per-axis minimum and maximum of `positions`, zero when there are none.
+fn bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    if positions.is_empty() {
+        return ([0.0; 3], [0.0; 3]);
+    }
+    positions
+        .iter()
+        .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), position| {
+            (
+                [0, 1, 2].map(|axis| min[axis].min(position[axis])),
+                [0, 1, 2].map(|axis| max[axis].max(position[axis])),
+            )
+        })
+}
@@ -0,0 +1,106 @@
+use std::{
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetMftEntry};
+
+/// Running totals of the work done reading an archive. Updated with relaxed
+/// atomics, so counting costs next to nothing and works from any thread.
+#[derive(Default, Debug)]
+pub(crate) struct Counters {
+    bytes_read: AtomicU64,
+    entries_read: AtomicU64,
+    entries_decompressed: AtomicU64,
+    bytes_decompressed: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+/// A snapshot of the counters of an archive, see `AnetArchive::metrics`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveMetrics {
+    pub bytes_read: u64, //< Stored entry bytes read from disk.
+    pub entries_read: u64,
+    pub entries_decompressed: u64,
+    pub bytes_decompressed: u64, //< Size of the decompressed content.
+    pub cache_hits: u64,         //< Entry type lookups answered without reading the entry.
+    pub cache_misses: u64,
+}
+
+impl Counters {
+    pub(crate) fn record_read(&self, entries: u64, bytes: u64) {
+        self.entries_read.fetch_add(entries, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_hits(&self, hits: u64) {
+        self.cache_hits.fetch_add(hits, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `AnetArchive::decompress_entry`, counting the entries it inflates.
+    pub(crate) fn decompress_entry(
+        &self,
+        entry: &AnetMftEntry,
+        data: Vec<u8>,
+    ) -> io::Result<Vec<u8>> {
+        let content = AnetArchive::decompress_entry(entry, data)?;
+        if entry.is_compressed() {
+            self.entries_decompressed.fetch_add(1, Ordering::Relaxed);
+            self.bytes_decompressed
+                .fetch_add(content.len() as u64, Ordering::Relaxed);
+        }
+        Ok(content)
+    }
+
+    fn snapshot(&self) -> ArchiveMetrics {
+        ArchiveMetrics {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            entries_read: self.entries_read.load(Ordering::Relaxed),
+            entries_decompressed: self.entries_decompressed.load(Ordering::Relaxed),
+            bytes_decompressed: self.bytes_decompressed.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        for counter in [
+            &self.bytes_read,
+            &self.entries_read,
+            &self.entries_decompressed,
+            &self.bytes_decompressed,
+            &self.cache_hits,
+            &self.cache_misses,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl ArchiveMetrics {
+    /// Share of entry type lookups served from the cache, `None` before the
+    /// first lookup.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+    }
+}
+
+impl AnetArchive {
+    /// Counters of the entry reads, decompressions and type cache lookups
+    /// done since the archive was loaded or `reset_metrics` was last called.
+    pub fn metrics(&self) -> ArchiveMetrics {
+        self.counters.snapshot()
+    }
+
+    pub fn reset_metrics(&self) {
+        self.counters.reset();
+    }
+}
@@ -0,0 +1,595 @@
+use std::io;
+
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+use crate::material::AnetModelMaterial;
+use crate::mesh::{Bone, Mesh, MeshGroup, Skeleton};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const MODL_CHUNK: &[u8; 4] = b"MODL";
+const GEOM_CHUNK: &[u8; 4] = b"GEOM";
+const SKEL_CHUNK: &[u8; 4] = b"SKEL";
+
+/// Vertex attribute flags of a model mesh, the `ANFVF` bits of the game.
+/// Attributes are stored in bit order, each at a fixed size.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VertexFormat(pub u32);
+
+impl VertexFormat {
+    pub const POSITION: u32 = 0x1; //< 3 f32.
+    pub const WEIGHTS: u32 = 0x2; //< 4 u8 bone weights.
+    pub const GROUP: u32 = 0x4; //< 4 u8 bone indices.
+    pub const NORMAL: u32 = 0x8; //< 3 f32.
+    pub const COLOR: u32 = 0x10;
+    pub const TANGENT: u32 = 0x20;
+    pub const BITANGENT: u32 = 0x40;
+    pub const TANGENT_FRAME: u32 = 0x80;
+    pub const UV32_MASK: u32 = 0xff00; //< One bit per 2 f32 UV set.
+    pub const UV16_MASK: u32 = 0xff0000; //< One bit per 2 f16 UV set.
+    pub const UNKNOWN_1: u32 = 0x1000000;
+    pub const UNKNOWN_2: u32 = 0x2000000;
+    pub const UNKNOWN_3: u32 = 0x4000000;
+    pub const UNKNOWN_4: u32 = 0x8000000;
+    pub const POSITION_COMPRESSED: u32 = 0x10000000; //< 3 f16.
+    pub const UNKNOWN_5: u32 = 0x20000000;
+
+    // Size in bytes of each single-bit attribute, in storage order.
+    const ATTRIBUTE_SIZES: [(u32, usize); 14] = [
+        (Self::POSITION, 12),
+        (Self::WEIGHTS, 4),
+        (Self::GROUP, 4),
+        (Self::NORMAL, 12),
+        (Self::COLOR, 4),
+        (Self::TANGENT, 12),
+        (Self::BITANGENT, 12),
+        (Self::TANGENT_FRAME, 12),
+        (Self::UNKNOWN_1, 48),
+        (Self::UNKNOWN_2, 4),
+        (Self::UNKNOWN_3, 4),
+        (Self::UNKNOWN_4, 16),
+        (Self::POSITION_COMPRESSED, 6),
+        (Self::UNKNOWN_5, 12),
+    ];
+
+    pub fn has(self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    pub fn uv32_count(self) -> usize {
+        (self.0 & Self::UV32_MASK).count_ones() as usize
+    }
+
+    pub fn uv16_count(self) -> usize {
+        (self.0 & Self::UV16_MASK).count_ones() as usize
+    }
+
+    /// Offset of `flag` in a vertex, counting every attribute stored before
+    /// it.
+    pub fn offset_of(self, flag: u32) -> usize {
+        let mut offset = 0;
+        for (attribute, size) in Self::ATTRIBUTE_SIZES {
+            if attribute == flag {
+                return offset;
+            }
+            if self.has(attribute) {
+                offset += size;
+            }
+            // UV sets are stored after the tangent frame.
+            if attribute == Self::TANGENT_FRAME {
+                if flag == Self::UV32_MASK {
+                    return offset;
+                }
+                offset += self.uv32_count() * 8;
+                if flag == Self::UV16_MASK {
+                    return offset;
+                }
+                offset += self.uv16_count() * 4;
+            }
+        }
+        offset
+    }
+
+    /// Size in bytes of one vertex.
+    pub fn stride(self) -> usize {
+        self.offset_of(0)
+    }
+}
+
+/// Converts an IEEE half-precision float to `f32`.
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f32::from(bits & 0x3ff);
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Converts a position or direction from the game's z-up axes to the y-up
+/// axes of glTF.
+fn to_y_up([x, y, z]: [f32; 3]) -> [f32; 3] {
+    [x, z, -y]
+}
+
+/// One submesh of a model: its vertices, triangles and levels of detail.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetModelMesh {
+    pub name: String,
+    pub material_index: u32, //< Index into the materials of each permutation.
+    pub flags: u32,
+    pub vertex_format: VertexFormat,
+    pub vertex_count: u32,
+    #[serde(skip)]
+    pub vertices: Vec<u8>, //< `vertex_count` vertices laid out by `vertex_format`.
+    pub indices: Vec<u16>,       //< Triangles of the most detailed level.
+    pub lods: Vec<Vec<u16>>,     //< Triangles of each coarser level.
+    pub bone_bindings: Vec<u32>, //< Skeleton bone of each vertex group index.
+}
+
+/// A bone of the model skeleton with its bind pose relative to its parent,
+/// in the game's z-up axes.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetModelBone {
+    pub name: String,
+    pub parent: i32, //< Index of the parent bone, -1 for roots.
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4], //< Quaternion, x, y, z, w.
+    pub scale: [f32; 3],
+}
+
+/// Skeleton bones moving each vertex of a mesh and their weights.
+#[derive(Default, Debug, Clone)]
+pub struct VertexSkinning {
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[f32; 4]>, //< Normalized to sum to 1.
+}
+
+/// A set of materials for every submesh, e.g. one per dye channel or
+/// armor weight.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetModelPermutation {
+    pub token: u64,
+    pub materials: Vec<AnetModelMaterial>,
+}
+
+/// A parsed `MODL` file.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetModel {
+    pub permutations: Vec<AnetModelPermutation>,
+    pub meshes: Vec<AnetModelMesh>,
+    pub bones: Vec<AnetModelBone>,
+}
+
+/// How `AnetModel::to_meshes` turns a model into meshes.
+#[derive(Default, Debug, Clone)]
+pub struct ModelExportOptions {
+    pub lod: usize, //< 0 for the most detailed level; meshes with fewer levels use their coarsest.
+    pub merge_by_material: bool, //< One mesh with a group per material instead of one per submesh.
+    pub permutation: Option<usize>, //< Names materials after the files of this permutation.
+}
+
+impl AnetModelMesh {
+    fn read(reader: &mut PfReader) -> io::Result<Self> {
+        let data = reader.data();
+        let mut mesh = AnetModelMesh {
+            name: reader.field("name").read_string()?,
+            material_index: reader.field("material_index").read_u32()?,
+            flags: reader.field("flags").read_u32()?,
+            vertex_format: VertexFormat(reader.field("vertex_format").read_u32()?),
+            vertex_count: reader.field("vertex_count").read_u32()?,
+            vertices: reader.field("vertices").read_byte_array()?,
+            indices: reader.field("indices").read_u16_array()?,
+            ..Default::default()
+        };
+        let (count, position) = reader.field("lods").read_array()?;
+        let mut lod_reader = PfReader::at(data, position);
+        for _ in 0..count {
+            mesh.lods.push(lod_reader.field("lod").read_u16_array()?);
+        }
+        mesh.bone_bindings = reader.field("bone_bindings").read_u32_array()?;
+
+        let stride = mesh.vertex_format.stride();
+        let vertices_fit = (mesh.vertex_count as usize)
+            .checked_mul(stride)
+            .is_some_and(|size| size <= mesh.vertices.len());
+        let indices_fit = [&mesh.indices]
+            .into_iter()
+            .chain(&mesh.lods)
+            .all(|indices| {
+                indices.len().is_multiple_of(3)
+                    && indices
+                        .iter()
+                        .all(|index| u32::from(*index) < mesh.vertex_count)
+            });
+        if !vertices_fit || !indices_fit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Model mesh indices do not form triangles over its vertices.",
+            ));
+        }
+        Ok(mesh)
+    }
+
+    /// Number of levels of detail, counting the most detailed one.
+    pub fn lod_count(&self) -> usize {
+        1 + self.lods.len()
+    }
+
+    /// Triangles of level `lod`, or of the coarsest level when the mesh has
+    /// fewer.
+    pub fn lod_indices(&self, lod: usize) -> &[u16] {
+        match lod.min(self.lods.len()) {
+            0 => &self.indices,
+            lod => &self.lods[lod - 1],
+        }
+    }
+
+    fn vertex(&self, index: usize) -> &[u8] {
+        let stride = self.vertex_format.stride();
+        &self.vertices[index * stride..(index + 1) * stride]
+    }
+
+    /// Vertex positions, y-up. Compressed positions are widened.
+    pub fn positions(&self) -> Vec<[f32; 3]> {
+        let format = self.vertex_format;
+        (0..self.vertex_count as usize)
+            .map(|index| {
+                let vertex = self.vertex(index);
+                let position = if format.has(VertexFormat::POSITION) {
+                    let offset = format.offset_of(VertexFormat::POSITION);
+                    [0, 1, 2].map(|axis| LittleEndian::read_f32(&vertex[offset + axis * 4..]))
+                } else if format.has(VertexFormat::POSITION_COMPRESSED) {
+                    let offset = format.offset_of(VertexFormat::POSITION_COMPRESSED);
+                    [0, 1, 2].map(|axis| {
+                        half_to_f32(LittleEndian::read_u16(&vertex[offset + axis * 2..]))
+                    })
+                } else {
+                    [0.0; 3]
+                };
+                to_y_up(position)
+            })
+            .collect()
+    }
+
+    /// Vertex normals, y-up, or empty when the format has none.
+    pub fn normals(&self) -> Vec<[f32; 3]> {
+        let format = self.vertex_format;
+        if !format.has(VertexFormat::NORMAL) {
+            return Vec::new();
+        }
+        let offset = format.offset_of(VertexFormat::NORMAL);
+        (0..self.vertex_count as usize)
+            .map(|index| {
+                let vertex = self.vertex(index);
+                to_y_up([0, 1, 2].map(|axis| LittleEndian::read_f32(&vertex[offset + axis * 4..])))
+            })
+            .collect()
+    }
+
+    fn read_bytes4(&self, flag: u32) -> Vec<[u8; 4]> {
+        if !self.vertex_format.has(flag) {
+            return Vec::new();
+        }
+        let offset = self.vertex_format.offset_of(flag);
+        (0..self.vertex_count as usize)
+            .map(|index| {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(&self.vertex(index)[offset..offset + 4]);
+                bytes
+            })
+            .collect()
+    }
+
+    /// Bone weights of each vertex out of 255, or empty when the format has
+    /// none.
+    pub fn weights(&self) -> Vec<[u8; 4]> {
+        self.read_bytes4(VertexFormat::WEIGHTS)
+    }
+
+    /// Vertex group indices of each vertex, or empty when the format has
+    /// none. `bone_bindings` maps them to skeleton bones.
+    pub fn groups(&self) -> Vec<[u8; 4]> {
+        self.read_bytes4(VertexFormat::GROUP)
+    }
+
+    /// Skeleton bones and normalized weights of each vertex, or empty when
+    /// the format has no vertex groups. Without bone bindings, group
+    /// indices are bone indices. Vertices without weights follow their
+    /// first bone alone.
+    pub fn skinning(&self) -> io::Result<VertexSkinning> {
+        let groups = self.groups();
+        let weights = self.weights();
+        let mut skinning = VertexSkinning::default();
+        for (index, group) in groups.iter().enumerate() {
+            let bones = group.map(|group| {
+                if self.bone_bindings.is_empty() {
+                    Some(u32::from(group))
+                } else {
+                    self.bone_bindings.get(usize::from(group)).copied()
+                }
+            });
+            let [Some(a), Some(b), Some(c), Some(d)] = bones else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Vertex group outside of the mesh bone bindings.",
+                ));
+            };
+            skinning
+                .joints
+                .push([a, b, c, d].map(|bone| bone.min(u32::from(u16::MAX)) as u16));
+            let weight = weights.get(index).copied().unwrap_or([255, 0, 0, 0]);
+            let total: u32 = weight.iter().map(|value| u32::from(*value)).sum();
+            skinning.weights.push(match total {
+                0 => [1.0, 0.0, 0.0, 0.0],
+                total => weight.map(|value| f32::from(value) / total as f32),
+            });
+        }
+        Ok(skinning)
+    }
+
+    /// The first UV set, full or half precision, or empty when the format
+    /// has none.
+    pub fn uvs(&self) -> Vec<[f32; 2]> {
+        let format = self.vertex_format;
+        let (offset, half) = if format.uv32_count() > 0 {
+            (format.offset_of(VertexFormat::UV32_MASK), false)
+        } else if format.uv16_count() > 0 {
+            (format.offset_of(VertexFormat::UV16_MASK), true)
+        } else {
+            return Vec::new();
+        };
+        (0..self.vertex_count as usize)
+            .map(|index| {
+                let vertex = &self.vertex(index)[offset..];
+                if half {
+                    [0, 1].map(|axis| half_to_f32(LittleEndian::read_u16(&vertex[axis * 2..])))
+                } else {
+                    [0, 1].map(|axis| LittleEndian::read_f32(&vertex[axis * 4..]))
+                }
+            })
+            .collect()
+    }
+}
+
+impl AnetModel {
+    /// Parses the `MODL` and `GEOM` chunks of a decompressed PF `MODL` entry.
+    /// Either chunk may be missing.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let mut model = AnetModel::default();
+
+        if let Some(chunk) = pf_file.chunk(MODL_CHUNK) {
+            let _scope = trace::scope("MODL");
+            let mut reader = PfReader::new(&chunk.data);
+            let (count, position) = reader.field("permutations").read_array()?;
+            let mut permutation_reader = PfReader::at(&chunk.data, position);
+            for _ in 0..count {
+                let mut permutation = AnetModelPermutation {
+                    token: permutation_reader.field("token").read_u64()?,
+                    ..Default::default()
+                };
+                let (count, position) = permutation_reader.field("materials").read_array()?;
+                let mut material_reader = PfReader::at(&chunk.data, position);
+                for _ in 0..count {
+                    permutation
+                        .materials
+                        .push(AnetModelMaterial::read(&mut material_reader)?);
+                }
+                model.permutations.push(permutation);
+            }
+        }
+
+        if let Some(chunk) = pf_file.chunk(GEOM_CHUNK) {
+            let _scope = trace::scope("GEOM");
+            let mut reader = PfReader::new(&chunk.data);
+            let (count, position) = reader.field("meshes").read_array()?;
+            let mut mesh_reader = PfReader::at(&chunk.data, position);
+            for _ in 0..count {
+                model.meshes.push(AnetModelMesh::read(&mut mesh_reader)?);
+            }
+        }
+
+        if let Some(chunk) = pf_file.chunk(SKEL_CHUNK) {
+            let _scope = trace::scope("SKEL");
+            let mut reader = PfReader::new(&chunk.data);
+            let (count, position) = reader.field("bones").read_array()?;
+            let mut bone_reader = PfReader::at(&chunk.data, position);
+            for index in 0..count {
+                let bone = AnetModelBone {
+                    name: bone_reader.field("name").read_string()?,
+                    parent: bone_reader.field("parent").read_i32()?,
+                    translation: [
+                        bone_reader.field("translation").read_f32()?,
+                        bone_reader.read_f32()?,
+                        bone_reader.read_f32()?,
+                    ],
+                    rotation: [
+                        bone_reader.field("rotation").read_f32()?,
+                        bone_reader.read_f32()?,
+                        bone_reader.read_f32()?,
+                        bone_reader.read_f32()?,
+                    ],
+                    scale: [
+                        bone_reader.field("scale").read_f32()?,
+                        bone_reader.read_f32()?,
+                        bone_reader.read_f32()?,
+                    ],
+                };
+                if bone.parent < -1 || i64::from(bone.parent) >= index as i64 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Bone parent does not precede the bone.",
+                    ));
+                }
+                model.bones.push(bone);
+            }
+        }
+        Ok(model)
+    }
+
+    /// The skeleton in y-up axes, or `None` for static models.
+    pub fn skeleton(&self) -> Option<Skeleton> {
+        if self.bones.is_empty() {
+            return None;
+        }
+        let bones = self
+            .bones
+            .iter()
+            .map(|bone| {
+                let [x, y, z, w] = bone.rotation;
+                let [scale_x, scale_y, scale_z] = bone.scale;
+                Bone {
+                    name: bone.name.clone(),
+                    parent: usize::try_from(bone.parent).ok(),
+                    translation: to_y_up(bone.translation),
+                    rotation: [x, z, -y, w],
+                    scale: [scale_x, scale_z, scale_y],
+                }
+            })
+            .collect();
+        Some(Skeleton { bones })
+    }
+
+    /// Number of levels of detail of the most detailed submesh.
+    pub fn lod_count(&self) -> usize {
+        self.meshes
+            .iter()
+            .map(AnetModelMesh::lod_count)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Name of the group drawn with material `index`: the material file ID
+    /// in `permutation`, when chosen and known, else the index.
+    fn material_name(&self, index: u32, permutation: Option<usize>) -> String {
+        let file_id = permutation
+            .and_then(|permutation| self.permutations.get(permutation))
+            .and_then(|permutation| permutation.materials.get(index as usize))
+            .and_then(AnetModelMaterial::material_file_id);
+        match file_id {
+            Some(file_id) => format!("material_{}_{}", index, file_id),
+            None => format!("material_{}", index),
+        }
+    }
+
+    /// Converts the submeshes at level `options.lod` to meshes, keeping only
+    /// the vertices that level uses. With `merge_by_material` the result is
+    /// a single mesh with one group per material; otherwise there is one
+    /// mesh per submesh.
+    pub fn to_meshes(&self, options: &ModelExportOptions) -> io::Result<Vec<Mesh>> {
+        if let Some(permutation) = options.permutation {
+            if permutation >= self.permutations.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Model has {} permutations, not {}.",
+                        self.permutations.len(),
+                        permutation + 1
+                    ),
+                ));
+            }
+        }
+        let mut meshes: Vec<Mesh> = Vec::new();
+        for (number, model_mesh) in self.meshes.iter().enumerate() {
+            let positions = model_mesh.positions();
+            let normals = model_mesh.normals();
+            let uvs = model_mesh.uvs();
+            let skinning = if self.bones.is_empty() {
+                VertexSkinning::default()
+            } else {
+                model_mesh.skinning()?
+            };
+            if skinning
+                .joints
+                .iter()
+                .flatten()
+                .any(|bone| usize::from(*bone) >= self.bones.len())
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Vertex bound to a bone the skeleton does not have.",
+                ));
+            }
+            let material = self.material_name(model_mesh.material_index, options.permutation);
+
+            let mesh_index = if options.merge_by_material {
+                if meshes.is_empty() {
+                    meshes.push(Mesh {
+                        name: "model".to_string(),
+                        ..Default::default()
+                    });
+                }
+                0
+            } else {
+                let name = if model_mesh.name.is_empty() {
+                    format!("mesh_{}", number)
+                } else {
+                    model_mesh.name.clone()
+                };
+                meshes.push(Mesh {
+                    name,
+                    ..Default::default()
+                });
+                meshes.len() - 1
+            };
+            let mesh = &mut meshes[mesh_index];
+            let group = match mesh
+                .groups
+                .iter()
+                .position(|group| group.material == material)
+            {
+                Some(group) => group,
+                None => {
+                    mesh.groups.push(MeshGroup {
+                        material,
+                        indices: Vec::new(),
+                    });
+                    mesh.groups.len() - 1
+                }
+            };
+
+            // Vertices are copied on first use so unused ones are dropped.
+            let mut remap = vec![u32::MAX; positions.len()];
+            for index in model_mesh.lod_indices(options.lod) {
+                let index = usize::from(*index);
+                if remap[index] == u32::MAX {
+                    remap[index] = mesh.positions.len() as u32;
+                    mesh.positions.push(positions[index]);
+                    mesh.normals.extend(normals.get(index));
+                    mesh.uvs.extend(uvs.get(index));
+                    mesh.joints.extend(skinning.joints.get(index));
+                    mesh.weights.extend(skinning.weights.get(index));
+                }
+                mesh.groups[group].indices.push(remap[index]);
+            }
+        }
+
+        // A merged mesh only keeps attributes every submesh has.
+        for mesh in &mut meshes {
+            if mesh.normals.len() != mesh.positions.len() {
+                mesh.compute_normals();
+            }
+            if mesh.uvs.len() != mesh.positions.len() {
+                mesh.uvs.clear();
+            }
+            if mesh.joints.len() != mesh.positions.len() {
+                mesh.joints.clear();
+                mesh.weights.clear();
+            }
+        }
+        Ok(meshes)
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the model with the given file ID.
+    pub fn read_model(&self, file_id: u32) -> io::Result<AnetModel> {
+        AnetModel::load_from_bytes(&self.read_file(file_id)?)
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter},
+    mem,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+
+/// One row of a name database given as a JSON array.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedFile {
+    #[serde(alias = "id", alias = "fileId")]
+    pub file_id: u32,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NameDatabaseFile {
+    Map(HashMap<String, String>), //< `{"184776": "map_queensdale_floor_1"}`
+    List(Vec<NamedFile>),
+}
+
+/// Human-readable names for file IDs, as collected by the community.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct EntryNames {
+    pub names: HashMap<u32, String>,
+}
+
+/// Splits a CSV line into its fields, unquoting quoted ones.
+pub(crate) fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// `name` with the characters file systems reject replaced by `_`.
+fn file_name_safe(name: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    safe.trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string()
+}
+
+impl EntryNames {
+    /// Loads a name database. A `.json` file holds either an object mapping
+    /// file IDs to names or an array of `{"file_id", "name"}` objects; any
+    /// other file is CSV with the file ID in the first column and the name
+    /// in the second. A header row and lines starting with `#` are skipped.
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(&file_path)?;
+        let is_json = file_path
+            .as_ref()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        if is_json {
+            return Self::from_json(&text);
+        }
+        Self::from_csv(&text)
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        let mut entry_names = EntryNames::default();
+        match serde_json::from_str(text)? {
+            NameDatabaseFile::Map(names) => {
+                for (file_id, name) in names {
+                    let file_id = file_id.trim().parse().map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid file ID '{}' in name database.", file_id),
+                        )
+                    })?;
+                    entry_names.insert(file_id, name);
+                }
+            }
+            NameDatabaseFile::List(files) => {
+                for file in files {
+                    entry_names.insert(file.file_id, file.name);
+                }
+            }
+        }
+        Ok(entry_names)
+    }
+
+    pub fn from_csv(text: &str) -> io::Result<Self> {
+        let mut entry_names = EntryNames::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = csv_fields(line).into_iter();
+            let file_id = fields.next().unwrap_or_default();
+            let Ok(file_id) = file_id.parse() else {
+                if number == 0 {
+                    continue;
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid file ID '{}' on line {}.", file_id, number + 1),
+                ));
+            };
+            entry_names.insert(file_id, fields.next().unwrap_or_default());
+        }
+        Ok(entry_names)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer_pretty(writer, &self.names)?;
+        Ok(())
+    }
+
+    /// Names `file_id`, replacing any earlier name. Empty names are ignored.
+    pub fn insert(&mut self, file_id: u32, name: String) {
+        if !name.is_empty() {
+            self.names.insert(file_id, name);
+        }
+    }
+
+    pub fn name_of(&self, file_id: u32) -> Option<&str> {
+        self.names.get(&file_id).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl AnetArchive {
+    pub fn entry_names(&self) -> &EntryNames {
+        &self.entry_names
+    }
+
+    /// Attaches `entry_names` to the archive, used by `entry_name`, the
+    /// extraction file names and the name selectors of `EntryFilter`.
+    pub fn set_entry_names(&mut self, entry_names: EntryNames) {
+        self.entry_names = entry_names;
+        self.shared_file_stems.take();
+    }
+
+    /// Name of the entry at `index`, under whichever of its file IDs the
+    /// name database knows, the base ID first.
+    pub fn entry_name(&self, index: usize) -> Option<&str> {
+        self.id_aliases
+            .get(index)?
+            .iter()
+            .find_map(|file_id| self.entry_names.name_of(*file_id))
+    }
+
+    /// `entry_name` made safe to use as a file name.
+    pub fn entry_file_stem(&self, index: usize) -> Option<String> {
+        self.entry_name(index)
+            .map(file_name_safe)
+            .filter(|name| !name.is_empty())
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Seek, SeekFrom, Write},
+};
+
+use crate::anet_archive::{
+    AnetArchive, AnetCompressionFlags, AnetMftEntryFlags, MFT_ENTRY_SIZE, MFT_HEADER_SIZE,
+};
+use crate::deflate::{anet_deflate, crc32};
+
+// Entries 0 to 2 hold the DAT header, the file ID table and the MFT itself.
+const METADATA_ENTRIES: usize = 3;
+
+impl AnetArchive {
+    /// Experimental. Replaces the content of the entry at `index` with
+    /// `data` in the archive file, compressed when `compress` is set.
+    ///
+    /// The data overwrites the old slot when it fits and no other entry in
+    /// use overlaps it, and is appended to the file otherwise. The MFT record
+    /// gets the new location, size, compression flag and CRC-32 and the
+    /// highest counter of the archive, so it reads as the latest write. The
+    /// MFT keeps its place and size, so the DAT header needs no change.
+    pub fn patch_entry(&mut self, index: usize, data: &[u8], compress: bool) -> io::Result<()> {
+        if index < METADATA_ENTRIES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Entry {} holds archive metadata and cannot be patched.",
+                    index
+                ),
+            ));
+        }
+        let mut entry = self.mft_data.get(index).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "MFT entry index out of range.")
+        })?;
+        let stored = if compress {
+            anet_deflate(data)
+        } else {
+            data.to_vec()
+        };
+        let size = u32::try_from(stored.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Entry data is too large for the MFT.",
+            )
+        })?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.file_path)?;
+        // Another entry may start inside the slot rather than at its offset.
+        let slot_end = entry.offset.saturating_add(entry.size as u64);
+        let slot_is_shared = self
+            .mft_data
+            .iter()
+            .enumerate()
+            .any(|(other, other_entry)| {
+                other != index
+                    && other_entry.is_in_use()
+                    && other_entry.offset < slot_end
+                    && entry.offset < other_entry.offset.saturating_add(other_entry.size as u64)
+            });
+        let offset = if size <= entry.size && !slot_is_shared {
+            entry.offset
+        } else {
+            file.seek(SeekFrom::End(0))?
+        };
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&stored)?;
+
+        entry.offset = offset;
+        entry.size = size;
+        entry.compression_flag = if compress {
+            AnetCompressionFlags::COMPRESSED.bits()
+        } else {
+            AnetCompressionFlags::empty().bits()
+        };
+        entry.entry_flag |= AnetMftEntryFlags::IN_USE.bits();
+        entry.counter = self.max_counter() + 1;
+        entry.crc = crc32(&stored);
+
+        file.seek(SeekFrom::Start(
+            self.dat_header.mft_offset + (MFT_HEADER_SIZE + index * MFT_ENTRY_SIZE) as u64,
+        ))?;
+        entry.write_to(&mut file)?;
+        file.flush()?;
+
+        self.mft_data[index] = entry;
+        self.dependency_graph.take();
+        self.type_scan.take();
+        self.entry_kinds.get_mut().remove(&index);
+        Ok(())
+    }
+}
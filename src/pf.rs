@@ -0,0 +1,367 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetFileReference, AnetPfChunkHeader, AnetPfHeader};
+use crate::trace;
+
+const PF_MAGIC_NUMBER: usize = 2;
+const PF_FOURCC_SIZE: usize = 4;
+const PF_HEADER_SIZE: u64 = 12;
+const PF_CHUNK_HEADER_SIZE: u64 = 16;
+// Chunk size field counts the bytes following the type and size fields.
+const PF_CHUNK_SIZE_FIELDS: u64 = 8;
+// Number of bytes of a raw byte read kept in the read trace.
+const TRACE_BYTES_PREVIEW: usize = 16;
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetPfChunk {
+    pub header: AnetPfChunkHeader,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetPfFile {
+    pub header: AnetPfHeader,
+    pub chunks: Vec<AnetPfChunk>,
+}
+
+/// Parses a PF file held in memory.
+pub fn parse_pf(data: &[u8]) -> io::Result<AnetPfFile> {
+    AnetPfFile::load_from_bytes(data)
+}
+
+impl AnetPfFile {
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let mut cursor = Cursor::new(data);
+        Self::load_from_reader(&mut cursor, data.len() as u64)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(reader))
+    )]
+    fn load_from_reader<R: Read + Seek>(reader: &mut R, length: u64) -> io::Result<Self> {
+        let _scope = trace::scope("PF");
+        let mut pf_file = AnetPfFile::default();
+        pf_file.read_header(reader)?;
+        pf_file.read_chunks(reader, length)?;
+        Ok(pf_file)
+    }
+
+    fn read_header<R: Read + Seek>(&mut self, file: &mut R) -> io::Result<&mut Self> {
+        let mut magic = [0; PF_MAGIC_NUMBER];
+        file.read_exact(&mut magic)?;
+        if &magic != b"PF" {
+            log_warn!(magic = ?magic, "Not a PF file.");
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a PF file: invalid header magic.",
+            ));
+        }
+        self.header.identifier = magic;
+        self.header.unknown_field = file.read_u16::<LittleEndian>()?;
+        self.header.unknown_field_2 = file.read_u16::<LittleEndian>()?;
+        self.header.header_size = file.read_u16::<LittleEndian>()?;
+        self.header.file_type_integer = file.read_u32::<LittleEndian>()?;
+        self.header.file_type = self.header.file_type_integer.to_le_bytes();
+        trace::record(0, PF_HEADER_SIZE as usize, "header", || {
+            format!("{:?}", self.header)
+        });
+        log_debug!(
+            file_type = %String::from_utf8_lossy(&self.header.file_type),
+            "Read PF header."
+        );
+        Ok(self)
+    }
+
+    fn read_chunks<R: Read + Seek>(&mut self, file: &mut R, length: u64) -> io::Result<&mut Self> {
+        let mut chunk_offset = u64::from(self.header.header_size).max(PF_HEADER_SIZE);
+        while chunk_offset + PF_CHUNK_HEADER_SIZE <= length {
+            file.seek(SeekFrom::Start(chunk_offset))?;
+            let mut header = AnetPfChunkHeader {
+                chunk_type_integer: file.read_u32::<LittleEndian>()?,
+                chunk_data_size: file.read_u32::<LittleEndian>()?,
+                chunk_version: file.read_u16::<LittleEndian>()?,
+                chunk_header_size: file.read_u16::<LittleEndian>()?,
+                offset_table_offset: file.read_u32::<LittleEndian>()?,
+                ..Default::default()
+            };
+            header.chunk_type = header.chunk_type_integer.to_le_bytes();
+            trace::record(
+                chunk_offset as usize,
+                PF_CHUNK_HEADER_SIZE as usize,
+                "chunk_header",
+                || format!("{:?}", header),
+            );
+
+            let chunk_end = chunk_offset + PF_CHUNK_SIZE_FIELDS + u64::from(header.chunk_data_size);
+            let data_start =
+                chunk_offset + u64::from(header.chunk_header_size).max(PF_CHUNK_HEADER_SIZE);
+            if chunk_end > length || data_start > chunk_end {
+                log_warn!(offset = chunk_offset, length, "PF chunk is truncated.");
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "PF chunk extends past the end of the file.",
+                ));
+            }
+
+            file.seek(SeekFrom::Start(data_start))?;
+            let mut data = vec![0; (chunk_end - data_start) as usize];
+            file.read_exact(&mut data)?;
+            log_trace!(
+                chunk = %String::from_utf8_lossy(&header.chunk_type),
+                offset = chunk_offset,
+                size = data.len(),
+                "Read PF chunk."
+            );
+            self.chunks.push(AnetPfChunk { header, data });
+            chunk_offset = chunk_end;
+        }
+        Ok(self)
+    }
+
+    pub fn file_type(&self) -> &[u8; PF_FOURCC_SIZE] {
+        &self.header.file_type
+    }
+
+    pub fn chunk(&self, chunk_type: &[u8; PF_FOURCC_SIZE]) -> Option<&AnetPfChunk> {
+        self.chunks
+            .iter()
+            .find(|chunk| &chunk.header.chunk_type == chunk_type)
+    }
+}
+
+/// Reads chunk data, following the self-relative offsets PF files use for
+/// arrays, pointers, strings and file references.
+pub struct PfReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    field: Option<&'static str>,
+}
+
+impl<'a> PfReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        PfReader {
+            data,
+            position: 0,
+            field: None,
+        }
+    }
+
+    pub fn at(data: &'a [u8], position: usize) -> Self {
+        PfReader {
+            data,
+            position,
+            field: None,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    pub fn skip(&mut self, length: usize) -> io::Result<()> {
+        self.read_bytes(length).map(|_| ())
+    }
+
+    /// Labels the next read in the read trace. Unlabelled reads are traced
+    /// under the name of the value type.
+    pub fn field(&mut self, name: &'static str) -> &mut Self {
+        self.field = Some(name);
+        self
+    }
+
+    fn trace<T: std::fmt::Debug>(&mut self, offset: usize, size: usize, kind: &str, value: &T) {
+        let field = self.field.take().unwrap_or(kind);
+        trace::record(offset, size, field, || format!("{:?}", value));
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let offset = self.position;
+        let bytes = self.take(length)?;
+        if trace::is_enabled() {
+            let preview = &bytes[..bytes.len().min(TRACE_BYTES_PREVIEW)];
+            self.trace(offset, length, "bytes", &preview);
+        }
+        Ok(bytes)
+    }
+
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "PF read past the end of the chunk.",
+                )
+            })?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        let offset = self.position;
+        let value = self.take(1)?[0];
+        self.trace(offset, 1, "u8", &value);
+        Ok(value)
+    }
+
+    pub fn read_u16(&mut self) -> io::Result<u16> {
+        let offset = self.position;
+        let value = self.take(2)?.read_u16::<LittleEndian>()?;
+        self.trace(offset, 2, "u16", &value);
+        Ok(value)
+    }
+
+    pub fn read_u32(&mut self) -> io::Result<u32> {
+        let offset = self.position;
+        let value = self.take(4)?.read_u32::<LittleEndian>()?;
+        self.trace(offset, 4, "u32", &value);
+        Ok(value)
+    }
+
+    pub fn read_i32(&mut self) -> io::Result<i32> {
+        let offset = self.position;
+        let value = self.take(4)?.read_i32::<LittleEndian>()?;
+        self.trace(offset, 4, "i32", &value);
+        Ok(value)
+    }
+
+    pub fn read_u64(&mut self) -> io::Result<u64> {
+        let offset = self.position;
+        let value = self.take(8)?.read_u64::<LittleEndian>()?;
+        self.trace(offset, 8, "u64", &value);
+        Ok(value)
+    }
+
+    pub fn read_f32(&mut self) -> io::Result<f32> {
+        let offset = self.position;
+        let value = self.take(4)?.read_f32::<LittleEndian>()?;
+        self.trace(offset, 4, "f32", &value);
+        Ok(value)
+    }
+
+    /// Reads a relative offset and resolves it to an absolute position in the
+    /// chunk data. Returns `None` for null offsets.
+    pub fn read_pointer(&mut self) -> io::Result<Option<usize>> {
+        let base = self.position;
+        let offset = self.take(4)?.read_i32::<LittleEndian>()?;
+        if offset == 0 {
+            self.trace(base, 4, "pointer", &Option::<usize>::None);
+            return Ok(None);
+        }
+        let target = base as i64 + i64::from(offset);
+        if target < 0 || target as usize > self.data.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "PF offset points outside of the chunk.",
+            ));
+        }
+        self.trace(base, 4, "pointer", &Some(target));
+        Ok(Some(target as usize))
+    }
+
+    /// Reads an array header: element count followed by a relative offset.
+    pub fn read_array(&mut self) -> io::Result<(usize, usize)> {
+        let field = self.field.take();
+        let count = self.field("array_count").read_u32()? as usize;
+        self.field = field;
+        let position = self.read_pointer()?.unwrap_or(self.data.len());
+        Ok((count, position))
+    }
+
+    pub fn read_byte_array(&mut self) -> io::Result<Vec<u8>> {
+        let (count, position) = self.read_array()?;
+        Ok(PfReader::at(self.data, position)
+            .read_bytes(count)?
+            .to_vec())
+    }
+
+    /// Reads an array header and the `size`-byte values it points to.
+    fn read_value_array<T>(
+        &mut self,
+        size: usize,
+        read: fn(&mut PfReader<'a>) -> io::Result<T>,
+    ) -> io::Result<Vec<T>> {
+        let (count, position) = self.read_array()?;
+        let mut item_reader = PfReader::at(self.data, position);
+        let mut values = Vec::with_capacity(count.min(item_reader.remaining() / size));
+        for _ in 0..count {
+            values.push(read(&mut item_reader)?);
+        }
+        Ok(values)
+    }
+
+    pub fn read_u16_array(&mut self) -> io::Result<Vec<u16>> {
+        self.read_value_array(2, Self::read_u16)
+    }
+
+    pub fn read_u32_array(&mut self) -> io::Result<Vec<u32>> {
+        self.read_value_array(4, Self::read_u32)
+    }
+
+    pub fn read_u64_array(&mut self) -> io::Result<Vec<u64>> {
+        self.read_value_array(8, Self::read_u64)
+    }
+
+    pub fn read_f32_array(&mut self) -> io::Result<Vec<f32>> {
+        self.read_value_array(4, Self::read_f32)
+    }
+
+    pub fn read_file_reference(&mut self) -> io::Result<Option<AnetFileReference>> {
+        match self.read_pointer()? {
+            Some(position) => Ok(Some(AnetFileReference {
+                parts: PfReader::at(self.data, position).read_bytes(6)?.to_vec(),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a pointer to a null-terminated UTF-16 string.
+    pub fn read_wstring(&mut self) -> io::Result<String> {
+        let Some(position) = self.read_pointer()? else {
+            return Ok(String::new());
+        };
+        let mut reader = PfReader::at(self.data, position);
+        let mut units = Vec::new();
+        loop {
+            let unit = reader.take(2)?.read_u16::<LittleEndian>()?;
+            if unit == 0 {
+                break;
+            }
+            units.push(unit);
+        }
+        let string = String::from_utf16_lossy(&units);
+        self.trace(position, units.len() * 2 + 2, "wstring", &string);
+        Ok(string)
+    }
+
+    /// Reads a pointer to a null-terminated narrow string.
+    pub fn read_string(&mut self) -> io::Result<String> {
+        let Some(position) = self.read_pointer()? else {
+            return Ok(String::new());
+        };
+        let data = &self.data[position..];
+        let end = data
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(data.len());
+        let string = String::from_utf8_lossy(&data[..end]).into_owned();
+        self.trace(position, end + 1, "string", &string);
+        Ok(string)
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
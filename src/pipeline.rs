@@ -0,0 +1,496 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+use crate::bookmark::BookmarkSet;
+use crate::budget::BudgetPermit;
+use crate::mesh;
+use crate::model::{AnetModel, ModelExportOptions};
+use crate::reader::ArchiveReader;
+use crate::sound::{AnetBankFile, AnetSoundData};
+use crate::texture::{self, RgbaImageData};
+
+// Entries each stage may queue per worker thread before it waits for the
+// next stage to catch up.
+const QUEUE_DEPTH_PER_THREAD: usize = 4;
+
+const TEXTURE_FILE_TYPES: [AnetFileType; 7] = [
+    AnetFileType::AnftAtex,
+    AnetFileType::AnftAttx,
+    AnetFileType::AnftAtec,
+    AnetFileType::AnftAtep,
+    AnetFileType::AnftAteu,
+    AnetFileType::AnftAtet,
+    AnetFileType::AnftCtex,
+];
+
+/// Which entries a pipeline runs on. Empty lists select everything; all the
+/// given criteria must match.
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineSelect {
+    pub file_ids: Vec<u32>,
+    pub bookmarks: Vec<String>,     //< Bookmark set names or paths.
+    pub content_types: Vec<String>, //< PF file types or texture identifiers, e.g. "ATEX".
+    pub min_size: Option<u32>,
+    pub max_size: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineDecode {
+    #[default]
+    Raw, //< Decompressed entry bytes.
+    Texture, //< Top mip level of a texture entry, as RGBA pixels.
+    Convert, //< Files made by the converter for the entry's file type, see `PipelineConverters`.
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PipelineTransform {
+    /// Halves the image `times` times.
+    Downsample { times: u32 },
+    /// Halves the image until neither side exceeds `max_size`.
+    FitWithin { max_size: u32 },
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineFormat {
+    #[default]
+    Bin,
+    Png,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineOutput {
+    pub directory: PathBuf,
+    pub format: PipelineFormat,
+    pub entries_per_shard: Option<usize>, //< Splits the output into numbered subdirectories.
+}
+
+/// A batch job over an archive: select entries, decode them, apply
+/// transforms in order and write the results.
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    pub select: PipelineSelect,
+    pub decode: PipelineDecode,
+    pub transforms: Vec<PipelineTransform>,
+    pub output: PipelineOutput,
+    pub threads: usize, //< Decode threads, 0 for one per available core.
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct PipelineReport {
+    pub written: Vec<PathBuf>,
+    pub skipped: usize,     //< Selected entries that could not be decoded.
+    pub unconverted: usize, //< Entries written as stored because their converter failed.
+}
+
+/// A file made from an entry by a converter, written as
+/// `<entry stem><suffix>.<extension>`.
+#[derive(Debug, Clone)]
+pub struct ConvertedFile {
+    pub suffix: String, //< Tells apart the files made from one entry, usually empty.
+    pub extension: String,
+    pub data: Vec<u8>,
+}
+
+/// Turns the decompressed data of an entry into the files written for it.
+pub type PipelineConverter = Box<dyn Fn(&[u8]) -> io::Result<Vec<ConvertedFile>> + Send + Sync>;
+
+/// Converters used by `PipelineDecode::Convert`, by the file type of the
+/// entries they take. Entries of other types are written as stored.
+#[derive(Default)]
+pub struct PipelineConverters {
+    converters: HashMap<AnetFileType, PipelineConverter>,
+}
+
+/// What the decode stage made of an entry.
+enum PipelineOutcome {
+    Filtered,
+    Skipped,
+    Raw { data: Vec<u8>, unconverted: bool },
+    Png(Vec<u8>),
+    Converted(Vec<ConvertedFile>),
+}
+
+impl ConvertedFile {
+    pub fn new(extension: &str, data: Vec<u8>) -> Self {
+        ConvertedFile {
+            suffix: String::new(),
+            extension: extension.to_string(),
+            data,
+        }
+    }
+}
+
+impl PipelineConverters {
+    /// Textures to PNG, sounds and sound bank clips to playable audio and
+    /// models to binary glTF.
+    pub fn standard() -> Self {
+        let mut converters = PipelineConverters::default();
+        for file_type in TEXTURE_FILE_TYPES {
+            converters.register(file_type, convert_texture);
+        }
+        converters
+            .register(AnetFileType::AnftSound, convert_sound)
+            .register(AnetFileType::AnftBank, convert_bank)
+            .register(AnetFileType::AnftModel, convert_model);
+        converters
+    }
+
+    /// Sets the converter for `file_type`, replacing any previous one.
+    pub fn register<F>(&mut self, file_type: AnetFileType, converter: F) -> &mut Self
+    where
+        F: Fn(&[u8]) -> io::Result<Vec<ConvertedFile>> + Send + Sync + 'static,
+    {
+        self.converters.insert(file_type, Box::new(converter));
+        self
+    }
+
+    /// Drops the converter for `file_type`, so its entries are written as
+    /// stored.
+    pub fn remove(&mut self, file_type: AnetFileType) -> &mut Self {
+        self.converters.remove(&file_type);
+        self
+    }
+
+    pub fn get(&self, file_type: AnetFileType) -> Option<&PipelineConverter> {
+        self.converters.get(&file_type)
+    }
+}
+
+fn convert_texture(data: &[u8]) -> io::Result<Vec<ConvertedFile>> {
+    let mut png = Vec::new();
+    texture::decode_texture_entry(data)?.write_png(&mut png)?;
+    Ok(vec![ConvertedFile::new("png", png)])
+}
+
+fn convert_sound(data: &[u8]) -> io::Result<Vec<ConvertedFile>> {
+    let sound = AnetSoundData::load_from_bytes(data)?;
+    Ok(vec![ConvertedFile::new(
+        sound.extension(),
+        sound.playable_data().into_owned(),
+    )])
+}
+
+fn convert_bank(data: &[u8]) -> io::Result<Vec<ConvertedFile>> {
+    AnetBankFile::load_from_bytes(data)?
+        .audio_files
+        .iter()
+        .map(|audio_file| {
+            Ok(ConvertedFile {
+                suffix: format!("_{}", audio_file.voice_id),
+                extension: audio_file.extension().to_string(),
+                data: audio_file.playable_data()?.into_owned(),
+            })
+        })
+        .collect()
+}
+
+fn convert_model(data: &[u8]) -> io::Result<Vec<ConvertedFile>> {
+    let model = AnetModel::load_from_bytes(data)?;
+    let meshes = model.to_meshes(&ModelExportOptions::default())?;
+    let mut glb = Vec::new();
+    mesh::write_glb_meshes(&meshes, model.skeleton().as_ref(), &mut glb)?;
+    Ok(vec![ConvertedFile::new("glb", glb)])
+}
+
+/// Identifies the content of decompressed entry data: the file type of PF
+/// files, otherwise the leading FourCC.
+pub fn content_fourcc(data: &[u8]) -> Option<[u8; 4]> {
+    let fourcc = if data.starts_with(b"PF") {
+        data.get(8..12)?
+    } else {
+        data.get(..4)?
+    };
+    fourcc.try_into().ok()
+}
+
+impl PipelineConfig {
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(file_path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        let needs_image = !self.transforms.is_empty() || self.output.format == PipelineFormat::Png;
+        if needs_image && self.decode != PipelineDecode::Texture {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Image transforms and PNG output require the texture decoder.",
+            ));
+        }
+        if self.output.entries_per_shard == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "entries_per_shard must be at least 1.",
+            ));
+        }
+        Ok(())
+    }
+
+    /// MFT indices matching the size, file ID and bookmark criteria. Content
+    /// types are checked later, once the entry has been read.
+    fn candidate_indices(&self, archive: &AnetArchive) -> io::Result<Vec<usize>> {
+        let select = &self.select;
+        let mut indices: Vec<usize> = if select.file_ids.is_empty() && select.bookmarks.is_empty() {
+            (0..archive.mft_data.len()).collect()
+        } else {
+            let mut indices = Vec::new();
+            for file_id in &select.file_ids {
+                indices.extend(archive.index_of(*file_id));
+            }
+            for name in &select.bookmarks {
+                indices.extend(BookmarkSet::load(name)?.indices(archive));
+            }
+            indices.sort_unstable();
+            indices.dedup();
+            indices
+        };
+        indices.retain(|index| {
+            let size = archive.mft_data[*index].size;
+            size > 0
+                && select.min_size.is_none_or(|min_size| size >= min_size)
+                && select.max_size.is_none_or(|max_size| size <= max_size)
+        });
+        Ok(indices)
+    }
+
+    fn matches_content(&self, data: &[u8]) -> bool {
+        if self.select.content_types.is_empty() {
+            return true;
+        }
+        content_fourcc(data).is_some_and(|fourcc| {
+            self.select
+                .content_types
+                .iter()
+                .any(|content_type| content_type.as_bytes() == fourcc)
+        })
+    }
+
+    /// Decompresses, identifies and decodes the stored `data` of entry
+    /// `index`.
+    fn process_entry(
+        &self,
+        reader: &ArchiveReader,
+        converters: &PipelineConverters,
+        index: usize,
+        data: Vec<u8>,
+    ) -> PipelineOutcome {
+        let Ok(data) = reader.decompress_entry(index, data) else {
+            return PipelineOutcome::Skipped;
+        };
+        if !self.matches_content(&data) {
+            return PipelineOutcome::Filtered;
+        }
+        match self.decode {
+            PipelineDecode::Raw => PipelineOutcome::Raw {
+                data,
+                unconverted: false,
+            },
+            PipelineDecode::Texture => {
+                // Corrupt headers can give a side of 0, which no transform
+                // can shrink.
+                let image = match texture::decode_texture_entry(&data) {
+                    Ok(image) if !image.is_empty() => image,
+                    _ => return PipelineOutcome::Skipped,
+                };
+                let mut png = Vec::new();
+                match self.transform(image).write_png(&mut png) {
+                    Ok(()) => PipelineOutcome::Png(png),
+                    Err(_) => PipelineOutcome::Skipped,
+                }
+            }
+            PipelineDecode::Convert => {
+                let Some(converter) = converters.get(AnetFileType::identify(&data)) else {
+                    return PipelineOutcome::Raw {
+                        data,
+                        unconverted: false,
+                    };
+                };
+                match converter(&data) {
+                    Ok(files) => PipelineOutcome::Converted(files),
+                    Err(_error) => {
+                        log_warn!(index, error = %_error, "Conversion failed, writing the entry as stored.");
+                        PipelineOutcome::Raw {
+                            data,
+                            unconverted: true,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Applies the transforms to `image`, which must not be empty.
+    fn transform(&self, mut image: RgbaImageData) -> RgbaImageData {
+        for transform in &self.transforms {
+            image = match *transform {
+                PipelineTransform::Downsample { times } => {
+                    (0..times).fold(image, |image, _| image.downsample())
+                }
+                PipelineTransform::FitWithin { max_size } => {
+                    while image.width.max(image.height) > max_size.max(1) {
+                        image = image.downsample();
+                    }
+                    image
+                }
+            };
+        }
+        image
+    }
+
+    fn output_path(&self, file_name: &str, written: usize) -> PathBuf {
+        let mut directory = self.output.directory.clone();
+        if let Some(entries_per_shard) = self.output.entries_per_shard {
+            directory.push(format!("{:04}", written / entries_per_shard));
+        }
+        directory.join(file_name)
+    }
+
+    /// Writes the files decoded from entry `index`.
+    fn write_outcome(
+        &self,
+        archive: &AnetArchive,
+        index: usize,
+        outcome: PipelineOutcome,
+        report: &mut PipelineReport,
+    ) -> io::Result<()> {
+        let files = match outcome {
+            PipelineOutcome::Filtered => return Ok(()),
+            PipelineOutcome::Skipped => {
+                report.skipped += 1;
+                return Ok(());
+            }
+            PipelineOutcome::Raw { data, unconverted } => {
+                report.unconverted += usize::from(unconverted);
+                vec![(archive.entry_file_name_for_data(index, &data), data)]
+            }
+            PipelineOutcome::Png(png) => {
+                let file_name = archive.entry_file_name(index);
+                let stem = file_name.strip_suffix(".bin").unwrap_or(&file_name);
+                vec![(format!("{}.png", stem), png)]
+            }
+            PipelineOutcome::Converted(files) => {
+                let file_name = archive.entry_file_name(index);
+                let stem = file_name.strip_suffix(".bin").unwrap_or(&file_name);
+                files
+                    .into_iter()
+                    .map(|file| {
+                        let file_name = format!("{}{}.{}", stem, file.suffix, file.extension);
+                        (file_name, file.data)
+                    })
+                    .collect()
+            }
+        };
+        for (file_name, data) in files {
+            let file_path = self.output_path(&file_name, report.written.len());
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            File::create(&file_path)?.write_all(&data)?;
+            report.written.push(file_path);
+        }
+        Ok(())
+    }
+
+    /// Runs the job with the standard converters.
+    pub fn run(&self, archive: &AnetArchive) -> io::Result<PipelineReport> {
+        self.run_with(archive, &PipelineConverters::standard())
+    }
+
+    /// Runs the job as a pipeline of stages: one thread reads the stored
+    /// entries, `threads` workers decompress, identify and decode them, and
+    /// this thread writes the results in selection order. Bounded queues
+    /// between the stages and the memory budget, see `set_memory_budget`,
+    /// limit the entries in flight.
+    pub fn run_with(
+        &self,
+        archive: &AnetArchive,
+        converters: &PipelineConverters,
+    ) -> io::Result<PipelineReport> {
+        self.validate()?;
+        let indices = self.candidate_indices(archive)?;
+        let thread_count = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |count| count.get()),
+            threads => threads,
+        }
+        .min(indices.len().max(1));
+        let queue_depth = thread_count * QUEUE_DEPTH_PER_THREAD;
+        let reader = archive.shared_reader()?;
+        let budget = archive.batch_budget();
+        let mut report = PipelineReport::default();
+        thread::scope(|scope| -> io::Result<()> {
+            let (read_sender, read_receiver) = mpsc::sync_channel(queue_depth);
+            let (reader, budget, indices) = (&reader, &budget, &indices);
+            scope.spawn(move || {
+                for (position, &index) in indices.iter().enumerate() {
+                    let result = budget
+                        .acquire_entry(reader, index)
+                        .and_then(|permit| Ok((reader.read_entry_data(index)?, permit)));
+                    if read_sender.send((position, index, result)).is_err() {
+                        return;
+                    }
+                }
+            });
+
+            // The workers share the read queue. Once the last of them is
+            // gone, the reader finds it closed.
+            let read_receiver = Arc::new(Mutex::new(read_receiver));
+            let (decode_sender, decode_receiver) = mpsc::sync_channel(queue_depth);
+            for _ in 0..thread_count {
+                let read_receiver = Arc::clone(&read_receiver);
+                let decode_sender = decode_sender.clone();
+                scope.spawn(move || loop {
+                    let received = read_receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .recv();
+                    let Ok((position, index, result)) = received else {
+                        return;
+                    };
+                    let (outcome, permit): (_, Option<BudgetPermit>) = match result {
+                        Ok((data, permit)) => (
+                            self.process_entry(reader, converters, index, data),
+                            Some(permit),
+                        ),
+                        Err(_) => (PipelineOutcome::Skipped, None),
+                    };
+                    if decode_sender
+                        .send((position, index, outcome, permit))
+                        .is_err()
+                    {
+                        return;
+                    }
+                });
+            }
+            drop((read_receiver, decode_sender));
+
+            // Entries arrive in the order the workers finish them and wait
+            // here, permits held, until the ones selected before are written.
+            let mut pending = BTreeMap::new();
+            let mut next_position = 0;
+            for (position, index, outcome, permit) in decode_receiver {
+                pending.insert(position, (index, outcome, permit));
+                while let Some((index, outcome, _permit)) = pending.remove(&next_position) {
+                    next_position += 1;
+                    self.write_outcome(archive, index, outcome, &mut report)?;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(report)
+    }
+}
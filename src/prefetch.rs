@@ -0,0 +1,114 @@
+use std::io;
+
+use crate::anet_archive::{check_range, AnetArchive};
+use crate::reader::{open_archive_file, read_exact_at};
+
+#[derive(Debug, Clone)]
+pub struct PrefetchOptions {
+    pub max_gap: u64, //< Unrequested bytes between two entries still read through to merge them.
+    pub max_span: u64, //< Upper bound on a merged read; larger entries are read on their own.
+}
+
+impl Default for PrefetchOptions {
+    fn default() -> Self {
+        PrefetchOptions {
+            max_gap: 0x10000,
+            max_span: 0x1000000,
+        }
+    }
+}
+
+/// One sequential read covering one or more entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadSpan {
+    pub offset: u64,
+    pub length: u64,
+    pub indices: Vec<usize>, //< Entries inside the span, by ascending offset.
+}
+
+impl AnetArchive {
+    /// Groups the entries at `indices` into reads sorted by offset in the
+    /// archive, merging entries that are adjacent or at most `max_gap`
+    /// bytes apart as long as the merged read stays within `max_span`.
+    /// Indices out of range are left out. Entries whose range overflows get a
+    /// span of their own, which `prefetch_entries` then fails.
+    pub fn plan_reads(&self, indices: &[usize], options: &PrefetchOptions) -> Vec<ReadSpan> {
+        let mut sorted: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|index| *index < self.mft_data.len())
+            .collect();
+        sorted.sort_unstable_by_key(|index| (self.mft_data[*index].offset, *index));
+        sorted.dedup();
+
+        let mut spans: Vec<ReadSpan> = Vec::new();
+        for index in sorted {
+            let mft_entry = &self.mft_data[index];
+            let Some(end) = mft_entry.offset.checked_add(u64::from(mft_entry.size)) else {
+                spans.push(ReadSpan {
+                    offset: mft_entry.offset,
+                    length: u64::from(mft_entry.size),
+                    indices: vec![index],
+                });
+                continue;
+            };
+            if let Some(span) = spans.last_mut() {
+                let span_end = span.offset.saturating_add(span.length);
+                if mft_entry.offset <= span_end.saturating_add(options.max_gap)
+                    && end.max(span_end) - span.offset <= options.max_span
+                {
+                    span.length = end.max(span_end) - span.offset;
+                    span.indices.push(index);
+                    continue;
+                }
+            }
+            spans.push(ReadSpan {
+                offset: mft_entry.offset,
+                length: end - mft_entry.offset,
+                indices: vec![index],
+            });
+        }
+        spans
+    }
+
+    /// Reads the entries at `indices` with the coalesced reads of
+    /// `plan_reads` and hands each decompressed entry to `visit`, in archive
+    /// order rather than the order given. An entry that fails to decompress
+    /// is passed on as an error; failing to read a span fails every entry in
+    /// it. Stops at the first error `visit` returns.
+    pub fn prefetch_entries(
+        &self,
+        indices: &[usize],
+        options: &PrefetchOptions,
+        visit: &mut dyn FnMut(usize, io::Result<Vec<u8>>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let file = open_archive_file(&self.file_path)?;
+        let file_length = file.metadata()?.len();
+        for span in self.plan_reads(indices, options) {
+            let result =
+                check_range("Entry", span.offset, span.length, file_length).and_then(|_| {
+                    let mut buffer = vec![0; span.length as usize];
+                    read_exact_at(&file, &mut buffer, span.offset).map(|_| buffer)
+                });
+            let buffer = match result {
+                Ok(buffer) => buffer,
+                Err(error) => {
+                    log_warn!(offset = span.offset, length = span.length, error = %error, "Prefetch read failed.");
+                    for &index in &span.indices {
+                        visit(index, Err(io::Error::new(error.kind(), error.to_string())))?;
+                    }
+                    continue;
+                }
+            };
+            self.counters
+                .record_read(span.indices.len() as u64, span.length);
+            for &index in &span.indices {
+                let mft_entry = &self.mft_data[index];
+                let start = (mft_entry.offset - span.offset) as usize;
+                let data = buffer[start..start + mft_entry.size as usize].to_vec();
+                visit(index, self.counters.decompress_entry(mft_entry, data))?;
+            }
+        }
+        Ok(())
+    }
+}
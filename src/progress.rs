@@ -0,0 +1,68 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStage {
+    LoadMft,
+    Extract,
+    Verify,
+    Hash,
+    Scan,
+    Search,
+}
+
+/// Reported by long operations as they advance. `done` never exceeds
+/// `total` and the last report of a stage has `done == total`.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub stage: ProgressStage,
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+}
+
+/// Callback for operations that do not need progress.
+pub fn no_progress(_: Progress) {}
+
+/// Progress shared between the thread running an operation and the ones
+/// displaying it. Clones observe the same counters.
+#[derive(Default, Debug, Clone)]
+pub struct ProgressHandle {
+    done: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+}
+
+impl ProgressHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn report(&self, progress: Progress) {
+        self.total.store(progress.total, Ordering::Relaxed);
+        self.done.store(progress.done, Ordering::Relaxed);
+    }
+
+    /// A callback to hand to a long operation.
+    pub fn reporter(&self) -> impl FnMut(Progress) + '_ {
+        move |progress| self.report(progress)
+    }
+
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,87 @@
+use std::{fs, path::PathBuf};
+
+use pyo3::{prelude::*, types::PyBytes};
+
+use crate::anet_archive::AnetArchive;
+
+/// Summary of an MFT entry, as seen from Python.
+#[pyclass(name = "Entry", frozen, get_all)]
+pub struct PyEntry {
+    index: usize,
+    file_id: u32, //< Base ID of the entry, 0 when it has none.
+    size: u32,
+    compressed: bool,
+    in_use: bool,
+    counter: u32,
+}
+
+#[pyclass(name = "Archive", unsendable)]
+pub struct PyArchive {
+    archive: AnetArchive,
+}
+
+#[pymethods]
+impl PyArchive {
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        Ok(PyArchive {
+            archive: AnetArchive::load_from_file(path)?,
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.archive.mft_data.len()
+    }
+
+    fn entries(&self) -> Vec<PyEntry> {
+        self.archive
+            .entries()
+            .map(|entry| PyEntry {
+                index: entry.index(),
+                file_id: self
+                    .archive
+                    .mft_index_data
+                    .get(entry.index())
+                    .map_or(0, |id_entry| id_entry.base_id),
+                size: entry.size(),
+                compressed: entry.is_compressed(),
+                in_use: entry.is_in_use(),
+                counter: entry.mft_entry().counter,
+            })
+            .collect()
+    }
+
+    fn index_of(&self, file_id: u32) -> Option<usize> {
+        self.archive.index_of(file_id)
+    }
+
+    /// Decompressed content of the entry at `index`.
+    fn read<'py>(&self, py: Python<'py>, index: usize) -> PyResult<Bound<'py, PyBytes>> {
+        let data = self.archive.read_entry(index)?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Decompressed content of the entry holding `file_id`.
+    fn read_file<'py>(&self, py: Python<'py>, file_id: u32) -> PyResult<Bound<'py, PyBytes>> {
+        let index = self.archive.index_of(file_id).ok_or_else(|| {
+            pyo3::exceptions::PyKeyError::new_err(format!("File ID {} not found.", file_id))
+        })?;
+        self.read(py, index)
+    }
+
+    /// Writes the entry at `index` into `directory` and returns its path.
+    fn extract(&self, index: usize, directory: PathBuf) -> PyResult<PathBuf> {
+        let data = self.archive.read_entry(index)?;
+        fs::create_dir_all(&directory)?;
+        let file_path = directory.join(self.archive.entry_file_name_for_data(index, &data));
+        fs::write(&file_path, &data)?;
+        Ok(file_path)
+    }
+}
+
+#[pymodule]
+fn leafadoo(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyArchive>()?;
+    module.add_class::<PyEntry>()?;
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::anet_archive::{check_range, AnetArchive, AnetMftEntry};
+use crate::entry::{COMPRESSED_HEADER_SIZE, UNCOMPRESSED_SIZE_OFFSET};
+use crate::metrics::Counters;
+
+// Sharing the game client allows while it has the archive open: it writes
+// to the archive itself and lets other processes read it.
+#[cfg(windows)]
+const FILE_SHARE_READ: u32 = 0x1;
+#[cfg(windows)]
+const FILE_SHARE_WRITE: u32 = 0x2;
+
+/// Opens the archive at `file_path` for reading with the sharing flags the
+/// game client accepts, so that the archive can be read while the client
+/// runs or patches it.
+pub fn open_archive_file<P: AsRef<Path>>(file_path: P) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        options.share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE);
+    }
+    options.open(file_path)
+}
+
+/// Fills `buffer` from `file` starting at `offset` without moving, or
+/// needing exclusive access to, the file cursor.
+#[cfg(unix)]
+pub(crate) fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, offset)
+}
+
+/// Fills `buffer` from `file` starting at `offset`. `seek_read` moves the
+/// cursor on Windows, but every read names its own offset so concurrent
+/// readers do not disturb each other.
+#[cfg(windows)]
+pub(crate) fn read_exact_at(file: &File, mut buffer: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, offset) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Failed to fill whole buffer.",
+                ))
+            }
+            Ok(read) => {
+                buffer = &mut buffer[read..];
+                offset += read as u64;
+            }
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(())
+}
+
+/// Fills `buffer` from `file` starting at `offset` on targets without
+/// positioned reads, e.g. `wasm32-unknown-unknown`, where file access
+/// fails anyway. Seeking moves the cursor every handle on the file shares,
+/// so one read runs at a time.
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    use std::sync::{Mutex, PoisonError};
+    static CURSOR: Mutex<()> = Mutex::new(());
+    let _cursor = CURSOR.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut file = file;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buffer)
+}
+
+/// A read-only handle on the archive file that any number of threads can
+/// read entries through at once. Every read is positioned, so there is no
+/// shared cursor to lock.
+pub struct ArchiveReader<'a> {
+    file: File,
+    file_length: u64,
+    mft_data: &'a [AnetMftEntry],
+    counters: &'a Counters,
+}
+
+impl AnetArchive {
+    /// Opens the archive this was loaded from for concurrent entry reads.
+    pub fn shared_reader(&self) -> io::Result<ArchiveReader<'_>> {
+        let file = open_archive_file(&self.file_path)?;
+        Ok(ArchiveReader {
+            file_length: file.metadata()?.len(),
+            file,
+            mft_data: &self.mft_data,
+            counters: &self.counters,
+        })
+    }
+}
+
+impl ArchiveReader<'_> {
+    fn mft_entry(&self, index: usize) -> io::Result<&AnetMftEntry> {
+        self.mft_data
+            .get(index)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "MFT entry index out of range."))
+    }
+
+    /// Reads up to `length` raw bytes from the start of the entry at `index`.
+    pub fn read_entry_prefix(&self, index: usize, length: usize) -> io::Result<Vec<u8>> {
+        let mft_entry = self.mft_entry(index)?;
+        let length = length.min(mft_entry.size as usize);
+        check_range("Entry", mft_entry.offset, length as u64, self.file_length)?;
+        let mut data = vec![0; length];
+        read_exact_at(&self.file, &mut data, mft_entry.offset)?;
+        self.counters.record_read(1, length as u64);
+        Ok(data)
+    }
+
+    /// Reads the raw stored bytes of the entry at `index`.
+    pub fn read_entry_data(&self, index: usize) -> io::Result<Vec<u8>> {
+        self.read_entry_prefix(index, usize::MAX)
+    }
+
+    /// Size of the entry at `index` once decompressed, read from the
+    /// compressed data header when the entry is compressed.
+    pub fn uncompressed_size(&self, index: usize) -> io::Result<u32> {
+        let mft_entry = self.mft_entry(index)?;
+        if !mft_entry.is_compressed() {
+            return Ok(mft_entry.size);
+        }
+        let header = self.read_entry_prefix(index, COMPRESSED_HEADER_SIZE)?;
+        if header.len() < COMPRESSED_HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Compressed entry is too small to hold its header.",
+            ));
+        }
+        Ok(LittleEndian::read_u32(&header[UNCOMPRESSED_SIZE_OFFSET..]))
+    }
+
+    /// Reads the entry at `index`, decompressing it if needed.
+    pub fn read_entry(&self, index: usize) -> io::Result<Vec<u8>> {
+        let data = self.read_entry_data(index)?;
+        self.decompress_entry(index, data)
+    }
+
+    /// Turns the stored bytes of the entry at `index`, as returned by
+    /// `read_entry_data`, into its content.
+    pub fn decompress_entry(&self, index: usize, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        self.counters.decompress_entry(self.mft_entry(index)?, data)
+    }
+}
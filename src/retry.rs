@@ -0,0 +1,140 @@
+use std::{error::Error, fmt, io, thread, time::Duration};
+
+use crate::anet_archive::{
+    check_range, parse_mft_entry, AnetArchive, AnetMftEntry, MFT_ENTRY_SIZE, MFT_HEADER_SIZE,
+};
+use crate::reader::{open_archive_file, read_exact_at};
+
+/// Wait before the first retry of an entry read, doubled for every further
+/// attempt, giving a patcher time to finish writing the entry.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(50);
+// Longest wait between two attempts, however many retries are set.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// An entry kept failing to read even after re-reading its MFT record,
+/// most likely because the archive is being patched while it is read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrentModification {
+    pub index: usize,
+    pub attempts: u32,
+    pub last_error: String,
+}
+
+impl fmt::Display for ConcurrentModification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Entry {} failed to read {} times, the archive is likely being modified: {}",
+            self.index, self.attempts, self.last_error
+        )
+    }
+}
+
+impl Error for ConcurrentModification {}
+
+impl From<ConcurrentModification> for io::Error {
+    fn from(error: ConcurrentModification) -> Self {
+        io::Error::other(error)
+    }
+}
+
+/// Whether `error` is a `ConcurrentModification`.
+pub fn is_concurrent_modification(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<ConcurrentModification>())
+}
+
+/// Whether a failed read could come from the entry moving under the reader,
+/// as opposed to e.g. the index being out of range.
+fn is_retryable(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+    )
+}
+
+impl AnetArchive {
+    pub fn read_retries(&self) -> u32 {
+        self.read_retries
+    }
+
+    /// Sets how many more times extraction tries an entry that fails to read
+    /// or decompress, see `read_entry_with_retry`. 0, the default, fails on
+    /// the first error.
+    pub fn set_read_retries(&mut self, read_retries: u32) {
+        self.read_retries = read_retries;
+    }
+
+    pub fn retry_delay(&self) -> Duration {
+        self.retry_delay.unwrap_or(DEFAULT_RETRY_DELAY)
+    }
+
+    /// Sets how long `read_entry_with_retry` waits before its first retry.
+    /// The wait doubles for every further attempt, up to 5 seconds.
+    /// `Duration::ZERO` retries right away.
+    pub fn set_retry_delay(&mut self, retry_delay: Duration) {
+        self.retry_delay = Some(retry_delay);
+    }
+
+    /// Reads the current MFT record of the entry at `index` from the archive
+    /// file, which may differ from the loaded one if the file was patched.
+    pub fn reread_mft_entry(&self, index: usize) -> io::Result<AnetMftEntry> {
+        let dat_header = Self::read_dat_header_from_file(&self.file_path)?;
+        let offset = (MFT_HEADER_SIZE + index * MFT_ENTRY_SIZE) as u64;
+        if offset + MFT_ENTRY_SIZE as u64 > u64::from(dat_header.mft_size) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "MFT entry index out of range.",
+            ));
+        }
+        let file = open_archive_file(&self.file_path)?;
+        let mut raw_entry = [0; MFT_ENTRY_SIZE];
+        read_exact_at(&file, &mut raw_entry, dat_header.mft_offset + offset)?;
+        Ok(parse_mft_entry(&raw_entry))
+    }
+
+    /// Reads and decompresses the entry `mft_entry` describes.
+    fn read_mft_entry_content(&self, mft_entry: &AnetMftEntry) -> io::Result<Vec<u8>> {
+        let file = open_archive_file(&self.file_path)?;
+        let length = u64::from(mft_entry.size);
+        check_range("Entry", mft_entry.offset, length, file.metadata()?.len())?;
+        let mut data = vec![0; mft_entry.size as usize];
+        read_exact_at(&file, &mut data, mft_entry.offset)?;
+        self.counters.record_read(1, length);
+        self.counters.decompress_entry(mft_entry, data)
+    }
+
+    /// Like `read_entry`, but when the data is truncated or does not
+    /// decompress, re-reads the MFT record of the entry and tries again, up
+    /// to `retries` more times, waiting longer before each attempt, see
+    /// `set_retry_delay`. Fails with a `ConcurrentModification` error when
+    /// every attempt failed.
+    pub fn read_entry_with_retry(&self, index: usize, retries: u32) -> io::Result<Vec<u8>> {
+        let mut last_error = match self.read_entry(index) {
+            Ok(data) => return Ok(data),
+            Err(error) if retries == 0 || !is_retryable(&error) => return Err(error),
+            Err(error) => error,
+        };
+        let mut delay = self.retry_delay();
+        for _attempt in 1..=retries {
+            log_debug!(index, attempt = _attempt, error = %last_error, "Retrying entry read.");
+            thread::sleep(delay);
+            delay = delay.saturating_mul(2).min(MAX_RETRY_DELAY);
+            let result = self
+                .reread_mft_entry(index)
+                .and_then(|mft_entry| self.read_mft_entry_content(&mft_entry));
+            match result {
+                Ok(data) => return Ok(data),
+                Err(error) if !is_retryable(&error) => return Err(error),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(ConcurrentModification {
+            index,
+            attempts: retries + 1,
+            last_error: last_error.to_string(),
+        }
+        .into())
+    }
+}
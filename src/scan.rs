@@ -0,0 +1,174 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+use crate::file_type::{ScanKey, TypeScan, FILE_TYPE_HEAD_SIZE};
+use crate::hash::{HashAlgo, HashReport};
+use crate::progress::{Progress, ProgressStage};
+use crate::reader::open_archive_file;
+
+const CHECKPOINT_EXTENSION: &str = ".leafscan";
+// Entries classified, and hashed, between two checkpoints.
+const DEFAULT_CHECKPOINT_INTERVAL: usize = 0x2000;
+
+#[derive(Debug, Clone)]
+pub struct FullScanOptions {
+    pub hash: Option<HashAlgo>,           //< Also hash every entry.
+    pub checkpoint_path: Option<PathBuf>, //< Defaults to `<archive>.leafscan`.
+    pub checkpoint_interval: usize,       //< MFT entries between checkpoints.
+}
+
+impl Default for FullScanOptions {
+    fn default() -> Self {
+        FullScanOptions {
+            hash: None,
+            checkpoint_path: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+        }
+    }
+}
+
+/// The result of a full scan: the type and optionally the digest of every
+/// entry.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FullScan {
+    pub types: TypeScan,
+    pub hashes: Option<HashReport>,
+}
+
+/// A full scan in progress, saved to disk so an interrupted scan resumes
+/// where it stopped. Only valid for the archive state it was made from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    pub next_index: usize, //< MFT entries before this one are done.
+    pub scan: FullScan,
+}
+
+impl ScanCheckpoint {
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(file_path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes the checkpoint next to `file_path` and renames it into place,
+    /// so an interruption never leaves a truncated checkpoint behind.
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let mut partial_path = OsString::from(file_path.as_ref().as_os_str());
+        partial_path.push(".partial");
+        let mut writer = BufWriter::new(File::create(&partial_path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&partial_path, file_path)
+    }
+
+    /// Whether the checkpoint can be resumed against `archive` as it is
+    /// now, with the same options.
+    pub fn matches(&self, archive: &AnetArchive, key: &ScanKey, hash: Option<HashAlgo>) -> bool {
+        self.scan.types.key == *key
+            && self.scan.types.file_types.len() == archive.mft_data.len()
+            && self.scan.hashes.as_ref().map(|hashes| hashes.algo) == hash
+            && self.next_index <= archive.mft_data.len()
+    }
+}
+
+impl AnetArchive {
+    /// Sidecar file a full scan checkpoints to, e.g. `Gw2.dat.leafscan`.
+    pub fn scan_checkpoint_path(&self) -> PathBuf {
+        let mut file_name = OsString::from(self.file_path.as_os_str());
+        file_name.push(CHECKPOINT_EXTENSION);
+        PathBuf::from(file_name)
+    }
+
+    /// Identifies, and optionally hashes, every in-use entry. Progress is
+    /// checkpointed every `checkpoint_interval` entries; a checkpoint left
+    /// by an interrupted scan of the same archive state is picked up and
+    /// the scan continues from it. The checkpoint is removed once the scan
+    /// completes.
+    pub fn scan_resumable(
+        &self,
+        options: &FullScanOptions,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<FullScan> {
+        let checkpoint_path = options
+            .checkpoint_path
+            .clone()
+            .unwrap_or_else(|| self.scan_checkpoint_path());
+        let key = self.scan_key()?;
+        let total = self.mft_data.len();
+
+        let mut checkpoint = match ScanCheckpoint::load_from_file(&checkpoint_path) {
+            Ok(checkpoint) if checkpoint.matches(self, &key, options.hash) => {
+                log_debug!(
+                    next_index = checkpoint.next_index,
+                    "Resuming scan from checkpoint."
+                );
+                checkpoint
+            }
+            _ => ScanCheckpoint {
+                next_index: 0,
+                scan: FullScan {
+                    types: TypeScan {
+                        key,
+                        file_types: vec![AnetFileType::AnftUnknown; total],
+                    },
+                    hashes: options.hash.map(|algo| HashReport {
+                        algo,
+                        digests: Vec::new(),
+                        failures: Vec::new(),
+                    }),
+                },
+            },
+        };
+
+        let mut reader = BufReader::new(open_archive_file(&self.file_path)?);
+        let interval = options.checkpoint_interval.max(1);
+        while checkpoint.next_index < total {
+            progress(Progress {
+                stage: ProgressStage::Scan,
+                done: checkpoint.next_index,
+                total,
+            });
+            let end = checkpoint.next_index.saturating_add(interval).min(total);
+            let indices: Vec<usize> = (checkpoint.next_index..end)
+                .filter_map(|index| self.entry(index))
+                .filter(|entry| entry.is_in_use() && entry.size() > 0)
+                .map(|entry| entry.index())
+                .collect();
+            for &index in &indices {
+                if let Ok(head) = self.read_entry_head_from(&mut reader, index, FILE_TYPE_HEAD_SIZE)
+                {
+                    checkpoint.scan.types.file_types[index] = AnetFileType::identify(&head);
+                }
+            }
+            if let Some(hashes) = checkpoint.scan.hashes.as_mut() {
+                let batch = self.hash_indices(&indices, hashes.algo, &mut |_| {})?;
+                hashes.digests.extend(batch.digests);
+                hashes.failures.extend(batch.failures);
+            }
+            checkpoint.next_index = end;
+            checkpoint.save_to_file(&checkpoint_path)?;
+        }
+        progress(Progress {
+            stage: ProgressStage::Scan,
+            done: total,
+            total,
+        });
+
+        // The types double as the scan index `type_scan` would otherwise
+        // rebuild.
+        if let Err(_error) = checkpoint.scan.types.save_to_file(self.scan_index_path()) {
+            log_warn!(error = %_error, "Could not save the scan index.");
+        }
+        if let Err(_error) = fs::remove_file(&checkpoint_path) {
+            log_warn!(error = %_error, "Could not remove the scan checkpoint.");
+        }
+        Ok(checkpoint.scan)
+    }
+}
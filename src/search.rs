@@ -0,0 +1,308 @@
+use std::{collections::HashMap, io, sync::mpsc, thread};
+
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType, LanguageType};
+use crate::progress::{no_progress, Progress, ProgressStage};
+use crate::text::{AnetStringFile, AnetTextPackManifest};
+
+/// A string that contains the text searched for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StringMatch {
+    pub file_id: u32,         //< Base ID of the `strs` file.
+    pub index: usize,         //< Position of the string in the file.
+    pub text_id: Option<u32>, //< String ID, when the text pack manifest lists the file.
+    pub language: u16,
+    pub text: String,
+}
+
+impl AnetArchive {
+    /// The strings per file of the text pack manifest, and the position of
+    /// each `strs` file it lists in string ID order, by file ID. Empty when
+    /// the archive has no manifest.
+    fn string_file_positions(&self) -> io::Result<(u32, HashMap<u32, usize>)> {
+        let Some(manifest_entry) = self
+            .find_by_type(AnetFileType::AnftTextPackManifest)?
+            .into_iter()
+            .next()
+        else {
+            return Ok((0, HashMap::new()));
+        };
+        let manifest =
+            AnetTextPackManifest::load_from_bytes(&self.read_entry(manifest_entry.index())?)?;
+        let mut positions = HashMap::new();
+        for files in &manifest.languages {
+            for (position, file) in files.iter().enumerate() {
+                if let Some(file_id) = file.as_ref().and_then(|file| file.file_id()) {
+                    positions.insert(file_id, position);
+                }
+            }
+        }
+        Ok((manifest.strings_per_file, positions))
+    }
+
+    /// Every decodable string containing `query`, ignoring case, in the
+    /// `strs` files of `language`, or of every language when `None`. The
+    /// files are found through the type scan, so repeated searches reuse
+    /// the scan index. Encrypted strings cannot be searched.
+    pub fn search_strings(
+        &self,
+        query: &str,
+        language: Option<LanguageType>,
+    ) -> io::Result<Vec<StringMatch>> {
+        self.search_strings_with_progress(query, language, &mut no_progress)
+    }
+
+    /// Like `search_strings`, reporting the `strs` files searched so far.
+    pub fn search_strings_with_progress(
+        &self,
+        query: &str,
+        language: Option<LanguageType>,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Vec<StringMatch>> {
+        let query = query.to_lowercase();
+        let (strings_per_file, positions) = self.string_file_positions()?;
+        let entries = self.find_by_type(AnetFileType::AnftStringFile)?;
+        let total = entries.len();
+        let mut matches = Vec::new();
+        for (done, entry) in entries.iter().enumerate() {
+            progress(Progress {
+                stage: ProgressStage::Search,
+                done,
+                total,
+            });
+            let string_file = self
+                .read_entry(entry.index())
+                .and_then(|data| AnetStringFile::load_from_bytes(&data));
+            let Some(string_file) = self.tolerate(Some(entry.index()), string_file)? else {
+                continue;
+            };
+            if language.is_some_and(|language| string_file.language != language as u16) {
+                continue;
+            }
+            let file_id = self
+                .mft_index_data
+                .get(entry.index())
+                .map_or(0, |id_entry| id_entry.base_id);
+            let position = self
+                .id_aliases
+                .get(entry.index())
+                .and_then(|aliases| aliases.iter().find_map(|file_id| positions.get(file_id)));
+            for (index, text) in string_file.strings.iter().enumerate() {
+                let Some(text) = text else {
+                    continue;
+                };
+                if !text.to_lowercase().contains(&query) {
+                    continue;
+                }
+                matches.push(StringMatch {
+                    file_id,
+                    index,
+                    text_id: position
+                        .map(|position| *position as u32 * strings_per_file + index as u32),
+                    language: string_file.language,
+                    text: text.clone(),
+                });
+            }
+        }
+        progress(Progress {
+            stage: ProgressStage::Search,
+            done: total,
+            total,
+        });
+        Ok(matches)
+    }
+}
+
+/// What `AnetArchive::grep` looks for in the decompressed entries.
+#[derive(Debug, Clone)]
+pub enum GrepPattern {
+    Bytes(Vec<u8>),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    pub max_entry_size: u64, //< Entries larger than this once decompressed are skipped.
+    pub max_hits_per_entry: usize,
+    pub threads: usize, //< 0 for one per available core.
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        GrepOptions {
+            max_entry_size: 0x4000000,
+            max_hits_per_entry: 16,
+            threads: 0,
+        }
+    }
+}
+
+/// Where a pattern was found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrepHit {
+    pub index: usize,
+    pub file_id: u32,  //< Base ID of the entry.
+    pub offset: usize, //< Offset of the match in the decompressed entry.
+    pub length: usize,
+}
+
+impl GrepPattern {
+    /// Parses bytes written in hex, e.g. `"de ad be ef"` or `"deadbeef"`.
+    pub fn from_hex(hex: &str) -> io::Result<Self> {
+        let digits: Vec<u8> = hex.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid hex pattern '{}'.", hex),
+            )
+        };
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+        digits
+            .chunks_exact(2)
+            .map(|pair| {
+                let pair = std::str::from_utf8(pair).ok()?;
+                u8::from_str_radix(pair, 16).ok()
+            })
+            .collect::<Option<Vec<u8>>>()
+            .map(GrepPattern::Bytes)
+            .ok_or_else(invalid)
+    }
+
+    /// `text` encoded as UTF-16LE, the encoding of most game strings.
+    pub fn utf16(text: &str) -> Self {
+        GrepPattern::Bytes(
+            text.encode_utf16()
+                .flat_map(|unit| unit.to_le_bytes())
+                .collect(),
+        )
+    }
+
+    /// A regular expression matched against raw bytes, see
+    /// `regex::bytes::Regex`; `(?-u)` turns off Unicode to match any byte.
+    pub fn regex(pattern: &str) -> io::Result<Self> {
+        Regex::new(pattern)
+            .map(GrepPattern::Regex)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))
+    }
+
+    /// Offsets and lengths of up to `limit` non-overlapping matches in `data`.
+    pub fn find_all(&self, data: &[u8], limit: usize) -> Vec<(usize, usize)> {
+        match self {
+            GrepPattern::Bytes(bytes) if bytes.is_empty() => Vec::new(),
+            GrepPattern::Bytes(bytes) => {
+                let mut matches = Vec::new();
+                let mut position = 0;
+                while matches.len() < limit {
+                    let Some(found) = data[position..]
+                        .windows(bytes.len())
+                        .position(|window| window == bytes.as_slice())
+                    else {
+                        break;
+                    };
+                    matches.push((position + found, bytes.len()));
+                    position += found + bytes.len();
+                }
+                matches
+            }
+            GrepPattern::Regex(regex) => regex
+                .find_iter(data)
+                .take(limit)
+                .map(|found| (found.start(), found.len()))
+                .collect(),
+        }
+    }
+}
+
+impl AnetArchive {
+    /// Searches every in-use entry for `pattern`, see `grep_indices`.
+    pub fn grep(&self, pattern: &GrepPattern, options: &GrepOptions) -> io::Result<Vec<GrepHit>> {
+        let indices: Vec<usize> = self
+            .in_use_entries()
+            .filter(|entry| entry.size() > 0)
+            .map(|entry| entry.index())
+            .collect();
+        self.grep_indices(&indices, pattern, options, &mut no_progress)
+    }
+
+    /// Searches the decompressed entries at `indices` for `pattern` on
+    /// several threads, which share one positioned reader and stay within
+    /// the memory budget of the archive. Entries larger than
+    /// `max_entry_size` are skipped unread. Hits are sorted by MFT index
+    /// and offset. Entries that fail to read fail the search, or are
+    /// skipped by a lenient archive.
+    pub fn grep_indices(
+        &self,
+        indices: &[usize],
+        pattern: &GrepPattern,
+        options: &GrepOptions,
+        progress: &mut dyn FnMut(Progress),
+    ) -> io::Result<Vec<GrepHit>> {
+        let total = indices.len();
+        let thread_count = match options.threads {
+            0 => thread::available_parallelism().map_or(1, |count| count.get()),
+            threads => threads,
+        }
+        .min(total.max(1));
+
+        let reader = self.shared_reader()?;
+        let budget = self.batch_budget();
+        let mut hits = Vec::new();
+        thread::scope(|scope| -> io::Result<()> {
+            let (sender, receiver) = mpsc::channel();
+            for worker in 0..thread_count {
+                let sender = sender.clone();
+                let (reader, budget) = (&reader, &budget);
+                scope.spawn(move || {
+                    for &index in indices.iter().skip(worker).step_by(thread_count) {
+                        let result = reader.uncompressed_size(index).and_then(|size| {
+                            if u64::from(size) > options.max_entry_size {
+                                return Ok(Vec::new());
+                            }
+                            let _permit = budget.acquire(u64::from(size));
+                            let data = reader.read_entry(index)?;
+                            Ok(pattern.find_all(&data, options.max_hits_per_entry))
+                        });
+                        if sender.send((index, result)).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            for (done, (index, result)) in receiver.into_iter().enumerate() {
+                progress(Progress {
+                    stage: ProgressStage::Search,
+                    done,
+                    total,
+                });
+                let Some(matches) = self.tolerate(Some(index), result)? else {
+                    continue;
+                };
+                let file_id = self
+                    .mft_index_data
+                    .get(index)
+                    .map_or(0, |id_entry| id_entry.base_id);
+                hits.extend(matches.into_iter().map(|(offset, length)| GrepHit {
+                    index,
+                    file_id,
+                    offset,
+                    length,
+                }));
+            }
+            Ok(())
+        })?;
+        progress(Progress {
+            stage: ProgressStage::Search,
+            done: total,
+            total,
+        });
+
+        hits.sort_unstable_by_key(|hit| (hit.index, hit.offset));
+        Ok(hits)
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+use crate::pf::AnetPfFile;
+
+const CDHS_FILE_TYPE: &[u8; 4] = b"CDHS";
+
+const DXBC_MAGIC: &[u8; 4] = b"DXBC";
+// Magic, checksum and the reserved word before the total size.
+const DXBC_TOTAL_SIZE_OFFSET: usize = 24;
+const DXBC_CHUNK_COUNT_OFFSET: usize = 28;
+const DXBC_HEADER_SIZE: usize = 32;
+const DXBC_CHUNK_HEADER_SIZE: usize = 8;
+// Shader code chunks, for shader model 4 and 5 respectively. Both start
+// with the version token.
+const SHDR_CHUNK: &[u8; 4] = b"SHDR";
+const SHEX_CHUNK: &[u8; 4] = b"SHEX";
+const DXBC_EXTENSION: &str = "dxbc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ShaderStage {
+    Pixel,
+    Vertex,
+    Geometry,
+    Hull,
+    Domain,
+    Compute,
+    Unknown(u16),
+}
+
+/// A compiled shader found in a shader cache.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnetShaderBlob {
+    pub chunk: String, //< FourCC of the PF chunk the blob was found in.
+    pub offset: usize, //< Offset of the blob in the chunk data.
+    pub stage: ShaderStage,
+    pub shader_model: (u8, u8), //< Major and minor version, (0, 0) without a code chunk.
+    pub data: Vec<u8>,
+}
+
+/// A parsed `CDHS` file: the DXBC shaders it caches.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetShaderCache {
+    pub shaders: Vec<AnetShaderBlob>,
+}
+
+impl ShaderStage {
+    /// Stage of the program type stored in the high word of a DXBC version
+    /// token.
+    pub fn from_program_type(program_type: u16) -> Self {
+        match program_type {
+            0 => ShaderStage::Pixel,
+            1 => ShaderStage::Vertex,
+            2 => ShaderStage::Geometry,
+            3 => ShaderStage::Hull,
+            4 => ShaderStage::Domain,
+            5 => ShaderStage::Compute,
+            _ => ShaderStage::Unknown(program_type),
+        }
+    }
+
+    /// Short name used for file names, as in `vs_5_0`.
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            ShaderStage::Pixel => "ps",
+            ShaderStage::Vertex => "vs",
+            ShaderStage::Geometry => "gs",
+            ShaderStage::Hull => "hs",
+            ShaderStage::Domain => "ds",
+            ShaderStage::Compute => "cs",
+            ShaderStage::Unknown(_) => "xs",
+        }
+    }
+}
+
+/// Length of the DXBC container at the start of `data`, or `None` if it is
+/// not one or its stored size runs past the end of `data`.
+fn dxbc_length(data: &[u8]) -> Option<usize> {
+    if !data.starts_with(DXBC_MAGIC) || data.len() < DXBC_HEADER_SIZE {
+        return None;
+    }
+    let length = LittleEndian::read_u32(&data[DXBC_TOTAL_SIZE_OFFSET..]) as usize;
+    (DXBC_HEADER_SIZE..=data.len())
+        .contains(&length)
+        .then_some(length)
+}
+
+/// Reads the stage and shader model from the version token of the code
+/// chunk of a DXBC container.
+fn dxbc_version(blob: &[u8]) -> Option<(ShaderStage, (u8, u8))> {
+    let chunk_count = LittleEndian::read_u32(&blob[DXBC_CHUNK_COUNT_OFFSET..]) as usize;
+    let offsets =
+        blob.get(DXBC_HEADER_SIZE..DXBC_HEADER_SIZE.checked_add(chunk_count.checked_mul(4)?)?)?;
+    offsets.chunks_exact(4).find_map(|offset| {
+        let offset = LittleEndian::read_u32(offset) as usize;
+        let header = blob.get(offset..offset.checked_add(DXBC_CHUNK_HEADER_SIZE + 4)?)?;
+        if &header[..4] != SHDR_CHUNK && &header[..4] != SHEX_CHUNK {
+            return None;
+        }
+        let token = LittleEndian::read_u32(&header[DXBC_CHUNK_HEADER_SIZE..]);
+        Some((
+            ShaderStage::from_program_type((token >> 16) as u16),
+            (((token >> 4) & 0xf) as u8, (token & 0xf) as u8),
+        ))
+    })
+}
+
+/// Finds the DXBC containers in `data`, as offsets and lengths. Scanning
+/// resumes after the end of each container found.
+pub fn find_dxbc_blobs(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut blobs = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = data[offset..]
+        .windows(DXBC_MAGIC.len())
+        .position(|window| window == DXBC_MAGIC)
+    {
+        let start = offset + found;
+        match dxbc_length(&data[start..]) {
+            Some(length) => {
+                blobs.push((start, length));
+                offset = start + length;
+            }
+            None => offset = start + 1,
+        }
+    }
+    blobs
+}
+
+impl AnetShaderBlob {
+    fn from_bytes(chunk: String, offset: usize, data: &[u8]) -> Self {
+        let (stage, shader_model) =
+            dxbc_version(data).unwrap_or((ShaderStage::Unknown(u16::MAX), (0, 0)));
+        AnetShaderBlob {
+            chunk,
+            offset,
+            stage,
+            shader_model,
+            data: data.to_vec(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl AnetShaderCache {
+    /// Collects the DXBC shaders in the chunks of a decompressed PF `CDHS`
+    /// entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        if pf_file.file_type() != CDHS_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a CDHS file.",
+            ));
+        }
+        let mut cache = AnetShaderCache::default();
+        for chunk in &pf_file.chunks {
+            let chunk_type = String::from_utf8_lossy(&chunk.header.chunk_type).into_owned();
+            for (offset, length) in find_dxbc_blobs(&chunk.data) {
+                cache.shaders.push(AnetShaderBlob::from_bytes(
+                    chunk_type.clone(),
+                    offset,
+                    &chunk.data[offset..offset + length],
+                ));
+            }
+        }
+        Ok(cache)
+    }
+
+    /// Writes every shader to `out_dir` as
+    /// `<file_id>_<n>_<stage>_<major>_<minor>.dxbc` and returns the paths
+    /// written.
+    pub fn save_shaders<P: AsRef<Path>>(
+        &self,
+        file_id: u32,
+        out_dir: P,
+    ) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(&out_dir)?;
+        let mut paths = Vec::new();
+        for (number, shader) in self.shaders.iter().enumerate() {
+            let path = out_dir.as_ref().join(format!(
+                "{}_{}_{}_{}_{}.{}",
+                file_id,
+                number,
+                shader.stage.short_name(),
+                shader.shader_model.0,
+                shader.shader_model.1,
+                DXBC_EXTENSION
+            ));
+            fs::write(&path, &shader.data)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+impl AnetArchive {
+    /// Reads and parses the shader cache with the given file ID.
+    pub fn read_shader_cache(&self, file_id: u32) -> io::Result<AnetShaderCache> {
+        AnetShaderCache::load_from_bytes(&self.read_file(file_id)?)
+    }
+}
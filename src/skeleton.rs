@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::anet_archive::{AnetArchive, AnetIdEntry, AnetMftEntry};
+
+const SKELETON_MAGIC: &[u8; 4] = b"LFSK";
+const SKELETON_VERSION: u32 = 1;
+
+// Skeleton bundles hold the structural tables of an archive (DAT header, MFT
+// and file ID table) without any entry payloads, so the structure of a
+// multi-gigabyte archive can be shared and diffed in a few megabytes.
+impl AnetArchive {
+    pub fn export_skeleton_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.export_skeleton(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn export_skeleton<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(SKELETON_MAGIC)?;
+        writer.write_u32::<LittleEndian>(SKELETON_VERSION)?;
+
+        self.dat_header.write_to(writer)?;
+        self.mft_header.write_to(writer)?;
+        writer.write_u32::<LittleEndian>(self.mft_data.len() as u32)?;
+        for entry in &self.mft_data {
+            entry.write_to(writer)?;
+        }
+
+        writer.write_u32::<LittleEndian>(self.file_id_table.len() as u32)?;
+        for id_entry in &self.file_id_table {
+            writer.write_u32::<LittleEndian>(id_entry.file_id)?;
+            writer.write_u32::<LittleEndian>(id_entry.base_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn import_skeleton_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+        Self::import_skeleton(&mut reader)
+    }
+
+    pub fn import_skeleton<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != SKELETON_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a leafadoo skeleton bundle: invalid magic.",
+            ));
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != SKELETON_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported skeleton bundle version {}.", version),
+            ));
+        }
+
+        let mut archive = AnetArchive::default();
+        let header = &mut archive.dat_header;
+        header.version = reader.read_u8()?;
+        reader.read_exact(&mut header.identifier)?;
+        header.header_size = reader.read_u32::<LittleEndian>()?;
+        header.unknown_field = reader.read_u32::<LittleEndian>()?;
+        header.chunk_size = reader.read_u32::<LittleEndian>()?;
+        header.crc = reader.read_u32::<LittleEndian>()?;
+        header.unknown_field_2 = reader.read_u32::<LittleEndian>()?;
+        header.mft_offset = reader.read_u64::<LittleEndian>()?;
+        header.mft_size = reader.read_u32::<LittleEndian>()?;
+        header.flags = reader.read_u32::<LittleEndian>()?;
+
+        let mft_header = &mut archive.mft_header;
+        reader.read_exact(&mut mft_header.identifier)?;
+        mft_header.unknown_field = reader.read_u64::<LittleEndian>()?;
+        mft_header.num_entries = reader.read_u32::<LittleEndian>()?;
+        mft_header.unknown_field_2 = reader.read_u64::<LittleEndian>()?;
+
+        let mft_count = reader.read_u32::<LittleEndian>()?;
+        for _ in 0..mft_count {
+            archive.mft_data.push(AnetMftEntry {
+                offset: reader.read_u64::<LittleEndian>()?,
+                size: reader.read_u32::<LittleEndian>()?,
+                compression_flag: reader.read_u16::<LittleEndian>()?,
+                entry_flag: reader.read_u16::<LittleEndian>()?,
+                counter: reader.read_u32::<LittleEndian>()?,
+                crc: reader.read_u32::<LittleEndian>()?,
+            });
+        }
+
+        let id_count = reader.read_u32::<LittleEndian>()?;
+        for _ in 0..id_count {
+            archive.file_id_table.push(AnetIdEntry {
+                file_id: reader.read_u32::<LittleEndian>()?,
+                base_id: reader.read_u32::<LittleEndian>()?,
+            });
+        }
+
+        archive.build_mft_index()?;
+        Ok(archive)
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"LFMS";
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// The state of one in-use MFT entry at the time of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub index: u32,
+    pub file_id: u32, //< Base ID of the entry, 0 when it has none.
+    pub size: u32,
+    pub counter: u32,
+    pub crc: u32,
+}
+
+/// What a build looked like, reduced to the in-use MFT entries. A full
+/// Gw2.dat snapshots to a few megabytes, so one can be kept per build and
+/// builds compared long after the archive itself has been patched over.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MftSnapshot {
+    pub max_counter: u32,
+    pub entries: Vec<SnapshotEntry>, //< Ordered by MFT index.
+}
+
+/// Changes between two snapshots.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub added: Vec<SnapshotEntry>,
+    pub updated: Vec<SnapshotEntry>, //< As they are in the newer snapshot.
+    pub removed: Vec<SnapshotEntry>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl AnetArchive {
+    pub fn snapshot(&self) -> MftSnapshot {
+        MftSnapshot {
+            max_counter: self.max_counter(),
+            entries: self
+                .in_use_entries()
+                .map(|entry| SnapshotEntry {
+                    index: entry.index() as u32,
+                    file_id: self
+                        .mft_index_data
+                        .get(entry.index())
+                        .map_or(0, |id_entry| id_entry.base_id),
+                    size: entry.size(),
+                    counter: entry.mft_entry().counter,
+                    crc: entry.mft_entry().crc,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl MftSnapshot {
+    /// Lists what changed going from `self` to the newer `other`. Entries
+    /// are matched by MFT index; an entry counts as updated when it was
+    /// written again, which bumps its counter, or its size or CRC differ.
+    pub fn diff(&self, other: &MftSnapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+        let mut before = self.entries.iter().peekable();
+        let mut after = other.entries.iter().peekable();
+        loop {
+            match (before.peek(), after.peek()) {
+                (Some(old), Some(new)) => match old.index.cmp(&new.index) {
+                    Ordering::Less => diff.removed.extend(before.next().cloned()),
+                    Ordering::Greater => diff.added.extend(after.next().cloned()),
+                    Ordering::Equal => {
+                        if old != new {
+                            diff.updated.push((*new).clone());
+                        }
+                        before.next();
+                        after.next();
+                    }
+                },
+                (Some(_), None) => diff.removed.extend(before.next().cloned()),
+                (None, Some(_)) => diff.added.extend(after.next().cloned()),
+                (None, None) => break,
+            }
+        }
+        diff
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the snapshot as a small header followed by the zlib
+    /// compressed entry records.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_u32::<LittleEndian>(SNAPSHOT_VERSION)?;
+        writer.write_u32::<LittleEndian>(self.max_counter)?;
+        writer.write_u32::<LittleEndian>(self.entries.len() as u32)?;
+
+        let mut encoder = ZlibEncoder::new(writer, Compression::default());
+        for entry in &self.entries {
+            encoder.write_u32::<LittleEndian>(entry.index)?;
+            encoder.write_u32::<LittleEndian>(entry.file_id)?;
+            encoder.write_u32::<LittleEndian>(entry.size)?;
+            encoder.write_u32::<LittleEndian>(entry.counter)?;
+            encoder.write_u32::<LittleEndian>(entry.crc)?;
+        }
+        encoder.finish()?;
+        Ok(())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+        Self::read_from(&mut reader)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a leafadoo MFT snapshot: invalid magic.",
+            ));
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported MFT snapshot version {}.", version),
+            ));
+        }
+        let max_counter = reader.read_u32::<LittleEndian>()?;
+        let count = reader.read_u32::<LittleEndian>()?;
+
+        let mut decoder = ZlibDecoder::new(reader);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            entries.push(SnapshotEntry {
+                index: decoder.read_u32::<LittleEndian>()?,
+                file_id: decoder.read_u32::<LittleEndian>()?,
+                size: decoder.read_u32::<LittleEndian>()?,
+                counter: decoder.read_u32::<LittleEndian>()?,
+                crc: decoder.read_u32::<LittleEndian>()?,
+            });
+        }
+        Ok(MftSnapshot {
+            max_counter,
+            entries,
+        })
+    }
+}
@@ -0,0 +1,617 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetFileReference;
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const ASND_CHUNK: &[u8; 4] = b"ASND";
+
+// Flag set on music tracks that should restart at `loop_start` when they end.
+const SOUND_FLAG_LOOPING: u32 = 0x1;
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetSoundData {
+    pub length: f32, //< Track length in seconds.
+    pub offset: f32, //< Playback start offset in seconds.
+    pub flags: u32,
+    pub loop_start: u32, //< Loop start in samples.
+    pub loop_end: u32,   //< Loop end in samples, zero means the end of the track.
+    pub audio_data: Vec<u8>,
+    pub other_data: Vec<u8>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnetLoopPoints {
+    pub loop_start: u32,
+    pub loop_end: u32,
+    pub length: f32,
+}
+
+impl AnetSoundData {
+    /// Parses the `ASND` chunk of a decompressed PF `ASND` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(ASND_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing ASND chunk."))?;
+        Self::load_from_chunk(&chunk.data)
+    }
+
+    pub fn load_from_chunk(data: &[u8]) -> io::Result<Self> {
+        let _scope = trace::scope("ASND");
+        let mut reader = PfReader::new(data);
+        Ok(AnetSoundData {
+            length: reader.field("length").read_f32()?,
+            offset: reader.field("offset").read_f32()?,
+            flags: reader.field("flags").read_u32()?,
+            loop_start: reader.field("loop_start").read_u32()?,
+            loop_end: reader.field("loop_end").read_u32()?,
+            audio_data: reader.field("audio_data").read_byte_array()?,
+            other_data: reader.field("other_data").read_byte_array()?,
+        })
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.flags & SOUND_FLAG_LOOPING != 0
+    }
+
+    pub fn loop_points(&self) -> Option<AnetLoopPoints> {
+        if !self.is_looping() {
+            return None;
+        }
+        Some(AnetLoopPoints {
+            loop_start: self.loop_start,
+            loop_end: self.loop_end,
+            length: self.length,
+        })
+    }
+
+    /// The payload in a form players open: MP3s realigned to their first
+    /// frame, see `repair_mp3`, any other payload as stored.
+    pub fn playable_data(&self) -> Cow<'_, [u8]> {
+        repair_mp3(&self.audio_data).unwrap_or(Cow::Borrowed(&self.audio_data))
+    }
+
+    /// Extension of `playable_data`.
+    pub fn extension(&self) -> &'static str {
+        match repair_mp3(&self.audio_data) {
+            Some(_) => "mp3",
+            None => audio_extension(&self.audio_data),
+        }
+    }
+
+    /// Writes the playable audio payload to `file_path` and, for looping
+    /// tracks, the loop points to a `.json` file next to it.
+    pub fn extract_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let file_path = file_path.as_ref();
+        File::create(file_path)?.write_all(&self.playable_data())?;
+
+        if let Some(loop_points) = self.loop_points() {
+            let sidecar = File::create(file_path.with_extension("json"))?;
+            serde_json::to_writer_pretty(sidecar, &loop_points)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn audio_extension(data: &[u8]) -> &'static str {
+    if data.starts_with(b"OggS") {
+        "ogg"
+    } else if data.starts_with(b"ID3") || data.starts_with(&[0xff, 0xfb]) {
+        "mp3"
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WAVE") {
+        "wav"
+    } else {
+        "bin"
+    }
+}
+
+// How far into a payload the first MP3 frame is looked for, and how many
+// consecutive frames must follow it for it to count as the stream start.
+const MAX_MP3_LEADING_GARBAGE: usize = 0x1000;
+const MP3_FRAMES_TO_CONFIRM: usize = 3;
+const ID3V2_HEADER_SIZE: usize = 10;
+
+// Bitrates in kbit/s by bitrate index: MPEG-1 layers I, II and III, then
+// MPEG-2 and 2.5 layer I, and layers II and III.
+const MP3_BITRATES: [[u32; 15]; 5] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+const MP3_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// Parses the MPEG audio frame header at the start of `data`. Returns the
+/// bits every frame of a stream shares and the frame length in bytes.
+fn mp3_frame_header(data: &[u8]) -> Option<(u32, usize)> {
+    let header = BigEndian::read_u32(data.get(..4)?);
+    let version = (header >> 19) & 0x3; // 0 is MPEG 2.5, 2 MPEG 2, 3 MPEG 1.
+    let layer = (header >> 17) & 0x3; // 1 is layer III, 3 layer I.
+    let bitrate_index = ((header >> 12) & 0xf) as usize;
+    let sample_rate_index = ((header >> 10) & 0x3) as usize;
+    if header >> 21 != 0x7ff
+        || version == 1
+        || layer == 0
+        || bitrate_index == 0
+        || bitrate_index == 15
+        || sample_rate_index == 3
+    {
+        return None;
+    }
+    let table = match (version == 3, layer) {
+        (true, 3) => 0,
+        (true, 2) => 1,
+        (true, _) => 2,
+        (false, 3) => 3,
+        (false, _) => 4,
+    };
+    let bitrate = MP3_BITRATES[table][bitrate_index] * 1000;
+    let sample_rate = MP3_SAMPLE_RATES[sample_rate_index] >> (3 - version).min(2);
+    let padding = (header >> 9) & 0x1;
+    let length = match layer {
+        3 => (12 * bitrate / sample_rate + padding) * 4,
+        1 if version != 3 => 72 * bitrate / sample_rate + padding,
+        _ => 144 * bitrate / sample_rate + padding,
+    };
+    Some((header & 0xfffe0c00, length as usize))
+}
+
+/// Length of the ID3v2 tag at the start of `data`, or 0 without one.
+fn id3v2_length(data: &[u8]) -> usize {
+    let Some(header) = data
+        .get(..ID3V2_HEADER_SIZE)
+        .filter(|header| header.starts_with(b"ID3"))
+    else {
+        return 0;
+    };
+    let size = header[6..10]
+        .iter()
+        .fold(0usize, |size, byte| (size << 7) | usize::from(byte & 0x7f));
+    let footer = if header[5] & 0x10 != 0 {
+        ID3V2_HEADER_SIZE
+    } else {
+        0
+    };
+    (ID3V2_HEADER_SIZE + size + footer).min(data.len())
+}
+
+/// Offset of the first MPEG audio frame in the first `max_offset` bytes of
+/// `data` that is followed by consistent frames, or ends the data.
+pub fn find_mp3_frame(data: &[u8], max_offset: usize) -> Option<usize> {
+    (0..data.len().min(max_offset + 1)).find(|&start| {
+        let Some((stream, _)) = mp3_frame_header(&data[start..]) else {
+            return false;
+        };
+        let mut position = start;
+        for _ in 0..MP3_FRAMES_TO_CONFIRM {
+            match mp3_frame_header(&data[position..]) {
+                Some((bits, length)) if bits == stream => position += length,
+                _ => return false,
+            }
+            if position >= data.len() {
+                return position == data.len();
+            }
+        }
+        true
+    })
+}
+
+/// Realigns an unwrapped MP3 to its first frame: whatever precedes it,
+/// after any leading ID3v2 tag, is dropped. Returns `None` when no frame
+/// starts near the beginning, i.e. the data is not MP3.
+pub fn repair_mp3(data: &[u8]) -> Option<Cow<'_, [u8]>> {
+    let tag_length = id3v2_length(data);
+    let garbage = find_mp3_frame(&data[tag_length..], MAX_MP3_LEADING_GARBAGE)?;
+    if garbage == 0 {
+        return Some(Cow::Borrowed(data));
+    }
+    log_debug!(garbage, "Stripped bytes before the first MP3 frame.");
+    let mut repaired = data[..tag_length].to_vec();
+    repaired.extend_from_slice(&data[tag_length + garbage..]);
+    Some(Cow::Owned(repaired))
+}
+
+// `wFormatTag` of uncompressed integer PCM.
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FMT_SIZE: usize = 16;
+// Bounds on the `fmt ` fields of bank clips, well past any real clip.
+const MAX_PCM_CHANNELS: u16 = 32;
+const MAX_PCM_SAMPLE_RATE: u32 = 768_000;
+
+/// Layout of uncompressed PCM samples, the body of a WAV `fmt ` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PcmFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+}
+
+impl PcmFormat {
+    /// Reads a 16-byte WAV `fmt ` body, as bank clips store in
+    /// `other_data`. Only integer PCM is accepted, with at most 32 channels
+    /// and 768 kHz.
+    pub fn from_wave_format(data: &[u8]) -> Option<Self> {
+        let data = data.get(..WAVE_FMT_SIZE)?;
+        let format = PcmFormat {
+            channels: LittleEndian::read_u16(&data[2..4]),
+            sample_rate: LittleEndian::read_u32(&data[4..8]),
+            bits_per_sample: LittleEndian::read_u16(&data[14..16]),
+        };
+        let valid = LittleEndian::read_u16(&data[0..2]) == WAVE_FORMAT_PCM
+            && (1..=MAX_PCM_CHANNELS).contains(&format.channels)
+            && (1..=MAX_PCM_SAMPLE_RATE).contains(&format.sample_rate)
+            && matches!(format.bits_per_sample, 8 | 16 | 24 | 32);
+        valid.then_some(format)
+    }
+
+    /// Bytes per sample frame, one sample of every channel, or `None` if
+    /// that does not fit the WAV header.
+    pub fn block_align(&self) -> Option<u16> {
+        self.channels.checked_mul(self.bits_per_sample.div_ceil(8))
+    }
+
+    pub fn byte_rate(&self) -> Option<u32> {
+        self.sample_rate.checked_mul(u32::from(self.block_align()?))
+    }
+}
+
+/// Wraps raw PCM samples in a RIFF WAVE header. A trailing partial sample
+/// frame is dropped.
+pub fn pcm_to_wav(samples: &[u8], format: PcmFormat) -> io::Result<Vec<u8>> {
+    let (Some(block_align), Some(byte_rate)) = (format.block_align(), format.byte_rate()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "PCM format does not fit a WAV header.",
+        ));
+    };
+    if block_align == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "PCM format has no channels.",
+        ));
+    }
+    let samples = &samples[..samples.len() - samples.len() % usize::from(block_align)];
+    let data_size = u32::try_from(samples.len())
+        .ok()
+        .filter(|size| *size <= u32::MAX - 36)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "PCM data is too large for a WAV file.",
+            )
+        })?;
+    let mut wav = Vec::with_capacity(samples.len() + 44);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&(WAVE_FMT_SIZE as u32).to_le_bytes());
+    wav.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+    wav.extend_from_slice(&format.channels.to_le_bytes());
+    wav.extend_from_slice(&format.sample_rate.to_le_bytes());
+    wav.extend_from_slice(&byte_rate.to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&format.bits_per_sample.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    wav.extend_from_slice(samples);
+    Ok(wav)
+}
+
+const BKCK_CHUNK: &[u8; 4] = b"BKCK";
+const BIDX_CHUNK: &[u8; 4] = b"BIDX";
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetBankAudioFile {
+    pub voice_id: u32,
+    pub flags: u32,
+    pub unknown_field: [u32; 4],
+    pub length: f32, //< Clip length in seconds.
+    pub offset: f32,
+    pub unknown_field_2: [u8; 4],
+    pub audio_data: Vec<u8>,
+    pub other_data: Vec<u8>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetBankFile {
+    pub audio_files: Vec<AnetBankAudioFile>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetBankIndex {
+    pub languages: Vec<Vec<AnetFileReference>>, //< Bank file references, one list per language.
+}
+
+impl AnetBankAudioFile {
+    fn read(reader: &mut PfReader) -> io::Result<Self> {
+        let mut audio_file = AnetBankAudioFile {
+            voice_id: reader.field("voice_id").read_u32()?,
+            flags: reader.field("flags").read_u32()?,
+            ..Default::default()
+        };
+        for field in audio_file.unknown_field.iter_mut() {
+            *field = reader.field("unknown_field").read_u32()?;
+        }
+        audio_file.length = reader.field("length").read_f32()?;
+        audio_file.offset = reader.field("offset").read_f32()?;
+        audio_file
+            .unknown_field_2
+            .copy_from_slice(reader.field("unknown_field_2").read_bytes(4)?);
+        audio_file.audio_data = reader.field("audio_data").read_byte_array()?;
+        audio_file.other_data = reader.field("other_data").read_byte_array()?;
+        Ok(audio_file)
+    }
+
+    /// Format of the samples when the clip is raw PCM: its payload is no
+    /// known container and `other_data` describes the samples.
+    pub fn pcm_format(&self) -> Option<PcmFormat> {
+        if audio_extension(&self.audio_data) != "bin" {
+            return None;
+        }
+        PcmFormat::from_wave_format(&self.other_data)
+    }
+
+    /// The clip as a WAV file, for raw PCM clips.
+    pub fn to_wav(&self) -> io::Result<Vec<u8>> {
+        let format = self.pcm_format().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Voice {} is not raw PCM.", self.voice_id),
+            )
+        })?;
+        pcm_to_wav(&self.audio_data, format)
+    }
+
+    /// The payload in a form players open: raw PCM wrapped as WAV, MP3s
+    /// realigned to their first frame, any other payload as stored.
+    pub fn playable_data(&self) -> io::Result<Cow<'_, [u8]>> {
+        if let Some(format) = self.pcm_format() {
+            return Ok(Cow::Owned(pcm_to_wav(&self.audio_data, format)?));
+        }
+        Ok(repair_mp3(&self.audio_data).unwrap_or(Cow::Borrowed(&self.audio_data)))
+    }
+
+    /// Extension of `playable_data`.
+    pub fn extension(&self) -> &'static str {
+        if self.pcm_format().is_some() {
+            "wav"
+        } else if repair_mp3(&self.audio_data).is_some() {
+            "mp3"
+        } else {
+            audio_extension(&self.audio_data)
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self.voice_id, self.extension())
+    }
+}
+
+impl AnetBankFile {
+    /// Parses the `BKCK` chunk of a decompressed PF `ABNK` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(BKCK_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing BKCK chunk."))?;
+
+        let _scope = trace::scope("BKCK");
+        let mut reader = PfReader::new(&chunk.data);
+        let (count, position) = reader.field("audio_files").read_array()?;
+        let mut reader = PfReader::at(&chunk.data, position);
+        let mut bank_file = AnetBankFile::default();
+        for _ in 0..count {
+            bank_file
+                .audio_files
+                .push(AnetBankAudioFile::read(&mut reader)?);
+        }
+        Ok(bank_file)
+    }
+
+    pub fn voice(&self, voice_id: u32) -> Option<&AnetBankAudioFile> {
+        self.audio_files
+            .iter()
+            .find(|audio_file| audio_file.voice_id == voice_id)
+    }
+
+    /// Writes every clip to `directory` as `<voice_id>.<ext>`, raw PCM clips
+    /// as WAV, and returns the written paths.
+    pub fn extract_voices<P: AsRef<Path>>(&self, directory: P) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(&directory)?;
+        let mut written = Vec::new();
+        for audio_file in &self.audio_files {
+            let file_path = directory.as_ref().join(audio_file.file_name());
+            File::create(&file_path)?.write_all(&audio_file.playable_data()?)?;
+            written.push(file_path);
+        }
+        Ok(written)
+    }
+}
+
+impl AnetBankIndex {
+    /// Parses the `BIDX` chunk of a decompressed PF `ABIX` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(BIDX_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing BIDX chunk."))?;
+
+        let _scope = trace::scope("BIDX");
+        let mut reader = PfReader::new(&chunk.data);
+        let (language_count, language_position) = reader.field("languages").read_array()?;
+        let mut language_reader = PfReader::at(&chunk.data, language_position);
+        let mut bank_index = AnetBankIndex::default();
+        for _ in 0..language_count {
+            let (file_count, file_position) = language_reader.read_array()?;
+            let mut file_reader = PfReader::at(&chunk.data, file_position);
+            let mut bank_files = Vec::with_capacity(file_count.min(chunk.data.len()));
+            for _ in 0..file_count {
+                if let Some(file_reference) = file_reader.read_file_reference()? {
+                    bank_files.push(file_reference);
+                }
+            }
+            bank_index.languages.push(bank_files);
+        }
+        Ok(bank_index)
+    }
+
+    /// File IDs of the bank files of `language`, see [`LanguageType`].
+    ///
+    /// [`LanguageType`]: crate::anet_archive::LanguageType
+    pub fn file_ids(&self, language: usize) -> Vec<u32> {
+        self.languages
+            .get(language)
+            .into_iter()
+            .flatten()
+            .filter_map(AnetFileReference::file_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A WAV `fmt ` body.
+    fn wave_format(format_tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&format_tag.to_le_bytes());
+        data.extend_from_slice(&channels.to_le_bytes());
+        data.extend_from_slice(&sample_rate.to_le_bytes());
+        data.extend_from_slice(&[0; 6]); //< Byte rate and block align, recomputed.
+        data.extend_from_slice(&bits.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn malformed_wave_formats_are_rejected() {
+        let format = PcmFormat::from_wave_format(&wave_format(1, 2, 44100, 16)).unwrap();
+        assert_eq!(format.block_align(), Some(4));
+        assert_eq!(format.byte_rate(), Some(176400));
+
+        for data in [
+            wave_format(1, 0xffff, 44100, 32),
+            wave_format(1, 2, u32::MAX, 32),
+            wave_format(1, 0, 44100, 16),
+            wave_format(1, 2, 0, 16),
+            wave_format(1, 2, 44100, 12),
+            wave_format(3, 2, 44100, 32),
+            wave_format(1, 2, 44100, 16)[..15].to_vec(),
+        ] {
+            assert_eq!(PcmFormat::from_wave_format(&data), None);
+        }
+    }
+
+    #[test]
+    fn wav_headers_describe_the_samples() {
+        let format = PcmFormat::from_wave_format(&wave_format(1, 2, 22050, 24)).unwrap();
+        // Two frames of six bytes and a partial one.
+        let wav = pcm_to_wav(&[7; 15], format).unwrap();
+        assert_eq!(wav.len(), 44 + 12);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(LittleEndian::read_u32(&wav[4..8]), 36 + 12);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(LittleEndian::read_u32(&wav[16..20]), 16);
+        assert_eq!(LittleEndian::read_u16(&wav[20..22]), WAVE_FORMAT_PCM);
+        assert_eq!(LittleEndian::read_u16(&wav[22..24]), 2);
+        assert_eq!(LittleEndian::read_u32(&wav[24..28]), 22050);
+        assert_eq!(LittleEndian::read_u32(&wav[28..32]), 22050 * 6);
+        assert_eq!(LittleEndian::read_u16(&wav[32..34]), 6);
+        assert_eq!(LittleEndian::read_u16(&wav[34..36]), 24);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(LittleEndian::read_u32(&wav[40..44]), 12);
+
+        let clip = AnetBankAudioFile {
+            voice_id: 5,
+            audio_data: vec![0; 16],
+            other_data: wave_format(1, 0xffff, 44100, 32),
+            ..Default::default()
+        };
+        assert!(clip.to_wav().is_err());
+        assert_eq!(clip.playable_data().unwrap(), &[0; 16][..]);
+    }
+
+    #[test]
+    fn overflowing_formats_are_not_written() {
+        let format = PcmFormat {
+            channels: 0xffff,
+            sample_rate: u32::MAX,
+            bits_per_sample: 32,
+        };
+        assert_eq!(format.block_align(), None);
+        assert!(pcm_to_wav(&[0; 16], format).is_err());
+        let format = PcmFormat {
+            channels: 8,
+            sample_rate: u32::MAX,
+            bits_per_sample: 32,
+        };
+        assert_eq!(format.byte_rate(), None);
+        assert!(pcm_to_wav(&[0; 64], format).is_err());
+    }
+
+    /// `count` MPEG-1 layer III frames at 128 kbit/s and 44.1 kHz, 417
+    /// bytes each.
+    fn mp3_frames(count: usize) -> Vec<u8> {
+        let mut frame = vec![0; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+        frame.repeat(count)
+    }
+
+    #[test]
+    fn mp3_streams_are_realigned_to_their_first_frame() {
+        let frames = mp3_frames(4);
+        assert!(matches!(repair_mp3(&frames), Some(Cow::Borrowed(_))));
+        // A lone frame ending the data counts too.
+        assert_eq!(find_mp3_frame(&mp3_frames(1), 0), Some(0));
+
+        // The garbage starts with a sync word no frame follows.
+        let mut data = vec![0xff, 0xfb, 0x90, 0x00, 1, 2, 3];
+        data.extend_from_slice(&frames);
+        assert_eq!(repair_mp3(&data).unwrap(), &frames[..]);
+
+        let mut tag = b"ID3\x03\0\0\0\0\0\x06".to_vec();
+        tag.extend_from_slice(b"tagged");
+        let mut data = tag.clone();
+        data.extend_from_slice(b"junk");
+        data.extend_from_slice(&frames);
+        let repaired = repair_mp3(&data).unwrap();
+        assert_eq!(&repaired[..tag.len()], &tag[..]);
+        assert_eq!(&repaired[tag.len()..], &frames[..]);
+    }
+
+    #[test]
+    fn data_without_mp3_frames_is_not_repaired() {
+        assert!(repair_mp3(b"").is_none());
+        assert!(repair_mp3(b"OggS\0\x02").is_none());
+        // Frames too far past the start.
+        let mut data = vec![0; MAX_MP3_LEADING_GARBAGE + 1];
+        data.extend_from_slice(&mp3_frames(4));
+        assert!(repair_mp3(&data).is_none());
+        // A frame cut short, and frames that do not agree on the stream.
+        assert!(repair_mp3(&mp3_frames(1)[..400]).is_none());
+        let mut data = mp3_frames(2);
+        data[417 + 2] = 0x94; //< 48 kHz.
+        assert!(repair_mp3(&data).is_none());
+        // A tag claiming more than the data holds.
+        assert!(repair_mp3(b"ID3\x03\0\0\x7f\x7f\x7f\x7f").is_none());
+    }
+}
@@ -0,0 +1,64 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+
+/// How an archive reacts to a malformed entry or ID record.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strictness {
+    #[default]
+    Strict, //< Fail on the first error.
+    Lenient, //< Record a warning, skip the offending item and carry on.
+}
+
+/// A problem skipped over in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveWarning {
+    pub index: Option<usize>, //< MFT index of the entry, when the problem is tied to one.
+    pub message: String,
+}
+
+impl AnetArchive {
+    pub fn strictness(&self) -> Strictness {
+        self.strictness
+    }
+
+    pub fn set_strictness(&mut self, strictness: Strictness) {
+        self.strictness = strictness;
+    }
+
+    /// Problems skipped over so far in lenient mode, oldest first.
+    pub fn warnings(&self) -> Vec<ArchiveWarning> {
+        self.warnings.borrow().clone()
+    }
+
+    /// Returns the warnings collected so far and forgets them.
+    pub fn take_warnings(&self) -> Vec<ArchiveWarning> {
+        self.warnings.take()
+    }
+
+    pub(crate) fn warn(&self, index: Option<usize>, message: String) {
+        log_warn!(index, error = %message, "Skipped malformed data.");
+        self.warnings
+            .borrow_mut()
+            .push(ArchiveWarning { index, message });
+    }
+
+    /// Passes `result` through in strict mode. In lenient mode an error is
+    /// recorded as a warning against `index` and `None` returned instead.
+    pub(crate) fn tolerate<T>(
+        &self,
+        index: Option<usize>,
+        result: io::Result<T>,
+    ) -> io::Result<Option<T>> {
+        match (result, self.strictness) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(error), Strictness::Lenient) => {
+                self.warn(index, error.to_string());
+                Ok(None)
+            }
+            (Err(error), Strictness::Strict) => Err(error),
+        }
+    }
+}
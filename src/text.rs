@@ -0,0 +1,278 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io,
+};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileReference, AnetFileType, LanguageType};
+use crate::pf::{AnetPfFile, PfReader};
+use crate::trace;
+
+const STRS_MAGIC: &[u8; 4] = b"strs";
+// Each string starts with its size, decryption offset and symbol size.
+const STRING_HEADER_SIZE: usize = 6;
+// Files end with the language they hold.
+const STRS_LANGUAGE_SIZE: usize = 2;
+const PLAIN_TEXT_BITS_PER_SYMBOL: u16 = 0x10;
+const TXTM_CHUNK: &[u8; 4] = b"txtm";
+const TXTV_FILE_TYPE: &[u8; 4] = b"txtv";
+const TXTV_VARIANT_FILE_TYPE: &[u8; 4] = b"txtV";
+const UTF8_BOM: &[u8; 3] = b"\xef\xbb\xbf";
+const UTF16_LE_BOM: &[u8; 2] = b"\xff\xfe";
+const UTF16_BE_BOM: &[u8; 2] = b"\xfe\xff";
+
+/// A decoded `strs` file. Strings are `None` where they are encrypted and
+/// need a key the archive does not hold, or empty.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetStringFile {
+    pub language: u16,
+    pub strings: Vec<Option<String>>,
+}
+
+/// A parsed `txtm` file: where the strings of each language are stored.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetTextPackManifest {
+    pub strings_per_file: u32,
+    pub languages: Vec<Vec<Option<AnetFileReference>>>, //< `strs` files per language, in text ID order.
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AnetTextVoice {
+    pub text_id: u32,
+    pub voice_file: Option<AnetFileReference>, //< Voice-over spoken for the text.
+}
+
+/// A parsed `txtv` or `txtV` file: the voice-overs of texts per language.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetTextPackVoices {
+    pub languages: Vec<Vec<AnetTextVoice>>,
+}
+
+fn decode_utf16(data: &[u8], read_unit: fn(&[u8]) -> u16) -> io::Result<String> {
+    if !data.len().is_multiple_of(2) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "UTF-16 text has an odd number of bytes.",
+        ));
+    }
+    let units: Vec<u16> = data.chunks_exact(2).map(read_unit).collect();
+    String::from_utf16(&units)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Text is not valid UTF-16."))
+}
+
+/// Decodes a text file: UTF-8 or UTF-16 of either byte order when it starts
+/// with a byte order mark, which is stripped, and otherwise UTF-8, falling
+/// back to UTF-16LE for text mostly made of ASCII characters with a zero
+/// high byte.
+pub fn decode_text(data: &[u8]) -> io::Result<String> {
+    if let Some(text) = data.strip_prefix(UTF8_BOM) {
+        return String::from_utf8(text.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Text is not valid UTF-8."));
+    }
+    if let Some(text) = data.strip_prefix(UTF16_LE_BOM) {
+        return decode_utf16(text, LittleEndian::read_u16);
+    }
+    if let Some(text) = data.strip_prefix(UTF16_BE_BOM) {
+        return decode_utf16(text, BigEndian::read_u16);
+    }
+    if let Ok(text) = std::str::from_utf8(data) {
+        return Ok(text.to_string());
+    }
+    let zero_high_bytes = data.iter().skip(1).step_by(2).filter(|byte| **byte == 0);
+    if zero_high_bytes.count() * 2 >= data.len() / 2 {
+        return decode_utf16(data, LittleEndian::read_u16);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Text is neither UTF-8 nor UTF-16.",
+    ))
+}
+
+impl AnetStringFile {
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        if !data.starts_with(STRS_MAGIC) || data.len() < STRS_MAGIC.len() + STRS_LANGUAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a strs file.",
+            ));
+        }
+        let end = data.len() - STRS_LANGUAGE_SIZE;
+        let mut string_file = AnetStringFile {
+            language: LittleEndian::read_u16(&data[end..]),
+            ..Default::default()
+        };
+        let mut position = STRS_MAGIC.len();
+        while position + STRING_HEADER_SIZE <= end {
+            let size = usize::from(LittleEndian::read_u16(&data[position..]));
+            let decryption_offset = LittleEndian::read_u16(&data[position + 2..]);
+            let bits_per_symbol = LittleEndian::read_u16(&data[position + 4..]);
+            if size < STRING_HEADER_SIZE || position + size > end {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "String extends past the end of the strs file.",
+                ));
+            }
+            let text = &data[position + STRING_HEADER_SIZE..position + size];
+            let string = if decryption_offset == 0 && bits_per_symbol == PLAIN_TEXT_BITS_PER_SYMBOL
+            {
+                let units: Vec<u16> = text.chunks_exact(2).map(LittleEndian::read_u16).collect();
+                Some(String::from_utf16_lossy(&units))
+            } else {
+                None
+            };
+            string_file
+                .strings
+                .push(string.filter(|string| !string.is_empty()));
+            position += size;
+        }
+        Ok(string_file)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.strings.get(index)?.as_deref()
+    }
+}
+
+impl AnetTextPackManifest {
+    /// Parses the `txtm` chunk of a decompressed PF `txtm` entry.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let chunk = pf_file
+            .chunk(TXTM_CHUNK)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing txtm chunk."))?;
+
+        let _scope = trace::scope("txtm");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut manifest = AnetTextPackManifest {
+            strings_per_file: reader.field("strings_per_file").read_u32()?,
+            ..Default::default()
+        };
+        let (language_count, language_position) = reader.field("languages").read_array()?;
+        let mut language_reader = PfReader::at(&chunk.data, language_position);
+        for _ in 0..language_count {
+            let (file_count, file_position) = language_reader.field("files").read_array()?;
+            let mut file_reader = PfReader::at(&chunk.data, file_position);
+            let mut files = Vec::with_capacity(file_count.min(chunk.data.len()));
+            for _ in 0..file_count {
+                files.push(file_reader.read_file_reference()?);
+            }
+            manifest.languages.push(files);
+        }
+        Ok(manifest)
+    }
+
+    /// The `strs` file holding a text in `language` and the index of the
+    /// text in it.
+    pub fn locate(&self, language: LanguageType, text_id: u32) -> Option<(u32, usize)> {
+        if self.strings_per_file == 0 {
+            return None;
+        }
+        let files = self.languages.get(language as usize)?;
+        let file = files
+            .get((text_id / self.strings_per_file) as usize)?
+            .as_ref()?;
+        Some((file.file_id()?, (text_id % self.strings_per_file) as usize))
+    }
+}
+
+impl AnetTextVoice {
+    pub fn voice_file_id(&self) -> Option<u32> {
+        self.voice_file.as_ref()?.file_id()
+    }
+}
+
+impl AnetTextPackVoices {
+    /// Parses a decompressed PF `txtv` or `txtV` entry, from the chunk named
+    /// like its file type.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let pf_file = AnetPfFile::load_from_bytes(data)?;
+        let file_type = pf_file.file_type();
+        if file_type != TXTV_FILE_TYPE && file_type != TXTV_VARIANT_FILE_TYPE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a txtv file.",
+            ));
+        }
+        let chunk = pf_file
+            .chunk(file_type)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing txtv chunk."))?;
+
+        let _scope = trace::scope("txtv");
+        let mut reader = PfReader::new(&chunk.data);
+        let mut voices = AnetTextPackVoices::default();
+        let (language_count, language_position) = reader.field("languages").read_array()?;
+        let mut language_reader = PfReader::at(&chunk.data, language_position);
+        for _ in 0..language_count {
+            let (voice_count, voice_position) = language_reader.field("voices").read_array()?;
+            let mut voice_reader = PfReader::at(&chunk.data, voice_position);
+            let mut language_voices = Vec::with_capacity(voice_count.min(chunk.data.len()));
+            for _ in 0..voice_count {
+                language_voices.push(AnetTextVoice {
+                    text_id: voice_reader.field("text_id").read_u32()?,
+                    voice_file: voice_reader.field("voice_file").read_file_reference()?,
+                });
+            }
+            voices.languages.push(language_voices);
+        }
+        Ok(voices)
+    }
+
+    pub fn voices(&self, language: LanguageType) -> &[AnetTextVoice] {
+        self.languages
+            .get(language as usize)
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+impl AnetArchive {
+    /// Reads the text file with the given file ID as a string, see
+    /// [`decode_text`] for the encodings handled.
+    pub fn read_text(&self, file_id: u32) -> io::Result<String> {
+        decode_text(&self.read_file(file_id)?)
+    }
+
+    /// Text of every voiced line in `language` with the file ID of its
+    /// voice-over. Lines whose text is encrypted are left out.
+    pub fn dialogue(&self, language: LanguageType) -> io::Result<Vec<(String, u32)>> {
+        let Some(manifest_entry) = self
+            .find_by_type(AnetFileType::AnftTextPackManifest)?
+            .into_iter()
+            .next()
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Archive has no text pack manifest.",
+            ));
+        };
+        let manifest =
+            AnetTextPackManifest::load_from_bytes(&self.read_entry(manifest_entry.index())?)?;
+
+        let mut voice_entries = self.find_by_type(AnetFileType::AnftTextPackVoices)?;
+        voice_entries.extend(self.find_by_type(AnetFileType::AnftTextPackVariant)?);
+        let mut string_files = HashMap::new();
+        let mut dialogue = Vec::new();
+        for entry in voice_entries {
+            let voices = AnetTextPackVoices::load_from_bytes(&self.read_entry(entry.index())?)?;
+            for voice in voices.voices(language) {
+                let Some(voice_file_id) = voice.voice_file_id() else {
+                    continue;
+                };
+                let Some((file_id, index)) = manifest.locate(language, voice.text_id) else {
+                    continue;
+                };
+                let string_file = match string_files.entry(file_id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(AnetStringFile::load_from_bytes(&self.read_file(file_id)?)?)
+                    }
+                };
+                if let Some(text) = string_file.get(index) {
+                    dialogue.push((text.to_string(), voice_file_id));
+                }
+            }
+        }
+        Ok(dialogue)
+    }
+}
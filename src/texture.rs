@@ -0,0 +1,632 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Seek, Write},
+    path::Path,
+};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+use crate::anet_archive::{AnetArchive, AnetAtexHeader};
+use crate::deflate::deflate_texture;
+use crate::formats::FormatTable;
+use crate::inflate::{inflate_texture, inflate_texture_level};
+
+const BLOCK_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockFormat {
+    Dxt1,
+    Dxt3,
+    Dxt5,
+    /// ATI 3Dc (BC5) two-channel normal maps, stored as `3DCX` or `DXTN`.
+    Dxn,
+    /// Single-channel (BC4) blocks, decoded as grayscale.
+    Dxta,
+}
+
+impl BlockFormat {
+    pub fn fourcc(self) -> &'static [u8; 4] {
+        match self {
+            BlockFormat::Dxt1 => b"DXT1",
+            BlockFormat::Dxt3 => b"DXT3",
+            BlockFormat::Dxt5 => b"DXT5",
+            BlockFormat::Dxn => b"3DCX",
+            BlockFormat::Dxta => b"DXTA",
+        }
+    }
+
+    pub fn from_fourcc(fourcc: &[u8]) -> Option<Self> {
+        match fourcc {
+            b"DXT1" => Some(BlockFormat::Dxt1),
+            b"DXT2" | b"DXT3" => Some(BlockFormat::Dxt3),
+            // DXTL blocks are laid out like DXT5's.
+            b"DXT4" | b"DXT5" | b"DXTL" => Some(BlockFormat::Dxt5),
+            b"3DCX" | b"ATI2" | b"DXTN" => Some(BlockFormat::Dxn),
+            b"DXTA" => Some(BlockFormat::Dxta),
+            _ => None,
+        }
+    }
+
+    pub fn block_bytes(self) -> usize {
+        match self {
+            BlockFormat::Dxt1 | BlockFormat::Dxta => 8,
+            BlockFormat::Dxt3 | BlockFormat::Dxt5 | BlockFormat::Dxn => 16,
+        }
+    }
+}
+
+/// Extension of standard image formats stored as-is in the archive, which
+/// are extracted without conversion.
+pub fn passthrough_image_extension(data: &[u8]) -> Option<&'static str> {
+    FormatTable::active()
+        .identify(data)
+        .filter(|format| format.category == "image")
+        .map(|format| format.extension.as_str())
+}
+
+/// Uncompressed 8-bit RGBA pixels.
+#[derive(Default, Debug, Clone)]
+pub struct RgbaImageData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImageData {
+    pub fn load_png<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        Self::read_png(BufReader::new(File::open(file_path)?))
+    }
+
+    /// Decodes the PNG in `data`. Only the first frame of an animated PNG
+    /// is read.
+    pub fn load_png_from_bytes(data: &[u8]) -> io::Result<Self> {
+        Self::read_png(Cursor::new(data))
+    }
+
+    fn read_png<R: BufRead + Seek>(reader: R) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(png_error)?;
+        let mut buffer = vec![0; reader.output_buffer_size().unwrap_or(0)];
+        let info = reader.next_frame(&mut buffer).map_err(png_error)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xff])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|g| [*g, *g, *g, 0xff]).collect(),
+            png::ColorType::Indexed => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Indexed PNG was not expanded.",
+                ))
+            }
+        };
+        Ok(RgbaImageData {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.write_png(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Copies the pixels into an `image` buffer, for use with the rest of
+    /// the `image` ecosystem.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> io::Result<image::RgbaImage> {
+        image::RgbaImage::from_raw(self.width, self.height, self.pixels.clone()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Pixel data does not match the image size.",
+            )
+        })
+    }
+
+    /// Saves the image as a PNG through the `image` crate.
+    #[cfg(feature = "image")]
+    pub fn to_png<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        self.to_image()?
+            .save_with_format(file_path, image::ImageFormat::Png)
+            .map_err(io::Error::other)
+    }
+
+    /// Whether the image has no pixels, with a width or height of 0.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Pixel at `x`, `y`, clamped to the image, which must not be empty.
+    fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let x = x.min(self.width - 1) as usize;
+        let y = y.min(self.height - 1) as usize;
+        let index = (y * self.width as usize + x) * 4;
+        [
+            self.pixels[index],
+            self.pixels[index + 1],
+            self.pixels[index + 2],
+            self.pixels[index + 3],
+        ]
+    }
+
+    /// Halves the image with a 2x2 box filter. An empty image is returned
+    /// unchanged.
+    pub fn downsample(&self) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let samples = [
+                    self.pixel(x * 2, y * 2),
+                    self.pixel(x * 2 + 1, y * 2),
+                    self.pixel(x * 2, y * 2 + 1),
+                    self.pixel(x * 2 + 1, y * 2 + 1),
+                ];
+                for channel in 0..4 {
+                    let sum: u32 = samples.iter().map(|s| u32::from(s[channel])).sum();
+                    pixels.push(((sum + 2) / 4) as u8);
+                }
+            }
+        }
+        RgbaImageData {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Scales the image down, keeping its aspect ratio, so neither side
+    /// exceeds `max_dim`. Each output pixel averages the source pixels it
+    /// covers. Images that already fit are returned unchanged.
+    pub fn resized_to_fit(&self, max_dim: u32) -> Self {
+        let max_dim = max_dim.max(1);
+        if self.width <= max_dim && self.height <= max_dim || self.is_empty() {
+            return self.clone();
+        }
+        let scale = f64::from(self.width.max(self.height)) / f64::from(max_dim);
+        let width = ((f64::from(self.width) / scale).round() as u32).clamp(1, max_dim);
+        let height = ((f64::from(self.height) / scale).round() as u32).clamp(1, max_dim);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let top = y * self.height / height;
+            let bottom = ((y + 1) * self.height / height).max(top + 1);
+            for x in 0..width {
+                let left = x * self.width / width;
+                let right = ((x + 1) * self.width / width).max(left + 1);
+                let mut sum = [0u64; 4];
+                for source_y in top..bottom {
+                    for source_x in left..right {
+                        let pixel = self.pixel(source_x, source_y);
+                        for channel in 0..4 {
+                            sum[channel] += u64::from(pixel[channel]);
+                        }
+                    }
+                }
+                let count = u64::from((bottom - top) * (right - left));
+                pixels.extend(sum.map(|total| ((total + count / 2) / count) as u8));
+            }
+        }
+        RgbaImageData {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Returns the full mip chain, starting with a copy of this image and
+    /// ending at 1x1. An empty image has no other level.
+    pub fn mip_chain(&self) -> Vec<RgbaImageData> {
+        let mut chain = vec![self.clone()];
+        while let Some(last) = chain.last() {
+            if last.is_empty() || last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            chain.push(next);
+        }
+        chain
+    }
+}
+
+fn png_error(error: png::DecodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+fn to_565(color: [u8; 4]) -> u16 {
+    (u16::from(color[0]) >> 3) << 11 | (u16::from(color[1]) >> 2) << 5 | u16::from(color[2]) >> 3
+}
+
+fn from_565(color: u16) -> [i32; 3] {
+    let r = i32::from((color >> 11) & 0x1f);
+    let g = i32::from((color >> 5) & 0x3f);
+    let b = i32::from(color & 0x1f);
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+fn encode_color_block(block: &[[u8; 4]; 16], writer: &mut Vec<u8>) {
+    let luma = |c: &[u8; 4]| u32::from(c[0]) * 2 + u32::from(c[1]) * 4 + u32::from(c[2]);
+    let max = *block.iter().max_by_key(|c| luma(c)).unwrap();
+    let min = *block.iter().min_by_key(|c| luma(c)).unwrap();
+    let mut color0 = to_565(max);
+    let mut color1 = to_565(min);
+    if color0 < color1 {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+
+    let mut indices = 0u32;
+    if color0 != color1 {
+        let c0 = from_565(color0);
+        let c1 = from_565(color1);
+        let palette = [
+            c0,
+            c1,
+            [0, 1, 2].map(|i| (2 * c0[i] + c1[i]) / 3),
+            [0, 1, 2].map(|i| (c0[i] + 2 * c1[i]) / 3),
+        ];
+        for (pixel_index, pixel) in block.iter().enumerate() {
+            let (best, _) = palette
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    let distance: i32 = (0..3)
+                        .map(|i| (entry[i] - i32::from(pixel[i])).pow(2))
+                        .sum();
+                    (index, distance)
+                })
+                .min_by_key(|(_, distance)| *distance)
+                .unwrap();
+            indices |= (best as u32) << (pixel_index * 2);
+        }
+    }
+
+    writer.write_u16::<LittleEndian>(color0).unwrap();
+    writer.write_u16::<LittleEndian>(color1).unwrap();
+    writer.write_u32::<LittleEndian>(indices).unwrap();
+}
+
+fn encode_alpha_block(block: &[[u8; 4]; 16], channel: usize, writer: &mut Vec<u8>) {
+    let alpha0 = block.iter().map(|c| c[channel]).max().unwrap();
+    let alpha1 = block.iter().map(|c| c[channel]).min().unwrap();
+    let mut palette = [i32::from(alpha0), i32::from(alpha1), 0, 0, 0, 0, 0, 0];
+    for i in 1..7 {
+        palette[i + 1] = ((7 - i as i32) * palette[0] + i as i32 * palette[1]) / 7;
+    }
+
+    let mut indices = 0u64;
+    if alpha0 != alpha1 {
+        for (pixel_index, pixel) in block.iter().enumerate() {
+            let best = palette
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, value)| (**value - i32::from(pixel[channel])).abs())
+                .map(|(index, _)| index)
+                .unwrap();
+            indices |= (best as u64) << (pixel_index * 3);
+        }
+    }
+
+    writer.push(alpha0);
+    writer.push(alpha1);
+    writer.extend_from_slice(&indices.to_le_bytes()[..6]);
+}
+
+fn encode_explicit_alpha_block(block: &[[u8; 4]; 16], writer: &mut Vec<u8>) {
+    let alpha = block
+        .iter()
+        .enumerate()
+        .fold(0u64, |alpha, (index, pixel)| {
+            alpha | (u64::from(pixel[3] >> 4) << (index * 4))
+        });
+    writer.extend_from_slice(&alpha.to_le_bytes());
+}
+
+/// Compresses a single image into BC1/BC2/BC3 (DXT1/DXT3/DXT5) or BC5 (3DCX)
+/// blocks. BC5 keeps only the red and green channels.
+pub fn encode_blocks(image: &RgbaImageData, format: BlockFormat) -> Vec<u8> {
+    let blocks_x = (image.width as usize).div_ceil(BLOCK_SIZE);
+    let blocks_y = (image.height as usize).div_ceil(BLOCK_SIZE);
+    let mut output = Vec::with_capacity(blocks_x * blocks_y * format.block_bytes());
+    for block_y in 0..blocks_y {
+        for block_x in 0..blocks_x {
+            let mut block = [[0u8; 4]; 16];
+            for (index, pixel) in block.iter_mut().enumerate() {
+                let x = (block_x * BLOCK_SIZE + index % BLOCK_SIZE) as u32;
+                let y = (block_y * BLOCK_SIZE + index / BLOCK_SIZE) as u32;
+                *pixel = image.pixel(x, y);
+            }
+            match format {
+                BlockFormat::Dxt1 => {}
+                BlockFormat::Dxt3 => encode_explicit_alpha_block(&block, &mut output),
+                BlockFormat::Dxt5 => encode_alpha_block(&block, 3, &mut output),
+                BlockFormat::Dxn => {
+                    encode_alpha_block(&block, 0, &mut output);
+                    encode_alpha_block(&block, 1, &mut output);
+                    continue;
+                }
+                BlockFormat::Dxta => {
+                    encode_alpha_block(&block, 0, &mut output);
+                    continue;
+                }
+            }
+            encode_color_block(&block, &mut output);
+        }
+    }
+    output
+}
+
+/// Builds a texture entry (`ATEX` family or `CTEX` identifier, which share
+/// the container) from `image`, regenerating the full mip chain and
+/// compressing every level to `format` blocks in the texture compression
+/// container, see `decode_texture_level`.
+pub fn encode_texture_entry(
+    image: &RgbaImageData,
+    identifier: &[u8; 4],
+    format: BlockFormat,
+) -> io::Result<Vec<u8>> {
+    if image.width == 0 || image.height == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Cannot encode an empty texture.",
+        ));
+    }
+    let width = u16::try_from(image.width)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Texture is too wide."))?;
+    let height = u16::try_from(image.height)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Texture is too tall."))?;
+
+    let levels: Vec<Vec<u8>> = image
+        .mip_chain()
+        .iter()
+        .map(|level| encode_blocks(level, format))
+        .collect();
+    Ok(deflate_texture(
+        identifier,
+        format.fourcc(),
+        width,
+        height,
+        &levels,
+    ))
+}
+
+pub fn encode_png_to_atex<P: AsRef<Path>>(
+    file_path: P,
+    format: BlockFormat,
+) -> io::Result<Vec<u8>> {
+    let image = RgbaImageData::load_png(file_path)?;
+    encode_texture_entry(&image, b"ATEX", format)
+}
+
+const TEXTURE_HEADER_SIZE: usize = 12;
+
+fn decode_color_block(block: &[u8], pixels: &mut [[u8; 4]; 16], has_alpha_mode: bool) {
+    let color0 = LittleEndian::read_u16(block);
+    let color1 = LittleEndian::read_u16(&block[2..]);
+    let indices = LittleEndian::read_u32(&block[4..]);
+    let c0 = from_565(color0);
+    let c1 = from_565(color1);
+    let palette: [[i32; 4]; 4] = if color0 > color1 || !has_alpha_mode {
+        [
+            [c0[0], c0[1], c0[2], 255],
+            [c1[0], c1[1], c1[2], 255],
+            [0, 1, 2, 3].map(|i| if i == 3 { 255 } else { (2 * c0[i] + c1[i]) / 3 }),
+            [0, 1, 2, 3].map(|i| if i == 3 { 255 } else { (c0[i] + 2 * c1[i]) / 3 }),
+        ]
+    } else {
+        [
+            [c0[0], c0[1], c0[2], 255],
+            [c1[0], c1[1], c1[2], 255],
+            [0, 1, 2, 3].map(|i| if i == 3 { 255 } else { (c0[i] + c1[i]) / 2 }),
+            [0, 0, 0, 0],
+        ]
+    };
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let color = palette[((indices >> (index * 2)) & 0x3) as usize];
+        *pixel = color.map(|channel| channel as u8);
+    }
+}
+
+fn decode_alpha_block(block: &[u8], channel: usize, pixels: &mut [[u8; 4]; 16]) {
+    let alpha0 = i32::from(block[0]);
+    let alpha1 = i32::from(block[1]);
+    let mut palette = [alpha0, alpha1, 0, 0, 0, 0, 0, 0];
+    if alpha0 > alpha1 {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i as i32) * alpha0 + i as i32 * alpha1) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i as i32) * alpha0 + i as i32 * alpha1) / 5;
+        }
+        palette[7] = 255;
+    }
+    let mut raw_indices = [0; 8];
+    raw_indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(raw_indices);
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        pixel[channel] = palette[((indices >> (index * 3)) & 0x7) as usize] as u8;
+    }
+}
+
+fn decode_explicit_alpha_block(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    let alpha = LittleEndian::read_u64(block);
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        pixel[3] = ((alpha >> (index * 4)) & 0xf) as u8 * 0x11;
+    }
+}
+
+/// Rebuilds the Z component of a unit normal from its X and Y components, all
+/// mapped from [-1, 1] to [0, 255].
+fn reconstruct_normal_z(x: u8, y: u8) -> u8 {
+    let x = f32::from(x) / 127.5 - 1.0;
+    let y = f32::from(y) / 127.5 - 1.0;
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    ((z + 1.0) * 127.5).round() as u8
+}
+
+/// Decompresses BC1/BC2/BC3 (DXT1/DXT3/DXT5) or BC5 (3DCX) block data of a
+/// single image. BC5 normal maps come out with Z reconstructed into blue.
+pub fn decode_blocks(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    format: BlockFormat,
+) -> io::Result<RgbaImageData> {
+    let blocks_x = (width as usize).div_ceil(BLOCK_SIZE);
+    let blocks_y = (height as usize).div_ceil(BLOCK_SIZE);
+    let block_bytes = format.block_bytes();
+    if data.len() < blocks_x * blocks_y * block_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Texture data is smaller than its dimensions require.",
+        ));
+    }
+
+    let mut image = RgbaImageData {
+        width,
+        height,
+        pixels: vec![0; width as usize * height as usize * 4],
+    };
+    for (block_index, block) in data
+        .chunks_exact(block_bytes)
+        .take(blocks_x * blocks_y)
+        .enumerate()
+    {
+        let mut pixels = [[0u8; 4]; 16];
+        match format {
+            BlockFormat::Dxt1 => decode_color_block(block, &mut pixels, true),
+            BlockFormat::Dxt3 => {
+                decode_color_block(&block[8..], &mut pixels, false);
+                decode_explicit_alpha_block(block, &mut pixels);
+            }
+            BlockFormat::Dxt5 => {
+                decode_color_block(&block[8..], &mut pixels, false);
+                decode_alpha_block(block, 3, &mut pixels);
+            }
+            BlockFormat::Dxn => {
+                decode_alpha_block(block, 0, &mut pixels);
+                decode_alpha_block(&block[8..], 1, &mut pixels);
+                for pixel in pixels.iter_mut() {
+                    pixel[2] = reconstruct_normal_z(pixel[0], pixel[1]);
+                    pixel[3] = 255;
+                }
+            }
+            BlockFormat::Dxta => {
+                decode_alpha_block(block, 0, &mut pixels);
+                for pixel in pixels.iter_mut() {
+                    *pixel = [pixel[0], pixel[0], pixel[0], 255];
+                }
+            }
+        }
+
+        let block_x = block_index % blocks_x * BLOCK_SIZE;
+        let block_y = block_index / blocks_x * BLOCK_SIZE;
+        for (index, pixel) in pixels.iter().enumerate() {
+            let x = block_x + index % BLOCK_SIZE;
+            let y = block_y + index / BLOCK_SIZE;
+            if x < width as usize && y < height as usize {
+                let offset = (y * width as usize + x) * 4;
+                image.pixels[offset..offset + 4].copy_from_slice(pixel);
+            }
+        }
+    }
+    Ok(image)
+}
+
+pub fn read_texture_header(data: &[u8]) -> io::Result<AnetAtexHeader> {
+    if data.len() < TEXTURE_HEADER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Texture is too small to hold its header.",
+        ));
+    }
+    let mut header = AnetAtexHeader::default();
+    header.identifier.copy_from_slice(&data[..4]);
+    header.identifier_integer = LittleEndian::read_u32(data);
+    header.format_integer = LittleEndian::read_u32(&data[4..]);
+    header.format = data[4];
+    header.width = LittleEndian::read_u16(&data[8..]);
+    header.height = LittleEndian::read_u16(&data[10..]);
+    Ok(header)
+}
+
+/// Decodes the top mip level of a texture entry (`ATEX` family or `CTEX`),
+/// undoing its texture compression first, see `inflate_texture`.
+pub fn decode_texture_entry(data: &[u8]) -> io::Result<RgbaImageData> {
+    let (format, width, height) = texture_format(data)?;
+    decode_blocks(&inflate_texture(data)?, width, height, format)
+}
+
+/// Decodes mip level `level` of a texture entry, 0 being the top one. The
+/// lower levels are read as `encode_texture_entry` writes them.
+pub fn decode_texture_level(data: &[u8], level: u32) -> io::Result<RgbaImageData> {
+    let (format, width, height) = texture_format(data)?;
+    let width = width.checked_shr(level).unwrap_or(0).max(1);
+    let height = height.checked_shr(level).unwrap_or(0).max(1);
+    decode_blocks(&inflate_texture_level(data, level)?, width, height, format)
+}
+
+/// Block format and size of the top level of a texture entry.
+fn texture_format(data: &[u8]) -> io::Result<(BlockFormat, u32, u32)> {
+    let header = read_texture_header(data)?;
+    let format_bytes = header.format_integer.to_le_bytes();
+    let format = BlockFormat::from_fourcc(&format_bytes).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Unsupported texture format {}.",
+                String::from_utf8_lossy(&format_bytes)
+            ),
+        )
+    })?;
+    Ok((format, u32::from(header.width), u32::from(header.height)))
+}
+
+impl AnetArchive {
+    /// Replaces the texture entry at `index` with the PNG at `file_path`,
+    /// see `encode_texture_entry`. The entry keeps its identifier and, unless
+    /// `format` is given, its block format; it is compressed if it was.
+    pub fn patch_texture_from_png<P: AsRef<Path>>(
+        &mut self,
+        index: usize,
+        file_path: P,
+        format: Option<BlockFormat>,
+    ) -> io::Result<()> {
+        let current = self.read_entry(index)?;
+        let (current_format, _, _) = texture_format(&current)?;
+        let identifier = read_texture_header(&current)?.identifier;
+        let image = RgbaImageData::load_png(file_path)?;
+        let entry = encode_texture_entry(&image, &identifier, format.unwrap_or(current_format))?;
+        let compress = self.mft_data[index].is_compressed();
+        self.patch_entry(index, &entry, compress)
+    }
+}
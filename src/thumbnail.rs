@@ -0,0 +1,32 @@
+use std::io;
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+use crate::texture::{decode_texture_entry, RgbaImageData};
+
+/// Decodes an entry holding a picture: an ANet texture, of which the top
+/// mip level is used, or an embedded PNG, of which the first frame is.
+pub fn decode_image(data: &[u8]) -> io::Result<RgbaImageData> {
+    match AnetFileType::identify(data) {
+        AnetFileType::AnftAtex
+        | AnetFileType::AnftAttx
+        | AnetFileType::AnftAtec
+        | AnetFileType::AnftAtep
+        | AnetFileType::AnftAteu
+        | AnetFileType::AnftAtet
+        | AnetFileType::AnftCtex => decode_texture_entry(data),
+        AnetFileType::AnftPng => RgbaImageData::load_png_from_bytes(data),
+        file_type => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Cannot decode {:?} as an image.", file_type),
+        )),
+    }
+}
+
+impl AnetArchive {
+    /// Decodes the picture stored as `file_id` and scales it down so that
+    /// neither side exceeds `max_dim`, for galleries and previews.
+    pub fn thumbnail(&self, file_id: u32, max_dim: u32) -> io::Result<RgbaImageData> {
+        let image = decode_image(&self.read_file(file_id)?)?;
+        Ok(image.resized_to_fit(max_dim))
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+use crate::composite::AnetComposite;
+use crate::material::{AnetMaterial, AnetModelMaterial};
+use crate::names::csv_fields;
+use crate::progress::{Progress, ProgressStage};
+
+/// Which kind of field a token was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TokenSource {
+    MaterialTexture,      //< Texture slot sampled by an `AMAT` material.
+    ModelMaterialTexture, //< Texture slot of a model material.
+    ModelMaterialConstant,
+    CompositePart, //< Name token of a `cmpc` part.
+}
+
+/// An entry a token was found in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenOwner {
+    pub index: usize,
+    pub file_id: u32, //< Base ID of the entry.
+    pub source: TokenSource,
+    pub position: usize, //< Index of the slot, constant or part holding the token.
+}
+
+/// Tokens seen while parsing, with the entries they were found in and,
+/// where a dictionary names them, their names.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct TokenRegistry {
+    pub owners: HashMap<u64, Vec<TokenOwner>>,
+    pub names: HashMap<u64, String>,
+}
+
+/// Parses a token written in decimal or as `0x` hex.
+pub fn parse_token(text: &str) -> io::Result<u64> {
+    let text = text.trim();
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid token '{}'.", text),
+        )
+    })
+}
+
+impl TokenRegistry {
+    pub fn record(&mut self, token: u64, owner: TokenOwner) {
+        self.owners.entry(token).or_default().push(owner);
+    }
+
+    pub fn add_material(&mut self, index: usize, file_id: u32, material: &AnetMaterial) {
+        for (position, token) in material.texture_tokens.iter().enumerate() {
+            self.record(
+                *token,
+                TokenOwner {
+                    index,
+                    file_id,
+                    source: TokenSource::MaterialTexture,
+                    position,
+                },
+            );
+        }
+    }
+
+    pub fn add_model_material(&mut self, index: usize, file_id: u32, material: &AnetModelMaterial) {
+        for (position, texture) in material.textures.iter().enumerate() {
+            self.record(
+                u64::from(texture.token),
+                TokenOwner {
+                    index,
+                    file_id,
+                    source: TokenSource::ModelMaterialTexture,
+                    position,
+                },
+            );
+        }
+        for (position, constant) in material.constants.iter().enumerate() {
+            self.record(
+                u64::from(constant.token),
+                TokenOwner {
+                    index,
+                    file_id,
+                    source: TokenSource::ModelMaterialConstant,
+                    position,
+                },
+            );
+        }
+    }
+
+    pub fn add_composite(&mut self, index: usize, file_id: u32, composite: &AnetComposite) {
+        for (position, part) in composite.parts.iter().enumerate() {
+            self.record(
+                part.token,
+                TokenOwner {
+                    index,
+                    file_id,
+                    source: TokenSource::CompositePart,
+                    position,
+                },
+            );
+        }
+    }
+
+    /// Entries the token was found in, in the order they were recorded.
+    pub fn owners_of(&self, token: u64) -> &[TokenOwner] {
+        self.owners.get(&token).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn name_of(&self, token: u64) -> Option<&str> {
+        self.names.get(&token).map(String::as_str)
+    }
+
+    /// Every token seen, ascending.
+    pub fn tokens(&self) -> Vec<u64> {
+        let mut tokens: Vec<u64> = self.owners.keys().copied().collect();
+        tokens.sort_unstable();
+        tokens
+    }
+
+    /// Loads a token dictionary. A `.json` file holds an object mapping
+    /// tokens to names; any other file is CSV with the token in the first
+    /// column and the name in the second. Tokens are decimal or `0x` hex.
+    /// A header row and lines starting with `#` are skipped.
+    pub fn load_names<P: AsRef<Path>>(&mut self, file_path: P) -> io::Result<()> {
+        let text = fs::read_to_string(&file_path)?;
+        let is_json = file_path
+            .as_ref()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        if is_json {
+            let names: HashMap<String, String> = serde_json::from_str(&text)?;
+            for (token, name) in names {
+                self.names.insert(parse_token(&token)?, name);
+            }
+            return Ok(());
+        }
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = csv_fields(line).into_iter();
+            let token = match parse_token(&fields.next().unwrap_or_default()) {
+                Ok(token) => token,
+                Err(_) if number == 0 => continue,
+                Err(error) => return Err(error),
+            };
+            self.names.insert(token, fields.next().unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    /// Tokens seen that the dictionary has no name for, ascending.
+    pub fn unnamed_tokens(&self) -> Vec<u64> {
+        self.tokens()
+            .into_iter()
+            .filter(|token| !self.names.contains_key(token))
+            .collect()
+    }
+}
+
+impl AnetArchive {
+    /// Collects the tokens of every `AMAT` material and `cmpc` composite.
+    pub fn collect_tokens(&self, progress: &mut dyn FnMut(Progress)) -> io::Result<TokenRegistry> {
+        let mut registry = TokenRegistry::default();
+        let materials = self.find_by_type(AnetFileType::AnftMaterial)?;
+        let composites = self.find_by_type(AnetFileType::AnftComposite)?;
+        let entries: Vec<(usize, bool)> = materials
+            .iter()
+            .map(|entry| (entry.index(), true))
+            .chain(composites.iter().map(|entry| (entry.index(), false)))
+            .collect();
+        let total = entries.len();
+        for (done, &(index, is_material)) in entries.iter().enumerate() {
+            progress(Progress {
+                stage: ProgressStage::Scan,
+                done,
+                total,
+            });
+            let file_id = self
+                .mft_index_data
+                .get(index)
+                .map_or(0, |id_entry| id_entry.base_id);
+            let Some(data) = self.tolerate(Some(index), self.read_entry(index))? else {
+                continue;
+            };
+            if is_material {
+                let material = AnetMaterial::load_from_bytes(&data);
+                if let Some(material) = self.tolerate(Some(index), material)? {
+                    registry.add_material(index, file_id, &material);
+                }
+            } else {
+                let composite = AnetComposite::load_from_bytes(&data);
+                if let Some(composite) = self.tolerate(Some(index), composite)? {
+                    registry.add_composite(index, file_id, &composite);
+                }
+            }
+        }
+        progress(Progress {
+            stage: ProgressStage::Scan,
+            done: total,
+            total,
+        });
+        Ok(registry)
+    }
+}
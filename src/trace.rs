@@ -0,0 +1,93 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A single read performed by a parser. `offset` is relative to the data the
+/// reader was created over, which for PF parsers is the chunk data named by
+/// `scope`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ReadRecord {
+    pub scope: String,
+    pub offset: usize,
+    pub size: usize,
+    pub field: String,
+    pub value: String,
+}
+
+/// Every read recorded while tracing was active, in the order they happened.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct ReadTrace {
+    pub records: Vec<ReadRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, //< Error the traced parser stopped with, if any.
+}
+
+thread_local! {
+    static ACTIVE_TRACE: RefCell<Option<ReadTrace>> = const { RefCell::new(None) };
+    static SCOPES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Pops the scope pushed by [`scope`] when dropped.
+pub struct ScopeGuard {
+    _private: (),
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        SCOPES.with(|scopes| scopes.borrow_mut().pop());
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ACTIVE_TRACE.with(|trace| trace.borrow().is_some())
+}
+
+/// Names the data subsequent reads on this thread are performed on, until the
+/// returned guard is dropped. Scopes nest and are joined with `/`.
+pub fn scope(name: &str) -> ScopeGuard {
+    SCOPES.with(|scopes| scopes.borrow_mut().push(name.to_string()));
+    ScopeGuard { _private: () }
+}
+
+/// Records a read if tracing is enabled on this thread. `value` is only
+/// formatted when the record is kept.
+pub fn record(offset: usize, size: usize, field: &str, value: impl FnOnce() -> String) {
+    ACTIVE_TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            trace.records.push(ReadRecord {
+                scope: SCOPES.with(|scopes| scopes.borrow().join("/")),
+                offset,
+                size,
+                field: field.to_string(),
+                value: value(),
+            });
+        }
+    });
+}
+
+/// Runs `parse` with tracing enabled on the current thread and returns its
+/// result together with every read it performed.
+pub fn trace<T>(parse: impl FnOnce() -> io::Result<T>) -> (io::Result<T>, ReadTrace) {
+    let previous = ACTIVE_TRACE.with(|trace| trace.replace(Some(ReadTrace::default())));
+    let result = parse();
+    let mut read_trace = ACTIVE_TRACE
+        .with(|trace| trace.replace(previous))
+        .unwrap_or_default();
+    if let Err(error) = &result {
+        read_trace.error = Some(error.to_string());
+    }
+    (result, read_trace)
+}
+
+impl ReadTrace {
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::{AnetArchive, AnetFileType};
+
+const BINK2_MAGIC: &[u8; 3] = b"KB2";
+const BINK2_HEADER_SIZE: usize = 44;
+// The size field counts the bytes following the magic and itself.
+const BINK2_SIZE_FIELD_BIAS: usize = 8;
+// Some entries carry a small wrapper in front of the video.
+const BINK2_SEARCH_LIMIT: usize = 0x100;
+const BINK2_EXTENSION: &str = "bk2";
+
+/// The fixed header at the start of a Bink 2 file.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnetBink2Header {
+    pub revision: u8,   //< Letter following `KB2`, e.g. `g`.
+    pub file_size: u32, //< Size of the whole file, magic included.
+    pub frame_count: u32,
+    pub largest_frame_size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate_dividend: u32,
+    pub frame_rate_divider: u32,
+    pub flags: u32,
+    pub audio_track_count: u32,
+}
+
+/// A Bink 2 video found in an entry, cut out of any wrapper around it.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AnetBink2Video {
+    pub header: AnetBink2Header,
+    pub offset: usize, //< Wrapper bytes skipped in front of the video.
+    #[serde(skip)]
+    pub data: Vec<u8>, //< The standalone `.bk2` file.
+}
+
+/// Offset of the Bink 2 magic in the first bytes of `data`.
+pub fn find_bink2_start(data: &[u8]) -> Option<usize> {
+    data[..data.len().min(BINK2_SEARCH_LIMIT + BINK2_MAGIC.len())]
+        .windows(BINK2_MAGIC.len())
+        .position(|window| window == BINK2_MAGIC)
+}
+
+impl AnetBink2Header {
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        if data.len() < BINK2_HEADER_SIZE || !data.starts_with(BINK2_MAGIC) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a Bink 2 video.",
+            ));
+        }
+        Ok(AnetBink2Header {
+            revision: data[3],
+            file_size: LittleEndian::read_u32(&data[4..])
+                .saturating_add(BINK2_SIZE_FIELD_BIAS as u32),
+            frame_count: LittleEndian::read_u32(&data[8..]),
+            largest_frame_size: LittleEndian::read_u32(&data[12..]),
+            width: LittleEndian::read_u32(&data[20..]),
+            height: LittleEndian::read_u32(&data[24..]),
+            frame_rate_dividend: LittleEndian::read_u32(&data[28..]),
+            frame_rate_divider: LittleEndian::read_u32(&data[32..]),
+            flags: LittleEndian::read_u32(&data[36..]),
+            audio_track_count: LittleEndian::read_u32(&data[40..]),
+        })
+    }
+
+    /// Frames per second, `None` when the header leaves it undefined.
+    pub fn frame_rate(&self) -> Option<f64> {
+        if self.frame_rate_dividend == 0 || self.frame_rate_divider == 0 {
+            return None;
+        }
+        Some(f64::from(self.frame_rate_dividend) / f64::from(self.frame_rate_divider))
+    }
+
+    /// Length of the video in seconds.
+    pub fn duration(&self) -> Option<f64> {
+        self.frame_rate()
+            .map(|frame_rate| f64::from(self.frame_count) / frame_rate)
+    }
+}
+
+impl AnetBink2Video {
+    /// Finds the video in decompressed entry `data` and cuts it to the size
+    /// its header gives. Trailing padding is dropped; a video shorter than
+    /// its header claims is kept as is.
+    pub fn load_from_bytes(data: &[u8]) -> io::Result<Self> {
+        let offset = find_bink2_start(data)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "No Bink 2 video found."))?;
+        let header = AnetBink2Header::load_from_bytes(&data[offset..])?;
+        let end = data
+            .len()
+            .min(offset.saturating_add(header.file_size as usize));
+        if end < offset + header.file_size as usize {
+            log_warn!(
+                offset,
+                claimed = header.file_size,
+                present = end - offset,
+                "Bink 2 video is shorter than its header claims."
+            );
+        }
+        Ok(AnetBink2Video {
+            header,
+            offset,
+            data: data[offset..end].to_vec(),
+        })
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, file_path: P) -> io::Result<()> {
+        fs::write(file_path, &self.data)
+    }
+}
+
+impl AnetArchive {
+    /// Reads the Bink 2 video with the given file ID.
+    pub fn read_bink2(&self, file_id: u32) -> io::Result<AnetBink2Video> {
+        AnetBink2Video::load_from_bytes(&self.read_file(file_id)?)
+    }
+
+    /// Writes every Bink 2 entry to `directory` as a standalone `.bk2` file
+    /// named after its base ID and returns the written paths.
+    pub fn extract_bink2_videos<P: AsRef<Path>>(&self, directory: P) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(&directory)?;
+        let mut written = Vec::new();
+        for entry in self.find_by_type(AnetFileType::AnftBink2video)? {
+            let video = AnetBink2Video::load_from_bytes(&self.read_entry(entry.index())?)?;
+            let file_path = directory.as_ref().join(
+                Path::new(&self.entry_file_name(entry.index())).with_extension(BINK2_EXTENSION),
+            );
+            video.save_to_file(&file_path)?;
+            written.push(file_path);
+        }
+        Ok(written)
+    }
+}
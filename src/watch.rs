@@ -0,0 +1,109 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anet_archive::AnetArchive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatchEventKind {
+    Added,   //< The slot was unused, or did not exist, before.
+    Updated, //< The slot was rewritten in place or moved.
+    Removed, //< The slot is no longer in use.
+}
+
+/// A change to one MFT entry between two reads of the archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchEvent {
+    pub kind: WatchEventKind,
+    pub index: usize,
+    pub file_id: u32, //< Base ID of the entry, 0 when it has none.
+    pub counter: u32,
+    pub size: u32,
+}
+
+/// Lists the entries of `current` that were added, rewritten or dropped
+/// since `previous`, in MFT order.
+pub fn diff_mft(previous: &AnetArchive, current: &AnetArchive) -> Vec<WatchEvent> {
+    let mut events = Vec::new();
+    for (index, entry) in current.mft_data.iter().enumerate() {
+        let before = previous
+            .mft_data
+            .get(index)
+            .filter(|before| before.is_in_use());
+        let kind = match (before, entry.is_in_use()) {
+            (None, true) => WatchEventKind::Added,
+            (Some(before), true) if before != entry => WatchEventKind::Updated,
+            (Some(_), false) => WatchEventKind::Removed,
+            _ => continue,
+        };
+        events.push(WatchEvent {
+            kind,
+            index,
+            file_id: current
+                .mft_index_data
+                .get(index)
+                .map_or(0, |id_entry| id_entry.base_id),
+            counter: entry.counter,
+            size: entry.size,
+        });
+    }
+    events
+}
+
+/// Follows an archive on disk that another process, such as the game
+/// patcher, is writing to. Only the header, MFT and ID table are read again,
+/// and only when the file has changed.
+pub struct ArchiveWatcher {
+    file_path: PathBuf,
+    snapshot: AnetArchive,
+    stamp: (Option<SystemTime>, u64), //< Modification time and length at the last read.
+}
+
+impl ArchiveWatcher {
+    pub fn new<P: AsRef<Path>>(file_path: P) -> io::Result<Self> {
+        let file_path = file_path.as_ref().to_path_buf();
+        let stamp = file_stamp(&file_path)?;
+        let snapshot = AnetArchive::load_from_file(&file_path)?;
+        Ok(ArchiveWatcher {
+            file_path,
+            snapshot,
+            stamp,
+        })
+    }
+
+    /// The archive as of the last successful read.
+    pub fn snapshot(&self) -> &AnetArchive {
+        &self.snapshot
+    }
+
+    /// Reads the archive again if it changed on disk and returns what
+    /// changed since the last read. An archive caught halfway through a
+    /// write may not parse; that read is dropped and the next poll retries,
+    /// so the events are never built from a torn MFT.
+    pub fn poll(&mut self) -> io::Result<Vec<WatchEvent>> {
+        let stamp = file_stamp(&self.file_path)?;
+        if stamp == self.stamp {
+            return Ok(Vec::new());
+        }
+        let current = match AnetArchive::load_from_file(&self.file_path) {
+            Ok(current) => current,
+            Err(_error) => {
+                log_debug!(error = %_error, "Archive changed but did not parse, retrying.");
+                return Ok(Vec::new());
+            }
+        };
+        let events = diff_mft(&self.snapshot, &current);
+        self.snapshot = current;
+        self.stamp = stamp;
+        Ok(events)
+    }
+}
+
+fn file_stamp(file_path: &Path) -> io::Result<(Option<SystemTime>, u64)> {
+    let metadata = fs::metadata(file_path)?;
+    Ok((metadata.modified().ok(), metadata.len()))
+}
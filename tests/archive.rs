@@ -0,0 +1,254 @@
+use std::{
+    env, fs,
+    io::Cursor,
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+
+use leafadoo::anet_archive::AnetArchive;
+use leafadoo::builder::ArchiveBuilder;
+use leafadoo::deflate::anet_deflate;
+use leafadoo::inflate::anet_inflate;
+use leafadoo::layout::render_layout_svg;
+use leafadoo::names::EntryNames;
+use leafadoo::prefetch::PrefetchOptions;
+use leafadoo::retry::is_concurrent_modification;
+
+// Offset past the end of any file, close enough to `u64::MAX` that adding
+// the size of any entry of 2 bytes or more overflows.
+const CORRUPT_OFFSET: u64 = u64::MAX - 1;
+
+/// Bytes of an archive holding `files`.
+fn build_archive_bytes(compress: bool, files: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut builder = ArchiveBuilder::new();
+    builder.compress(compress);
+    for (file_id, data) in files {
+        builder.add_file(*file_id, *data);
+    }
+    builder.to_bytes().unwrap()
+}
+
+/// An archive holding `files`, loaded back from memory.
+fn build_archive(compress: bool, files: &[(u32, &[u8])]) -> AnetArchive {
+    AnetArchive::load_from_bytes(&build_archive_bytes(compress, files)).unwrap()
+}
+
+/// Bytes of an archive holding `files`, with the entry of `file_id` moved
+/// to `offset`, and the MFT index of that entry.
+fn build_corrupt_archive(files: &[(u32, &[u8])], file_id: u32, offset: u64) -> (Vec<u8>, usize) {
+    let mut bytes = build_archive_bytes(false, files);
+    let index = AnetArchive::load_from_bytes(&bytes)
+        .unwrap()
+        .index_of(file_id)
+        .unwrap();
+    let mft = bytes
+        .windows(4)
+        .position(|window| window == b"Mft\x1a")
+        .unwrap();
+    let entry = mft + 24 + index * 24;
+    bytes[entry..entry + 8].copy_from_slice(&offset.to_le_bytes());
+    (bytes, index)
+}
+
+/// Text-like data that compresses, `length` bytes long.
+fn sample_data(seed: u32, length: usize) -> Vec<u8> {
+    let words: [&[u8]; 5] = [b"texture ", b"model ", b"sound ", b"PF", b"\0\0\x01\0"];
+    let mut state = seed.wrapping_mul(2654435761).max(1);
+    let mut data = Vec::with_capacity(length);
+    while data.len() < length {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        data.extend_from_slice(words[state as usize % words.len()]);
+    }
+    data.truncate(length);
+    data
+}
+
+/// `length` bytes that barely compress.
+fn noise(seed: u32, length: usize) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(2654435761).max(1);
+    (0..length)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+/// Writes `bytes` to a file of the temporary directory unique to `name`.
+fn temp_archive(name: &str, bytes: &[u8]) -> PathBuf {
+    let file_path = env::temp_dir().join(format!("leafadoo-test-{}-{}.dat", process::id(), name));
+    fs::write(&file_path, bytes).unwrap();
+    file_path
+}
+
+#[test]
+fn built_archives_read_back() {
+    let large = sample_data(1, 0x30000);
+    let files: [(u32, &[u8]); 3] = [(100, b"short"), (101, &large), (205, b"\0\x01\x02\x03")];
+    for compress in [false, true] {
+        let bytes = build_archive_bytes(compress, &files);
+        let archive = AnetArchive::load_from_bytes(&bytes).unwrap();
+        let mut reader = Cursor::new(&bytes);
+        for (file_id, data) in files {
+            let index = archive.index_of(file_id).unwrap();
+            assert_eq!(archive.mft_data[index].is_compressed(), compress);
+            assert_eq!(archive.read_entry_from(&mut reader, index).unwrap(), data);
+        }
+        assert!(archive.index_of(102).is_none());
+    }
+}
+
+#[test]
+fn deflated_data_inflates_back() {
+    // The noise spans several CRC intervals of the compressed stream.
+    for data in [
+        b"a".to_vec(),
+        vec![0; 0x10000],
+        sample_data(2, 0x12345),
+        noise(3, 0x50000),
+    ] {
+        let compressed = anet_deflate(&data);
+        assert_eq!(anet_inflate(&compressed, None).unwrap(), data);
+        let expected_size = Some(data.len() as u32);
+        assert_eq!(anet_inflate(&compressed, expected_size).unwrap(), data);
+    }
+}
+
+#[test]
+fn patched_entries_read_back() {
+    let bytes = build_archive_bytes(false, &[(100, b"aaaa"), (101, b"bbbbbbbb")]);
+    let file_path = temp_archive("patch", &bytes);
+    let mut archive = AnetArchive::load_from_file(&file_path).unwrap();
+    let first = archive.index_of(100).unwrap();
+    let second = archive.index_of(101).unwrap();
+    let grown = sample_data(4, 0x2000);
+    archive.patch_entry(first, &grown, true).unwrap();
+    archive.patch_entry(second, b"bb", false).unwrap();
+    assert!(archive.patch_entry(0, b"header", false).is_err());
+    assert_eq!(archive.read_entry(first).unwrap(), grown);
+
+    let archive = AnetArchive::load_from_file(&file_path).unwrap();
+    assert!(archive.mft_data[first].is_compressed());
+    assert_eq!(archive.read_file(100).unwrap(), grown);
+    assert_eq!(archive.read_file(101).unwrap(), b"bb");
+    fs::remove_file(file_path).unwrap();
+}
+
+#[test]
+fn patches_leave_overlapping_entries_alone() {
+    let files: [(u32, &[u8]); 2] = [(100, b"aaaabbbb"), (101, b"cccc")];
+    let archive = build_archive(false, &files);
+    let offset = archive.mft_data[archive.index_of(100).unwrap()].offset;
+    // The second entry starts in the middle of the first one.
+    let (bytes, _) = build_corrupt_archive(&files, 101, offset + 4);
+    let file_path = temp_archive("patch-overlap", &bytes);
+    let mut archive = AnetArchive::load_from_file(&file_path).unwrap();
+    let index = archive.index_of(100).unwrap();
+    archive.patch_entry(index, b"dddddd", false).unwrap();
+    assert_ne!(archive.mft_data[index].offset, offset);
+
+    let archive = AnetArchive::load_from_file(&file_path).unwrap();
+    assert_eq!(archive.read_file(100).unwrap(), b"dddddd");
+    assert_eq!(archive.read_file(101).unwrap(), b"bbbb");
+    fs::remove_file(file_path).unwrap();
+}
+
+#[test]
+fn compacted_archives_keep_their_entries() {
+    let files: [(u32, &[u8]); 3] = [(100, b"aaaa"), (101, b"bbbbbbbb"), (102, b"cc")];
+    let file_path = temp_archive("compact-source", &build_archive_bytes(true, &files));
+    let output_path = temp_archive("compact-output", b"");
+    let mut archive = AnetArchive::load_from_file(&file_path).unwrap();
+    // Growing an entry moves it to the end and leaves its old data behind.
+    let grown = sample_data(5, 0x1000);
+    archive
+        .patch_entry(archive.index_of(101).unwrap(), &grown, false)
+        .unwrap();
+    assert!(archive.compact(&file_path).is_err());
+
+    let report = archive.compact(&output_path).unwrap();
+    assert_eq!(
+        report.compacted_size,
+        fs::metadata(&output_path).unwrap().len()
+    );
+    assert!(report.compacted_size < report.original_size);
+    let compacted = AnetArchive::load_from_file(&output_path).unwrap();
+    assert_eq!(compacted.read_file(100).unwrap(), b"aaaa");
+    assert_eq!(compacted.read_file(101).unwrap(), grown);
+    assert_eq!(compacted.read_file(102).unwrap(), b"cc");
+    fs::remove_file(file_path).unwrap();
+    fs::remove_file(output_path).unwrap();
+}
+
+#[test]
+fn entries_past_the_end_of_the_address_space_fail_alone() {
+    let files: [(u32, &[u8]); 3] = [(100, b"aaaa"), (101, b"bbbb"), (102, b"cccc")];
+    let (bytes, corrupt) = build_corrupt_archive(&files, 101, CORRUPT_OFFSET);
+    let file_path = temp_archive("overflow", &bytes);
+    let archive = AnetArchive::load_from_file(&file_path).unwrap();
+    let indices: Vec<usize> = (0..archive.mft_data.len()).collect();
+
+    let spans = archive.plan_reads(&indices, &PrefetchOptions::default());
+    let span = spans
+        .iter()
+        .find(|span| span.indices.contains(&corrupt))
+        .unwrap();
+    assert_eq!(span.indices, [corrupt]);
+    let mut failed = Vec::new();
+    archive
+        .prefetch_entries(
+            &indices,
+            &PrefetchOptions::default(),
+            &mut |index, result| {
+                if result.is_err() {
+                    failed.push(index);
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert_eq!(failed, [corrupt]);
+
+    let report = archive.layout_report(bytes.len() as u64);
+    assert_eq!(report.out_of_bounds, [corrupt]);
+    let mut svg = Vec::new();
+    render_layout_svg(&archive, bytes.len() as u64, &mut svg).unwrap();
+    assert!(svg.ends_with(b"</svg>\n"));
+
+    assert!(archive
+        .read_entry_chunks(corrupt, &mut |_chunk| Ok(()))
+        .is_err());
+    assert!(archive.read_entry(corrupt).is_err());
+    assert_eq!(archive.read_file(102).unwrap(), b"cccc");
+    fs::remove_file(file_path).unwrap();
+}
+
+#[test]
+fn shared_names_get_the_base_id_appended() {
+    let mut archive = build_archive(false, &[(100, b"aaa"), (101, b"bbb"), (102, b"ccc")]);
+    let names = EntryNames::from_csv("100,Texture\n101,texture\n102,solo\n").unwrap();
+    archive.set_entry_names(names);
+    let name_of = |file_id| archive.entry_file_name(archive.index_of(file_id).unwrap());
+    assert_eq!(name_of(100), "Texture_100.bin");
+    assert_eq!(name_of(101), "texture_101.bin");
+    assert_eq!(name_of(102), "solo.bin");
+}
+
+#[test]
+fn retries_wait_longer_each_time() {
+    let (bytes, index) =
+        build_corrupt_archive(&[(100, b"aaa"), (101, b"bbb")], 100, CORRUPT_OFFSET);
+    let file_path = temp_archive("retry", &bytes);
+    let mut archive = AnetArchive::load_from_file(&file_path).unwrap();
+    archive.set_retry_delay(Duration::from_millis(20));
+    let start = Instant::now();
+    let error = archive.read_entry_with_retry(index, 3).unwrap_err();
+    assert!(is_concurrent_modification(&error));
+    assert!(start.elapsed() >= Duration::from_millis(20 + 40 + 80));
+    fs::remove_file(file_path).unwrap();
+}
@@ -0,0 +1,38 @@
+use std::{env, fs, process};
+
+use leafadoo::anet_archive::AnetArchive;
+use leafadoo::builder::ArchiveBuilder;
+use leafadoo::pipeline::{PipelineConfig, PipelineDecode, PipelineFormat, PipelineTransform};
+use leafadoo::texture::{encode_texture_entry, BlockFormat, RgbaImageData};
+
+#[test]
+fn empty_textures_are_skipped_before_transforms() {
+    let image = RgbaImageData {
+        width: 8,
+        height: 8,
+        pixels: vec![0x80; 8 * 8 * 4],
+    };
+    let texture = encode_texture_entry(&image, b"ATEX", BlockFormat::Dxt5).unwrap();
+    let mut empty = texture.clone();
+    empty[8..10].copy_from_slice(&0u16.to_le_bytes());
+    let mut builder = ArchiveBuilder::new();
+    builder.add_file(100, texture).add_file(101, empty);
+    let name = format!("leafadoo-test-{}-pipeline", process::id());
+    let archive_path = env::temp_dir().join(format!("{}.dat", name));
+    builder.save_to_file(&archive_path).unwrap();
+    let archive = AnetArchive::load_from_file(&archive_path).unwrap();
+
+    let mut config = PipelineConfig {
+        decode: PipelineDecode::Texture,
+        transforms: vec![PipelineTransform::FitWithin { max_size: 2 }],
+        ..Default::default()
+    };
+    config.select.file_ids = vec![100, 101];
+    config.output.format = PipelineFormat::Png;
+    config.output.directory = env::temp_dir().join(&name);
+    let report = config.run(&archive).unwrap();
+    assert_eq!(report.written.len(), 1);
+    assert_eq!(report.skipped, 1);
+    fs::remove_dir_all(&config.output.directory).unwrap();
+    fs::remove_file(archive_path).unwrap();
+}
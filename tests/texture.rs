@@ -0,0 +1,220 @@
+use std::{env, fs, process};
+
+use leafadoo::anet_archive::AnetArchive;
+use leafadoo::builder::ArchiveBuilder;
+use leafadoo::inflate::inflate_texture;
+use leafadoo::texture::{
+    decode_texture_entry, decode_texture_level, encode_texture_entry, BlockFormat, RgbaImageData,
+};
+
+/// A texture entry: header, data size, compression `flags`, then `words`
+/// (the pass bit stream and the stored components).
+fn texture_entry(format: &[u8; 4], width: u16, height: u16, flags: u32, words: &[u32]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(b"ATEX");
+    data.extend_from_slice(format);
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&flags.to_le_bytes());
+    for word in words {
+        data.extend_from_slice(&word.to_le_bytes());
+    }
+    data
+}
+
+fn pixel(data: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+    let offset = (y * width + x) * 4;
+    data[offset..offset + 4].try_into().unwrap()
+}
+
+#[test]
+fn stored_components_are_interleaved_back_into_blocks() {
+    // Two DXT5 blocks, no passes: alpha halves, then the first color word
+    // of each block, then the second.
+    let entry = texture_entry(
+        b"DXT5",
+        8,
+        4,
+        0,
+        &[0x8080, 0, 0x4040, 0, 0xf800, 0x001f, 0, 0],
+    );
+    let blocks = inflate_texture(&entry).unwrap();
+    assert_eq!(blocks.len(), 32);
+    assert_eq!(&blocks[..4], &[0x80, 0x80, 0, 0]);
+    assert_eq!(&blocks[8..12], &[0x00, 0xf8, 0, 0]);
+    assert_eq!(&blocks[16..20], &[0x40, 0x40, 0, 0]);
+    assert_eq!(&blocks[24..28], &[0x1f, 0x00, 0, 0]);
+
+    let image = decode_texture_entry(&entry).unwrap();
+    assert_eq!(pixel(&image.pixels, 8, 0, 0), [255, 0, 0, 0x80]);
+    assert_eq!(pixel(&image.pixels, 8, 7, 3), [0, 0, 255, 0x40]);
+}
+
+#[test]
+fn constant_alpha_pass_fills_both_blocks() {
+    // Alpha 0x80, then one run of two blocks (`001111`), set and not null.
+    let entry = texture_entry(b"DXT5", 8, 4, 0x04, &[0x803f_0000, 0xf800, 0x001f, 0, 0]);
+    let image = decode_texture_entry(&entry).unwrap();
+    assert_eq!(pixel(&image.pixels, 8, 0, 0), [255, 0, 0, 0x80]);
+    assert_eq!(pixel(&image.pixels, 8, 4, 0), [0, 0, 255, 0x80]);
+}
+
+#[test]
+fn white_color_pass_makes_opaque_white_blocks() {
+    // One run of one block (`1`), set (`1`); nothing is stored.
+    let entry = texture_entry(b"DXTL", 4, 4, 0x01, &[0xc000_0000]);
+    let image = decode_texture_entry(&entry).unwrap();
+    for pixel in image.pixels.chunks_exact(4) {
+        assert!(pixel[..3].iter().all(|channel| *channel >= 250));
+        assert_eq!(pixel[3], 255);
+    }
+}
+
+#[test]
+fn plain_color_pass_covers_the_blocks() {
+    // Red 0xff, green 0, blue 0, then one run of one block, set.
+    let entry = texture_entry(b"DXT1", 4, 4, 0x08, &[0xff00_00c0]);
+    let image = decode_texture_entry(&entry).unwrap();
+    for pixel in image.pixels.chunks_exact(4) {
+        assert_eq!(pixel, [255, 0, 0, 255]);
+    }
+}
+
+#[test]
+fn alpha_only_and_normal_map_formats_decode() {
+    assert_eq!(BlockFormat::from_fourcc(b"DXTA"), Some(BlockFormat::Dxta));
+    assert_eq!(BlockFormat::from_fourcc(b"DXTL"), Some(BlockFormat::Dxt5));
+    assert_eq!(BlockFormat::from_fourcc(b"DXTN"), Some(BlockFormat::Dxn));
+
+    let entry = texture_entry(b"DXTA", 4, 4, 0, &[0xc8c8, 0]);
+    let image = decode_texture_entry(&entry).unwrap();
+    assert_eq!(pixel(&image.pixels, 4, 3, 3), [0xc8, 0xc8, 0xc8, 255]);
+}
+
+#[test]
+fn corrupt_texture_streams_are_rejected() {
+    // A run of 18 blocks (`01`) in a texture of one block.
+    let entry = texture_entry(b"DXT1", 4, 4, 0x01, &[0x6000_0000]);
+    assert!(inflate_texture(&entry).is_err());
+    // A constant alpha pass on a format whose alpha is not stored.
+    let entry = texture_entry(b"DXT1", 4, 4, 0x04, &[0xffff_ffff]);
+    assert!(inflate_texture(&entry).is_err());
+    // Dimensions far beyond what the input can expand to.
+    let entry = texture_entry(b"DXT5", 0xffff, 0xffff, 0, &[]);
+    assert!(inflate_texture(&entry).is_err());
+    assert!(decode_texture_entry(b"ATEXDXT5").is_err());
+}
+
+#[test]
+fn encoded_entries_decode_back() {
+    // Red on the left block, half transparent blue on the right one.
+    let mut image = RgbaImageData {
+        width: 8,
+        height: 4,
+        pixels: Vec::new(),
+    };
+    for index in 0..32 {
+        let pixel = if index % 8 < 4 {
+            [255, 0, 0, 255]
+        } else {
+            [0, 0, 255, 0x80]
+        };
+        image.pixels.extend_from_slice(&pixel);
+    }
+    for format in [BlockFormat::Dxt3, BlockFormat::Dxt5] {
+        let entry = encode_texture_entry(&image, b"ATEX", format).unwrap();
+        assert_eq!(&entry[4..8], format.fourcc());
+        let decoded = decode_texture_entry(&entry).unwrap();
+        assert_eq!((decoded.width, decoded.height), (8, 4));
+        assert_eq!(pixel(&decoded.pixels, 8, 0, 0), [255, 0, 0, 255]);
+        let right = pixel(&decoded.pixels, 8, 7, 3);
+        assert_eq!(right[..3], [0, 0, 255]);
+        assert!(right[3].abs_diff(0x80) <= 0x11);
+    }
+
+    let entry = encode_texture_entry(&image, b"ATEX", BlockFormat::Dxt1).unwrap();
+    let decoded = decode_texture_entry(&entry).unwrap();
+    assert_eq!(pixel(&decoded.pixels, 8, 1, 1)[..3], [255, 0, 0]);
+    assert_eq!(pixel(&decoded.pixels, 8, 6, 2)[..3], [0, 0, 255]);
+
+    let entry = encode_texture_entry(&image, b"ATEX", BlockFormat::Dxta).unwrap();
+    let decoded = decode_texture_entry(&entry).unwrap();
+    assert_eq!(pixel(&decoded.pixels, 8, 0, 0), [255, 255, 255, 255]);
+    assert_eq!(pixel(&decoded.pixels, 8, 4, 0), [0, 0, 0, 255]);
+}
+
+#[test]
+fn empty_images_are_left_as_they_are() {
+    let texture = texture_entry(b"DXT1", 0, 4, 0, &[]);
+    let image = decode_texture_entry(&texture).unwrap();
+    assert!(image.is_empty());
+    let downsampled = image.downsample();
+    assert_eq!((downsampled.width, downsampled.height), (0, 4));
+    assert_eq!(image.mip_chain().len(), 1);
+    assert!(image.resized_to_fit(2).is_empty());
+}
+
+/// A `width` by `height` image of `color`.
+fn solid_image(width: u32, height: u32, color: [u8; 4]) -> RgbaImageData {
+    RgbaImageData {
+        width,
+        height,
+        pixels: color.repeat((width * height) as usize),
+    }
+}
+
+#[test]
+fn encoded_entries_hold_every_mip_level() {
+    let image = solid_image(16, 8, [0, 255, 0, 255]);
+    for identifier in [b"ATEX", b"CTEX"] {
+        let entry = encode_texture_entry(&image, identifier, BlockFormat::Dxt5).unwrap();
+        let sizes: Vec<(u32, u32)> = (0..5)
+            .map(|level| {
+                let level = decode_texture_level(&entry, level).unwrap();
+                assert_eq!(
+                    pixel(&level.pixels, level.width as usize, 0, 0),
+                    [0, 255, 0, 255]
+                );
+                (level.width, level.height)
+            })
+            .collect();
+        assert_eq!(sizes, [(16, 8), (8, 4), (4, 2), (2, 1), (1, 1)]);
+        assert!(decode_texture_level(&entry, 5).is_err());
+    }
+}
+
+#[test]
+fn textures_are_patched_from_png() {
+    let texture = encode_texture_entry(
+        &solid_image(4, 4, [255, 0, 0, 255]),
+        b"ATEX",
+        BlockFormat::Dxt1,
+    )
+    .unwrap();
+    let mut builder = ArchiveBuilder::new();
+    builder.compress(true).add_file(100, texture);
+    let name = format!("leafadoo-test-{}-texture", process::id());
+    let archive_path = env::temp_dir().join(format!("{}.dat", name));
+    let png_path = env::temp_dir().join(format!("{}.png", name));
+    builder.save_to_file(&archive_path).unwrap();
+    solid_image(8, 8, [0, 0, 255, 255])
+        .save_png(&png_path)
+        .unwrap();
+
+    let mut archive = AnetArchive::load_from_file(&archive_path).unwrap();
+    let index = archive.index_of(100).unwrap();
+    archive
+        .patch_texture_from_png(index, &png_path, None)
+        .unwrap();
+    let archive = AnetArchive::load_from_file(&archive_path).unwrap();
+    assert!(archive.mft_data[index].is_compressed());
+    let entry = archive.read_file(100).unwrap();
+    assert_eq!(&entry[..8], b"ATEXDXT1");
+    let image = decode_texture_entry(&entry).unwrap();
+    assert_eq!((image.width, image.height), (8, 8));
+    assert_eq!(pixel(&image.pixels, 8, 7, 7), [0, 0, 255, 255]);
+    assert_eq!(decode_texture_level(&entry, 3).unwrap().width, 1);
+    fs::remove_file(archive_path).unwrap();
+    fs::remove_file(png_path).unwrap();
+}